@@ -30,7 +30,7 @@ int main() {
 
 	uint32_t *isnanisinf_out_spv;
 	uint32_t isnanisinf_out_count;
-	spirv_webgpu_transform_isnanisinfpatch_alloc(dref_out_spv, dref_out_count, &isnanisinf_out_spv, &isnanisinf_out_count);
+	spirv_webgpu_transform_isnanisinfpatch_alloc(dref_out_spv, dref_out_count, &isnanisinf_out_spv, &isnanisinf_out_count, SPIRV_WEBGPU_TRANSFORM_IS_NAN_IS_INF_MODE_DEFAULT);
 
 	uint32_t *storagecube_out_spv;
 	uint32_t storagecube_out_count;
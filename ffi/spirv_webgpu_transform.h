@@ -20,18 +20,132 @@ typedef struct _SpvTransformCorrectionMap *SpvTransformCorrectionMap;
 
 typedef DEFINE_OPTIONAL(uint32_t) SpvTransformOptionalU32;
 
+typedef enum {
+	SPIRV_WEBGPU_TRANSFORM_IS_NAN_IS_INF_MODE_DEFAULT = 0,
+	SPIRV_WEBGPU_TRANSFORM_IS_NAN_IS_INF_MODE_FUNCTION = 0,
+	SPIRV_WEBGPU_TRANSFORM_IS_NAN_IS_INF_MODE_INLINE = 1,
+	SPIRV_WEBGPU_TRANSFORM_IS_NAN_IS_INF_MODE_COMPARISON = 2,
+} SpvTransformIsNanIsInfMode;
+
+typedef enum {
+	SPIRV_WEBGPU_TRANSFORM_TEXEL_BUFFER_FORMAT_R32_FLOAT = 0,
+	SPIRV_WEBGPU_TRANSFORM_TEXEL_BUFFER_FORMAT_DEFAULT = 1,
+	SPIRV_WEBGPU_TRANSFORM_TEXEL_BUFFER_FORMAT_RGBA32_FLOAT = 1,
+	SPIRV_WEBGPU_TRANSFORM_TEXEL_BUFFER_FORMAT_RGBA8_UNORM = 2,
+	SPIRV_WEBGPU_TRANSFORM_TEXEL_BUFFER_FORMAT_R32_UINT = 3,
+} SpvTransformTexelBufferFormat;
+
+typedef struct {
+	uint32_t set;
+	uint32_t binding;
+	SpvTransformTexelBufferFormat format;
+} SpvTransformTexelBufferBinding;
+
+typedef enum {
+	SPIRV_WEBGPU_TRANSFORM_SPEC_CONSTANT_MODE_DEFAULT = 0,
+	SPIRV_WEBGPU_TRANSFORM_SPEC_CONSTANT_MODE_BAKE_ALL = 0,
+	SPIRV_WEBGPU_TRANSFORM_SPEC_CONSTANT_MODE_OVERRIDABLE = 1,
+} SpvTransformSpecConstantMode;
+
+typedef enum {
+	SPIRV_WEBGPU_TRANSFORM_PRUNE_UNUSED_DREF_MODE_DEFAULT = 0,
+	SPIRV_WEBGPU_TRANSFORM_PRUNE_UNUSED_DREF_MODE_PRUNE = 0,
+	SPIRV_WEBGPU_TRANSFORM_PRUNE_UNUSED_DREF_MODE_REPORT = 1,
+} SpvTransformPruneUnusedDrefMode;
+
+typedef enum {
+	SPIRV_WEBGPU_TRANSFORM_UNUSED_RESOURCE_KIND_SAMPLER = 0,
+	SPIRV_WEBGPU_TRANSFORM_UNUSED_RESOURCE_KIND_TEXTURE = 1,
+	SPIRV_WEBGPU_TRANSFORM_UNUSED_RESOURCE_KIND_COMBINED_IMAGE_SAMPLER = 2,
+	SPIRV_WEBGPU_TRANSFORM_UNUSED_RESOURCE_KIND_STORAGE_TEXTURE = 3,
+} SpvTransformUnusedResourceKind;
+
+typedef struct {
+	uint32_t set;
+	uint32_t binding;
+	SpvTransformUnusedResourceKind kind;
+} SpvTransformUnusedResource;
+
+typedef struct {
+	const char *name;
+	uint32_t execution_model;
+	uint32_t *spv;
+	uint32_t count;
+} SpvTransformEntryPointModule;
+
 void spirv_webgpu_transform_combimgsampsplitter_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformCorrectionMap *correction_map);
 void spirv_webgpu_transform_combimgsampsplitter_free(uint32_t *out_spv);
 void spirv_webgpu_transform_drefsplitter_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformCorrectionMap *correction_map);
 void spirv_webgpu_transform_drefsplitter_free(uint32_t *out_spv);
 void spirv_webgpu_transform_immediatespatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformCorrectionMap *correction_map);
 void spirv_webgpu_transform_immediatespatch_free(uint32_t *out_spv);
-void spirv_webgpu_transform_isnanisinfpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count);
+// Lays out one buffer shared by every module, all arrays hold `module_count` entries.
+// Run `mirrorpatch_many` afterwards to place it at the same set and binding in every module.
+void spirv_webgpu_transform_immediatespatch_shared_alloc(
+		uint32_t **in_spvs, uint32_t *in_counts, SpvTransformCorrectionMap *corrections, uint32_t module_count,
+		uint32_t **out_spvs, uint32_t *out_counts);
+void spirv_webgpu_transform_immediatespatch_shared_free(uint32_t **out_spvs, uint32_t *out_counts, uint32_t module_count);
+void spirv_webgpu_transform_isnanisinfpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformIsNanIsInfMode mode);
 void spirv_webgpu_transform_isnanisinfpatch_free(uint32_t *out_spv);
+void spirv_webgpu_transform_extinstpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count);
+void spirv_webgpu_transform_extinstpatch_free(uint32_t *out_spv);
+void spirv_webgpu_transform_boolblockpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count);
+void spirv_webgpu_transform_boolblockpatch_free(uint32_t *out_spv);
+void spirv_webgpu_transform_widenstoragepatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count);
+void spirv_webgpu_transform_widenstoragepatch_free(uint32_t *out_spv);
 void spirv_webgpu_transform_storagecubepatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformCorrectionMap *correction_map);
 void spirv_webgpu_transform_storagecubepatch_free(uint32_t *out_spv);
+void spirv_webgpu_transform_texelbufferpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformTexelBufferBinding *bindings, uint32_t binding_count, SpvTransformTexelBufferFormat default_format, SpvTransformCorrectionMap *correction_map);
+void spirv_webgpu_transform_texelbufferpatch_free(uint32_t *out_spv);
+void spirv_webgpu_transform_subpassinputpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformCorrectionMap *correction_map);
+void spirv_webgpu_transform_subpassinputpatch_free(uint32_t *out_spv);
+// `spec_values` holds the raw bits of each value, `out_baked_spec_ids` is sorted.
+void spirv_webgpu_transform_specconstantpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, uint32_t *spec_ids, uint64_t *spec_values, uint32_t spec_count, SpvTransformSpecConstantMode mode, uint32_t **out_baked_spec_ids, uint32_t *out_baked_count);
+void spirv_webgpu_transform_specconstantpatch_free(uint32_t *out_spv, uint32_t *out_baked_spec_ids, uint32_t out_baked_count);
+void spirv_webgpu_transform_splitentrypoints_alloc(uint32_t *in_spv, uint32_t in_count, SpvTransformEntryPointModule **out_modules, uint32_t *out_module_count);
+void spirv_webgpu_transform_splitentrypoints_free(SpvTransformEntryPointModule *out_modules, uint32_t out_module_count);
+void spirv_webgpu_transform_pruneunused_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count);
+void spirv_webgpu_transform_pruneunused_free(uint32_t *out_spv);
+
+typedef struct {
+	uint32_t original_offset;
+	uint32_t offset;
+	uint32_t size;
+} SpvTransformUniformCopy;
+
+typedef struct {
+	uint32_t set;
+	uint32_t binding;
+	// The size of the std140 uniform buffer in bytes.
+	uint32_t size;
+	// Contiguous regions to copy from data laid out for the original block.
+	const SpvTransformUniformCopy *copies;
+	uint32_t copy_count;
+} SpvTransformUniformRelayout;
+
+// One relayout per uniform block that WGSL could not express.
+void spirv_webgpu_transform_uniformlayoutpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformUniformRelayout **out_relayouts, uint32_t *out_relayout_count);
+void spirv_webgpu_transform_uniformlayoutpatch_free(uint32_t *out_spv, SpvTransformUniformRelayout *out_relayouts, uint32_t out_relayout_count);
+// `out_data` must hold `relayout->size` bytes, padding is zeroed.
+SPIRV_WEBGPU_TRANSFORM_BOOL spirv_webgpu_transform_repack_uniform(
+		const SpvTransformUniformRelayout *relayout,
+		const uint8_t *in_data,
+		uint32_t in_size,
+		uint8_t *out_data);
+
+typedef struct {
+	uint32_t set;
+	uint32_t binding;
+} SpvTransformWordStorageBuffer;
+
+// Uniform blocks are relaid out as by `uniformlayoutpatch`, storage buffers in `out_word_buffers` keep their data layout.
+void spirv_webgpu_transform_scalarlayoutpatch_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformUniformRelayout **out_relayouts, uint32_t *out_relayout_count, SpvTransformWordStorageBuffer **out_word_buffers, uint32_t *out_word_buffer_count);
+void spirv_webgpu_transform_scalarlayoutpatch_free(uint32_t *out_spv, SpvTransformUniformRelayout *out_relayouts, uint32_t out_relayout_count, SpvTransformWordStorageBuffer *out_word_buffers, uint32_t out_word_buffer_count);
 void spirv_webgpu_transform_pruneunuseddref_alloc(uint32_t *int_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count);
 void spirv_webgpu_transform_pruneunuseddref_free(uint32_t *out_spv);
+// `out_unused` is sorted by set and binding, `REPORT` mode leaves `out_spv` unchanged.
+void spirv_webgpu_transform_pruneunuseddref_with_mode_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformPruneUnusedDrefMode mode, SpvTransformUnusedResource **out_unused, uint32_t *out_unused_count);
+void spirv_webgpu_transform_pruneunuseddref_with_mode_free(uint32_t *out_spv, SpvTransformUnusedResource *out_unused, uint32_t out_unused_count);
 void spirv_webgpu_transform_splitbindingarray_alloc(uint32_t *in_spv, uint32_t in_count, uint32_t **out_spv, uint32_t *out_count, SpvTransformCorrectionMap *correction_map);
 void spirv_webgpu_transform_splitbindingarray_free(uint32_t *out_spv);
 
@@ -41,6 +155,11 @@ void spirv_webgpu_transform_mirrorpatch_alloc(
 		uint32_t **out_left_spv, uint32_t *out_left_count,
 		uint32_t **out_right_spv, uint32_t *out_right_count);
 void spirv_webgpu_transform_mirrorpatch_free(uint32_t *out_left_spv, uint32_t *out_right_spv);
+// All arrays hold `module_count` entries, `out_spvs` and `out_counts` are filled in by the call.
+void spirv_webgpu_transform_mirrorpatch_many_alloc(
+		uint32_t **in_spvs, uint32_t *in_counts, SpvTransformCorrectionMap *corrections, uint32_t module_count,
+		uint32_t **out_spvs, uint32_t *out_counts);
+void spirv_webgpu_transform_mirrorpatch_many_free(uint32_t **out_spvs, uint32_t *out_counts, uint32_t module_count);
 
 typedef enum {
 	SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_SPLIT_COMBINED = 0,
@@ -48,8 +167,23 @@ typedef enum {
 	SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_SPLIT_DREF_COMPARISON = 2,
 	SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_CONVERT_STORAGE_CUBE = 3,
 	SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_SPLIT_BINDING_ARRAY = 4,
+	SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_CONVERT_TEXEL_BUFFER = 5,
+	SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_CONVERT_SUBPASS_INPUT = 6,
 } SpvTransformCorrectionType;
 
+typedef struct {
+	uint32_t set;
+	uint32_t binding;
+	SpvTransformCorrectionType correction_type;
+} SpvTransformMirrorConflict;
+
+// Bindings converted in some modules but not others, sorted by set and binding.
+// `mirrorpatch` and `mirrorpatch_many` fail with null outputs while any conflict exists.
+void spirv_webgpu_transform_mirrorpatch_conflicts_alloc(
+		uint32_t **in_spvs, uint32_t *in_counts, SpvTransformCorrectionMap *corrections, uint32_t module_count,
+		SpvTransformMirrorConflict **out_conflicts, uint32_t *out_conflict_count);
+void spirv_webgpu_transform_mirrorpatch_conflicts_free(SpvTransformMirrorConflict *out_conflicts, uint32_t out_conflict_count);
+
 // SAFETY: `corrections` invalidates when `correction_map` is written to.
 // Returns true if there is `Some` correction type.
 SPIRV_WEBGPU_TRANSFORM_BOOL spirv_webgpu_transform_correction_sets_index(
@@ -59,6 +193,12 @@ SPIRV_WEBGPU_TRANSFORM_BOOL spirv_webgpu_transform_correction_sets_index(
 		uint16_t **corrections_ptr,
 		uint32_t *correction_count);
 
+// Returns the `input_attachment_index` of a `SPIRV_WEBGPU_TRANSFORM_CORRECTION_TYPE_CONVERT_SUBPASS_INPUT` binding.
+SpvTransformOptionalU32 spirv_webgpu_transform_correction_sets_input_attachment_index(
+		SpvTransformCorrectionMap correction_map,
+		uint32_t set,
+		uint32_t binding);
+
 typedef enum {
 	SPRIV_WEBGPU_TRANSFORM_IMMEDIATES_SET_MODE_DEFAULT = 0,
 	SPRIV_WEBGPU_TRANSFORM_IMMEDIATES_SET_MODE_ABSOLUTE = 0,
@@ -68,8 +208,76 @@ typedef enum {
 
 SpvTransformOptionalU32 spirv_webgpu_transform_correction_read_immediates_set(
 		SpvTransformCorrectionMap correction_map);
+// The binding of the first immediate uniform, set by `immediatespatch` and `mirrorpatch`.
+SpvTransformOptionalU32 spirv_webgpu_transform_correction_read_immediates_binding(
+		SpvTransformCorrectionMap correction_map);
 void spirv_webgpu_transform_correction_write_immediates_set(
 		SpvTransformCorrectionMap *correction_map, uint32_t value, SpvTransformImmediatesSetMode mode);
+void spirv_webgpu_transform_correction_write_immediates_binding(
+		SpvTransformCorrectionMap *correction_map, uint32_t value);
+
+typedef enum {
+	SPIRV_WEBGPU_TRANSFORM_IMMEDIATES_BUFFER_KIND_DEFAULT = 0,
+	SPIRV_WEBGPU_TRANSFORM_IMMEDIATES_BUFFER_KIND_UNIFORM = 0,
+	// Keeps the std430 layout of the push constant block.
+	SPIRV_WEBGPU_TRANSFORM_IMMEDIATES_BUFFER_KIND_READ_ONLY_STORAGE = 1,
+} SpvTransformImmediatesBufferKind;
+
+// The kind of buffer chosen by `immediatespatch`.
+SpvTransformImmediatesBufferKind spirv_webgpu_transform_correction_read_immediates_buffer_kind(
+		SpvTransformCorrectionMap correction_map);
+void spirv_webgpu_transform_correction_write_immediates_buffer_kind(
+		SpvTransformCorrectionMap *correction_map, SpvTransformImmediatesBufferKind kind);
+// Rewrite `matCx2` members of std140 uniforms as arrays of `vec4` columns, which naga accepts.
+void spirv_webgpu_transform_correction_write_pad_matcx2(
+		SpvTransformCorrectionMap *correction_map, SPIRV_WEBGPU_TRANSFORM_BOOL value);
+
+typedef struct {
+	uint32_t push_constant_offset;
+	uint32_t offset;
+	uint32_t size;
+} SpvTransformImmediatesMember;
+
+typedef struct {
+	uint32_t push_constant_offset;
+	uint32_t offset;
+	uint32_t size;
+} SpvTransformImmediatesCopy;
+
+typedef struct {
+	uint32_t set;
+	uint32_t binding;
+	// The size of the std140 uniform buffer or std430 storage buffer in bytes.
+	uint32_t size;
+	const SpvTransformImmediatesMember *members;
+	uint32_t member_count;
+	// Contiguous regions to copy from push constant data into the uniform buffer.
+	const SpvTransformImmediatesCopy *copies;
+	uint32_t copy_count;
+} SpvTransformImmediatesBuffer;
+
+// One buffer per push constant block replaced by `immediatespatch`.
+uint32_t spirv_webgpu_transform_correction_immediates_buffer_count(
+		SpvTransformCorrectionMap correction_map);
+// SAFETY: `out_buffer->members` invalidates when `correction_map` is written to.
+SPIRV_WEBGPU_TRANSFORM_BOOL spirv_webgpu_transform_correction_immediates_buffer_index(
+		SpvTransformCorrectionMap correction_map,
+		uint32_t index,
+		SpvTransformImmediatesBuffer *out_buffer);
+// Whether the entry point named `name` with `execution_model` statically uses buffer `index`.
+// Modules with several entry points may give each stage its own push constant block.
+SPIRV_WEBGPU_TRANSFORM_BOOL spirv_webgpu_transform_correction_immediates_buffer_uses_entry_point(
+		SpvTransformCorrectionMap correction_map,
+		uint32_t index,
+		const char *name,
+		uint32_t execution_model);
+// Converts `in_data`, laid out like the original push constant block, into the uniform buffer's layout.
+// `out_data` must hold `buffer->size` bytes, padding is zeroed.
+SPIRV_WEBGPU_TRANSFORM_BOOL spirv_webgpu_transform_repack_immediates(
+		const SpvTransformImmediatesBuffer *buffer,
+		const uint8_t *in_data,
+		uint32_t in_size,
+		uint8_t *out_data);
 
 void spirv_webgpu_transform_correction_map_free(SpvTransformCorrectionMap correction_map);
 
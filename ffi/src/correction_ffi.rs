@@ -12,12 +12,100 @@ pub struct SpvTransformOptionalU32 {
 }
 
 #[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformEntryPointModule {
+    pub name: *const ffi::c_char,
+    pub execution_model: u32,
+    pub spv: *const u32,
+    pub count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformUnusedResource {
+    pub set: u32,
+    pub binding: u32,
+    pub kind: TransformUnusedResourceKind,
+}
+
+#[repr(C)]
+#[derive(Debug)]
 pub enum TransformCorrectionType {
     SpirvWebgpuTransformCorrectionTypeSplitCombined = 0,
     SpirvWebgpuTransformCorrectionTypeSplitDrefRegular = 1,
     SpirvWebgpuTransformCorrectionTypeSplitDrefComparison = 2,
     SpirvWebgpuTransformCorrectionTypeConvertStorageCube = 3,
     SpirvWebgpuTransformCorrectionTypeSplitBindingArray = 4,
+    SpirvWebgpuTransformCorrectionTypeConvertTexelBuffer = 5,
+    SpirvWebgpuTransformCorrectionTypeConvertSubpassInput = 6,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformMirrorConflict {
+    pub set: u32,
+    pub binding: u32,
+    pub correction_type: TransformCorrectionType,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformImmediatesMember {
+    pub push_constant_offset: u32,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformImmediatesCopy {
+    pub push_constant_offset: u32,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformImmediatesBuffer {
+    pub set: u32,
+    pub binding: u32,
+    pub size: u32,
+    pub members: *const SpvTransformImmediatesMember,
+    pub member_count: u32,
+    pub copies: *const SpvTransformImmediatesCopy,
+    pub copy_count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformUniformCopy {
+    pub original_offset: u32,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformUniformRelayout {
+    pub set: u32,
+    pub binding: u32,
+    pub size: u32,
+    pub copies: *const SpvTransformUniformCopy,
+    pub copy_count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SpvTransformWordStorageBuffer {
+    pub set: u32,
+    pub binding: u32,
+}
+
+#[repr(C)]
+pub struct SpvTransformTexelBufferBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub format: TransformTexelBufferFormat,
 }
 
 #[repr(C)]
@@ -26,6 +114,48 @@ pub enum TransformImmediatesSetMode {
     SpirvWebgpuTransformImmediatesSetModeMaxUpTo = 1,
     SpirvWebgpuTransformImmediatesSetModeMaxPlusOneUpTo = 2,
 }
+
+#[repr(C)]
+pub enum TransformImmediatesBufferKind {
+    SpirvWebgpuTransformImmediatesBufferKindUniform = 0,
+    SpirvWebgpuTransformImmediatesBufferKindReadOnlyStorage = 1,
+}
+
+#[repr(C)]
+pub enum TransformIsNanIsInfMode {
+    SpirvWebgpuTransformIsNanIsInfModeFunction = 0,
+    SpirvWebgpuTransformIsNanIsInfModeInline = 1,
+    SpirvWebgpuTransformIsNanIsInfModeComparison = 2,
+}
+
+#[repr(C)]
+pub enum TransformTexelBufferFormat {
+    SpirvWebgpuTransformTexelBufferFormatR32Float = 0,
+    SpirvWebgpuTransformTexelBufferFormatRgba32Float = 1,
+    SpirvWebgpuTransformTexelBufferFormatRgba8Unorm = 2,
+    SpirvWebgpuTransformTexelBufferFormatR32Uint = 3,
+}
+
+#[repr(C)]
+pub enum TransformSpecConstantMode {
+    SpirvWebgpuTransformSpecConstantModeBakeAll = 0,
+    SpirvWebgpuTransformSpecConstantModeOverridable = 1,
+}
+
+#[repr(C)]
+pub enum TransformPruneUnusedDrefMode {
+    SpirvWebgpuTransformPruneUnusedDrefModePrune = 0,
+    SpirvWebgpuTransformPruneUnusedDrefModeReport = 1,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub enum TransformUnusedResourceKind {
+    SpirvWebgpuTransformUnusedResourceKindSampler = 0,
+    SpirvWebgpuTransformUnusedResourceKindTexture = 1,
+    SpirvWebgpuTransformUnusedResourceKindCombinedImageSampler = 2,
+    SpirvWebgpuTransformUnusedResourceKindStorageTexture = 3,
+}
 pub unsafe fn cast_correction_map(map: SpvTransformCorrectionMap) -> &'static mut CorrectionMap {
     unsafe { &mut *(map as *mut CorrectionMap) }
 }
@@ -89,6 +219,31 @@ pub unsafe extern "C" fn spirv_webgpu_transform_correction_sets_index(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_sets_input_attachment_index(
+    correction_map: SpvTransformCorrectionMap,
+    set: u32,
+    binding: u32,
+) -> SpvTransformOptionalU32 {
+    if !correction_map.is_null() {
+        let correction_map = unsafe { cast_correction_map(correction_map) };
+        if let Some(sets) = correction_map.sets.as_ref()
+            && let Some(set) = sets.get(&set)
+            && let Some(binding) = set.bindings.get(&binding)
+            && let Some(value) = binding.input_attachment_index
+        {
+            return SpvTransformOptionalU32 {
+                some: C_TRUE,
+                value,
+            };
+        }
+    }
+    SpvTransformOptionalU32 {
+        some: C_FALSE,
+        ..Default::default()
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_correction_read_immediates_set(
     correction_map: SpvTransformCorrectionMap,
@@ -105,6 +260,171 @@ pub unsafe extern "C" fn spirv_webgpu_transform_correction_read_immediates_set(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_read_immediates_binding(
+    correction_map: SpvTransformCorrectionMap,
+) -> SpvTransformOptionalU32 {
+    if !correction_map.is_null() {
+        let correction_map = unsafe { cast_correction_map(correction_map) };
+        if let Some(value) = correction_map.immediates_binding {
+            return SpvTransformOptionalU32 { some: 1, value };
+        }
+    }
+    SpvTransformOptionalU32 {
+        some: C_FALSE,
+        ..Default::default()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_write_immediates_binding(
+    correction_map: *mut SpvTransformCorrectionMap,
+    value: u32,
+) {
+    let correction_map = unsafe { cast_correction_map_or_default_alloc(correction_map) };
+    correction_map.immediates_binding = Some(value);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_read_immediates_buffer_kind(
+    correction_map: SpvTransformCorrectionMap,
+) -> TransformImmediatesBufferKind {
+    let kind = if correction_map.is_null() {
+        ImmediatesBufferKind::default()
+    } else {
+        let correction_map = unsafe { cast_correction_map(correction_map) };
+        correction_map.immediates_buffer_kind.unwrap_or_default()
+    };
+    match kind {
+        ImmediatesBufferKind::Uniform => {
+            TransformImmediatesBufferKind::SpirvWebgpuTransformImmediatesBufferKindUniform
+        }
+        ImmediatesBufferKind::ReadOnlyStorage => {
+            TransformImmediatesBufferKind::SpirvWebgpuTransformImmediatesBufferKindReadOnlyStorage
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_write_immediates_buffer_kind(
+    correction_map: *mut SpvTransformCorrectionMap,
+    kind: TransformImmediatesBufferKind,
+) {
+    let correction_map = unsafe { cast_correction_map_or_default_alloc(correction_map) };
+    correction_map.immediates_buffer_kind = Some(match kind {
+        TransformImmediatesBufferKind::SpirvWebgpuTransformImmediatesBufferKindUniform => {
+            ImmediatesBufferKind::Uniform
+        }
+        TransformImmediatesBufferKind::SpirvWebgpuTransformImmediatesBufferKindReadOnlyStorage => {
+            ImmediatesBufferKind::ReadOnlyStorage
+        }
+    });
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_write_pad_matcx2(
+    correction_map: *mut SpvTransformCorrectionMap,
+    value: u8,
+) {
+    let correction_map = unsafe { cast_correction_map_or_default_alloc(correction_map) };
+    correction_map.pad_matcx2 = value != C_FALSE;
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_immediates_buffer_count(
+    correction_map: SpvTransformCorrectionMap,
+) -> u32 {
+    if correction_map.is_null() {
+        0
+    } else {
+        let correction_map = unsafe { cast_correction_map(correction_map) };
+        correction_map.immediates_buffers.len() as u32
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_immediates_buffer_index(
+    correction_map: SpvTransformCorrectionMap,
+    index: u32,
+    out_buffer: *mut SpvTransformImmediatesBuffer,
+) -> u8 {
+    if !correction_map.is_null() {
+        let correction_map = unsafe { cast_correction_map(correction_map) };
+        if let Some(buffer) = correction_map.immediates_buffers.get(index as usize) {
+            unsafe {
+                *out_buffer = SpvTransformImmediatesBuffer {
+                    set: buffer.set,
+                    binding: buffer.binding,
+                    size: buffer.size,
+                    members: buffer.members.as_ptr() as *const SpvTransformImmediatesMember,
+                    member_count: buffer.members.len() as u32,
+                    copies: buffer.copies.as_ptr() as *const SpvTransformImmediatesCopy,
+                    copy_count: buffer.copies.len() as u32,
+                };
+            }
+            return C_TRUE;
+        }
+    }
+    C_FALSE
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_correction_immediates_buffer_uses_entry_point(
+    correction_map: SpvTransformCorrectionMap,
+    index: u32,
+    name: *const ffi::c_char,
+    execution_model: u32,
+) -> u8 {
+    if !correction_map.is_null() {
+        let correction_map = unsafe { cast_correction_map(correction_map) };
+        let name = unsafe { ffi::CStr::from_ptr(name) };
+        if let Some(buffer) = correction_map.immediates_buffers.get(index as usize)
+            && buffer.entry_points.iter().any(|entry_point| {
+                entry_point.execution_model == execution_model
+                    && entry_point.name.as_bytes() == name.to_bytes()
+            })
+        {
+            return C_TRUE;
+        }
+    }
+    C_FALSE
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_repack_immediates(
+    buffer: *const SpvTransformImmediatesBuffer,
+    in_data: *const u8,
+    in_size: u32,
+    out_data: *mut u8,
+) -> u8 {
+    let buffer = unsafe { &*buffer };
+    let copies = unsafe { slice::from_raw_parts(buffer.copies, buffer.copy_count as usize) };
+    let in_data = unsafe { slice::from_raw_parts(in_data, in_size as usize) };
+    let immediates_buffer = ImmediatesBuffer {
+        set: buffer.set,
+        binding: buffer.binding,
+        size: buffer.size,
+        members: vec![],
+        copies: copies
+            .iter()
+            .map(|copy| ImmediatesCopy {
+                push_constant_offset: copy.push_constant_offset,
+                offset: copy.offset,
+                size: copy.size,
+            })
+            .collect(),
+        entry_points: vec![],
+    };
+    match repack_immediates(&immediates_buffer, in_data) {
+        Ok(data) => {
+            let out_data = unsafe { slice::from_raw_parts_mut(out_data, data.len()) };
+            out_data.copy_from_slice(&data);
+            C_TRUE
+        }
+        Err(_) => C_FALSE,
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_correction_write_immediates_set(
     correction_map: *mut SpvTransformCorrectionMap,
@@ -2,8 +2,14 @@
 
 use core::{ffi, ptr, slice};
 use spirv_webgpu_transform::{
-    CorrectionMap, ImmediatesSetMode, combimgsampsplitter, drefsplitter, immediatespatch,
-    isnanisinfpatch, mirrorpatch, pruneunuseddref, splitbindingarray, storagecubepatch,
+    CorrectionMap, CorrectionType, ImmediatesBuffer, ImmediatesBufferKind, ImmediatesCopy,
+    ImmediatesSetMode, IsNanIsInfMode, PruneUnusedDrefMode, SpecConstantMode, TexelBufferFormat,
+    UniformCopy, UniformRelayout, UnusedResourceKind, boolblockpatch, combimgsampsplitter,
+    drefsplitter, extinstpatch, immediatespatch, immediatespatch_shared, isnanisinfpatch,
+    mirrorpatch, mirrorpatch_conflicts, mirrorpatch_many, pruneunused, pruneunuseddref,
+    pruneunuseddref_with_mode, repack_immediates, repack_uniform, scalarlayoutpatch,
+    specconstantpatch, splitbindingarray, splitentrypoints, storagecubepatch, subpassinputpatch,
+    texelbufferpatch, uniformlayoutpatch, widenstoragepatch,
 };
 
 mod correction_ffi;
@@ -100,15 +106,76 @@ pub unsafe extern "C" fn spirv_webgpu_transform_immediatespatch_free(out_spv: *m
     unsafe { drop(Box::from_raw(out_spv)) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_immediatespatch_shared_alloc(
+    in_spvs: *const *const u32,
+    in_counts: *const u32,
+    corrections: *mut SpvTransformCorrectionMap,
+    module_count: u32,
+    out_spvs: *mut *const u32,
+    out_counts: *mut u32,
+) {
+    let module_count = module_count as usize;
+    let in_spvs = unsafe { slice::from_raw_parts(in_spvs, module_count) };
+    let in_counts = unsafe { slice::from_raw_parts(in_counts, module_count) };
+    let out_spvs = unsafe { slice::from_raw_parts_mut(out_spvs, module_count) };
+    let out_counts = unsafe { slice::from_raw_parts_mut(out_counts, module_count) };
+
+    let mut modules = in_spvs
+        .iter()
+        .zip(in_counts.iter())
+        .enumerate()
+        .map(|(idx, (&in_spv, &in_count))| {
+            let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+            let correction_map =
+                unsafe { cast_correction_map_or_default_alloc(corrections.add(idx)) };
+            (in_spv, correction_map)
+        })
+        .collect::<Vec<_>>();
+
+    match immediatespatch_shared(&mut modules) {
+        Ok(outputs) => {
+            for (idx, spv) in outputs.into_iter().enumerate() {
+                out_counts[idx] = spv.len() as u32;
+                out_spvs[idx] = Box::leak(spv.into_boxed_slice()).as_ptr();
+            }
+        }
+        Err(_) => {
+            out_spvs.fill(ptr::null());
+            out_counts.fill(0);
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_immediatespatch_shared_free(
+    out_spvs: *mut *mut u32,
+    out_counts: *const u32,
+    module_count: u32,
+) {
+    unsafe { spirv_webgpu_transform_mirrorpatch_many_free(out_spvs, out_counts, module_count) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_isnanisinfpatch_alloc(
     in_spv: *const u32,
     in_count: u32,
     out_spv: *mut *const u32,
     out_count: *mut u32,
+    mode: TransformIsNanIsInfMode,
 ) {
+    let mode = match mode {
+        TransformIsNanIsInfMode::SpirvWebgpuTransformIsNanIsInfModeFunction => {
+            IsNanIsInfMode::Function
+        }
+        TransformIsNanIsInfMode::SpirvWebgpuTransformIsNanIsInfModeInline => IsNanIsInfMode::Inline,
+        TransformIsNanIsInfMode::SpirvWebgpuTransformIsNanIsInfModeComparison => {
+            IsNanIsInfMode::Comparison
+        }
+    };
+
     let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
-    match isnanisinfpatch(in_spv) {
+    match isnanisinfpatch(in_spv, mode) {
         Ok(spv) => unsafe {
             *out_count = spv.len() as u32;
             let leaked = Box::leak(spv.into_boxed_slice());
@@ -126,6 +193,84 @@ pub unsafe extern "C" fn spirv_webgpu_transform_isnanisinfpatch_free(out_spv: *m
     unsafe { drop(Box::from_raw(out_spv)) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_extinstpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match extinstpatch(in_spv) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_extinstpatch_free(out_spv: *mut u32) {
+    unsafe { drop(Box::from_raw(out_spv)) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_boolblockpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match boolblockpatch(in_spv) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_boolblockpatch_free(out_spv: *mut u32) {
+    unsafe { drop(Box::from_raw(out_spv)) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_widenstoragepatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match widenstoragepatch(in_spv) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_widenstoragepatch_free(out_spv: *mut u32) {
+    unsafe { drop(Box::from_raw(out_spv)) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_storagecubepatch_alloc(
     in_spv: *const u32,
@@ -156,6 +301,249 @@ pub unsafe extern "C" fn spirv_webgpu_transform_storagecubepatch_free(out_spv: *
     unsafe { drop(Box::from_raw(out_spv)) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_texelbufferpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+    bindings: *const SpvTransformTexelBufferBinding,
+    binding_count: u32,
+    default_format: TransformTexelBufferFormat,
+    correction_map: *mut SpvTransformCorrectionMap,
+) {
+    let map = correction_map;
+    let correction_map = unsafe { cast_correction_map_or_default_alloc(map) };
+
+    let texel_buffer_format = |format: &TransformTexelBufferFormat| match format {
+        TransformTexelBufferFormat::SpirvWebgpuTransformTexelBufferFormatR32Float => {
+            TexelBufferFormat::R32Float
+        }
+        TransformTexelBufferFormat::SpirvWebgpuTransformTexelBufferFormatRgba32Float => {
+            TexelBufferFormat::Rgba32Float
+        }
+        TransformTexelBufferFormat::SpirvWebgpuTransformTexelBufferFormatRgba8Unorm => {
+            TexelBufferFormat::Rgba8Unorm
+        }
+        TransformTexelBufferFormat::SpirvWebgpuTransformTexelBufferFormatR32Uint => {
+            TexelBufferFormat::R32Uint
+        }
+    };
+    let default_format = texel_buffer_format(&default_format);
+    let formats = if binding_count == 0 {
+        Default::default()
+    } else {
+        let bindings = unsafe { slice::from_raw_parts(bindings, binding_count as usize) };
+        bindings
+            .iter()
+            .map(|binding| {
+                (
+                    (binding.set, binding.binding),
+                    texel_buffer_format(&binding.format),
+                )
+            })
+            .collect()
+    };
+
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match texelbufferpatch(in_spv, &formats, default_format, correction_map) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_texelbufferpatch_free(out_spv: *mut u32) {
+    unsafe { drop(Box::from_raw(out_spv)) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_subpassinputpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+    correction_map: *mut SpvTransformCorrectionMap,
+) {
+    let map = correction_map;
+    let correction_map = unsafe { cast_correction_map_or_default_alloc(map) };
+
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match subpassinputpatch(in_spv, correction_map) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_subpassinputpatch_free(out_spv: *mut u32) {
+    unsafe { drop(Box::from_raw(out_spv)) }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_specconstantpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+    spec_ids: *const u32,
+    spec_values: *const u64,
+    spec_count: u32,
+    mode: TransformSpecConstantMode,
+    out_baked_spec_ids: *mut *const u32,
+    out_baked_count: *mut u32,
+) {
+    let mode = match mode {
+        TransformSpecConstantMode::SpirvWebgpuTransformSpecConstantModeBakeAll => {
+            SpecConstantMode::BakeAll
+        }
+        TransformSpecConstantMode::SpirvWebgpuTransformSpecConstantModeOverridable => {
+            SpecConstantMode::Overridable
+        }
+    };
+
+    let values = if spec_count == 0 {
+        Default::default()
+    } else {
+        let spec_ids = unsafe { slice::from_raw_parts(spec_ids, spec_count as usize) };
+        let spec_values = unsafe { slice::from_raw_parts(spec_values, spec_count as usize) };
+        spec_ids
+            .iter()
+            .copied()
+            .zip(spec_values.iter().copied())
+            .collect()
+    };
+
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    let mut report = Default::default();
+    match specconstantpatch(in_spv, &values, mode, &mut report) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+
+            *out_baked_count = report.baked.len() as u32;
+            let leaked = Box::leak(report.baked.into_boxed_slice());
+            *out_baked_spec_ids = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+            *out_baked_spec_ids = ptr::null();
+            *out_baked_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_specconstantpatch_free(
+    out_spv: *mut u32,
+    out_baked_spec_ids: *mut u32,
+    out_baked_count: u32,
+) {
+    unsafe {
+        drop(Box::from_raw(out_spv));
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            out_baked_spec_ids,
+            out_baked_count as usize,
+        )));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_splitentrypoints_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_modules: *mut *const SpvTransformEntryPointModule,
+    out_module_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match splitentrypoints(in_spv) {
+        Ok(modules) => unsafe {
+            let modules = modules
+                .into_iter()
+                .map(|module| {
+                    let count = module.spv.len() as u32;
+                    let name = std::ffi::CString::new(module.name).unwrap_or_default();
+                    SpvTransformEntryPointModule {
+                        name: name.into_raw(),
+                        execution_model: module.execution_model,
+                        spv: Box::leak(module.spv.into_boxed_slice()).as_ptr(),
+                        count,
+                    }
+                })
+                .collect::<Vec<_>>();
+            *out_module_count = modules.len() as u32;
+            let leaked = Box::leak(modules.into_boxed_slice());
+            *out_modules = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_modules = ptr::null();
+            *out_module_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_splitentrypoints_free(
+    out_modules: *mut SpvTransformEntryPointModule,
+    out_module_count: u32,
+) {
+    unsafe {
+        let modules = Box::from_raw(ptr::slice_from_raw_parts_mut(
+            out_modules,
+            out_module_count as usize,
+        ));
+        for module in modules.iter() {
+            drop(std::ffi::CString::from_raw(module.name as *mut ffi::c_char));
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                module.spv as *mut u32,
+                module.count as usize,
+            )));
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_pruneunused_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    match pruneunused(in_spv) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_pruneunused_free(out_spv: *mut u32) {
+    unsafe { drop(Box::from_raw(out_spv)) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_pruneunuseddref_alloc(
     in_spv: *const u32,
@@ -182,6 +570,83 @@ pub unsafe extern "C" fn spirv_webgpu_transform_pruneunuseddref_free(out_spv: *m
     unsafe { drop(Box::from_raw(out_spv)) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_pruneunuseddref_with_mode_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+    mode: TransformPruneUnusedDrefMode,
+    out_unused: *mut *const SpvTransformUnusedResource,
+    out_unused_count: *mut u32,
+) {
+    let mode = match mode {
+        TransformPruneUnusedDrefMode::SpirvWebgpuTransformPruneUnusedDrefModePrune => {
+            PruneUnusedDrefMode::Prune
+        }
+        TransformPruneUnusedDrefMode::SpirvWebgpuTransformPruneUnusedDrefModeReport => {
+            PruneUnusedDrefMode::Report
+        }
+    };
+
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    let mut report = Default::default();
+    match pruneunuseddref_with_mode(in_spv, mode, &mut report) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+
+            let unused = report
+                .unused
+                .into_iter()
+                .map(|resource| SpvTransformUnusedResource {
+                    set: resource.set,
+                    binding: resource.binding,
+                    kind: match resource.kind {
+                        UnusedResourceKind::Sampler => {
+                            TransformUnusedResourceKind::SpirvWebgpuTransformUnusedResourceKindSampler
+                        }
+                        UnusedResourceKind::Texture => {
+                            TransformUnusedResourceKind::SpirvWebgpuTransformUnusedResourceKindTexture
+                        }
+                        UnusedResourceKind::CombinedImageSampler => {
+                            TransformUnusedResourceKind::SpirvWebgpuTransformUnusedResourceKindCombinedImageSampler
+                        }
+                        UnusedResourceKind::StorageTexture => {
+                            TransformUnusedResourceKind::SpirvWebgpuTransformUnusedResourceKindStorageTexture
+                        }
+                    },
+                })
+                .collect::<Vec<_>>();
+            *out_unused_count = unused.len() as u32;
+            let leaked = Box::leak(unused.into_boxed_slice());
+            *out_unused = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+            *out_unused = ptr::null();
+            *out_unused_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_pruneunuseddref_with_mode_free(
+    out_spv: *mut u32,
+    out_unused: *mut SpvTransformUnusedResource,
+    out_unused_count: u32,
+) {
+    unsafe {
+        drop(Box::from_raw(out_spv));
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            out_unused,
+            out_unused_count as usize,
+        )));
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_splitbindingarray_alloc(
     in_spv: *const u32,
@@ -212,6 +677,178 @@ pub unsafe extern "C" fn spirv_webgpu_transform_splitbindingarray_free(out_spv:
     unsafe { drop(Box::from_raw(out_spv)) }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_uniformlayoutpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+    out_relayouts: *mut *const SpvTransformUniformRelayout,
+    out_relayout_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    let mut report = Default::default();
+    match uniformlayoutpatch(in_spv, &mut report) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+
+            (*out_relayouts, *out_relayout_count) = leak_uniform_relayouts(report.relaid_out);
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+            *out_relayouts = ptr::null();
+            *out_relayout_count = 0;
+        },
+    }
+}
+
+fn leak_uniform_relayouts(
+    relaid_out: Vec<UniformRelayout>,
+) -> (*const SpvTransformUniformRelayout, u32) {
+    let relayouts = relaid_out
+        .into_iter()
+        .map(|relayout| {
+            let copies = relayout
+                .copies
+                .into_iter()
+                .map(|copy| SpvTransformUniformCopy {
+                    original_offset: copy.original_offset,
+                    offset: copy.offset,
+                    size: copy.size,
+                })
+                .collect::<Vec<_>>();
+            SpvTransformUniformRelayout {
+                set: relayout.set,
+                binding: relayout.binding,
+                size: relayout.size,
+                copy_count: copies.len() as u32,
+                copies: Box::leak(copies.into_boxed_slice()).as_ptr(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let count = relayouts.len() as u32;
+    (Box::leak(relayouts.into_boxed_slice()).as_ptr(), count)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_uniformlayoutpatch_free(
+    out_spv: *mut u32,
+    out_relayouts: *mut SpvTransformUniformRelayout,
+    out_relayout_count: u32,
+) {
+    unsafe {
+        drop(Box::from_raw(out_spv));
+        let relayouts = Box::from_raw(ptr::slice_from_raw_parts_mut(
+            out_relayouts,
+            out_relayout_count as usize,
+        ));
+        for relayout in relayouts.iter() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                relayout.copies as *mut SpvTransformUniformCopy,
+                relayout.copy_count as usize,
+            )));
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_scalarlayoutpatch_alloc(
+    in_spv: *const u32,
+    in_count: u32,
+    out_spv: *mut *const u32,
+    out_count: *mut u32,
+    out_relayouts: *mut *const SpvTransformUniformRelayout,
+    out_relayout_count: *mut u32,
+    out_word_buffers: *mut *const SpvTransformWordStorageBuffer,
+    out_word_buffer_count: *mut u32,
+) {
+    let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+    let mut report = Default::default();
+    match scalarlayoutpatch(in_spv, &mut report) {
+        Ok(spv) => unsafe {
+            *out_count = spv.len() as u32;
+            let leaked = Box::leak(spv.into_boxed_slice());
+            *out_spv = leaked.as_ptr();
+
+            (*out_relayouts, *out_relayout_count) =
+                leak_uniform_relayouts(report.uniforms.relaid_out);
+
+            let word_buffers = report
+                .word_buffers
+                .into_iter()
+                .map(|buffer| SpvTransformWordStorageBuffer {
+                    set: buffer.set,
+                    binding: buffer.binding,
+                })
+                .collect::<Vec<_>>();
+            *out_word_buffer_count = word_buffers.len() as u32;
+            let leaked = Box::leak(word_buffers.into_boxed_slice());
+            *out_word_buffers = leaked.as_ptr();
+        },
+        Err(_) => unsafe {
+            *out_spv = ptr::null();
+            *out_count = 0;
+            *out_relayouts = ptr::null();
+            *out_relayout_count = 0;
+            *out_word_buffers = ptr::null();
+            *out_word_buffer_count = 0;
+        },
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_scalarlayoutpatch_free(
+    out_spv: *mut u32,
+    out_relayouts: *mut SpvTransformUniformRelayout,
+    out_relayout_count: u32,
+    out_word_buffers: *mut SpvTransformWordStorageBuffer,
+    out_word_buffer_count: u32,
+) {
+    unsafe {
+        spirv_webgpu_transform_uniformlayoutpatch_free(out_spv, out_relayouts, out_relayout_count);
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            out_word_buffers,
+            out_word_buffer_count as usize,
+        )));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_repack_uniform(
+    relayout: *const SpvTransformUniformRelayout,
+    in_data: *const u8,
+    in_size: u32,
+    out_data: *mut u8,
+) -> u8 {
+    let relayout = unsafe { &*relayout };
+    let copies = unsafe { slice::from_raw_parts(relayout.copies, relayout.copy_count as usize) };
+    let in_data = unsafe { slice::from_raw_parts(in_data, in_size as usize) };
+    let uniform_relayout = UniformRelayout {
+        set: relayout.set,
+        binding: relayout.binding,
+        size: relayout.size,
+        copies: copies
+            .iter()
+            .map(|copy| UniformCopy {
+                original_offset: copy.original_offset,
+                offset: copy.offset,
+                size: copy.size,
+            })
+            .collect(),
+    };
+    match repack_uniform(&uniform_relayout, in_data) {
+        Ok(data) => {
+            let out_data = unsafe { slice::from_raw_parts_mut(out_data, data.len()) };
+            out_data.copy_from_slice(&data);
+            C_TRUE
+        }
+        Err(_) => C_FALSE,
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn spirv_webgpu_transform_mirrorpatch_alloc(
     in_left_spv: *const u32,
@@ -268,3 +905,148 @@ pub unsafe extern "C" fn spirv_webgpu_transform_mirrorpatch_free(
         drop(Box::from_raw(out_right_spv));
     }
 }
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_mirrorpatch_many_alloc(
+    in_spvs: *const *const u32,
+    in_counts: *const u32,
+    corrections: *mut SpvTransformCorrectionMap,
+    module_count: u32,
+    out_spvs: *mut *const u32,
+    out_counts: *mut u32,
+) {
+    let module_count = module_count as usize;
+    let in_spvs = unsafe { slice::from_raw_parts(in_spvs, module_count) };
+    let in_counts = unsafe { slice::from_raw_parts(in_counts, module_count) };
+    let out_spvs = unsafe { slice::from_raw_parts_mut(out_spvs, module_count) };
+    let out_counts = unsafe { slice::from_raw_parts_mut(out_counts, module_count) };
+
+    let in_spvs = in_spvs
+        .iter()
+        .zip(in_counts.iter())
+        .map(|(&in_spv, &in_count)| unsafe { slice::from_raw_parts(in_spv, in_count as usize) })
+        .collect::<Vec<_>>();
+    let mut modules = in_spvs
+        .iter()
+        .enumerate()
+        .map(|(idx, &in_spv)| {
+            let correction_map =
+                unsafe { cast_correction_map_or_default_alloc(corrections.add(idx)) };
+            (in_spv, correction_map)
+        })
+        .collect::<Vec<_>>();
+
+    match mirrorpatch_many(&mut modules) {
+        Ok(outputs) => {
+            for (idx, output) in outputs.into_iter().enumerate() {
+                // Unchanged modules are copied so that there are no null outputs.
+                let spv = output.unwrap_or_else(|| in_spvs[idx].to_vec());
+                out_counts[idx] = spv.len() as u32;
+                out_spvs[idx] = Box::leak(spv.into_boxed_slice()).as_ptr();
+            }
+        }
+        Err(_) => {
+            out_spvs.fill(ptr::null());
+            out_counts.fill(0);
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_mirrorpatch_many_free(
+    out_spvs: *mut *mut u32,
+    out_counts: *const u32,
+    module_count: u32,
+) {
+    let out_spvs = unsafe { slice::from_raw_parts(out_spvs, module_count as usize) };
+    let out_counts = unsafe { slice::from_raw_parts(out_counts, module_count as usize) };
+    for (&out_spv, &out_count) in out_spvs.iter().zip(out_counts.iter()) {
+        if !out_spv.is_null() {
+            unsafe {
+                drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                    out_spv,
+                    out_count as usize,
+                )))
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_mirrorpatch_conflicts_alloc(
+    in_spvs: *const *const u32,
+    in_counts: *const u32,
+    corrections: *const SpvTransformCorrectionMap,
+    module_count: u32,
+    out_conflicts: *mut *const SpvTransformMirrorConflict,
+    out_conflict_count: *mut u32,
+) {
+    let module_count = module_count as usize;
+    let in_spvs = unsafe { slice::from_raw_parts(in_spvs, module_count) };
+    let in_counts = unsafe { slice::from_raw_parts(in_counts, module_count) };
+    let corrections = unsafe { slice::from_raw_parts(corrections, module_count) };
+
+    let empty_map = CorrectionMap::default();
+    let modules = in_spvs
+        .iter()
+        .zip(in_counts.iter())
+        .zip(corrections.iter())
+        .map(|((&in_spv, &in_count), &correction_map)| {
+            let in_spv = unsafe { slice::from_raw_parts(in_spv, in_count as usize) };
+            let correction_map = if correction_map.is_null() {
+                &empty_map
+            } else {
+                unsafe { &*cast_correction_map(correction_map) }
+            };
+            (in_spv, correction_map)
+        })
+        .collect::<Vec<_>>();
+
+    let conflicts = mirrorpatch_conflicts(&modules)
+        .into_iter()
+        .map(|conflict| SpvTransformMirrorConflict {
+            set: conflict.set,
+            binding: conflict.binding,
+            correction_type: match conflict.correction_type {
+                CorrectionType::SplitCombined => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeSplitCombined
+                }
+                CorrectionType::SplitDrefRegular => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeSplitDrefRegular
+                }
+                CorrectionType::SplitDrefComparison => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeSplitDrefComparison
+                }
+                CorrectionType::ConvertStorageCube => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeConvertStorageCube
+                }
+                CorrectionType::SplitBindingArray => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeSplitBindingArray
+                }
+                CorrectionType::ConvertTexelBuffer => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeConvertTexelBuffer
+                }
+                CorrectionType::ConvertSubpassInput => {
+                    TransformCorrectionType::SpirvWebgpuTransformCorrectionTypeConvertSubpassInput
+                }
+            },
+        })
+        .collect::<Vec<_>>();
+    unsafe {
+        *out_conflict_count = conflicts.len() as u32;
+        *out_conflicts = Box::leak(conflicts.into_boxed_slice()).as_ptr();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn spirv_webgpu_transform_mirrorpatch_conflicts_free(
+    out_conflicts: *mut SpvTransformMirrorConflict,
+    out_conflict_count: u32,
+) {
+    unsafe {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            out_conflicts,
+            out_conflict_count as usize,
+        )));
+    }
+}
@@ -15,11 +15,25 @@ fn main() {
     })() else {
         eprintln!(
             "Usage: spv_webgpu_transform <MODE> [FLAGS] <input.spv> <output.spv>
-Modes: combimg|dref|isnanisinf|storagecube|pruneunuseddref|immediates|bindingarray
+Modes: combimg|dref|isnanisinf|extinst|boolblock|widenstorage|storagecube|texelbuffer|subpassinput|specconstant|splitentrypoints|pruneunused|pruneunuseddref|immediates|bindingarray|uniformlayout|scalarlayout
 Flags: 
     --immediates-absolute <N>
     --immediates-max-up-to <N>
-    --immediates-max-plus-one-up-to <N>",
+    --immediates-max-plus-one-up-to <N>
+    --immediates-binding <N>
+    --immediates-storage
+    --immediates-pad-matcx2
+    --isnanisinf-function
+    --isnanisinf-inline
+    --isnanisinf-comparison
+    --texelbuffer-r32f
+    --texelbuffer-rgba32f
+    --texelbuffer-rgba8
+    --texelbuffer-r32ui
+    --texelbuffer-format <SET>.<BINDING>=<r32f|rgba32f|rgba8|r32ui>
+    --specconstant-overridable
+    --specconstant-value <SPEC_ID>=<VALUE>
+    --pruneunuseddref-report",
         );
         process::exit(1);
     };
@@ -30,6 +44,18 @@ Flags:
 
     let spv = spirv_webgpu_transform::u8_slice_to_u32_vec(&spv_bytes);
 
+    // Each entry point is written next to `output.spv` as `output.<NAME>.spv`
+    if mode == "splitentrypoints" {
+        let output_path = std::path::Path::new(output_path);
+        for module in spirv_webgpu_transform::splitentrypoints(&spv).unwrap() {
+            let module_path = output_path.with_extension(format!("{}.spv", module.name));
+            eprintln!("Writing {} to {}", module.name, module_path.display());
+            let module_bytes = spirv_webgpu_transform::u32_slice_to_u8_vec(&module.spv);
+            fs::write(module_path, module_bytes).unwrap();
+        }
+        return;
+    }
+
     let mut out_correction_map = Default::default();
 
     let out_spv = match mode.as_str() {
@@ -37,11 +63,63 @@ Flags:
             spirv_webgpu_transform::combimgsampsplitter(&spv, &mut out_correction_map).unwrap()
         }
         "dref" => spirv_webgpu_transform::drefsplitter(&spv, &mut out_correction_map).unwrap(),
-        "isnanisinf" => spirv_webgpu_transform::isnanisinfpatch(&spv).unwrap(),
+        "isnanisinf" => {
+            let mode = parse_isnanisinf_mode(&options);
+            spirv_webgpu_transform::isnanisinfpatch(&spv, mode).unwrap()
+        }
+        "extinst" => {
+            let mut report = Default::default();
+            let out_spv =
+                spirv_webgpu_transform::extinstpatch_with_report(&spv, &mut report).unwrap();
+            println!("Centroid input locations: {:?}", report.centroid_locations);
+            out_spv
+        }
+        "boolblock" => spirv_webgpu_transform::boolblockpatch(&spv).unwrap(),
+        "widenstorage" => spirv_webgpu_transform::widenstoragepatch(&spv).unwrap(),
         "storagecube" => {
             spirv_webgpu_transform::storagecubepatch(&spv, &mut out_correction_map).unwrap()
         }
-        "pruneunuseddref" => spirv_webgpu_transform::pruneunuseddref(&spv).unwrap(),
+        "texelbuffer" => {
+            let (formats, default_format) = parse_texelbuffer_opts(&options);
+            spirv_webgpu_transform::texelbufferpatch(
+                &spv,
+                &formats,
+                default_format,
+                &mut out_correction_map,
+            )
+            .unwrap()
+        }
+        "subpassinput" => {
+            spirv_webgpu_transform::subpassinputpatch(&spv, &mut out_correction_map).unwrap()
+        }
+        "specconstant" => {
+            let (values, mode) = parse_specconstant_opts(&options);
+            let mut report = Default::default();
+            let out_spv =
+                spirv_webgpu_transform::specconstantpatch(&spv, &values, mode, &mut report)
+                    .unwrap();
+            println!("Baked spec ids: {:?}", report.baked);
+            println!("Overridable spec ids: {:?}", report.overridable);
+            out_spv
+        }
+        "pruneunused" => spirv_webgpu_transform::pruneunused(&spv).unwrap(),
+        "pruneunuseddref" => {
+            let mode = if get_opt(&options, "--pruneunuseddref-report").is_some() {
+                spirv_webgpu_transform::PruneUnusedDrefMode::Report
+            } else {
+                spirv_webgpu_transform::PruneUnusedDrefMode::Prune
+            };
+            let mut report = Default::default();
+            let out_spv =
+                spirv_webgpu_transform::pruneunuseddref_with_mode(&spv, mode, &mut report).unwrap();
+            for resource in report.unused {
+                println!(
+                    "Unused set {} binding {}: {:?}",
+                    resource.set, resource.binding, resource.kind
+                );
+            }
+            out_spv
+        }
         "immediates" => {
             parse_opts(&options, &mut out_correction_map);
             spirv_webgpu_transform::immediatespatch(&spv, &mut out_correction_map).unwrap()
@@ -49,6 +127,43 @@ Flags:
         "bindingarray" => {
             spirv_webgpu_transform::splitbindingarray(&spv, &mut out_correction_map).unwrap()
         }
+        "uniformlayout" => {
+            let mut report = Default::default();
+            let out_spv = spirv_webgpu_transform::uniformlayoutpatch(&spv, &mut report).unwrap();
+            for relayout in report.relaid_out {
+                println!(
+                    "Relaid out set {} binding {}: {} bytes",
+                    relayout.set, relayout.binding, relayout.size
+                );
+                for copy in relayout.copies {
+                    println!(
+                        "\tOriginal offset {} -> offset {}, size {}",
+                        copy.original_offset, copy.offset, copy.size
+                    );
+                }
+            }
+            out_spv
+        }
+        "scalarlayout" => {
+            let mut report = spirv_webgpu_transform::ScalarLayoutReport::default();
+            let out_spv = spirv_webgpu_transform::scalarlayoutpatch(&spv, &mut report).unwrap();
+            for buffer in report.word_buffers {
+                println!("Word buffer set {} binding {}", buffer.set, buffer.binding);
+            }
+            for relayout in report.uniforms.relaid_out {
+                println!(
+                    "Relaid out set {} binding {}: {} bytes",
+                    relayout.set, relayout.binding, relayout.size
+                );
+                for copy in relayout.copies {
+                    println!(
+                        "\tOriginal offset {} -> offset {}, size {}",
+                        copy.original_offset, copy.offset, copy.size
+                    );
+                }
+            }
+            out_spv
+        }
         mode => {
             eprintln!("unknown mode {:?}", mode);
             process::exit(1)
@@ -64,6 +179,27 @@ Flags:
     if let Some(immediates_set) = out_correction_map.immediates_set {
         println!("Immediates set: {}", immediates_set);
     }
+    if let Some(immediates_buffer_kind) = out_correction_map.immediates_buffer_kind {
+        println!("Immediates buffer kind: {:?}", immediates_buffer_kind);
+    }
+    for buffer in out_correction_map.immediates_buffers.iter() {
+        println!(
+            "Immediates set {} binding {}: {} bytes",
+            buffer.set, buffer.binding, buffer.size
+        );
+        for entry_point in buffer.entry_points.iter() {
+            println!(
+                "\tUsed by {} (execution model {})",
+                entry_point.name, entry_point.execution_model
+            );
+        }
+        for member in buffer.members.iter() {
+            println!(
+                "\tPush constant offset {} -> offset {}, size {}",
+                member.push_constant_offset, member.offset, member.size
+            );
+        }
+    }
 
     // Remember to sort your hash maps!
     if let Some(sets) = out_correction_map.sets {
@@ -78,6 +214,9 @@ Flags:
             bindings.sort_by_key(|(k, _)| **k);
             for (binding_num, binding) in bindings {
                 println!("\tBinding {} <- {:?}", binding_num, binding.corrections);
+                if let Some(input_attachment_index) = binding.input_attachment_index {
+                    println!("\t\tInput attachment index: {}", input_attachment_index);
+                }
             }
         }
     } else {
@@ -119,4 +258,92 @@ fn parse_opts(options: &[&String], correction_map: &mut spirv_webgpu_transform::
         correction_map.immediates_set_mode =
             Some(spirv_webgpu_transform::ImmediatesSetMode::MaxPlusOneUpTo);
     }
+    if let Some(Some(n)) = get_opt(options, "--immediates-binding")
+        && let Ok(n) = n.parse::<u32>()
+    {
+        correction_map.immediates_binding = Some(n);
+    }
+    if get_opt(options, "--immediates-storage").is_some() {
+        correction_map.immediates_buffer_kind =
+            Some(spirv_webgpu_transform::ImmediatesBufferKind::ReadOnlyStorage);
+    }
+    if get_opt(options, "--immediates-pad-matcx2").is_some() {
+        correction_map.pad_matcx2 = true;
+    }
+}
+
+fn parse_isnanisinf_mode(options: &[&String]) -> spirv_webgpu_transform::IsNanIsInfMode {
+    if get_opt(options, "--isnanisinf-comparison").is_some() {
+        spirv_webgpu_transform::IsNanIsInfMode::Comparison
+    } else if get_opt(options, "--isnanisinf-inline").is_some() {
+        spirv_webgpu_transform::IsNanIsInfMode::Inline
+    } else {
+        spirv_webgpu_transform::IsNanIsInfMode::Function
+    }
+}
+
+fn parse_texelbuffer_opts(
+    options: &[&String],
+) -> (
+    std::collections::HashMap<(u32, u32), spirv_webgpu_transform::TexelBufferFormat>,
+    spirv_webgpu_transform::TexelBufferFormat,
+) {
+    let parse_format = |format: &str| match format {
+        "r32f" => Some(spirv_webgpu_transform::TexelBufferFormat::R32Float),
+        "rgba32f" => Some(spirv_webgpu_transform::TexelBufferFormat::Rgba32Float),
+        "rgba8" => Some(spirv_webgpu_transform::TexelBufferFormat::Rgba8Unorm),
+        "r32ui" => Some(spirv_webgpu_transform::TexelBufferFormat::R32Uint),
+        _ => None,
+    };
+    // `--texelbuffer-format 0.3=r32f`
+    let formats = options
+        .windows(2)
+        .filter(|pair| pair[0] == "--texelbuffer-format")
+        .filter_map(|pair| {
+            let (set_binding, format) = pair[1].split_once('=')?;
+            let (set, binding) = set_binding.split_once('.')?;
+            Some((
+                (set.parse::<u32>().ok()?, binding.parse::<u32>().ok()?),
+                parse_format(format)?,
+            ))
+        })
+        .collect();
+    let default_format = if get_opt(options, "--texelbuffer-r32f").is_some() {
+        spirv_webgpu_transform::TexelBufferFormat::R32Float
+    } else if get_opt(options, "--texelbuffer-rgba8").is_some() {
+        spirv_webgpu_transform::TexelBufferFormat::Rgba8Unorm
+    } else if get_opt(options, "--texelbuffer-r32ui").is_some() {
+        spirv_webgpu_transform::TexelBufferFormat::R32Uint
+    } else {
+        spirv_webgpu_transform::TexelBufferFormat::Rgba32Float
+    };
+    (formats, default_format)
+}
+
+fn parse_specconstant_opts(
+    options: &[&String],
+) -> (
+    std::collections::HashMap<u32, u64>,
+    spirv_webgpu_transform::SpecConstantMode,
+) {
+    // Values are either integers or floats, `--specconstant-value 3=0.5`
+    let values = options
+        .windows(2)
+        .filter(|pair| pair[0] == "--specconstant-value")
+        .filter_map(|pair| {
+            let (spec_id, value) = pair[1].split_once('=')?;
+            let value = value
+                .parse::<u64>()
+                .ok()
+                .or_else(|| value.parse::<i64>().ok().map(|v| v as u64))
+                .or_else(|| value.parse::<f32>().ok().map(|v| v.to_bits() as u64))?;
+            Some((spec_id.parse::<u32>().ok()?, value))
+        })
+        .collect();
+    let mode = if get_opt(options, "--specconstant-overridable").is_some() {
+        spirv_webgpu_transform::SpecConstantMode::Overridable
+    } else {
+        spirv_webgpu_transform::SpecConstantMode::BakeAll
+    };
+    (values, mode)
 }
@@ -0,0 +1,714 @@
+use super::*;
+use crate::immediatespatch::{layout::*, type_registry::*};
+
+fn inc(ib: &mut u32) -> u32 {
+    *ib += 1;
+    *ib - 1
+}
+
+mod convert;
+
+use convert::*;
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Does not produce any side effects or corrections.
+///
+/// Fails when a struct containing bools is also used outside of `Uniform` and `StorageBuffer` blocks,
+/// or when an aggregate holding a runtime or specialization constant sized array is accessed as a whole.
+pub fn boolblockpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_runtime_array_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_constant_composite_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+    let mut op_access_chain_idxs = vec![];
+    let mut op_load_idxs = vec![];
+    let mut op_store_idxs = vec![];
+    let mut op_copy_memory_idxs = vec![];
+    let mut first_op_function_idx = None;
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        match instruction {
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => op_type_runtime_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE => op_constant_composite_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ACCESS_CHAIN | SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN => {
+                op_access_chain_idxs.push(spv_idx)
+            }
+            SPV_INSTRUCTION_OP_LOAD => op_load_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_STORE => op_store_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_COPY_MEMORY => op_copy_memory_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => {
+                first_op_function_idx.get_or_insert(spv_idx);
+            }
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    let Some(&bool_idx) = op_type_bool_idxs.first() else {
+        return Ok(in_spv.to_vec());
+    };
+    let bool_id = spv[bool_idx + 1];
+
+    let is_host_shareable = |storage_class| {
+        storage_class == SPV_STORAGE_CLASS_UNIFORM
+            || storage_class == SPV_STORAGE_CLASS_STORAGE_BUFFER
+    };
+
+    // 2. Find every type reachable from a block that contains a bool
+    let type_idxs = op_type_bool_idxs
+        .iter()
+        .chain(op_type_vector_idxs.iter())
+        .chain(op_type_array_idxs.iter())
+        .chain(op_type_runtime_array_idxs.iter())
+        .chain(op_type_struct_idxs.iter())
+        .map(|&idx| (spv[idx + 1], idx))
+        .collect::<HashMap<_, _>>();
+
+    fn contains_bool(spv: &[u32], type_idxs: &HashMap<u32, usize>, bool_id: u32, id: u32) -> bool {
+        let Some(&idx) = type_idxs.get(&id) else {
+            return false;
+        };
+        match loword(spv[idx]) {
+            SPV_INSTRUCTION_OP_TYPE_BOOL => true,
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => spv[idx + 2] == bool_id,
+            SPV_INSTRUCTION_OP_TYPE_ARRAY | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => {
+                contains_bool(spv, type_idxs, bool_id, spv[idx + 2])
+            }
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => (2..hiword(spv[idx]) as usize)
+                .any(|m_word| contains_bool(spv, type_idxs, bool_id, spv[idx + m_word])),
+            _ => false,
+        }
+    }
+
+    let mut affected_idxs = vec![];
+    let mut to_visit = op_type_pointer_idxs
+        .iter()
+        .filter_map(|&tp_idx| is_host_shareable(spv[tp_idx + 2]).then_some(spv[tp_idx + 3]))
+        .collect::<Vec<_>>();
+    while let Some(id) = to_visit.pop() {
+        if !contains_bool(&spv, &type_idxs, bool_id, id) {
+            continue;
+        }
+        let idx = type_idxs[&id];
+        if affected_idxs.contains(&idx) {
+            continue;
+        }
+        affected_idxs.push(idx);
+        match loword(spv[idx]) {
+            SPV_INSTRUCTION_OP_TYPE_VECTOR
+            | SPV_INSTRUCTION_OP_TYPE_ARRAY
+            | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => to_visit.push(spv[idx + 2]),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+                to_visit.extend((2..hiword(spv[idx]) as usize).map(|m_word| spv[idx + m_word]))
+            }
+            _ => {}
+        }
+    }
+
+    if affected_idxs.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    // Walk in order so that elements are retyped before their arrays
+    affected_idxs.sort();
+
+    // Retyped structs are modified in place, so they must not be shared with anything else
+    let is_affected_struct = |id| {
+        op_type_struct_idxs
+            .iter()
+            .any(|&idx| spv[idx + 1] == id && affected_idxs.contains(&idx))
+    };
+    if op_type_pointer_idxs
+        .iter()
+        .any(|&tp_idx| !is_host_shareable(spv[tp_idx + 2]) && is_affected_struct(spv[tp_idx + 3]))
+    {
+        return Err(());
+    }
+
+    // 3. Ensure `uint` and `uvecN` are declared before the first bool type
+    let mut bool_insert = InstructionInsert {
+        previous_spv_idx: bool_idx,
+        instruction: vec![],
+    };
+
+    let mut hoist_or_declare = |existing_idx: Option<usize>,
+                                instruction_bound: &mut u32,
+                                new_spv: &mut [u32],
+                                template: &[u32]| {
+        let id = match existing_idx {
+            Some(idx) if idx < bool_idx => return spv[idx + 1],
+            Some(idx) => {
+                for i in 0..hiword(spv[idx]) as usize {
+                    new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                }
+                spv[idx + 1]
+            }
+            None => inc(instruction_bound),
+        };
+        bool_insert.instruction.push(template[0]);
+        bool_insert.instruction.push(id);
+        bool_insert.instruction.extend_from_slice(&template[1..]);
+        id
+    };
+
+    let uint_id = hoist_or_declare(
+        op_type_int_idxs
+            .iter()
+            .copied()
+            .find(|&idx| spv[idx + 2] == 32 && spv[idx + 3] == SPV_SIGNEDNESS_UNSIGNED),
+        &mut instruction_bound,
+        &mut new_spv,
+        &[
+            encode_word(4, SPV_INSTRUCTION_OP_TYPE_INT),
+            32,
+            SPV_SIGNEDNESS_UNSIGNED,
+        ],
+    );
+
+    let mut uint_vectors = HashMap::new();
+    for &idx in &affected_idxs {
+        if loword(spv[idx]) != SPV_INSTRUCTION_OP_TYPE_VECTOR {
+            continue;
+        }
+        let count = spv[idx + 3];
+        uint_vectors.entry(count).or_insert_with(|| {
+            hoist_or_declare(
+                op_type_vector_idxs
+                    .iter()
+                    .copied()
+                    .find(|&v_idx| spv[v_idx + 2] == uint_id && spv[v_idx + 3] == count),
+                &mut instruction_bound,
+                &mut new_spv,
+                &[
+                    encode_word(4, SPV_INSTRUCTION_OP_TYPE_VECTOR),
+                    uint_id,
+                    count,
+                ],
+            )
+        });
+    }
+
+    instruction_inserts.push(bool_insert);
+
+    // 4. Retype bools, bool vectors, and arrays of them to their `u32` equivalents.
+    //    Structs are retyped in place, arrays are redeclared next to the originals.
+    let mut retyped = HashMap::new();
+    for &idx in &affected_idxs {
+        let instruction = loword(spv[idx]);
+        let id = spv[idx + 1];
+        let new_id = match instruction {
+            SPV_INSTRUCTION_OP_TYPE_BOOL => uint_id,
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => uint_vectors[&spv[idx + 3]],
+            SPV_INSTRUCTION_OP_TYPE_ARRAY | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => {
+                let element_id = retyped[&spv[idx + 2]];
+                if element_id == spv[idx + 2] {
+                    id
+                } else {
+                    let new_id = inc(&mut instruction_bound);
+                    let mut array = spv[idx..idx + hiword(spv[idx]) as usize].to_vec();
+                    array[1] = new_id;
+                    array[2] = element_id;
+                    instruction_inserts.push(InstructionInsert {
+                        previous_spv_idx: idx,
+                        instruction: array,
+                    });
+                    new_id
+                }
+            }
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+                for m_word in 2..hiword(spv[idx]) as usize {
+                    if let Some(&member_id) = retyped.get(&spv[idx + m_word]) {
+                        new_spv[idx + m_word] = member_id;
+                    }
+                }
+                id
+            }
+            _ => unreachable!(),
+        };
+        retyped.insert(id, new_id);
+    }
+
+    // Values keep their bools, so aggregates that were retyped in place get a bool twin for values.
+    // Redeclared arrays leave the original as that twin.
+    let mut logical = HashMap::new();
+    let mut logical_types = vec![];
+    for &idx in &affected_idxs {
+        let instruction = loword(spv[idx]);
+        let id = spv[idx + 1];
+        let is_runtime_array = |id| {
+            op_type_runtime_array_idxs
+                .iter()
+                .any(|&rta_idx| spv[rta_idx + 1] == id)
+        };
+        let logical_id = match instruction {
+            SPV_INSTRUCTION_OP_TYPE_BOOL | SPV_INSTRUCTION_OP_TYPE_VECTOR => id,
+            SPV_INSTRUCTION_OP_TYPE_ARRAY if retyped[&id] != id => id,
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => {
+                let Some(&element_id) = logical.get(&spv[idx + 2]) else {
+                    continue;
+                };
+                let new_id = inc(&mut instruction_bound);
+                logical_types.push(vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_TYPE_ARRAY),
+                    new_id,
+                    element_id,
+                    spv[idx + 3],
+                ]);
+                new_id
+            }
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+                let members = &spv[idx + 2..idx + hiword(spv[idx]) as usize];
+                if members.iter().any(|&member_id| {
+                    is_runtime_array(member_id)
+                        || (retyped.contains_key(&member_id) && !logical.contains_key(&member_id))
+                }) {
+                    continue;
+                }
+                let new_id = inc(&mut instruction_bound);
+                let mut logical_struct = vec![
+                    encode_word(2 + members.len() as u16, SPV_INSTRUCTION_OP_TYPE_STRUCT),
+                    new_id,
+                ];
+                logical_struct.extend(
+                    members
+                        .iter()
+                        .map(|member_id| logical.get(member_id).copied().unwrap_or(*member_id)),
+                );
+                logical_types.push(logical_struct);
+                new_id
+            }
+            _ => continue,
+        };
+        logical.insert(id, logical_id);
+    }
+
+    let array_lengths = affected_idxs
+        .iter()
+        .filter(|&&idx| loword(spv[idx]) == SPV_INSTRUCTION_OP_TYPE_ARRAY)
+        .filter_map(|&idx| {
+            op_constant_idxs.iter().find_map(|&c_idx| {
+                (spv[c_idx + 2] == spv[idx + 3]).then_some((spv[idx + 1], spv[c_idx + 3]))
+            })
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Array strides belong to the new arrays
+    for &d_idx in &op_decorate_idxs {
+        let target_id = spv[d_idx + 1];
+        let decoration = spv[d_idx + 2];
+        if decoration == SPV_DECORATION_ARRAY_STRIDE
+            && let Some(&new_id) = retyped.get(&target_id)
+        {
+            new_spv[d_idx + 1] = new_id;
+        }
+    }
+
+    // 5. Fill in any layout decorations that are missing, bools are laid out like `u32`
+    let type_registry = build_type_registry(BuildTypeRegistryIn {
+        spv: &spv,
+        op_type_bool_idxs: &op_type_bool_idxs,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_int_idxs: &op_type_int_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_type_matrix_idxs: &op_type_matrix_idxs,
+        op_type_array_idxs: &op_type_array_idxs,
+        op_type_struct_idxs: &op_type_struct_idxs,
+        op_constant_idxs: &op_constant_idxs,
+    });
+
+    let has_decoration = |target_id, decoration| {
+        op_decorate_idxs
+            .iter()
+            .any(|&d_idx| spv[d_idx + 1] == target_id && spv[d_idx + 2] == decoration)
+    };
+
+    let mut layout_decorations = vec![];
+    let mut laid_out = HashSet::new();
+    for &v_idx in &op_variable_idxs {
+        let storage_class = spv[v_idx + 3];
+        if !is_host_shareable(storage_class) {
+            continue;
+        }
+        let Some(block_struct_id) = op_type_pointer_idxs
+            .iter()
+            .find_map(|&tp_idx| (spv[tp_idx + 1] == spv[v_idx + 1]).then_some(spv[tp_idx + 3]))
+        else {
+            continue;
+        };
+        let Some(ty) = type_registry.get(&block_struct_id) else {
+            continue;
+        };
+        let rule = if storage_class == SPV_STORAGE_CLASS_UNIFORM
+            && has_decoration(block_struct_id, SPV_DECORATION_BLOCK)
+        {
+            LayoutRule::Std140
+        } else {
+            LayoutRule::Std430
+        };
+        fill_missing_layout(FillMissingLayoutIn {
+            spv: &spv,
+            ty,
+            rule,
+            retyped: &retyped,
+            op_decorate_idxs: &op_decorate_idxs,
+            op_member_decorate_idxs: &op_member_decorate_idxs,
+            laid_out: &mut laid_out,
+            decorations: &mut layout_decorations,
+        });
+    }
+
+    if !layout_decorations.is_empty() {
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: last_of_indices!(op_decorate_idxs, op_member_decorate_idxs)
+                .expect("Block has no OpDecorate (missing Block decoration?)"),
+            instruction: layout_decorations,
+        });
+    }
+
+    // 6. Retarget pointers
+    let mut retyped_pointers = HashMap::new();
+    for &tp_idx in &op_type_pointer_idxs {
+        let ptr_id = spv[tp_idx + 1];
+        let underlying_type_id = spv[tp_idx + 3];
+        if !is_host_shareable(spv[tp_idx + 2]) {
+            continue;
+        }
+        let Some(&new_underlying_type_id) = retyped.get(&underlying_type_id) else {
+            continue;
+        };
+        new_spv[tp_idx + 3] = new_underlying_type_id;
+        retyped_pointers.insert(ptr_id, underlying_type_id);
+    }
+
+    let pointer_types = op_variable_idxs
+        .iter()
+        .chain(op_access_chain_idxs.iter())
+        .map(|&idx| (spv[idx + 2], spv[idx + 1]))
+        .collect::<HashMap<_, _>>();
+    let retyped_type_of_pointer = |ptr_id| {
+        pointer_types
+            .get(&ptr_id)
+            .and_then(|pointer_type_id| retyped_pointers.get(pointer_type_id))
+            .copied()
+    };
+
+    // 7. Convert at every load, store, and copy between a retyped and a regular pointer
+    let header_position = last_of_indices!(
+        op_type_bool_idxs,
+        op_type_int_idxs,
+        op_type_float_idxs,
+        op_type_vector_idxs,
+        op_type_matrix_idxs,
+        op_type_array_idxs,
+        op_type_runtime_array_idxs,
+        op_type_struct_idxs,
+        op_type_pointer_idxs,
+        op_constant_idxs,
+        op_constant_composite_idxs
+    );
+    let mut header_insert = InstructionInsert {
+        previous_spv_idx: header_position.unwrap(),
+        instruction: vec![],
+    };
+
+    let mut accesses = op_load_idxs
+        .iter()
+        .map(|&idx| (idx, spv[idx + 3]))
+        .chain(op_store_idxs.iter().map(|&idx| (idx, spv[idx + 1])))
+        .filter_map(|(idx, ptr_id)| Some((idx, retyped_type_of_pointer(ptr_id)?, false)))
+        .collect::<Vec<_>>();
+    for &idx in &op_copy_memory_idxs {
+        match (
+            retyped_type_of_pointer(spv[idx + 1]),
+            retyped_type_of_pointer(spv[idx + 2]),
+        ) {
+            (Some(type_id), None) => accesses.push((idx, type_id, false)),
+            (None, Some(type_id)) => accesses.push((idx, type_id, true)),
+            _ => {}
+        }
+    }
+
+    let mut leaves = vec![];
+    for &(_, type_id, _) in &accesses {
+        bool_leaf_types(&spv, &type_idxs, &retyped, type_id, &mut leaves);
+    }
+
+    let mut uint_scalar_constants = HashMap::new();
+    let mut conversions = HashMap::new();
+    for bool_type_id in leaves {
+        let uint_type_id = retyped[&bool_type_id];
+        let mut scalar = |value| {
+            *uint_scalar_constants.entry(value).or_insert_with(|| {
+                op_constant_idxs
+                    .iter()
+                    .find_map(|&c_idx| {
+                        (spv[c_idx + 1] == uint_id && spv[c_idx + 3] == value)
+                            .then_some(spv[c_idx + 2])
+                    })
+                    .unwrap_or_else(|| {
+                        let new_id = inc(&mut instruction_bound);
+                        header_insert.instruction.append(&mut vec![
+                            encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                            uint_id,
+                            new_id,
+                            value,
+                        ]);
+                        new_id
+                    })
+            })
+        };
+        let (uint_0, uint_1) = (scalar(0), scalar(1));
+        if uint_type_id == uint_id {
+            conversions.insert(
+                bool_type_id,
+                BoolConversion {
+                    bool_id: bool_type_id,
+                    uint_id,
+                    uint_0,
+                    uint_1,
+                },
+            );
+            continue;
+        }
+
+        let count = *uint_vectors
+            .iter()
+            .find_map(|(count, &id)| (id == uint_type_id).then_some(count))
+            .unwrap();
+        let mut splat = |scalar_id| {
+            let new_id = inc(&mut instruction_bound);
+            header_insert.instruction.append(&mut vec![
+                encode_word(3 + count as u16, SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE),
+                uint_type_id,
+                new_id,
+            ]);
+            header_insert
+                .instruction
+                .append(&mut vec![scalar_id; count as usize]);
+            new_id
+        };
+        conversions.insert(
+            bool_type_id,
+            BoolConversion {
+                bool_id: bool_type_id,
+                uint_id: uint_type_id,
+                uint_0: splat(uint_0),
+                uint_1: splat(uint_1),
+            },
+        );
+    }
+
+    let convert_types = ConvertTypes {
+        spv: &spv,
+        type_idxs: &type_idxs,
+        array_lengths: &array_lengths,
+        retyped: &retyped,
+        logical: &logical,
+        conversions: &conversions,
+    };
+
+    for &(idx, type_id, to_bool) in &accesses {
+        let word_count = hiword(spv[idx]) as usize;
+        let instruction = &spv[idx..idx + word_count];
+        let converted = match loword(spv[idx]) {
+            SPV_INSTRUCTION_OP_LOAD => {
+                bool_load_spv(&mut instruction_bound, &convert_types, type_id, instruction)?
+            }
+            SPV_INSTRUCTION_OP_STORE => {
+                bool_store_spv(&mut instruction_bound, &convert_types, type_id, instruction)?
+            }
+            _ => bool_copy_memory_spv(
+                &mut instruction_bound,
+                &convert_types,
+                type_id,
+                instruction,
+                to_bool,
+            )?,
+        };
+
+        for i in 0..word_count {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: converted,
+        });
+    }
+
+    let mut used_logical_ids = accesses
+        .iter()
+        .filter_map(|(_, type_id, _)| logical.get(type_id).copied())
+        .collect::<HashSet<_>>();
+
+    // Values that were typed as a struct retyped in place now use its bool twin
+    let mut spv_idx = first_op_function_idx.unwrap_or(spv.len());
+    while spv_idx < spv.len() {
+        if matches!(
+            loword(spv[spv_idx]),
+            SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT
+                | SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT
+                | SPV_INSTRUCTION_OP_COMPOSITE_INSERT
+                | SPV_INSTRUCTION_OP_COPY_OBJECT
+                | SPV_INSTRUCTION_OP_SELECT
+                | SPV_INSTRUCTION_OP_PHI
+        ) && let Some(&logical_id) = logical.get(&spv[spv_idx + 1])
+        {
+            new_spv[spv_idx + 1] = logical_id;
+            used_logical_ids.insert(logical_id);
+        }
+        spv_idx += hiword(spv[spv_idx]) as usize;
+    }
+
+    // Only declare the twins that are used, members are declared before their parents
+    for logical_type in logical_types.iter().rev() {
+        if used_logical_ids.contains(&logical_type[1]) {
+            used_logical_ids.extend(logical_type[2..].iter().copied());
+        }
+    }
+    let logical_types = logical_types
+        .into_iter()
+        .filter(|logical_type| used_logical_ids.contains(&logical_type[1]))
+        .flatten()
+        .collect::<Vec<_>>();
+    if !logical_types.is_empty() {
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: *affected_idxs.last().unwrap(),
+            instruction: logical_types,
+        });
+    }
+
+    // 8. Insert New Instructions
+    instruction_inserts.push(header_insert);
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 9. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 10. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
+
+struct FillMissingLayoutIn<'a> {
+    spv: &'a [u32],
+    ty: &'a Type,
+    rule: LayoutRule,
+    retyped: &'a HashMap<u32, u32>,
+    op_decorate_idxs: &'a [usize],
+    op_member_decorate_idxs: &'a [usize],
+    laid_out: &'a mut HashSet<u32>,
+    decorations: &'a mut Vec<u32>,
+}
+
+// Add `Offset` and `ArrayStride` decorations to retyped types that lack them.
+fn fill_missing_layout(fl_in: FillMissingLayoutIn) {
+    let FillMissingLayoutIn {
+        spv,
+        ty,
+        rule,
+        retyped,
+        op_decorate_idxs,
+        op_member_decorate_idxs,
+        laid_out,
+        decorations,
+    } = fl_in;
+
+    if !retyped.contains_key(&ty.id) || !laid_out.insert(ty.id) {
+        return;
+    }
+
+    let children = match &ty.kind {
+        TypeKind::Struct { members } => {
+            let layout = layout_struct(members, rule);
+            for (member, &offset) in layout.member_offsets.iter().enumerate() {
+                let has_offset = op_member_decorate_idxs.iter().any(|&md_idx| {
+                    spv[md_idx + 1] == ty.id
+                        && spv[md_idx + 2] == member as u32
+                        && spv[md_idx + 3] == SPV_DECORATION_OFFSET
+                });
+                if !has_offset {
+                    decorations.append(&mut vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                        ty.id,
+                        member as u32,
+                        SPV_DECORATION_OFFSET,
+                        offset,
+                    ]);
+                }
+            }
+            members.iter().collect::<Vec<_>>()
+        }
+        TypeKind::Array { element, .. } => {
+            let has_stride = op_decorate_idxs.iter().any(|&d_idx| {
+                spv[d_idx + 1] == ty.id && spv[d_idx + 2] == SPV_DECORATION_ARRAY_STRIDE
+            });
+            if !has_stride {
+                decorations.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                    retyped[&ty.id],
+                    SPV_DECORATION_ARRAY_STRIDE,
+                    array_stride(&element.kind, rule),
+                ]);
+            }
+            vec![element.as_ref()]
+        }
+        _ => vec![],
+    };
+
+    for child in children {
+        fill_missing_layout(FillMissingLayoutIn {
+            spv,
+            ty: child,
+            rule,
+            retyped,
+            op_decorate_idxs,
+            op_member_decorate_idxs,
+            laid_out,
+            decorations,
+        });
+    }
+}
@@ -0,0 +1,244 @@
+use super::*;
+
+/// Every id is a vector when the converted value is a vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct BoolConversion {
+    pub bool_id: u32,
+    pub uint_id: u32,
+    pub uint_0: u32,
+    pub uint_1: u32,
+}
+
+/// Everything needed to convert a value of a retyped type, member by member.
+pub(super) struct ConvertTypes<'a> {
+    pub spv: &'a [u32],
+    pub type_idxs: &'a HashMap<u32, usize>,
+    pub array_lengths: &'a HashMap<u32, u32>,
+    /// Original type to the type that is now in memory.
+    pub retyped: &'a HashMap<u32, u32>,
+    /// Original type to the type that values hold, bools included.
+    pub logical: &'a HashMap<u32, u32>,
+    /// Bool or bool vector type to its conversion.
+    pub conversions: &'a HashMap<u32, BoolConversion>,
+}
+
+// Every bool and bool vector type that converting a value of `type_id` goes through.
+pub(super) fn bool_leaf_types(
+    spv: &[u32],
+    type_idxs: &HashMap<u32, usize>,
+    retyped: &HashMap<u32, u32>,
+    type_id: u32,
+    leaves: &mut Vec<u32>,
+) {
+    if !retyped.contains_key(&type_id) {
+        return;
+    }
+    let idx = type_idxs[&type_id];
+    match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_TYPE_BOOL | SPV_INSTRUCTION_OP_TYPE_VECTOR
+            if !leaves.contains(&type_id) =>
+        {
+            leaves.push(type_id)
+        }
+        SPV_INSTRUCTION_OP_TYPE_ARRAY | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => {
+            bool_leaf_types(spv, type_idxs, retyped, spv[idx + 2], leaves)
+        }
+        SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+            for m_word in 2..hiword(spv[idx]) as usize {
+                bool_leaf_types(spv, type_idxs, retyped, spv[idx + m_word], leaves)
+            }
+        }
+        _ => {}
+    }
+}
+
+// Convert `value` between its `u32` and bool representations, member by member for aggregates.
+// The converted value is written to `result_id`.
+fn convert_spv(
+    ib: &mut u32,
+    ct: &ConvertTypes,
+    type_id: u32,
+    value: u32,
+    result_id: u32,
+    to_bool: bool,
+) -> Result<Vec<u32>, ()> {
+    let ConvertTypes {
+        spv,
+        type_idxs,
+        array_lengths,
+        retyped,
+        logical,
+        conversions,
+    } = ct;
+    let idx = type_idxs[&type_id];
+
+    let members = match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_TYPE_BOOL | SPV_INSTRUCTION_OP_TYPE_VECTOR => {
+            let BoolConversion {
+                bool_id,
+                uint_id,
+                uint_0,
+                uint_1,
+            } = conversions[&type_id];
+            #[rustfmt::skip]
+            return Ok(if to_bool {
+                vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_I_NOT_EQUAL),
+                        bool_id, result_id, value, uint_0,
+                ]
+            } else {
+                vec![
+                    encode_word(6, SPV_INSTRUCTION_OP_SELECT),
+                        uint_id, result_id, value, uint_1, uint_0,
+                ]
+            });
+        }
+        SPV_INSTRUCTION_OP_TYPE_ARRAY => {
+            vec![spv[idx + 2]; *array_lengths.get(&type_id).ok_or(())? as usize]
+        }
+        SPV_INSTRUCTION_OP_TYPE_STRUCT => spv[idx + 2..idx + hiword(spv[idx]) as usize].to_vec(),
+        _ => return Err(()),
+    };
+
+    //
+    //  %result = OpLoad %Struct %ptr
+    //
+    //   %word = OpLoad %Struct_uint %ptr
+    //      %0 = OpCompositeExtract %float %word 0
+    //      %1 = OpCompositeExtract %uint %word 1
+    //   %bool = OpINotEqual %bool %1 %uint_0
+    // %result = OpCompositeConstruct %Struct_bool %0 %bool
+
+    let (from, into) = if to_bool {
+        (retyped, logical)
+    } else {
+        (logical, retyped)
+    };
+    let type_of = |types: &HashMap<u32, u32>, id| types.get(&id).copied().unwrap_or(id);
+
+    let mut converted = vec![];
+    let mut constituents = vec![];
+    for (member_idx, &member_id) in members.iter().enumerate() {
+        let extracted = inc(ib);
+        #[rustfmt::skip]
+        converted.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                type_of(from, member_id), extracted, value, member_idx as u32,
+        ]);
+        if retyped.contains_key(&member_id) {
+            let member_result_id = inc(ib);
+            converted.append(&mut convert_spv(
+                ib,
+                ct,
+                member_id,
+                extracted,
+                member_result_id,
+                to_bool,
+            )?);
+            constituents.push(member_result_id);
+        } else {
+            constituents.push(extracted);
+        }
+    }
+
+    let into_type_id = into.get(&type_id).copied().ok_or(())?;
+    converted.append(&mut vec![
+        encode_word(
+            3 + constituents.len() as u16,
+            SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT,
+        ),
+        into_type_id,
+        result_id,
+    ]);
+    converted.append(&mut constituents);
+    Ok(converted)
+}
+
+// Reroute an `OpLoad` of a retyped type through a `u32` temporary.
+// Memory operands of the original load are kept.
+pub(super) fn bool_load_spv(
+    ib: &mut u32,
+    ct: &ConvertTypes,
+    type_id: u32,
+    load: &[u32],
+) -> Result<Vec<u32>, ()> {
+    //
+    //     %1 = OpLoad %bool %ptr
+    //
+    //  %word = OpLoad %uint %ptr
+    //     %1 = OpINotEqual %bool %word %uint_0
+
+    let result_id = load[2];
+    let word = inc(ib);
+
+    let mut spv = load.to_vec();
+    spv[1] = ct.retyped[&type_id];
+    spv[2] = word;
+
+    spv.append(&mut convert_spv(ib, ct, type_id, word, result_id, true)?);
+    Ok(spv)
+}
+
+// Reroute an `OpStore` to a retyped type through a `u32` temporary.
+// Memory operands of the original store are kept.
+pub(super) fn bool_store_spv(
+    ib: &mut u32,
+    ct: &ConvertTypes,
+    type_id: u32,
+    store: &[u32],
+) -> Result<Vec<u32>, ()> {
+    //
+    //          OpStore %ptr %value
+    //
+    //  %word = OpSelect %uint %value %uint_1 %uint_0
+    //          OpStore %ptr %word
+
+    let value = store[2];
+    let word = inc(ib);
+
+    let mut spv = convert_spv(ib, ct, type_id, value, word, false)?;
+    let mut store = store.to_vec();
+    store[2] = word;
+    spv.append(&mut store);
+    Ok(spv)
+}
+
+// Split an `OpCopyMemory` between a retyped and a regular pointer into a converting load and store.
+// Memory operands are dropped.
+pub(super) fn bool_copy_memory_spv(
+    ib: &mut u32,
+    ct: &ConvertTypes,
+    type_id: u32,
+    copy: &[u32],
+    to_bool: bool,
+) -> Result<Vec<u32>, ()> {
+    //
+    //          OpCopyMemory %bool_ptr %uint_ptr
+    //
+    //  %word = OpLoad %uint %uint_ptr
+    //  %bool = OpINotEqual %bool %word %uint_0
+    //          OpStore %bool_ptr %bool
+
+    let (target, source) = (copy[1], copy[2]);
+    let loaded = inc(ib);
+    let converted = inc(ib);
+    let loaded_type_id = if to_bool {
+        ct.retyped[&type_id]
+    } else {
+        *ct.logical.get(&type_id).ok_or(())?
+    };
+
+    #[rustfmt::skip]
+    let mut spv = vec![
+        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+            loaded_type_id, loaded, source,
+    ];
+    let mut conversion = convert_spv(ib, ct, type_id, loaded, converted, to_bool)?;
+    spv.append(&mut conversion);
+    #[rustfmt::skip]
+    spv.append(&mut vec![
+        encode_word(3, SPV_INSTRUCTION_OP_STORE),
+            target, converted,
+    ]);
+    Ok(spv)
+}
@@ -18,12 +18,19 @@ pub enum CorrectionType {
     /// A binding array has been split into new variables. Insert the same resource again.
     /// For an `N` sized array, expect `N-1` entries.
     SplitBindingArray,
+    /// A texel buffer has been converted into a storage buffer, bind a buffer instead of a buffer view.
+    ConvertTexelBuffer,
+    /// A subpass input has been converted into a sampled texture 2D, bind the input attachment's
+    /// texture view. See [`CorrectionBinding::input_attachment_index`] for which attachment it was.
+    ConvertSubpassInput,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CorrectionBinding {
     /// In order, what additional bindings have been appended to this one.
     pub corrections: Vec<CorrectionType>,
+    /// The `input_attachment_index` of a [`CorrectionType::ConvertSubpassInput`] binding.
+    pub input_attachment_index: Option<u32>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -46,6 +53,16 @@ pub enum ImmediatesSetMode {
     MaxPlusOneUpTo,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImmediatesBufferKind {
+    /// Replace push constant blocks with uniform buffers, relaid out as std140.
+    #[default]
+    Uniform,
+    /// Replace push constant blocks with read-only storage buffers, keeping the std430 layout.
+    /// Large arrays stay tightly packed and the push constant data can be uploaded as is.
+    ReadOnlyStorage,
+}
+
 /// Lookup a set and a binding for a list of [`CorrectionType`].
 /// In order, insert a new variable for each, see [`CorrectionType`] for what type of object should
 /// be inserted for each variant.
@@ -60,4 +77,67 @@ pub struct CorrectionMap {
     /// This is valuable when dealing with WebGPU's `maxBindGroup` especially if your sets
     /// previously followed the maximum.
     pub immediates_set_mode: Option<ImmediatesSetMode>,
+    /// Represents both an input/output where the first immediate uniform should/is written to
+    /// within `immediates_set`.
+    /// If this is [`None`], this becomes the first binding not already in use.
+    pub immediates_binding: Option<u32>,
+    /// Represents both an input/output of which kind of buffer replaces push constant blocks.
+    /// If this is [`None`], [`ImmediatesBufferKind::Uniform`] is used.
+    pub immediates_buffer_kind: Option<ImmediatesBufferKind>,
+    /// Input only, rewrite `matCx2` members of std140 uniforms as arrays of `vec4` columns.
+    /// The layout is unchanged, but naga rejects `matCx2` with a `MatrixStride` of 16.
+    pub pad_matcx2: bool,
+    /// Output only, the buffers that replaced each push constant block, in order.
+    /// This is empty if the module had no immediates to patch.
+    pub immediates_buffers: Vec<ImmediatesBuffer>,
+}
+
+/// A std140 uniform buffer or std430 storage buffer that replaced a push constant block.
+/// Use this to allocate and fill the buffer from data laid out for the push constant block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImmediatesBuffer {
+    pub set: u32,
+    pub binding: u32,
+    /// The size of the buffer in bytes.
+    pub size: u32,
+    /// The top level members of the block, in order.
+    pub members: Vec<ImmediatesMember>,
+    /// Every contiguous region of the push constant block and where it lands in the buffer.
+    /// Copying each region is enough to convert push constant data, see [`repack_immediates`].
+    pub copies: Vec<ImmediatesCopy>,
+    /// The entry points that statically use this buffer, in module order.
+    /// Modules with several entry points may give each stage its own push constant block, only
+    /// bind the buffers an entry point lists when creating its pipeline.
+    pub entry_points: Vec<ImmediatesEntryPoint>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImmediatesEntryPoint {
+    pub name: String,
+    /// The `OpEntryPoint`'s execution model, such as `0` for vertex, `4` for fragment, and `5` for
+    /// compute.
+    pub execution_model: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImmediatesMember {
+    /// The offset of this member in the original push constant block.
+    pub push_constant_offset: u32,
+    /// The offset of this member in the buffer.
+    pub offset: u32,
+    /// The size of this member in the buffer.
+    /// Arrays, matrices, and structs within may be padded differently from the push constant block.
+    pub size: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImmediatesCopy {
+    /// The offset of this region in the original push constant block.
+    pub push_constant_offset: u32,
+    /// The offset of this region in the buffer.
+    pub offset: u32,
+    /// The size of this region in bytes, identical on both sides.
+    pub size: u32,
 }
@@ -0,0 +1,565 @@
+use super::*;
+
+fn inc(ib: &mut u32) -> u32 {
+    *ib += 1;
+    *ib - 1
+}
+
+mod frexp;
+
+use frexp::*;
+
+/// The fragment shader inputs that [extinstpatch_with_report] decorated `Centroid`, sorted by
+/// location.
+/// WebGPU requires the matching vertex outputs to use the same sampling, declare them with
+/// `@interpolate(perspective, centroid)` (or `centroid out` in GLSL), otherwise pipeline creation
+/// fails.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtInstReport {
+    pub centroid_locations: Vec<u32>,
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Does not produce any corrections.
+/// Equivalent to [extinstpatch_with_report] without looking at the report, only use this if no
+/// shader uses `interpolateAtCentroid`.
+pub fn extinstpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
+    extinstpatch_with_report(in_spv, &mut Default::default())
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Does not produce any corrections, inputs that are now `Centroid` are written to `report`.
+pub fn extinstpatch_with_report(
+    in_spv: &[u32],
+    report: &mut ExtInstReport,
+) -> Result<Vec<u32>, ()> {
+    *report = ExtInstReport::default();
+
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_capability_idxs = vec![];
+    let mut op_ext_inst_import_idxs = vec![];
+    let mut op_ext_inst_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_constant_composite_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_access_chain_idxs = vec![];
+    let mut first_op_function_idx = None;
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        match instruction {
+            SPV_INSTRUCTION_OP_CAPABILITY => op_capability_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXT_INST_IMPORT => op_ext_inst_import_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXT_INST => op_ext_inst_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE => op_constant_composite_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ACCESS_CHAIN | SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN => {
+                op_access_chain_idxs.push(spv_idx)
+            }
+            SPV_INSTRUCTION_OP_FUNCTION => {
+                first_op_function_idx.get_or_insert(spv_idx);
+            }
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    let glsl_std_ids = op_ext_inst_import_idxs
+        .iter()
+        .filter_map(|&idx| {
+            let word_count = hiword(spv[idx]) as usize;
+            let name = literal_to_string_le(&spv[idx + 2..idx + word_count]).ok()?;
+            name.starts_with("GLSL.std.").then_some(spv[idx + 1])
+        })
+        .collect::<HashSet<_>>();
+
+    let op_glsl_std_idxs = op_ext_inst_idxs
+        .iter()
+        .copied()
+        .filter(|&idx| glsl_std_ids.contains(&spv[idx + 3]))
+        .collect::<Vec<_>>();
+
+    if op_glsl_std_idxs.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    let header_position = last_of_indices!(
+        op_type_bool_idxs,
+        op_type_int_idxs,
+        op_type_float_idxs,
+        op_type_vector_idxs,
+        op_type_struct_idxs,
+        op_type_pointer_idxs,
+        op_constant_idxs,
+        op_constant_composite_idxs
+    );
+
+    // 2. Useful closures
+    let get_float_type_width = |id| {
+        op_type_float_idxs
+            .iter()
+            .find_map(|idx| (spv[idx + 1] == id).then_some(spv[idx + 2]))
+    };
+
+    let get_underlying_vector_type = |id| {
+        op_type_vector_idxs.iter().find_map(|idx| {
+            let result_id = spv[idx + 1];
+            let component_type = spv[idx + 2];
+            let component_count = spv[idx + 3];
+
+            (result_id == id).then_some((component_type, component_count))
+        })
+    };
+
+    let get_struct_member_type = |id, member| {
+        op_type_struct_idxs.iter().find_map(|idx| {
+            let result_id = spv[idx + 1];
+            (result_id == id).then_some(spv[idx + 2 + member])
+        })
+    };
+
+    // The `Input` variable an interpolant points into, through any access chains.
+    let get_interpolant_variable = |id| {
+        let mut id = id;
+        loop {
+            if let Some(&idx) = op_variable_idxs.iter().find(|&&idx| spv[idx + 2] == id) {
+                return (spv[idx + 3] == SPV_STORAGE_CLASS_INPUT).then_some(id);
+            }
+            let &idx = op_access_chain_idxs
+                .iter()
+                .find(|&&idx| spv[idx + 2] == id)?;
+            id = spv[idx + 3];
+        }
+    };
+
+    let get_pointer_underlying_type = |id| {
+        op_type_pointer_idxs.iter().find_map(|idx| {
+            let result_id = spv[idx + 1];
+            (result_id == id).then_some(spv[idx + 3])
+        })
+    };
+
+    // 3. Shared types and constants are created as we need them
+    let mut header_insert = InstructionInsert {
+        previous_spv_idx: header_position.unwrap(),
+        instruction: vec![],
+    };
+
+    let mut uint32_id = None;
+    let mut bool_id = None;
+    let mut vector_types = HashMap::new();
+    let mut constants = HashMap::new();
+    let mut frexp_constants = HashMap::new();
+
+    // 4. Lower each unsupported extended instruction
+    let mut lowered_interpolate = false;
+    let mut centroid_variable_ids = vec![];
+
+    for &op_idx in &op_glsl_std_idxs {
+        let word_count = hiword(spv[op_idx]) as usize;
+        let result_type_id = spv[op_idx + 1];
+        let result_id = spv[op_idx + 2];
+        let glsl_std_id = spv[op_idx + 3];
+        let glsl_instruction = spv[op_idx + 4];
+        let x = spv[op_idx + 5];
+
+        let lowered = match glsl_instruction {
+            // %result = OpExtInst %ResType %glsl_std ModfStruct %x
+            //
+            //   %whole = OpExtInst %float %glsl_std Trunc %x
+            //   %fract = OpFSub %float %x %whole
+            //  %result = OpCompositeConstruct %ResType %fract %whole
+            SPV_GLSL_STD_INSTRUCTION_MODF_STRUCT => {
+                let float_id = get_struct_member_type(result_type_id, 0)
+                    .expect("ModfStruct does not return a struct?");
+                let whole = inc(&mut instruction_bound);
+                let fract = inc(&mut instruction_bound);
+                #[rustfmt::skip]
+                let lowered = vec![
+                    encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                        float_id, whole, glsl_std_id, SPV_GLSL_STD_INSTRUCTION_TRUNC, x,
+                    encode_word(5, SPV_INSTRUCTION_OP_F_SUB),
+                        float_id, fract, x, whole,
+                    encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+                        result_type_id, result_id, fract, whole,
+                ];
+                lowered
+            }
+            // %result = OpExtInst %float %glsl_std Modf %x %i
+            //
+            //   %whole = OpExtInst %float %glsl_std Trunc %x
+            //            OpStore %i %whole
+            //  %result = OpFSub %float %x %whole
+            SPV_GLSL_STD_INSTRUCTION_MODF => {
+                let whole_ptr = spv[op_idx + 6];
+                let whole = inc(&mut instruction_bound);
+                #[rustfmt::skip]
+                let lowered = vec![
+                    encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                        result_type_id, whole, glsl_std_id, SPV_GLSL_STD_INSTRUCTION_TRUNC, x,
+                    encode_word(3, SPV_INSTRUCTION_OP_STORE),
+                        whole_ptr, whole,
+                    encode_word(5, SPV_INSTRUCTION_OP_F_SUB),
+                        result_type_id, result_id, x, whole,
+                ];
+                lowered
+            }
+            // See `frexp_spv`
+            SPV_GLSL_STD_INSTRUCTION_FREXP_STRUCT | SPV_GLSL_STD_INSTRUCTION_FREXP => {
+                let is_struct = glsl_instruction == SPV_GLSL_STD_INSTRUCTION_FREXP_STRUCT;
+                let (float_id, exponent_id) = if is_struct {
+                    (
+                        get_struct_member_type(result_type_id, 0)
+                            .expect("FrexpStruct does not return a struct?"),
+                        get_struct_member_type(result_type_id, 1)
+                            .expect("FrexpStruct does not return a struct?"),
+                    )
+                } else {
+                    let exponent_ptr = spv[op_idx + 6];
+                    let exponent_ptr_type_id =
+                        trace_previous_intermediate_id(&spv, exponent_ptr, op_idx)
+                            .expect("Frexp's exponent is not defined?");
+                    (
+                        result_type_id,
+                        get_pointer_underlying_type(exponent_ptr_type_id)
+                            .expect("Frexp's exponent is not a pointer?"),
+                    )
+                };
+
+                let (float_component_id, component_count) = get_underlying_vector_type(float_id)
+                    .map(|(a, b)| (a, Some(b)))
+                    .unwrap_or((float_id, None));
+                let (exponent_component_id, _) =
+                    get_underlying_vector_type(exponent_id).unwrap_or((exponent_id, 1));
+
+                // The bit layout is only handled for 32-bit floats
+                if get_float_type_width(float_component_id) != Some(32) {
+                    return Err(());
+                }
+
+                let frexp_types_and_constants = *frexp_constants
+                    .entry((float_id, exponent_id))
+                    .or_insert_with(|| {
+                        let uint32_id = *uint32_id.get_or_insert_with(|| {
+                            ensure_type_int(
+                                &spv,
+                                &op_type_int_idxs,
+                                &mut instruction_bound,
+                                &mut header_insert.instruction,
+                                32,
+                                SPV_SIGNEDNESS_UNSIGNED,
+                            )
+                        });
+                        let bool_id = *bool_id.get_or_insert_with(|| {
+                            ensure_type_bool(
+                                &spv,
+                                &op_type_bool_idxs,
+                                &mut instruction_bound,
+                                &mut header_insert.instruction,
+                            )
+                        });
+
+                        let mut splat = |component_id, value| {
+                            let scalar_id =
+                                *constants.entry((component_id, value)).or_insert_with(|| {
+                                    let new_id = inc(&mut instruction_bound);
+                                    header_insert.instruction.append(&mut vec![
+                                        encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                                        component_id,
+                                        new_id,
+                                        value,
+                                    ]);
+                                    new_id
+                                });
+                            let Some(component_count) = component_count else {
+                                return (component_id, scalar_id);
+                            };
+                            let vector_id = *vector_types
+                                .entry((component_id, component_count))
+                                .or_insert_with(|| {
+                                    ensure_type_vector(
+                                        &spv,
+                                        &op_type_vector_idxs,
+                                        &mut instruction_bound,
+                                        &mut header_insert.instruction,
+                                        component_id,
+                                        component_count,
+                                    )
+                                });
+                            let vector_constant_id =
+                                *constants.entry((vector_id, scalar_id)).or_insert_with(|| {
+                                    let new_id = inc(&mut instruction_bound);
+                                    header_insert.instruction.append(&mut vec![
+                                        encode_word(
+                                            3 + component_count as u16,
+                                            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE,
+                                        ),
+                                        vector_id,
+                                        new_id,
+                                    ]);
+                                    header_insert.instruction.append(&mut vec![
+                                        scalar_id;
+                                        component_count
+                                            as usize
+                                    ]);
+                                    new_id
+                                });
+                            (vector_id, vector_constant_id)
+                        };
+
+                        let (uint_id, uint_23) = splat(uint32_id, 23);
+                        let (_, uint_255) = splat(uint32_id, 255);
+                        let (_, uint_sign_frac_mask) = splat(uint32_id, FREXP_SIGN_FRAC_MASK);
+                        let (_, uint_half_exp) = splat(uint32_id, FREXP_HALF_EXP);
+                        let (_, exponent_126) = splat(exponent_component_id, 126);
+                        let (_, exponent_0) = splat(exponent_component_id, 0);
+                        let (_, float_0) = splat(float_component_id, 0);
+                        let bool_id = component_count
+                            .map(|component_count| {
+                                *vector_types
+                                    .entry((bool_id, component_count))
+                                    .or_insert_with(|| {
+                                        ensure_type_vector(
+                                            &spv,
+                                            &op_type_vector_idxs,
+                                            &mut instruction_bound,
+                                            &mut header_insert.instruction,
+                                            bool_id,
+                                            component_count,
+                                        )
+                                    })
+                            })
+                            .unwrap_or(bool_id);
+
+                        (
+                            FrexpTypes {
+                                float_id,
+                                uint_id,
+                                exponent_id,
+                                bool_id,
+                            },
+                            FrexpConstants {
+                                uint_23,
+                                uint_255,
+                                uint_sign_frac_mask,
+                                uint_half_exp,
+                                exponent_126,
+                                exponent_0,
+                                float_0,
+                            },
+                        )
+                    });
+                let (frexp_types, frexp_constants) = frexp_types_and_constants;
+
+                if is_struct {
+                    //  %result = OpCompositeConstruct %ResType %sig %exp
+                    let sig = inc(&mut instruction_bound);
+                    let (exp, mut lowered) =
+                        frexp_spv(&mut instruction_bound, frexp_types, frexp_constants, x, sig);
+                    lowered.append(&mut vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+                        result_type_id,
+                        result_id,
+                        sig,
+                        exp,
+                    ]);
+                    lowered
+                } else {
+                    //            OpStore %exp_ptr %exp
+                    let exponent_ptr = spv[op_idx + 6];
+                    let (exp, mut lowered) = frexp_spv(
+                        &mut instruction_bound,
+                        frexp_types,
+                        frexp_constants,
+                        x,
+                        result_id,
+                    );
+                    lowered.append(&mut vec![
+                        encode_word(3, SPV_INSTRUCTION_OP_STORE),
+                        exponent_ptr,
+                        exp,
+                    ]);
+                    lowered
+                }
+            }
+            // %result = OpExtInst %double %glsl_std PackDouble2x32 %v
+            // %result = OpExtInst %v2uint %glsl_std UnpackDouble2x32 %d
+            //
+            //  %result = OpBitcast %double %v
+            //  %result = OpBitcast %v2uint %d
+            SPV_GLSL_STD_INSTRUCTION_PACK_DOUBLE_2X32
+            | SPV_GLSL_STD_INSTRUCTION_UNPACK_DOUBLE_2X32 => {
+                vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                    result_type_id,
+                    result_id,
+                    x,
+                ]
+            }
+            // %result = OpExtInst %v4float %glsl_std InterpolateAtCentroid %interpolant
+            //
+            //            OpDecorate %variable Centroid
+            //  %result = OpLoad %v4float %interpolant
+            SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_CENTROID => {
+                let variable_id = get_interpolant_variable(x).ok_or(())?;
+                if !centroid_variable_ids.contains(&variable_id) {
+                    centroid_variable_ids.push(variable_id);
+                }
+                lowered_interpolate = true;
+                vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                    result_type_id,
+                    result_id,
+                    x,
+                ]
+            }
+            // There is no decoration that picks a sample or an offset per read.
+            SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_SAMPLE
+            | SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_OFFSET => return Err(()),
+            // %result = OpExtInst %v4int %glsl_std FindILsb %v4uint_x
+            //
+            //     %lsb = OpExtInst %v4uint %glsl_std FindILsb %v4uint_x
+            //  %result = OpBitcast %v4int %lsb
+            SPV_GLSL_STD_INSTRUCTION_FIND_I_LSB
+            | SPV_GLSL_STD_INSTRUCTION_FIND_S_MSB
+            | SPV_GLSL_STD_INSTRUCTION_FIND_U_MSB => {
+                let x_type_id = trace_previous_intermediate_id(&spv, x, op_idx)
+                    .expect("FindXXX's argument is not defined?");
+                if x_type_id == result_type_id {
+                    continue;
+                }
+
+                let found = inc(&mut instruction_bound);
+                #[rustfmt::skip]
+                let lowered = vec![
+                    encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                        x_type_id, found, glsl_std_id, glsl_instruction, x,
+                    encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                        result_type_id, result_id, found,
+                ];
+                lowered
+            }
+            _ => continue,
+        };
+
+        for i in 0..word_count {
+            new_spv[op_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: op_idx,
+            instruction: lowered,
+        });
+    }
+
+    // 5. Decorating an interpolant `Centroid` only keeps its meaning if every read of it was at the
+    //    centroid, or if it already was `Centroid`
+    for &variable_id in &centroid_variable_ids {
+        let decorations = op_decorate_idxs
+            .iter()
+            .copied()
+            .filter(|&idx| spv[idx + 1] == variable_id)
+            .collect::<Vec<_>>();
+        if decorations
+            .iter()
+            .any(|&idx| spv[idx + 2] == SPV_DECORATION_SAMPLE)
+        {
+            return Err(());
+        }
+        if decorations
+            .iter()
+            .any(|&idx| spv[idx + 2] == SPV_DECORATION_CENTROID)
+        {
+            continue;
+        }
+
+        let mut spv_idx = first_op_function_idx.ok_or(())?;
+        while spv_idx < spv.len() {
+            let word_count = hiword(spv[spv_idx]) as usize;
+            let read_at_centroid = loword(spv[spv_idx]) == SPV_INSTRUCTION_OP_EXT_INST
+                && glsl_std_ids.contains(&spv[spv_idx + 3])
+                && spv[spv_idx + 4] == SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_CENTROID;
+            let is_access_chain = op_access_chain_idxs.contains(&spv_idx);
+            if !read_at_centroid
+                && !is_access_chain
+                && referenced_ids(&spv, spv_idx)
+                    .into_iter()
+                    .any(|id| get_interpolant_variable(id) == Some(variable_id))
+            {
+                return Err(());
+            }
+            spv_idx += word_count;
+        }
+
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: *decorations.first().ok_or(())?,
+            instruction: vec![
+                encode_word(3, SPV_INSTRUCTION_OP_DECORATE),
+                variable_id,
+                SPV_DECORATION_CENTROID,
+            ],
+        });
+        let location = decorations
+            .iter()
+            .find_map(|&idx| (spv[idx + 2] == SPV_DECORATION_LOCATION).then_some(spv[idx + 3]))
+            .ok_or(())?;
+        report.centroid_locations.push(location);
+    }
+    report.centroid_locations.sort();
+
+    // 6. `InterpolationFunction` is no longer needed
+    if lowered_interpolate {
+        for &idx in &op_capability_idxs {
+            if spv[idx + 1] == SPV_CAPABILITY_INTERPOLATION_FUNCTION {
+                new_spv[idx] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                new_spv[idx + 1] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        }
+    }
+
+    // 7. Insert New Instructions
+    instruction_inserts.insert(0, header_insert);
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 8. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 9. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
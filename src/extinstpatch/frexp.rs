@@ -0,0 +1,104 @@
+use super::*;
+
+/// Every id is a vector when `x` is a vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct FrexpTypes {
+    pub float_id: u32,
+    pub uint_id: u32,
+    pub exponent_id: u32,
+    pub bool_id: u32,
+}
+
+/// Every id is splatted to match [`FrexpTypes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct FrexpConstants {
+    pub uint_23: u32,
+    pub uint_255: u32,
+    pub uint_sign_frac_mask: u32,
+    pub uint_half_exp: u32,
+    pub exponent_126: u32,
+    pub exponent_0: u32,
+    pub float_0: u32,
+}
+
+pub(super) const FREXP_SIGN_FRAC_MASK: u32 = 0x807fffff;
+pub(super) const FREXP_HALF_EXP: u32 = 0x3f000000;
+
+// Split the already loaded 32-bit float `x` into its significand and exponent.
+// The significand is written to `sig`, returns the id of the exponent.
+// Denormals are treated as normals with the minimum exponent.
+pub(super) fn frexp_spv(
+    ib: &mut u32,
+    types: FrexpTypes,
+    constants: FrexpConstants,
+    x: u32,
+    sig: u32,
+) -> (u32, Vec<u32>) {
+    //
+    //       %bits = OpBitcast %uint %x
+    //    %shifted = OpShiftRightLogical %uint %bits %uint_23
+    //   %exp_bits = OpBitwiseAnd %uint %shifted %uint_255
+    //   %sig_bits = OpBitwiseAnd %uint %bits %uint_0x807fffff
+    //   %sig_bits = OpBitwiseOr %uint %sig_bits %uint_0x3f000000
+    //        %sig = OpBitcast %float %sig_bits
+    //        %exp = OpBitcast %int %exp_bits
+    //        %exp = OpISub %int %exp %int_126
+    //    %is_zero = OpFOrdEqual %bool %x %float_0
+    //        %sig = OpSelect %float %is_zero %x %sig
+    //        %exp = OpSelect %int %is_zero %int_0 %exp
+
+    let FrexpTypes {
+        float_id,
+        uint_id,
+        exponent_id,
+        bool_id,
+    } = types;
+    let FrexpConstants {
+        uint_23,
+        uint_255,
+        uint_sign_frac_mask,
+        uint_half_exp,
+        exponent_126,
+        exponent_0,
+        float_0,
+    } = constants;
+
+    let bits = inc(ib);
+    let shifted = inc(ib);
+    let exp_bits = inc(ib);
+    let masked_sig_bits = inc(ib);
+    let sig_bits = inc(ib);
+    let raw_sig = inc(ib);
+    let biased_exp = inc(ib);
+    let raw_exp = inc(ib);
+    let is_zero = inc(ib);
+    let exp = inc(ib);
+
+    #[rustfmt::skip]
+    let spv = vec![
+        encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+            uint_id, bits, x,
+        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL),
+            uint_id, shifted, bits, uint_23,
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+            uint_id, exp_bits, shifted, uint_255,
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+            uint_id, masked_sig_bits, bits, uint_sign_frac_mask,
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_OR),
+            uint_id, sig_bits, masked_sig_bits, uint_half_exp,
+        encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+            float_id, raw_sig, sig_bits,
+        encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+            exponent_id, biased_exp, exp_bits,
+        encode_word(5, SPV_INSTRUCTION_OP_I_SUB),
+            exponent_id, raw_exp, biased_exp, exponent_126,
+        encode_word(5, SPV_INSTRUCTION_OP_F_ORD_EQUAL),
+            bool_id, is_zero, x, float_0,
+        encode_word(6, SPV_INSTRUCTION_OP_SELECT),
+            float_id, sig, is_zero, x, raw_sig,
+        encode_word(6, SPV_INSTRUCTION_OP_SELECT),
+            exponent_id, exp, is_zero, exponent_0, raw_exp,
+    ];
+
+    (exp, spv)
+}
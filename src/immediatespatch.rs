@@ -1,13 +1,30 @@
 use super::*;
 
-mod layout;
-mod type_registry;
+pub(crate) mod layout;
+pub(crate) mod padmatcx2;
+pub(crate) mod repack;
+mod shared;
+pub(crate) mod type_registry;
 
 use layout::*;
+use padmatcx2::*;
+pub use repack::repack_immediates;
+use repack::*;
+pub use shared::immediatespatch_shared;
+use shared::*;
 use type_registry::*;
 
 /// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
 pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Result<Vec<u32>, ()> {
+    immediatespatch_with_layout(in_spv, corrections, None)
+}
+
+// Every top level member is placed at its offset in `shared_layout` instead of its own layout.
+fn immediatespatch_with_layout(
+    in_spv: &[u32],
+    corrections: &mut CorrectionMap,
+    shared_layout: Option<&SharedLayout>,
+) -> Result<Vec<u32>, ()> {
     let spv = in_spv.to_owned();
 
     let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
@@ -30,18 +47,35 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
     let mut op_type_array_idxs = vec![];
     let mut op_type_matrix_idxs = vec![];
     let mut op_type_vector_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
     let mut op_type_float_idxs = vec![];
     let mut op_type_int_idxs = vec![];
     let mut op_constant_idxs = vec![];
     let mut op_decorate_idxs = vec![];
     let mut op_member_decorate_idxs = vec![];
+    let mut op_decorate_id_idxs = vec![];
+    let mut op_entry_point_idxs = vec![];
+    let mut op_execution_mode_id_idxs = vec![];
+    // Types, constants, and global variables
+    let mut op_global_idxs = vec![];
+    // From `OpFunction` up to and including `OpFunctionEnd`
+    let mut function_ranges = vec![];
 
+    let mut function_start = None;
     let mut spv_idx = 0;
     while spv_idx < spv.len() {
         let op = spv[spv_idx];
         let word_count = hiword(op);
         let instruction = loword(op);
 
+        if word_count == 0 {
+            return Err(());
+        }
+
+        if function_start.is_none() && global_result_id(&spv, spv_idx).is_some() {
+            op_global_idxs.push(spv_idx);
+        }
+
         match instruction {
             SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
@@ -49,11 +83,20 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
             SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE_ID => op_decorate_id_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ENTRY_POINT => op_entry_point_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXECUTION_MODE_ID => op_execution_mode_id_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => function_start = Some(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION_END => {
+                let start = function_start.take().ok_or(())?;
+                function_ranges.push((start, spv_idx + word_count as usize));
+            }
             _ => {}
         }
 
@@ -94,9 +137,33 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
         })
         .collect::<Vec<_>>();
 
+    // Find which push constant variables each entry point can reach, each stage may have its own.
+    let entry_point_reach = op_entry_point_idxs
+        .iter()
+        .map(|&ep_idx| {
+            let execution_model = spv[ep_idx + 1];
+            let (name, used_ids) = entry_point_used_ids(EntryPointUsedIdsIn {
+                spv: &spv,
+                op_global_idxs: &op_global_idxs,
+                function_ranges: &function_ranges,
+                op_decorate_id_idxs: &op_decorate_id_idxs,
+                op_execution_mode_idxs: &op_execution_mode_id_idxs,
+                ep_idx,
+            })?;
+            Ok((
+                ImmediatesEntryPoint {
+                    name,
+                    execution_model,
+                },
+                used_ids,
+            ))
+        })
+        .collect::<Result<Vec<_>, ()>>()?;
+
     // 4. Build a registry of every relevant OpType*
     let type_registry = build_type_registry(BuildTypeRegistryIn {
         spv: &spv,
+        op_type_bool_idxs: &op_type_bool_idxs,
         op_type_float_idxs: &op_type_float_idxs,
         op_type_int_idxs: &op_type_int_idxs,
         op_type_vector_idxs: &op_type_vector_idxs,
@@ -106,16 +173,35 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
         op_constant_idxs: &op_constant_idxs,
     });
 
-    // 5. Rewrite Offset / ArrayStride / MatrixStride decoration
-    for &block_struct_id in &block_struct_ids {
-        relayout_type_recursive(
-            &spv,
-            &mut new_spv,
-            block_struct_id,
-            &type_registry,
-            &op_decorate_idxs,
-            &op_member_decorate_idxs,
-        );
+    // 5. Rewrite Offset / ArrayStride / MatrixStride decoration, storage buffers keep std430
+    let buffer_kind = corrections.immediates_buffer_kind.unwrap_or_default();
+    let layout_rule = match buffer_kind {
+        ImmediatesBufferKind::Uniform => LayoutRule::Std140,
+        ImmediatesBufferKind::ReadOnlyStorage => LayoutRule::Std430,
+    };
+    if buffer_kind == ImmediatesBufferKind::Uniform {
+        for &block_struct_id in &block_struct_ids {
+            relayout_type_recursive(
+                &spv,
+                &mut new_spv,
+                block_struct_id,
+                &type_registry,
+                &op_decorate_idxs,
+                &op_member_decorate_idxs,
+            );
+        }
+    }
+
+    // A shared layout describes a single block, each member goes where the union of modules put it
+    if let Some(shared_layout) = shared_layout {
+        let [block_struct_id] = block_struct_ids[..] else {
+            return Err(());
+        };
+        for &md_idx in &op_member_decorate_idxs {
+            if spv[md_idx + 1] == block_struct_id && spv[md_idx + 3] == SPV_DECORATION_OFFSET {
+                new_spv[md_idx + 4] = *shared_layout.offsets.get(&spv[md_idx + 4]).ok_or(())?;
+            }
+        }
     }
 
     // 6. Correct OpTypePointer and OpVariable PushConstant -> Uniform
@@ -130,6 +216,49 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
         new_spv[v_idx + 3] = SPV_STORAGE_CLASS_UNIFORM;
     }
 
+    // Storage buffers are `BufferBlock` in the `Uniform` storage class with every member read-only.
+    if buffer_kind == ImmediatesBufferKind::ReadOnlyStorage {
+        for &block_struct_id in &block_struct_ids {
+            let block_d_idx = op_decorate_idxs
+                .iter()
+                .copied()
+                .find(|&d_idx| {
+                    spv[d_idx + 1] == block_struct_id && spv[d_idx + 2] == SPV_DECORATION_BLOCK
+                })
+                .ok_or(())?;
+            new_spv[block_d_idx + 2] = SPV_DECORATION_BUFFER_BLOCK;
+
+            let Some(Type {
+                kind: TypeKind::Struct { members },
+                ..
+            }) = type_registry.get(&block_struct_id)
+            else {
+                return Err(());
+            };
+            let non_writable = (0..members.len() as u32)
+                .filter(|&member| {
+                    !op_member_decorate_idxs.iter().any(|&md_idx| {
+                        spv[md_idx + 1] == block_struct_id
+                            && spv[md_idx + 2] == member
+                            && spv[md_idx + 3] == SPV_DECORATION_NON_WRITABLE
+                    })
+                })
+                .flat_map(|member| {
+                    [
+                        encode_word(4, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                        block_struct_id,
+                        member,
+                        SPV_DECORATION_NON_WRITABLE,
+                    ]
+                })
+                .collect::<Vec<_>>();
+            instruction_inserts.push(InstructionInsert {
+                previous_spv_idx: block_d_idx,
+                instruction: non_writable,
+            });
+        }
+    }
+
     // 7. Place new uniforms in the set after the last set.
     let first_op_decorate_idx = op_decorate_idxs.first().copied();
     // TODO: What if our `immediates_set` value is faulty?
@@ -163,14 +292,28 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
 
     let set_bindings =
         decorate_map_set_bindings(&spv, &op_decorate_idxs, &HashSet::from([target_set]));
-    let starting_binding = set_bindings
+    let used_bindings = set_bindings
         .get(&target_set)
         .unwrap()
-        .last()
-        .map(|&(_, b)| b + 1)
-        .unwrap_or(0u32);
+        .iter()
+        .map(|&(_, b)| b)
+        .collect::<HashSet<_>>();
+    let target_bindings = match corrections.immediates_binding {
+        Some(binding) => {
+            let bindings = (binding..).take(pc_variables.len()).collect::<Vec<_>>();
+            if bindings.iter().any(|b| used_bindings.contains(b)) {
+                return Err(());
+            }
+            bindings
+        }
+        // Fill gaps first, this matters when sharing a set through `ImmediatesSetMode::MaxUpTo`
+        None => (0..)
+            .filter(|b| !used_bindings.contains(b))
+            .take(pc_variables.len())
+            .collect::<Vec<_>>(),
+    };
 
-    for (binding_idx, &(_, _, var_id)) in pc_variables.iter().enumerate() {
+    for (&(_, _, var_id), &binding) in pc_variables.iter().zip(target_bindings.iter()) {
         instruction_inserts.push(InstructionInsert {
             previous_spv_idx: first_op_decorate_idx
                 .expect("Push constant block has no OpDecorate (missing Block decoration?)"),
@@ -182,25 +325,107 @@ pub fn immediatespatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Resul
                 encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
                 var_id,
                 SPV_DECORATION_BINDING,
-                starting_binding + binding_idx as u32,
+                binding,
             ],
         });
     }
 
     corrections.immediates_set = Some(target_set);
+    corrections.immediates_binding = target_bindings.first().copied();
+    corrections.immediates_buffer_kind = Some(buffer_kind);
 
-    // 8. Insert New Instructions
+    // 8. Report the layout of each new buffer and how to fill it from push constant data
+    corrections.immediates_buffers = block_struct_ids
+        .iter()
+        .zip(pc_variables.iter())
+        .zip(target_bindings.iter())
+        .map(|((block_struct_id, &(_, _, var_id)), &binding)| {
+            let Some(Type {
+                kind: block @ TypeKind::Struct { members },
+                ..
+            }) = type_registry.get(block_struct_id)
+            else {
+                return Err(());
+            };
+            let members = members
+                .iter()
+                .enumerate()
+                .map(|(member_idx, member)| {
+                    let offset_md_idx = op_member_decorate_idxs
+                        .iter()
+                        .copied()
+                        .find(|&md_idx| {
+                            spv[md_idx + 1] == *block_struct_id
+                                && spv[md_idx + 2] == member_idx as u32
+                                && spv[md_idx + 3] == SPV_DECORATION_OFFSET
+                        })
+                        .ok_or(())?;
+                    Ok(ImmediatesMember {
+                        push_constant_offset: spv[offset_md_idx + 4],
+                        offset: new_spv[offset_md_idx + 4],
+                        size: size_of(&member.kind, layout_rule),
+                    })
+                })
+                .collect::<Result<Vec<_>, ()>>()?;
+            let size = match shared_layout {
+                Some(shared_layout) => shared_layout.size,
+                None => round_up(
+                    members
+                        .iter()
+                        .map(|member| member.offset + member.size)
+                        .max()
+                        .unwrap_or(0),
+                    base_align(block, layout_rule),
+                ),
+            };
+            let copies = collect_copies(
+                &CollectCopiesIn {
+                    spv: &spv,
+                    new_spv: &new_spv,
+                    op_decorate_idxs: &op_decorate_idxs,
+                    op_member_decorate_idxs: &op_member_decorate_idxs,
+                },
+                &type_registry[block_struct_id],
+            )?;
+            let entry_points = entry_point_reach
+                .iter()
+                .filter(|(_, used_ids)| used_ids.contains(&var_id))
+                .map(|(entry_point, _)| entry_point.clone())
+                .collect();
+            Ok(ImmediatesBuffer {
+                set: target_set,
+                binding,
+                size,
+                members,
+                copies,
+                entry_points,
+            })
+        })
+        .collect::<Result<Vec<_>, ()>>()?;
+
+    // 9. Insert New Instructions
     insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
 
-    // 9. Remove Instructions that have been Whited Out.
+    // 10. Remove Instructions that have been Whited Out.
     prune_noops(&mut new_spv);
 
-    // 10. Write New Header and New Code
-    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+    // 11. Write New Header and New Code
+    let out_spv = fuse_final(spv_header, new_spv, instruction_bound);
+
+    // 12. Optionally hide `matCx2` from naga
+    if corrections.pad_matcx2 && buffer_kind == ImmediatesBufferKind::Uniform {
+        let variable_ids = pc_variables
+            .iter()
+            .map(|&(_, _, var_id)| var_id)
+            .collect::<Vec<_>>();
+        padmatcx2(&out_spv, &variable_ids)
+    } else {
+        Ok(out_spv)
+    }
 }
 
 // Recursively patch Offset / ArrayStride / MatrixStride decorations using our type registry.
-fn relayout_type_recursive(
+pub(crate) fn relayout_type_recursive(
     spv: &[u32],
     new_spv: &mut [u32],
     type_id: u32,
@@ -4,10 +4,9 @@
 // bump array / struct base alignment to 16, and bump `ArrayStride` / `MatrixStride` to >=16.
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(super) enum LayoutRule {
+pub(crate) enum LayoutRule {
     /// "Standard Storage Buffer Layout"
     /// Push constants use this one.
-    #[allow(dead_code)]
     Std430,
     /// "Standard Uniform Buffer Layout"
     /// Arrays and structs have their base alignment rounded up to a multiple of 16.
@@ -15,13 +14,13 @@ pub(super) enum LayoutRule {
 }
 
 #[derive(Debug, Clone)]
-pub(super) struct Type {
+pub(crate) struct Type {
     pub id: u32,
     pub kind: TypeKind,
 }
 
 #[derive(Debug, Clone)]
-pub(super) enum TypeKind {
+pub(crate) enum TypeKind {
     Scalar { width_bytes: u32 },
     Vector { component: Box<Type>, count: u32 },
     Matrix { column: Box<Type>, cols: u32 },
@@ -29,18 +28,18 @@ pub(super) enum TypeKind {
     Struct { members: Vec<Type> },
 }
 
-pub(super) struct StructLayout {
+pub(crate) struct StructLayout {
     pub member_offsets: Vec<u32>,
     pub size: u32,
     #[allow(dead_code)]
     pub align: u32,
 }
 
-const fn round_up(x: u32, a: u32) -> u32 {
+pub(crate) const fn round_up(x: u32, a: u32) -> u32 {
     (x + a - 1) & !(a - 1)
 }
 
-pub(super) fn base_align(t: &TypeKind, rule: LayoutRule) -> u32 {
+pub(crate) fn base_align(t: &TypeKind, rule: LayoutRule) -> u32 {
     let inner = match t {
         // §15.6.4: "A scalar has a base alignment equal to its scalar alignment."
         TypeKind::Scalar { width_bytes } => *width_bytes,
@@ -65,14 +64,18 @@ pub(super) fn base_align(t: &TypeKind, rule: LayoutRule) -> u32 {
     // §15.6.4 "Standard Uniform Buffer Layout":
     //   - Array's base alignment is rounded up to a multiple of 16.
     //   - Struct's base alignment is rounded up to a multiple of 16.
+    //   - Matrices are laid out as an array of their columns, so the same applies.
     // The Standard Storage Buffer Layout (and push constants) omit this rule.
     match (rule, t) {
-        (LayoutRule::Std140, TypeKind::Array { .. } | TypeKind::Struct { .. }) => inner.max(16),
+        (
+            LayoutRule::Std140,
+            TypeKind::Matrix { .. } | TypeKind::Array { .. } | TypeKind::Struct { .. },
+        ) => inner.max(16),
         _ => inner,
     }
 }
 
-pub(super) fn size_of(t: &TypeKind, rule: LayoutRule) -> u32 {
+pub(crate) fn size_of(t: &TypeKind, rule: LayoutRule) -> u32 {
     match t {
         TypeKind::Scalar { width_bytes } => *width_bytes,
         // Tight component packing.
@@ -90,7 +93,7 @@ pub(super) fn size_of(t: &TypeKind, rule: LayoutRule) -> u32 {
 
 // "An array's `ArrayStride` is equal to its element's consumed size rounded up to the array's base alignment."  Under Standard
 // Uniform Buffer Layout the element's base alignment is itself ≥16, so the resulting stride is also ≥16.
-pub(super) fn array_stride(elem: &TypeKind, rule: LayoutRule) -> u32 {
+pub(crate) fn array_stride(elem: &TypeKind, rule: LayoutRule) -> u32 {
     let align = base_align(elem, rule);
     let raw = round_up(size_of(elem, rule), align);
     match rule {
@@ -101,7 +104,7 @@ pub(super) fn array_stride(elem: &TypeKind, rule: LayoutRule) -> u32 {
 
 // `column_vec_count` is the component count of one column for a ColMajor matrix, or one row for a RowMajor matrix.
 // The stride is the size of that column / row rounded up to its own vector base alignment and to 16 under std140.
-pub(super) fn matrix_stride(column_vec_count: u32, scalar_w: u32, rule: LayoutRule) -> u32 {
+pub(crate) fn matrix_stride(column_vec_count: u32, scalar_w: u32, rule: LayoutRule) -> u32 {
     let vec_align = match column_vec_count {
         1 => scalar_w,
         2 => 2 * scalar_w,
@@ -119,7 +122,7 @@ pub(super) fn matrix_stride(column_vec_count: u32, scalar_w: u32, rule: LayoutRu
 //
 // "The members are assigned consecutive offsets starting from zero, with each member's offset adjusted upwards to satisfy its base alignment."
 // "The structure's size is the offset of the last member, plus the size of the last member, rounded up to a multiple of the structure's base alignment."
-pub(super) fn layout_struct(members: &[Type], rule: LayoutRule) -> StructLayout {
+pub(crate) fn layout_struct(members: &[Type], rule: LayoutRule) -> StructLayout {
     let mut offset = 0u32;
     let mut offsets = Vec::with_capacity(members.len());
     let mut align = 4u32;
@@ -140,7 +143,7 @@ pub(super) fn layout_struct(members: &[Type], rule: LayoutRule) -> StructLayout
     }
 }
 
-pub(super) fn column_vec_count(column: &Type) -> u32 {
+pub(crate) fn column_vec_count(column: &Type) -> u32 {
     match &column.kind {
         TypeKind::Vector { count, .. } => *count,
         TypeKind::Scalar { .. } => 1,
@@ -148,7 +151,7 @@ pub(super) fn column_vec_count(column: &Type) -> u32 {
     }
 }
 
-pub(super) fn column_scalar_width(column: &Type) -> u32 {
+pub(crate) fn column_scalar_width(column: &Type) -> u32 {
     match &column.kind {
         TypeKind::Vector { component, .. } => match &component.kind {
             TypeKind::Scalar { width_bytes } => *width_bytes,
@@ -0,0 +1,550 @@
+use super::*;
+
+// A block member that is a column major `matCx2`, or an array of them, `depth` arrays deep.
+struct PaddedMember {
+    struct_id: u32,
+    member: u32,
+    depth: usize,
+    matrix_id: u32,
+    column_id: u32,
+    column_count: u32,
+    component_id: u32,
+    column_array_id: u32,
+    vec4_id: u32,
+}
+
+enum PaddedAccess {
+    Matrix,
+    Column,
+}
+
+/// Rewrite every column major `matCx2` member of the given block variables, and arrays of them, as
+/// an array of `vec4` columns. Loads reconstruct the matrix with `OpCompositeConstruct`.
+/// The std140 layout is unchanged since each column already has a `MatrixStride` of 16, but naga
+/// rejects that stride on `matCx2`.
+pub(crate) fn padmatcx2(in_spv: &[u32], variable_ids: &[u32]) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations of instructions we need
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+    let mut op_access_chain_idxs = vec![];
+    let mut function_instruction_idxs = vec![];
+    let mut global_idxs = HashMap::new();
+    let mut previous_idxs = HashMap::new();
+    // Annotations go right before the first global, new pointers go right after the last one
+    let mut last_annotation_idx = None;
+    let mut last_global_idx = None;
+
+    let mut in_function = false;
+    let mut previous_idx = None;
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        in_function |= instruction == SPV_INSTRUCTION_OP_FUNCTION;
+        if in_function {
+            function_instruction_idxs.push(spv_idx);
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ACCESS_CHAIN | SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN => {
+                op_access_chain_idxs.push(spv_idx)
+            }
+            _ => {}
+        }
+        if !in_function && let Some(id) = global_result_id(&spv, spv_idx) {
+            if last_global_idx.is_none() {
+                last_annotation_idx = previous_idx;
+            }
+            last_global_idx = Some(spv_idx);
+            global_idxs.insert(id, spv_idx);
+        }
+
+        if let Some(previous_idx) = previous_idx {
+            previous_idxs.insert(spv_idx, previous_idx);
+        }
+        previous_idx = Some(spv_idx);
+        spv_idx += word_count as usize;
+    }
+
+    let find_idx = |idxs: &[usize], id: u32| idxs.iter().copied().find(|&idx| spv[idx + 1] == id);
+    let array_stride = |id: u32| {
+        op_decorate_idxs.iter().find_map(|&d_idx| {
+            (spv[d_idx + 1] == id && spv[d_idx + 2] == SPV_DECORATION_ARRAY_STRIDE)
+                .then_some(spv[d_idx + 3])
+        })
+    };
+    let member_decoration_idxs = |struct_id: u32, member: u32, decoration: u32| {
+        op_member_decorate_idxs
+            .iter()
+            .copied()
+            .filter(|&md_idx| {
+                spv[md_idx + 1] == struct_id
+                    && spv[md_idx + 2] == member
+                    && spv[md_idx + 3] == decoration
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // 2. Find the block struct of each variable
+    let variable_structs = variable_ids
+        .iter()
+        .map(|&variable_id| {
+            let v_idx = op_variable_idxs
+                .iter()
+                .copied()
+                .find(|&v_idx| spv[v_idx + 2] == variable_id)
+                .ok_or(())?;
+            let tp_idx = find_idx(&op_type_pointer_idxs, spv[v_idx + 1]).ok_or(())?;
+            Ok((variable_id, spv[tp_idx + 3]))
+        })
+        .collect::<Result<HashMap<_, _>, ()>>()?;
+
+    // 3. Find every `matCx2` member
+    let mut struct_idxs = vec![];
+    let mut padded_members: Vec<PaddedMember> = vec![];
+    for struct_id in variable_ids
+        .iter()
+        .map(|variable_id| variable_structs[variable_id])
+    {
+        if padded_members
+            .iter()
+            .any(|padded| padded.struct_id == struct_id)
+        {
+            continue;
+        }
+        let s_idx = find_idx(&op_type_struct_idxs, struct_id).ok_or(())?;
+        for member in 0..hiword(spv[s_idx]) as u32 - 2 {
+            if !member_decoration_idxs(struct_id, member, SPV_DECORATION_ROW_MAJOR).is_empty() {
+                continue;
+            }
+            let mut type_id = spv[s_idx + 2 + member as usize];
+            let mut depth = 0;
+            while let Some(a_idx) = find_idx(&op_type_array_idxs, type_id) {
+                type_id = spv[a_idx + 2];
+                depth += 1;
+            }
+            let Some(m_idx) = find_idx(&op_type_matrix_idxs, type_id) else {
+                continue;
+            };
+            let column_id = spv[m_idx + 2];
+            let v_idx = find_idx(&op_type_vector_idxs, column_id).ok_or(())?;
+            let component_id = spv[v_idx + 2];
+            let is_float32 = find_idx(&op_type_float_idxs, component_id)
+                .is_some_and(|f_idx| spv[f_idx + 2] == 32);
+            if spv[v_idx + 3] == 2 && is_float32 {
+                struct_idxs.push(s_idx);
+                padded_members.push(PaddedMember {
+                    struct_id,
+                    member,
+                    depth,
+                    matrix_id: type_id,
+                    column_id,
+                    column_count: spv[m_idx + 3],
+                    component_id,
+                    column_array_id: 0,
+                    vec4_id: 0,
+                });
+            }
+        }
+    }
+
+    if padded_members.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+    let padded_variables = variable_structs
+        .iter()
+        .filter(|&(_, struct_id)| {
+            padded_members
+                .iter()
+                .any(|padded| padded.struct_id == *struct_id)
+        })
+        .map(|(&variable_id, _)| variable_id)
+        .collect::<HashSet<_>>();
+
+    // 4. Declare the new member types right before the first affected struct
+    let first_struct_idx = struct_idxs.iter().copied().min().ok_or(())?;
+    let mut ctx = PadContext {
+        globals: NewGlobals::new(&spv, instruction_bound),
+        new_spv: &mut new_spv,
+        global_idxs: &global_idxs,
+        insert_idx: first_struct_idx,
+    };
+    for padded_member in padded_members.iter_mut() {
+        let PaddedMember {
+            struct_id,
+            member,
+            matrix_id,
+            column_count,
+            component_id,
+            ..
+        } = *padded_member;
+
+        let vec4_id = ctx.globals.cached(
+            vec![SPV_INSTRUCTION_OP_TYPE_VECTOR as u32, component_id, 4],
+            |ctx| {
+                ensure_type_vector(
+                    ctx.spv,
+                    &op_type_vector_idxs,
+                    &mut ctx.instruction_bound,
+                    &mut ctx.header,
+                    component_id,
+                    4,
+                )
+            },
+        );
+        ctx.hoist(vec4_id);
+
+        let column_count_id = match op_constant_idxs.iter().find(|&&c_idx| {
+            spv[c_idx + 3] == column_count
+                && find_idx(&op_type_int_idxs, spv[c_idx + 1])
+                    .is_some_and(|i_idx| spv[i_idx + 2] == 32)
+        }) {
+            Some(&c_idx) => spv[c_idx + 2],
+            None => {
+                let uint_id =
+                    ctx.globals
+                        .cached(vec![SPV_INSTRUCTION_OP_TYPE_INT as u32, 32, 0], |ctx| {
+                            ensure_type_int(
+                                ctx.spv,
+                                &op_type_int_idxs,
+                                &mut ctx.instruction_bound,
+                                &mut ctx.header,
+                                32,
+                                0,
+                            )
+                        });
+                ctx.hoist(uint_id);
+                ctx.globals.cached(
+                    vec![SPV_INSTRUCTION_OP_CONSTANT as u32, uint_id, column_count],
+                    |ctx| {
+                        let constant_id = ctx.inc();
+                        #[rustfmt::skip]
+                        ctx.header.append(&mut vec![
+                            encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                                uint_id, constant_id, column_count,
+                        ]);
+                        constant_id
+                    },
+                )
+            }
+        };
+        ctx.hoist(column_count_id);
+
+        // The `MatrixStride` becomes the `ArrayStride` of the columns
+        let matrix_stride_idxs =
+            member_decoration_idxs(struct_id, member, SPV_DECORATION_MATRIX_STRIDE);
+        let matrix_stride = matrix_stride_idxs
+            .first()
+            .map(|&md_idx| spv[md_idx + 4])
+            .ok_or(())?;
+        let column_array_id = ctx.globals.cached(
+            vec![
+                SPV_INSTRUCTION_OP_TYPE_ARRAY as u32,
+                vec4_id,
+                column_count_id,
+                matrix_stride,
+            ],
+            |ctx| {
+                let array_id = ctx.inc();
+                #[rustfmt::skip]
+                ctx.header.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_TYPE_ARRAY),
+                        array_id, vec4_id, column_count_id,
+                ]);
+                #[rustfmt::skip]
+                ctx.decorations.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                        array_id, SPV_DECORATION_ARRAY_STRIDE, matrix_stride,
+                ]);
+                array_id
+            },
+        );
+
+        // Rebuild arrays of matrices around the columns, innermost first
+        let s_idx = find_idx(&op_type_struct_idxs, struct_id).ok_or(())?;
+        let mut array_ids = vec![];
+        let mut type_id = spv[s_idx + 2 + member as usize];
+        while type_id != matrix_id {
+            array_ids.push(type_id);
+            type_id = spv[find_idx(&op_type_array_idxs, type_id).ok_or(())? + 2];
+        }
+        let mut member_type_id = column_array_id;
+        for &array_id in array_ids.iter().rev() {
+            let length_id = spv[find_idx(&op_type_array_idxs, array_id).ok_or(())? + 3];
+            let stride = array_stride(array_id).ok_or(())?;
+            ctx.hoist(length_id);
+            member_type_id = ctx.globals.cached(
+                vec![
+                    SPV_INSTRUCTION_OP_TYPE_ARRAY as u32,
+                    member_type_id,
+                    length_id,
+                    stride,
+                ],
+                |ctx| {
+                    let new_array_id = ctx.inc();
+                    #[rustfmt::skip]
+                    ctx.header.append(&mut vec![
+                        encode_word(4, SPV_INSTRUCTION_OP_TYPE_ARRAY),
+                            new_array_id, member_type_id, length_id,
+                    ]);
+                    #[rustfmt::skip]
+                    ctx.decorations.append(&mut vec![
+                        encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                            new_array_id, SPV_DECORATION_ARRAY_STRIDE, stride,
+                    ]);
+                    new_array_id
+                },
+            );
+        }
+
+        ctx.new_spv[s_idx + 2 + member as usize] = member_type_id;
+        for md_idx in matrix_stride_idxs.into_iter().chain(member_decoration_idxs(
+            struct_id,
+            member,
+            SPV_DECORATION_COL_MAJOR,
+        )) {
+            for i in 0..hiword(spv[md_idx]) as usize {
+                ctx.new_spv[md_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        }
+
+        padded_member.column_array_id = column_array_id;
+        padded_member.vec4_id = vec4_id;
+    }
+    let mut pointer_header = vec![];
+
+    // 5. Retype access chains to the matrices and their columns
+    let mut padded_accesses = HashMap::new();
+    for &ac_idx in &op_access_chain_idxs {
+        let result_type_id = spv[ac_idx + 1];
+        let result_id = spv[ac_idx + 2];
+        let Some(&struct_id) = variable_structs.get(&spv[ac_idx + 3]) else {
+            continue;
+        };
+        let indices = &spv[ac_idx + 4..ac_idx + hiword(spv[ac_idx]) as usize];
+        let Some(&member_index_id) = indices.first() else {
+            continue;
+        };
+        let member = op_constant_idxs
+            .iter()
+            .find_map(|&c_idx| (spv[c_idx + 2] == member_index_id).then_some(spv[c_idx + 3]))
+            .ok_or(())?;
+        let Some(padded_member) = padded_members
+            .iter()
+            .find(|padded| padded.struct_id == struct_id && padded.member == member)
+        else {
+            continue;
+        };
+
+        let (pointee_id, access) = match (indices.len() - 1).cmp(&padded_member.depth) {
+            // An array of matrices is never loaded directly by glslang
+            std::cmp::Ordering::Less => return Err(()),
+            std::cmp::Ordering::Equal => (padded_member.column_array_id, PaddedAccess::Matrix),
+            std::cmp::Ordering::Greater if indices.len() - 1 == padded_member.depth + 1 => {
+                (padded_member.vec4_id, PaddedAccess::Column)
+            }
+            // Scalars are at the same place within a `vec4`
+            std::cmp::Ordering::Greater => continue,
+        };
+        let storage_class = spv[find_idx(&op_type_pointer_idxs, result_type_id).ok_or(())? + 2];
+        let pointer_id = ctx.globals.cached(
+            vec![
+                SPV_INSTRUCTION_OP_TYPE_POINTER as u32,
+                storage_class,
+                pointee_id,
+            ],
+            |ctx| {
+                ensure_type_pointer(
+                    ctx.spv,
+                    &op_type_pointer_idxs,
+                    &mut ctx.instruction_bound,
+                    &mut pointer_header,
+                    storage_class,
+                    pointee_id,
+                )
+            },
+        );
+        ctx.new_spv[ac_idx + 1] = pointer_id;
+        padded_accesses.insert(result_id, (access, padded_member));
+    }
+
+    // 6. Reconstruct loaded matrices and columns
+    for &f_idx in &function_instruction_idxs {
+        let instruction = loword(spv[f_idx]);
+        let referenced = referenced_ids(&spv, f_idx);
+
+        if instruction == SPV_INSTRUCTION_OP_LOAD
+            && let Some((access, padded_member)) = padded_accesses.get(&spv[f_idx + 3])
+        {
+            let word_count = hiword(spv[f_idx]) as usize;
+            let result_type_id = spv[f_idx + 1];
+            let result_id = spv[f_idx + 2];
+            let pointer_id = spv[f_idx + 3];
+            let memory_operands = &spv[f_idx + 4..f_idx + word_count];
+
+            let mut load = vec![];
+            match access {
+                PaddedAccess::Column => {
+                    let vec4_id = ctx.globals.inc();
+                    #[rustfmt::skip]
+                    load.append(&mut vec![
+                        encode_word(word_count as u16, SPV_INSTRUCTION_OP_LOAD),
+                            padded_member.vec4_id, vec4_id, pointer_id,
+                    ]);
+                    load.extend_from_slice(memory_operands);
+                    #[rustfmt::skip]
+                    load.append(&mut vec![
+                        encode_word(7, SPV_INSTRUCTION_OP_VECTOR_SHUFFLE),
+                            result_type_id, result_id, vec4_id, vec4_id, 0, 1,
+                    ]);
+                }
+                PaddedAccess::Matrix => {
+                    let columns_id = ctx.globals.inc();
+                    #[rustfmt::skip]
+                    load.append(&mut vec![
+                        encode_word(word_count as u16, SPV_INSTRUCTION_OP_LOAD),
+                            padded_member.column_array_id, columns_id, pointer_id,
+                    ]);
+                    load.extend_from_slice(memory_operands);
+                    let mut column_ids = vec![];
+                    for column in 0..padded_member.column_count {
+                        let vec4_id = ctx.globals.inc();
+                        let column_id = ctx.globals.inc();
+                        #[rustfmt::skip]
+                        load.append(&mut vec![
+                            encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                                padded_member.vec4_id, vec4_id, columns_id, column,
+                            encode_word(7, SPV_INSTRUCTION_OP_VECTOR_SHUFFLE),
+                                padded_member.column_id, column_id, vec4_id, vec4_id, 0, 1,
+                        ]);
+                        column_ids.push(column_id);
+                    }
+                    load.append(&mut vec![
+                        encode_word(
+                            3 + column_ids.len() as u16,
+                            SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT,
+                        ),
+                        padded_member.matrix_id,
+                        result_id,
+                    ]);
+                    load.append(&mut column_ids);
+                }
+            }
+            for i in 0..word_count {
+                ctx.new_spv[f_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+            instruction_inserts.push(InstructionInsert {
+                previous_spv_idx: f_idx,
+                instruction: load,
+            });
+            continue;
+        }
+
+        // Anything else that sees the block or its matrices would need the old types
+        let is_block_access_chain = (instruction == SPV_INSTRUCTION_OP_ACCESS_CHAIN
+            || instruction == SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN)
+            && padded_variables.contains(&spv[f_idx + 3]);
+        if is_block_access_chain && padded_accesses.contains_key(&spv[f_idx + 2]) {
+            continue;
+        }
+        if referenced.iter().any(|id| padded_accesses.contains_key(id))
+            || (!is_block_access_chain && referenced.iter().any(|id| padded_variables.contains(id)))
+        {
+            return Err(());
+        }
+    }
+
+    // 7. Insert New Instructions
+    let NewGlobals {
+        instruction_bound,
+        header,
+        decorations,
+        ..
+    } = ctx.globals;
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: *previous_idxs.get(&first_struct_idx).ok_or(())?,
+        instruction: header,
+    });
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: last_global_idx.ok_or(())?,
+        instruction: pointer_header,
+    });
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: last_annotation_idx.ok_or(())?,
+        instruction: decorations,
+    });
+    insert_new_instructions(&spv, &mut new_spv, &[], &instruction_inserts);
+
+    // 8. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 9. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
+
+struct PadContext<'a> {
+    globals: NewGlobals<'a>,
+    new_spv: &'a mut [u32],
+    global_idxs: &'a HashMap<u32, usize>,
+    // New member types are declared before this instruction
+    insert_idx: usize,
+}
+
+impl PadContext<'_> {
+    // Types and constants found by `ensure_type_*` may be declared after the new member types,
+    // move them and what they reference up front.
+    fn hoist(&mut self, id: u32) {
+        let Some(&idx) = self.global_idxs.get(&id) else {
+            return;
+        };
+        if idx < self.insert_idx || self.new_spv[idx] == encode_word(1, SPV_INSTRUCTION_OP_NOP) {
+            return;
+        }
+        for referenced_id in referenced_ids(self.globals.spv, idx) {
+            self.hoist(referenced_id);
+        }
+        let word_count = hiword(self.globals.spv[idx]) as usize;
+        self.globals
+            .header
+            .extend_from_slice(&self.globals.spv[idx..idx + word_count]);
+        for i in 0..word_count {
+            self.new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+    }
+}
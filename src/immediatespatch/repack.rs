@@ -0,0 +1,192 @@
+use super::*;
+
+/// Convert push constant data into the contents of the uniform buffer that replaced it.
+/// `push_constant_data` is laid out exactly as the push constant block was before [immediatespatch].
+/// The result is `buffer.size` bytes long, padding is zeroed.
+pub fn repack_immediates(
+    buffer: &ImmediatesBuffer,
+    push_constant_data: &[u8],
+) -> Result<Vec<u8>, ()> {
+    repack_regions(
+        buffer.size,
+        buffer
+            .copies
+            .iter()
+            .map(|copy| (copy.push_constant_offset, copy.offset, copy.size)),
+        push_constant_data,
+    )
+}
+
+// Copy each `(source offset, offset, size)` region of `data` into a zeroed buffer of `size` bytes.
+pub(crate) fn repack_regions(
+    size: u32,
+    regions: impl IntoIterator<Item = (u32, u32, u32)>,
+    data: &[u8],
+) -> Result<Vec<u8>, ()> {
+    let mut out = vec![0u8; size as usize];
+    for (src, dst, size) in regions {
+        let (src, dst, size) = (src as usize, dst as usize, size as usize);
+        let (Some(src_bytes), Some(dst_bytes)) =
+            (data.get(src..src + size), out.get_mut(dst..dst + size))
+        else {
+            return Err(());
+        };
+        dst_bytes.copy_from_slice(src_bytes);
+    }
+    Ok(out)
+}
+
+pub struct CollectCopiesIn<'a> {
+    pub spv: &'a [u32],
+    pub new_spv: &'a [u32],
+    pub op_decorate_idxs: &'a [usize],
+    pub op_member_decorate_idxs: &'a [usize],
+}
+
+// `MatrixStride` and `RowMajor` are member decorations, so they are carried down from the struct.
+#[derive(Clone, Copy)]
+struct MatrixLayout {
+    push_constant_stride: u32,
+    stride: u32,
+    row_major: bool,
+}
+
+/// Walk a push constant block and collect every contiguous region that must be copied.
+/// Source offsets are read from `spv`, destination offsets from the relaid out `new_spv`.
+pub fn collect_copies(
+    collect_in: &CollectCopiesIn,
+    block: &Type,
+) -> Result<Vec<ImmediatesCopy>, ()> {
+    let mut copies = vec![];
+    collect_copies_recursive(collect_in, block, 0, 0, None, &mut copies)?;
+    Ok(copies)
+}
+
+fn collect_copies_recursive(
+    collect_in: &CollectCopiesIn,
+    ty: &Type,
+    push_constant_offset: u32,
+    offset: u32,
+    matrix_layout: Option<MatrixLayout>,
+    copies: &mut Vec<ImmediatesCopy>,
+) -> Result<(), ()> {
+    let CollectCopiesIn {
+        spv,
+        new_spv,
+        op_decorate_idxs,
+        op_member_decorate_idxs,
+    } = collect_in;
+
+    match &ty.kind {
+        TypeKind::Scalar { .. } | TypeKind::Vector { .. } => {
+            push_copy(
+                copies,
+                push_constant_offset,
+                offset,
+                size_of(&ty.kind, LayoutRule::Std430),
+            );
+        }
+        TypeKind::Matrix { column, cols } => {
+            let MatrixLayout {
+                push_constant_stride,
+                stride,
+                row_major,
+            } = matrix_layout.ok_or(())?;
+            let rows = column_vec_count(column);
+            let scalar_w = column_scalar_width(column);
+            // Row major matrices store each row contiguously instead of each column.
+            let (vec_count, vec_size) = if row_major {
+                (rows, cols * scalar_w)
+            } else {
+                (*cols, rows * scalar_w)
+            };
+            for i in 0..vec_count {
+                push_copy(
+                    copies,
+                    push_constant_offset + i * push_constant_stride,
+                    offset + i * stride,
+                    vec_size,
+                );
+            }
+        }
+        TypeKind::Array { element, len } => {
+            let array_stride = |spv: &[u32]| {
+                op_decorate_idxs.iter().find_map(|&d_idx| {
+                    (spv[d_idx + 1] == ty.id && spv[d_idx + 2] == SPV_DECORATION_ARRAY_STRIDE)
+                        .then_some(spv[d_idx + 3])
+                })
+            };
+            let push_constant_stride = array_stride(spv).ok_or(())?;
+            let stride = array_stride(new_spv).ok_or(())?;
+            for i in 0..*len {
+                collect_copies_recursive(
+                    collect_in,
+                    element,
+                    push_constant_offset + i * push_constant_stride,
+                    offset + i * stride,
+                    matrix_layout,
+                    copies,
+                )?;
+            }
+        }
+        TypeKind::Struct { members } => {
+            for (member_idx, member) in members.iter().enumerate() {
+                let find_member_decoration = |decoration: u32| {
+                    op_member_decorate_idxs.iter().copied().find(|&md_idx| {
+                        spv[md_idx + 1] == ty.id
+                            && spv[md_idx + 2] == member_idx as u32
+                            && spv[md_idx + 3] == decoration
+                    })
+                };
+                let member_literal = |spv: &[u32], decoration: u32| {
+                    find_member_decoration(decoration).map(|md_idx| spv[md_idx + 4])
+                };
+                let member_push_constant_offset =
+                    member_literal(spv, SPV_DECORATION_OFFSET).ok_or(())?;
+                let member_offset = member_literal(new_spv, SPV_DECORATION_OFFSET).ok_or(())?;
+                let member_matrix_layout = match (
+                    member_literal(spv, SPV_DECORATION_MATRIX_STRIDE),
+                    member_literal(new_spv, SPV_DECORATION_MATRIX_STRIDE),
+                ) {
+                    (Some(push_constant_stride), Some(stride)) => Some(MatrixLayout {
+                        push_constant_stride,
+                        stride,
+                        row_major: find_member_decoration(SPV_DECORATION_ROW_MAJOR).is_some(),
+                    }),
+                    _ => None,
+                };
+                collect_copies_recursive(
+                    collect_in,
+                    member,
+                    push_constant_offset + member_push_constant_offset,
+                    offset + member_offset,
+                    member_matrix_layout,
+                    copies,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Extend the previous region instead if both sides are contiguous.
+pub fn push_copy(
+    copies: &mut Vec<ImmediatesCopy>,
+    push_constant_offset: u32,
+    offset: u32,
+    size: u32,
+) {
+    if let Some(last) = copies.last_mut()
+        && last.push_constant_offset + last.size == push_constant_offset
+        && last.offset + last.size == offset
+    {
+        last.size += size;
+        return;
+    }
+    copies.push(ImmediatesCopy {
+        push_constant_offset,
+        offset,
+        size,
+    });
+}
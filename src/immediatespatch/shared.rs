@@ -0,0 +1,238 @@
+use super::*;
+
+/// [immediatespatch] for several modules, such as the stages of one pipeline, that should bind the
+/// same buffer.
+/// In Vulkan each stage may declare only its own range of the push constant block, for example with
+/// `layout(offset = 64)`. Instead of laying out each stage's block on its own, the buffer is laid
+/// out once for the union of every stage's members, and each stage reads its members at their
+/// offset within it.
+/// Each module may have at most one push constant block, see [splitentrypoints], and every module
+/// must use the same [`ImmediatesBufferKind`].
+/// Every module's [`ImmediatesBuffer`] describes the whole shared buffer, use [mirrorpatch_many]
+/// afterwards to place it at the same set and binding in every module.
+pub fn immediatespatch_shared(
+    modules: &mut [(&[u32], &mut CorrectionMap)],
+) -> Result<Vec<Vec<u32>>, ()> {
+    // 1. Find the top level members of every module's push constant block
+    let Some(buffer_kind) = modules
+        .first()
+        .map(|(_, corrections)| corrections.immediates_buffer_kind.unwrap_or_default())
+    else {
+        return Ok(vec![]);
+    };
+    if modules.iter().any(|(_, corrections)| {
+        corrections.immediates_buffer_kind.unwrap_or_default() != buffer_kind
+    }) {
+        return Err(());
+    }
+    let layout_rule = match buffer_kind {
+        ImmediatesBufferKind::Uniform => LayoutRule::Std140,
+        ImmediatesBufferKind::ReadOnlyStorage => LayoutRule::Std430,
+    };
+
+    let mut members = vec![];
+    for (spv, _) in modules.iter() {
+        members.extend(push_constant_members(spv)?);
+    }
+    members.sort_by_key(|(push_constant_offset, _)| *push_constant_offset);
+
+    // 2. Lay out the union of every member, members at the same offset must match
+    let shared_layout = layout_shared(&members, layout_rule)?;
+
+    // 3. Patch each module with the shared layout
+    let mut outputs = vec![];
+    for (spv, corrections) in modules.iter_mut() {
+        outputs.push(immediatespatch_with_layout(
+            spv,
+            corrections,
+            Some(&shared_layout),
+        )?);
+    }
+
+    // 4. Describe the whole buffer in every module, not just what that module uses
+    let mut shared_members = modules
+        .iter()
+        .flat_map(|(_, corrections)| corrections.immediates_buffers.iter())
+        .flat_map(|buffer| buffer.members.iter().copied())
+        .collect::<Vec<_>>();
+    shared_members.sort_by_key(|member| member.push_constant_offset);
+    shared_members.dedup();
+
+    let mut module_copies = modules
+        .iter()
+        .flat_map(|(_, corrections)| corrections.immediates_buffers.iter())
+        .flat_map(|buffer| buffer.copies.iter().copied())
+        .collect::<Vec<_>>();
+    module_copies.sort_by_key(|copy| copy.push_constant_offset);
+    module_copies.dedup();
+    let mut shared_copies = vec![];
+    for copy in module_copies {
+        push_copy(
+            &mut shared_copies,
+            copy.push_constant_offset,
+            copy.offset,
+            copy.size,
+        );
+    }
+
+    for (_, corrections) in modules.iter_mut() {
+        for buffer in corrections.immediates_buffers.iter_mut() {
+            buffer.members = shared_members.clone();
+            buffer.copies = shared_copies.clone();
+        }
+    }
+
+    Ok(outputs)
+}
+
+pub(crate) struct SharedLayout {
+    // The offset of a top level member in the push constant block -> its offset in the buffer
+    pub offsets: HashMap<u32, u32>,
+    pub size: u32,
+}
+
+// Uniforms pack members in push constant order, storage buffers keep push constant offsets as is.
+fn layout_shared(members: &[(u32, Type)], layout_rule: LayoutRule) -> Result<SharedLayout, ()> {
+    let mut offsets = HashMap::new();
+    let mut union_members: Vec<(u32, Type)> = vec![];
+    let mut push_constant_end = 0;
+    let mut end = 0;
+    for (push_constant_offset, member) in members.iter() {
+        let push_constant_size = size_of(&member.kind, LayoutRule::Std430);
+        let size = size_of(&member.kind, layout_rule);
+        let align = base_align(&member.kind, layout_rule);
+
+        if let Some((previous_offset, previous)) = union_members.last()
+            && previous_offset == push_constant_offset
+        {
+            if size_of(&previous.kind, LayoutRule::Std430) != push_constant_size
+                || size_of(&previous.kind, layout_rule) != size
+                || base_align(&previous.kind, layout_rule) != align
+            {
+                return Err(());
+            }
+            continue;
+        }
+        // Stages disagree about what lives in this range
+        if *push_constant_offset < push_constant_end {
+            return Err(());
+        }
+        push_constant_end = push_constant_offset + push_constant_size;
+
+        let offset = match layout_rule {
+            LayoutRule::Std140 => round_up(end, align),
+            LayoutRule::Std430 => *push_constant_offset,
+        };
+        offsets.insert(*push_constant_offset, offset);
+        end = offset + size;
+        union_members.push((*push_constant_offset, member.clone()));
+    }
+
+    let block = TypeKind::Struct {
+        members: union_members
+            .into_iter()
+            .map(|(_, member)| member)
+            .collect(),
+    };
+    Ok(SharedLayout {
+        offsets,
+        size: round_up(end, base_align(&block, layout_rule)),
+    })
+}
+
+// The push constant offset and type of each top level member, empty without a push constant block.
+fn push_constant_members(in_spv: &[u32]) -> Result<Vec<(u32, Type)>, ()> {
+    let spv = in_spv.get(SPV_HEADER_LENGTH..).ok_or(())?;
+
+    let mut op_variable_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    let block_struct_ids = op_variable_idxs
+        .iter()
+        .filter(|&&v_idx| spv[v_idx + 3] == SPV_STORAGE_CLASS_PUSH_CONSTANT)
+        .map(|&v_idx| {
+            op_type_pointer_idxs
+                .iter()
+                .find_map(|&tp_idx| (spv[tp_idx + 1] == spv[v_idx + 1]).then_some(spv[tp_idx + 3]))
+                .ok_or(())
+        })
+        .collect::<Result<Vec<_>, ()>>()?;
+    let block_struct_id = match block_struct_ids[..] {
+        [] => return Ok(vec![]),
+        [block_struct_id] => block_struct_id,
+        _ => return Err(()),
+    };
+
+    let type_registry = build_type_registry(BuildTypeRegistryIn {
+        spv,
+        op_type_bool_idxs: &op_type_bool_idxs,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_int_idxs: &op_type_int_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_type_matrix_idxs: &op_type_matrix_idxs,
+        op_type_array_idxs: &op_type_array_idxs,
+        op_type_struct_idxs: &op_type_struct_idxs,
+        op_constant_idxs: &op_constant_idxs,
+    });
+    let Some(Type {
+        kind: TypeKind::Struct { members },
+        ..
+    }) = type_registry.get(&block_struct_id)
+    else {
+        return Err(());
+    };
+
+    members
+        .iter()
+        .enumerate()
+        .map(|(member_idx, member)| {
+            let push_constant_offset = op_member_decorate_idxs
+                .iter()
+                .find_map(|&md_idx| {
+                    (spv[md_idx + 1] == block_struct_id
+                        && spv[md_idx + 2] == member_idx as u32
+                        && spv[md_idx + 3] == SPV_DECORATION_OFFSET)
+                        .then_some(spv[md_idx + 4])
+                })
+                .ok_or(())?;
+            Ok((push_constant_offset, member.clone()))
+        })
+        .collect()
+}
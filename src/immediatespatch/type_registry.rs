@@ -3,6 +3,7 @@ use super::*;
 pub type TypeRegistry = HashMap<u32, Type>;
 pub struct BuildTypeRegistryIn<'a> {
     pub spv: &'a [u32],
+    pub op_type_bool_idxs: &'a [usize],
     pub op_type_float_idxs: &'a [usize],
     pub op_type_int_idxs: &'a [usize],
     pub op_type_vector_idxs: &'a [usize],
@@ -15,6 +16,7 @@ pub struct BuildTypeRegistryIn<'a> {
 pub fn build_type_registry(build_in: BuildTypeRegistryIn) -> TypeRegistry {
     let BuildTypeRegistryIn {
         spv,
+        op_type_bool_idxs,
         op_type_float_idxs,
         op_type_int_idxs,
         op_type_vector_idxs,
@@ -23,8 +25,9 @@ pub fn build_type_registry(build_in: BuildTypeRegistryIn) -> TypeRegistry {
         op_type_struct_idxs,
         op_constant_idxs,
     } = build_in;
-    let mut all_idxs = op_type_float_idxs
+    let mut all_idxs = op_type_bool_idxs
         .iter()
+        .chain(op_type_float_idxs.iter())
         .chain(op_type_int_idxs.iter())
         .chain(op_type_vector_idxs.iter())
         .chain(op_type_matrix_idxs.iter())
@@ -44,6 +47,16 @@ pub fn build_type_registry(build_in: BuildTypeRegistryIn) -> TypeRegistry {
         let id = spv[idx + 1];
 
         match instruction {
+            // Bools have no defined size, but glslang lays them out as 32-bit integers.
+            SPV_INSTRUCTION_OP_TYPE_BOOL => {
+                reg.insert(
+                    id,
+                    Type {
+                        id,
+                        kind: TypeKind::Scalar { width_bytes: 4 },
+                    },
+                );
+            }
             SPV_INSTRUCTION_OP_TYPE_FLOAT | SPV_INSTRUCTION_OP_TYPE_INT => {
                 let width_bytes = spv[idx + 2] / 8;
                 reg.insert(
@@ -6,16 +6,33 @@ fn inc(ib: &mut u32) -> u32 {
 }
 
 // Someone should make a rust-spirv dsl macro
+mod comparison;
 mod isnan_isinf;
 mod shared;
 
+use comparison::*;
 use isnan_isinf::*;
 use shared::*;
 
+/// Choose how [`isnanisinfpatch`] implements `isnan` and `isinf`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IsNanIsInfMode {
+    /// Inspect the IEEE-754 bit pattern inside of generated helper functions.
+    #[default]
+    Function,
+    /// Inspect the IEEE-754 bit pattern at each call site without any helper functions.
+    /// The check operates on the value directly, so no `Function` variables are introduced.
+    Inline,
+    /// Use `x != x` for `isnan` and `abs(x) > MAX` for `isinf`.
+    /// This produces the smallest code, but compilers that assume fast-math may fold these
+    /// comparisons away.
+    Comparison,
+}
+
 /// Perform the operation on a `Vec<u32>`.
 /// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
 /// Does not produce any side effects or corrections.
-pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
+pub fn isnanisinfpatch(in_spv: &[u32], mode: IsNanIsInfMode) -> Result<Vec<u32>, ()> {
     let spv = in_spv.to_owned();
 
     let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
@@ -32,6 +49,9 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
     let mut new_spv = spv.clone();
 
     // 1. Find locations instructions we need
+    let mut op_capability_idxs = vec![];
+    let mut op_extension_idxs = vec![];
+    let mut op_ext_inst_import_idxs = vec![];
     let mut op_function_idxs = vec![];
     let mut op_load_idxs = vec![];
     let mut op_type_pointer_idxs = vec![];
@@ -49,6 +69,9 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         let instruction = loword(op);
 
         match instruction {
+            SPV_INSTRUCTION_OP_CAPABILITY => op_capability_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXTENSION => op_extension_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXT_INST_IMPORT => op_ext_inst_import_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_FUNCTION => op_function_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_LOAD => op_load_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
@@ -92,15 +115,22 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         })
     };
 
-    // 3. Insert shared uint definitions and shared constants
-    // Since there are only two main float widths, we will include both for simplicity
+    // 3. Insert shared uint definitions
+    // The constants depend on the float width, so they are created lazily per float type below.
     let mut header_insert = InstructionInsert {
         previous_spv_idx: header_position.unwrap(),
         instruction: vec![],
     };
 
-    // NOTE: 64-bit isnan and isinf doesn't actually make much sense, so I will just leave it.
-    // In standard glsl, you cannot write that kind of substitution because there is no `uint64_t` nor `doubleBitsToUint`.
+    let mut ext_inst_import_insert = InstructionInsert {
+        previous_spv_idx: last_of_indices!(
+            op_capability_idxs,
+            op_extension_idxs,
+            op_ext_inst_import_idxs
+        )
+        .expect("Module has no OpCapability?"),
+        instruction: vec![],
+    };
 
     let uint32_id = ensure_type_int(
         &spv,
@@ -110,36 +140,15 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         32,
         SPV_SIGNEDNESS_UNSIGNED,
     );
-    let uint32_ptr_id = ensure_type_pointer(
-        &spv,
-        &op_type_pointer_idxs,
-        &mut instruction_bound,
-        &mut header_insert.instruction,
-        SPV_STORAGE_CLASS_FUNCTION,
-        uint32_id,
-    );
-    let shared_type_inputs_32 = NanInfSharedTypeInputs {
-        uint_id: uint32_id,
-        ptr_uint_id: uint32_ptr_id,
-    };
 
-    // let uint64_id = ensure_type_int(&mut header_insert.instruction, &mut instruction_bound, 64);
-    // let uint64_ptr_id = ensure_type_ptr(
-    //     &mut header_insert.instruction,
-    //     &mut instruction_bound,
-    //     uint64_id,
-    // );
-    // let shared_type_inputs_64 = NanInfSharedTypeInputs {
-    //     uint_id: uint64_id,
-    //     ptr_uint_id: uint64_ptr_id,
-    // };
-
-    let (shared_constants_32, mut constants_spv_32) =
-        nan_inf_shared_constants_spv(&mut instruction_bound, shared_type_inputs_32);
-    header_insert.instruction.append(&mut constants_spv_32);
-    // let (shared_constants_64, mut constants_spv_64) =
-    //     nan_inf_shared_constants(&mut instruction_bound, shared_type_inputs_64);
-    // header_insert.instruction.append(&mut constants_spv_64);
+    // `uint64_t` is only available with `Int64`, otherwise `double` is split into two `uint`.
+    let has_int64 = op_capability_idxs
+        .iter()
+        .any(|&idx| spv[idx + 1] == SPV_CAPABILITY_INT64);
+    let mut shared_by_float_type: HashMap<u32, (NanInfBits, NanInfSharedConstants)> =
+        HashMap::new();
+    let mut comparison_by_float_type: HashMap<u32, NanInfComparisonConstants> = HashMap::new();
+    let mut glsl_std_id = None;
 
     // 4. Insert shared isnan / isinf declaration and definitions
 
@@ -174,25 +183,33 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
                     get_underlying_vector_type(float_ty_id)
                         .map(|(a, b)| (a, Some(b)))
                         .unwrap_or((float_ty_id, None));
-                let pointer_float_ty_id = op_type_pointer_idxs
-                    .iter()
-                    .find_map(|&tp_idx| {
-                        let result_id = spv[tp_idx + 1];
-                        let underlying_type_id = spv[tp_idx + 3];
-
-                        (underlying_type_id == underlying_float_ty_id).then_some(result_id)
-                    })
-                    .unwrap_or_else(|| {
-                        let new_id = instruction_bound;
-                        instruction_bound += 1;
-                        header_insert.instruction.append(&mut vec![
-                            encode_word(4, SPV_INSTRUCTION_OP_TYPE_POINTER),
-                            new_id,
-                            SPV_STORAGE_CLASS_FUNCTION,
-                            underlying_float_ty_id,
-                        ]);
-                        new_id
-                    });
+                // Only helper functions take their argument through a `Function` pointer
+                let pointer_float_ty_id = if mode == IsNanIsInfMode::Function {
+                    op_type_pointer_idxs
+                        .iter()
+                        .find_map(|&tp_idx| {
+                            let result_id = spv[tp_idx + 1];
+                            let storage_class = spv[tp_idx + 2];
+                            let underlying_type_id = spv[tp_idx + 3];
+
+                            (storage_class == SPV_STORAGE_CLASS_FUNCTION
+                                && underlying_type_id == underlying_float_ty_id)
+                                .then_some(result_id)
+                        })
+                        .unwrap_or_else(|| {
+                            let new_id = instruction_bound;
+                            instruction_bound += 1;
+                            header_insert.instruction.append(&mut vec![
+                                encode_word(4, SPV_INSTRUCTION_OP_TYPE_POINTER),
+                                new_id,
+                                SPV_STORAGE_CLASS_FUNCTION,
+                                underlying_float_ty_id,
+                            ]);
+                            new_id
+                        })
+                } else {
+                    0
+                };
                 let bool_ty_id = spv[op_idx + 1];
                 let (underlying_bool_ty_id, bool_component_count) =
                     get_underlying_vector_type(bool_ty_id)
@@ -217,47 +234,164 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
 
     let mut function_definition_words = vec![];
 
+    #[derive(Clone, Copy)]
+    enum Implementation {
+        Function {
+            fn_id: u32,
+        },
+        Inline {
+            bits: NanInfBits,
+            constants: NanInfSharedConstants,
+        },
+        Comparison {
+            constants: NanInfComparisonConstants,
+        },
+    }
+
     struct PatchEntry {
-        fn_id: u32,
+        ty: IsNanOrIsInf,
+        implementation: Implementation,
         input: NanInfSharedFunctionInputs,
-        original_float_type_id: u32,
         bool_component_count: Option<usize>,
     }
     let mut patch_map: HashMap<usize, PatchEntry> = HashMap::new();
     for (ty, input, original_float_type_id, component_count) in fn_set {
-        let (fn_type, mut spv) = nan_inf_fn_type_spv(&mut instruction_bound, input);
-        let fn_type = if let Some(existing_fn_type) = fn_type_defs.get(&input).copied() {
-            existing_fn_type
-        } else {
-            header_insert.instruction.append(&mut spv);
-            fn_type_defs.insert(input, fn_type);
-            fn_type
-        };
-
-        let (selected_type_inputs, selected_constants) =
-            match get_float_type_width(input.float_id).expect("Our OpTypeFloat dispeared?") {
-                32 => (shared_type_inputs_32, shared_constants_32),
-                // 64 => (shared_type_inputs_64, shared_constants_64),
-                n => panic!(
-                    "Float width {} not supported for isnan/isinf substitution",
-                    n
-                ),
-            };
-
-        let (fn_id, mut spv) = is_nan_is_inf_spv(
-            &mut instruction_bound,
-            ty,
-            selected_type_inputs,
-            input,
-            fn_type,
-            selected_constants,
-        );
-        let fn_id = if let Some(existing_fn_id) = fn_defs.get(&(ty, input, selected_type_inputs)) {
-            *existing_fn_id
+        let width = get_float_type_width(input.float_id).expect("Our OpTypeFloat dispeared?");
+
+        let implementation = if mode == IsNanIsInfMode::Comparison {
+            let constants = *comparison_by_float_type
+                .entry(input.float_id)
+                .or_insert_with(|| {
+                    let glsl_std_id = *glsl_std_id.get_or_insert_with(|| {
+                        ensure_ext_inst_import(
+                            &spv,
+                            &op_ext_inst_import_idxs,
+                            &mut instruction_bound,
+                            &mut ext_inst_import_insert.instruction,
+                            |s| s.starts_with("GLSL.std."),
+                            "GLSL.std.450",
+                        )
+                    });
+                    let (constants, mut constants_spv) = nan_inf_comparison_constants_spv(
+                        &mut instruction_bound,
+                        input.float_id,
+                        width,
+                        glsl_std_id,
+                    );
+                    header_insert.instruction.append(&mut constants_spv);
+                    constants
+                });
+            Implementation::Comparison { constants }
         } else {
-            function_definition_words.append(&mut spv);
-            fn_defs.insert((ty, input, selected_type_inputs), fn_id);
-            fn_id
+            let (selected_bits, selected_constants) = *shared_by_float_type
+                .entry(input.float_id)
+                .or_insert_with(|| {
+                    let (bits, uint_id, uint_width) = match width {
+                        16 => {
+                            let float32_id = ensure_type_float(
+                                &spv,
+                                &op_type_float_idxs,
+                                &mut instruction_bound,
+                                &mut header_insert.instruction,
+                                32,
+                            );
+                            (
+                                NanInfBits::Direct {
+                                    uint_id: uint32_id,
+                                    widen_id: Some(float32_id),
+                                },
+                                uint32_id,
+                                32,
+                            )
+                        }
+                        64 if has_int64 => {
+                            let uint64_id = ensure_type_int(
+                                &spv,
+                                &op_type_int_idxs,
+                                &mut instruction_bound,
+                                &mut header_insert.instruction,
+                                64,
+                                SPV_SIGNEDNESS_UNSIGNED,
+                            );
+                            (
+                                NanInfBits::Direct {
+                                    uint_id: uint64_id,
+                                    widen_id: None,
+                                },
+                                uint64_id,
+                                64,
+                            )
+                        }
+                        64 => {
+                            let v2uint_id = ensure_type_vector(
+                                &spv,
+                                &op_type_vector_idxs,
+                                &mut instruction_bound,
+                                &mut header_insert.instruction,
+                                uint32_id,
+                                2,
+                            );
+                            (
+                                NanInfBits::Split {
+                                    uint_id: uint32_id,
+                                    v2uint_id,
+                                },
+                                uint32_id,
+                                32,
+                            )
+                        }
+                        _ => (
+                            NanInfBits::Direct {
+                                uint_id: uint32_id,
+                                widen_id: None,
+                            },
+                            uint32_id,
+                            32,
+                        ),
+                    };
+                    let (constants, mut constants_spv) = nan_inf_shared_constants_spv(
+                        &mut instruction_bound,
+                        uint_id,
+                        uint_width,
+                        // `float16_t` is checked as the `float` it was converted to
+                        nan_inf_float_layout(width.max(32), bits),
+                    );
+                    header_insert.instruction.append(&mut constants_spv);
+                    (bits, constants)
+                });
+
+            if mode == IsNanIsInfMode::Inline {
+                Implementation::Inline {
+                    bits: selected_bits,
+                    constants: selected_constants,
+                }
+            } else {
+                let (fn_type, mut fn_type_spv) = nan_inf_fn_type_spv(&mut instruction_bound, input);
+                let fn_type = if let Some(existing_fn_type) = fn_type_defs.get(&input).copied() {
+                    existing_fn_type
+                } else {
+                    header_insert.instruction.append(&mut fn_type_spv);
+                    fn_type_defs.insert(input, fn_type);
+                    fn_type
+                };
+
+                let (fn_id, mut fn_spv) = is_nan_is_inf_spv(
+                    &mut instruction_bound,
+                    ty,
+                    selected_bits,
+                    input,
+                    fn_type,
+                    selected_constants,
+                );
+                let fn_id = if let Some(existing_fn_id) = fn_defs.get(&(ty, input, selected_bits)) {
+                    *existing_fn_id
+                } else {
+                    function_definition_words.append(&mut fn_spv);
+                    fn_defs.insert((ty, input, selected_bits), fn_id);
+                    fn_id
+                };
+                Implementation::Function { fn_id }
+            }
         };
 
         let key = (ty, input, original_float_type_id, component_count);
@@ -265,54 +399,24 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
             patch_map.insert(
                 *op_idx,
                 PatchEntry {
-                    fn_id,
+                    ty,
+                    implementation,
                     input,
-                    original_float_type_id,
                     bool_component_count: component_count,
                 },
             );
         }
     }
 
-    // 5. Insert additional temp variables and indexing constants for vectored cases
-    // We will create the shared data used to generate these variables here
-    let mut indexing_constant_instructions = InstructionInsert {
-        previous_spv_idx: header_position.unwrap(),
-        instruction: vec![],
-    };
-
-    let max_components = patch_map
-        .values()
-        .filter_map(|v| v.bool_component_count)
-        .max()
-        .unwrap_or(0);
-
-    // We need constants for indexing
-    let mut index_ids = vec![];
-    for n in 0..max_components {
-        let index_id = instruction_bound;
-        instruction_bound += 1;
-        index_ids.push(index_id);
-
-        indexing_constant_instructions.instruction.append(&mut vec![
-            encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
-            uint32_id,
-            index_id,
-            n as u32,
-        ]);
-    }
-
-    instruction_inserts.push(indexing_constant_instructions);
-
-    // 6. Insert and patch isnan / isinf usage
+    // 5. Insert and patch isnan / isinf usage
     for &op_idx in op_is_nan_idxs.iter().chain(op_is_inf_idxs.iter()) {
         let result_type_id = spv[op_idx + 1];
         let result_id = spv[op_idx + 2];
         let x = spv[op_idx + 3];
         let PatchEntry {
-            fn_id,
+            ty,
+            implementation,
             input,
-            original_float_type_id,
             bool_component_count,
         } = patch_map[&op_idx];
 
@@ -320,20 +424,58 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
             new_spv[op_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
         }
 
-        // Both patch implementations need a temp variable
+        // Only function calls need a temp variable, everything else works on `x` directly
         // TODO: OPT further reduce the number of temp variables by sharing then within the same functions
         let mut temp_variable_instructions = InstructionInsert {
             previous_spv_idx: get_function_label_index_of_instruction_index(&spv, op_idx),
             instruction: vec![],
         };
-        let param_id = instruction_bound;
-        instruction_bound += 1;
-        temp_variable_instructions.instruction.append(&mut vec![
-            encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
-            input.ptr_float_id,
-            param_id,
-            SPV_STORAGE_CLASS_FUNCTION,
-        ]);
+        let param_id = if let Implementation::Function { .. } = implementation {
+            let param_id = instruction_bound;
+            instruction_bound += 1;
+            temp_variable_instructions.instruction.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
+                input.ptr_float_id,
+                param_id,
+                SPV_STORAGE_CLASS_FUNCTION,
+            ]);
+            param_id
+        } else {
+            0
+        };
+
+        // Substitute a single scalar `isnan` / `isinf` writing into `result_id`
+        let substitute = |instruction_bound: &mut u32, x, result_id| match implementation {
+            Implementation::Function { fn_id } => nan_inf_call_spv(NanInfCallInputs {
+                fn_id,
+                bool_id: input.bool_id,
+                param_id,
+                x,
+                result_id,
+            }),
+            Implementation::Inline { bits, constants } => nan_inf_check_spv(
+                instruction_bound,
+                NanInfCheckInputs {
+                    ty,
+                    bits,
+                    constants,
+                    bool_id: input.bool_id,
+                    x,
+                    result_id,
+                },
+            ),
+            Implementation::Comparison { constants } => nan_inf_comparison_spv(
+                instruction_bound,
+                NanInfComparisonInputs {
+                    ty,
+                    constants,
+                    bool_id: input.bool_id,
+                    float_id: input.float_id,
+                    x,
+                    result_id,
+                },
+            ),
+        };
 
         if let Some(component_count) = bool_component_count {
             let mut new_instructions = InstructionInsert {
@@ -341,51 +483,25 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
                 instruction: vec![],
             };
 
-            // We need a temp variable for the vector itself
-            let float_vector_type_pointer_id = op_type_pointer_idxs
-                .iter()
-                .find_map(|idx| (spv[idx + 3] == original_float_type_id).then_some(spv[idx + 1]))
-                .expect("This vector type has no type pointer?");
-            let temp_vector_id = instruction_bound;
-            instruction_bound += 1;
-            temp_variable_instructions.instruction.append(&mut vec![
-                encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
-                float_vector_type_pointer_id,
-                temp_vector_id,
-                SPV_STORAGE_CLASS_FUNCTION,
-            ]);
-
             let mut component_results = (0..component_count)
                 .map(|n| {
-                    let accessed_id = instruction_bound;
-                    instruction_bound += 1;
-                    let loaded_id = instruction_bound;
+                    let component_id = instruction_bound;
                     instruction_bound += 1;
-                    let fn_result_id = instruction_bound;
+                    let component_result_id = instruction_bound;
                     instruction_bound += 1;
                     new_instructions.instruction.append(&mut vec![
-                        encode_word(3, SPV_INSTRUCTION_OP_STORE),
-                        temp_vector_id,
-                        x,
-                        encode_word(5, SPV_INSTRUCTION_OP_ACCESS_CHAIN),
-                        input.ptr_float_id,
-                        accessed_id,
-                        temp_vector_id,
-                        index_ids[n],
-                        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                        encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
                         input.float_id,
-                        loaded_id,
-                        accessed_id,
-                        encode_word(3, SPV_INSTRUCTION_OP_STORE),
-                        param_id,
-                        loaded_id,
-                        encode_word(5, SPV_INSTRUCTION_OP_FUNCTION_CALL),
-                        input.bool_id,
-                        fn_result_id,
-                        fn_id,
-                        param_id,
+                        component_id,
+                        x,
+                        n as u32,
                     ]);
-                    fn_result_id
+                    new_instructions.instruction.append(&mut substitute(
+                        &mut instruction_bound,
+                        component_id,
+                        component_result_id,
+                    ));
+                    component_result_id
                 })
                 .collect::<Vec<u32>>();
 
@@ -402,16 +518,7 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         } else {
             let new_instructions = InstructionInsert {
                 previous_spv_idx: op_idx,
-                instruction: vec![
-                    encode_word(3, SPV_INSTRUCTION_OP_STORE),
-                    param_id,
-                    x,
-                    encode_word(5, SPV_INSTRUCTION_OP_FUNCTION_CALL),
-                    result_type_id,
-                    result_id,
-                    fn_id,
-                    param_id,
-                ],
+                instruction: substitute(&mut instruction_bound, x, result_id),
             };
             instruction_inserts.push(new_instructions);
         }
@@ -419,14 +526,15 @@ pub fn isnanisinfpatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         instruction_inserts.push(temp_variable_instructions);
     }
 
-    // 7. Insert New Instructions
+    // 6. Insert New Instructions
     instruction_inserts.insert(0, header_insert);
+    instruction_inserts.insert(0, ext_inst_import_insert);
     insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
     new_spv.append(&mut function_definition_words);
 
-    // 8. Remove Instructions that have been Whited Out.
+    // 7. Remove Instructions that have been Whited Out.
     prune_noops(&mut new_spv);
 
-    // 9. Write New Header and New Code
+    // 8. Write New Header and New Code
     Ok(fuse_final(spv_header, new_spv, instruction_bound))
 }
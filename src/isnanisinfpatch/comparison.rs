@@ -0,0 +1,103 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct NanInfComparisonConstants {
+    pub float_max: u32,
+    pub glsl_std_id: u32,
+}
+
+pub(super) fn nan_inf_comparison_constants_spv(
+    ib: &mut u32,
+    float_id: u32,
+    float_width: u32,
+    glsl_std_id: u32,
+) -> (NanInfComparisonConstants, Vec<u32>) {
+    //
+    //    %float_max = OpConstant %float 3.40282347e+38
+
+    let float_max = inc(ib);
+    let spv = match float_width {
+        16 => vec![
+            encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+            float_id,
+            float_max,
+            0x7bff,
+        ],
+        32 => vec![
+            encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+            float_id,
+            float_max,
+            0x7f7fffff,
+        ],
+        // 64-bit literals take two words, low-order word first.
+        64 => vec![
+            encode_word(5, SPV_INSTRUCTION_OP_CONSTANT),
+            float_id,
+            float_max,
+            0xffffffff,
+            0x7fefffff,
+        ],
+        n => panic!(
+            "Float width {} not supported for isnan/isinf substitution",
+            n
+        ),
+    };
+
+    (
+        NanInfComparisonConstants {
+            float_max,
+            glsl_std_id,
+        },
+        spv,
+    )
+}
+
+pub(super) struct NanInfComparisonInputs {
+    pub ty: IsNanOrIsInf,
+    pub constants: NanInfComparisonConstants,
+    pub bool_id: u32,
+    pub float_id: u32,
+    pub x: u32,
+    pub result_id: u32,
+}
+
+// Compare the already loaded float `x` against itself or the largest finite float.
+pub(super) fn nan_inf_comparison_spv(ib: &mut u32, inputs: NanInfComparisonInputs) -> Vec<u32> {
+    //
+    //  isnan:
+    //     %1 = OpFUnordNotEqual %bool %x %x
+    //
+    //  isinf:
+    //   %abs = OpExtInst %float %glsl_std FAbs %x
+    //     %1 = OpFOrdGreaterThan %bool %abs %float_max
+
+    let NanInfComparisonInputs {
+        ty,
+        constants,
+        bool_id,
+        float_id,
+        x,
+        result_id,
+    } = inputs;
+
+    match ty {
+        IsNanOrIsInf::IsNan => vec![
+            encode_word(5, SPV_INSTRUCTION_OP_F_UNORD_NOT_EQUAL),
+            bool_id,
+            result_id,
+            x,
+            x,
+        ],
+        IsNanOrIsInf::IsInf => {
+            let abs_id = inc(ib);
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                    float_id, abs_id, constants.glsl_std_id, SPV_GLSL_STD_INSTRUCTION_FABS, x,
+                encode_word(5, SPV_INSTRUCTION_OP_F_ORD_GREATER_THAN),
+                    bool_id, result_id, abs_id, constants.float_max,
+            ];
+            spv
+        }
+    }
+}
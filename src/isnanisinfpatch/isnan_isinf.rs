@@ -1,77 +1,153 @@
 use super::*;
 
+pub(super) struct NanInfCheckInputs {
+    pub ty: IsNanOrIsInf,
+    pub bits: NanInfBits,
+    pub constants: NanInfSharedConstants,
+    pub bool_id: u32,
+    pub x: u32,
+    pub result_id: u32,
+}
+
+// Test the bit pattern of the already loaded float `x`.
+// The resulting `bool` is written to `result_id`.
+pub(super) fn nan_inf_check_spv(ib: &mut u32, inputs: NanInfCheckInputs) -> Vec<u32> {
+    // The only difference between the two is one OpIEqual vs OpINotEqual
+    //
+    //  %bits = OpBitcast %uint %x
+    //   %exp = OpShiftRightLogical %uint %bits %uint_23
+    //   %exp = OpBitwiseAnd %uint %exp %uint_255
+    //  %frac = OpBitwiseAnd %uint %bits %uint_8388607
+    //     %1 = OpIEqual %bool %exp %uint_255
+    //     %2 = OpINotEqual %bool %frac %uint_0   ; isnan
+    //     %2 = OpIEqual %bool %frac %uint_0      ; isinf
+    //     %3 = OpLogicalAnd %bool %1 %2
+    //
+    // `float16_t` is first converted to `float`, and `double` without `Int64` is split into a
+    // `uvec2` where the low word is OR'd into the fraction.
+
+    let NanInfCheckInputs {
+        ty,
+        bits,
+        constants,
+        bool_id,
+        x,
+        result_id,
+    } = inputs;
+    let NanInfSharedConstants {
+        uint_shift,
+        uint_exp_mask,
+        uint_frac_mask,
+        uint_0,
+    } = constants;
+
+    let mut spv = vec![];
+
+    let (uint_id, bits_id, low_id) = match bits {
+        NanInfBits::Direct { uint_id, widen_id } => {
+            let x = if let Some(widen_id) = widen_id {
+                let wide_id = inc(ib);
+                spv.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_F_CONVERT),
+                    widen_id,
+                    wide_id,
+                    x,
+                ]);
+                wide_id
+            } else {
+                x
+            };
+            let bits_id = inc(ib);
+            spv.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                uint_id,
+                bits_id,
+                x,
+            ]);
+            (uint_id, bits_id, None)
+        }
+        NanInfBits::Split { uint_id, v2uint_id } => {
+            let words_id = inc(ib);
+            let low_id = inc(ib);
+            let high_id = inc(ib);
+            #[rustfmt::skip]
+            spv.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                    v2uint_id, words_id, x,
+                encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                    uint_id, low_id, words_id, 0,
+                encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                    uint_id, high_id, words_id, 1,
+            ]);
+            (uint_id, high_id, Some(low_id))
+        }
+    };
+
+    let shifted_id = inc(ib);
+    let exp_id = inc(ib);
+    let frac_id = inc(ib);
+    #[rustfmt::skip]
+    spv.append(&mut vec![
+        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL),
+            uint_id, shifted_id, bits_id, uint_shift,
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+            uint_id, exp_id, shifted_id, uint_exp_mask,
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+            uint_id, frac_id, bits_id, uint_frac_mask,
+    ]);
+
+    // The low word of a split `double` is entirely fraction.
+    let frac_id = if let Some(low_id) = low_id {
+        let full_frac_id = inc(ib);
+        spv.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_BITWISE_OR),
+            uint_id,
+            full_frac_id,
+            frac_id,
+            low_id,
+        ]);
+        full_frac_id
+    } else {
+        frac_id
+    };
+
+    let exp_cmp_id = inc(ib);
+    let frac_cmp_id = inc(ib);
+    #[rustfmt::skip]
+    spv.append(&mut vec![
+        encode_word(5, SPV_INSTRUCTION_OP_I_EQUAL),
+            bool_id, exp_cmp_id, exp_id, uint_exp_mask,
+        encode_word(5,
+            match ty {
+                IsNanOrIsInf::IsNan => SPV_INSTRUCTION_OP_I_NOT_EQUAL,
+                IsNanOrIsInf::IsInf => SPV_INSTRUCTION_OP_I_EQUAL,
+            }
+            ),
+            bool_id, frac_cmp_id, frac_id, uint_0,
+        encode_word(5, SPV_INSTRUCTION_OP_LOGICAL_AND),
+            bool_id, result_id, exp_cmp_id, frac_cmp_id,
+    ]);
+
+    spv
+}
+
 pub(super) fn is_nan_is_inf_spv(
     ib: &mut u32,
     ty: IsNanOrIsInf,
-    ty_inputs: NanInfSharedTypeInputs,
+    bits: NanInfBits,
     inputs: NanInfSharedFunctionInputs,
     function_type: NanInfFunctionType,
     shared_constants: NanInfSharedConstants,
 ) -> (u32, Vec<u32>) {
-    // The only difference between the two is one OpIEqual vs OpINotEqual
-    //
-    // %isnan_f1_ = OpFunction %bool None %_function_type
-    //         %x = OpFunctionParameter %_ptr_Function_float
-    //         %1 = OpLabel
-    //      %bits = OpVariable %_ptr_Function_uint Function
-    //       %exp = OpVariable %_ptr_Function_uint Function
-    //      %frac = OpVariable %_ptr_Function_uint Function
-    //         %2 = OpLoad %float %x
-    //         %3 = OpBitcast %uint %2
-    //              OpStore %bits %3
-    //         %4 = OpLoad %uint %bits
-    //         %5 = OpShiftRightLogical %uint %4 %uint_23
-    //         %6 = OpBitwiseAnd %uint %5 %uint_255
-    //              OpStore %exp %6
-    //         %7 = OpLoad %uint %bits
-    //         %8 = OpBitwiseAnd %uint %7 %uint_8388607
-    //              OpStore %frac %8
-    //         %9 = OpLoad %uint %exp
-    //        %10 = OpIEqual %bool %9 %uint_255
-    //        %11 = OpLoad %uint %frac
-    //        %12 = OpIEqual %bool %11 %uint_0
-    //        %13 = OpLogicalAnd %bool %10 %12
-    //              OpReturnValue %13
-    //              OpFunctionEnd
-    //
-    // %_isinf_f1_ = OpFunction %bool None %function_type
+    // %_isnan_f1_ = OpFunction %bool None %_function_type
     //          %x = OpFunctionParameter %_ptr_Function_float
     //          %1 = OpLabel
-    //       %bits = OpVariable %_ptr_Function_uint Function
-    //        %exp = OpVariable %_ptr_Function_uint Function
-    //       %frac = OpVariable %_ptr_Function_uint Function
     //          %2 = OpLoad %float %x
-    //          %3 = OpBitcast %uint %2
-    //               OpStore %bits %3
-    //          %4 = OpLoad %uint %bits
-    //          %5 = OpShiftRightLogical %uint %4 %int_23
-    //          %6 = OpBitwiseAnd %uint %5 %uint_255
-    //               OpStore %exp %6
-    //          %7 = OpLoad %uint %bits
-    //          %8 = OpBitwiseAnd %uint %7 %uint_8388607
-    //               OpStore %frac %8
-    //          %9 = OpLoad %uint %exp
-    //         %10 = OpIEqual %bool %9 %uint_255
-    //         %11 = OpLoad %uint %frac
-    //         %12 = OpINotEqual %bool %11 %uint_0
-    //         %13 = OpLogicalAnd %bool %10 %12
-    //               OpReturnValue %13
+    //               ... see `nan_inf_check_spv`
+    //               OpReturnValue %3
     //               OpFunctionEnd
-    //
 
     let function_type = function_type.0;
-    let NanInfSharedConstants {
-        uint_23,
-        uint_255,
-        uint_8388607,
-        uint_0,
-    } = shared_constants;
-
-    let NanInfSharedTypeInputs {
-        uint_id,
-        ptr_uint_id,
-    } = ty_inputs;
-
     let NanInfSharedFunctionInputs {
         bool_id,
         float_id,
@@ -81,75 +157,71 @@ pub(super) fn is_nan_is_inf_spv(
     let is_nan = inc(ib);
     let x = inc(ib);
     let res_1 = inc(ib);
-    let bits = inc(ib);
-    let exp = inc(ib);
-    let frac = inc(ib);
     let res_2 = inc(ib);
     let res_3 = inc(ib);
-    let res_4 = inc(ib);
-    let res_5 = inc(ib);
-    let res_6 = inc(ib);
-    let res_7 = inc(ib);
-    let res_8 = inc(ib);
-    let res_9 = inc(ib);
-    let res_10 = inc(ib);
-    let res_11 = inc(ib);
-    let res_12 = inc(ib);
-    let res_13 = inc(ib);
 
     #[rustfmt::skip]
-    let spv = vec![
-        encode_word(5, SPV_INSTRUCTION_OP_FUNCTION), 
-            bool_id, is_nan, SPV_FUNCTION_CONTROL_INLINE, function_type, 
+    let mut spv = vec![
+        encode_word(5, SPV_INSTRUCTION_OP_FUNCTION),
+            bool_id, is_nan, SPV_FUNCTION_CONTROL_INLINE, function_type,
         encode_word(3, SPV_INSTRUCTION_OP_FUNCTION_PARAMETER),
-            ptr_float_id, x, 
+            ptr_float_id, x,
         encode_word(2, SPV_INSTRUCTION_OP_LABEL),
             res_1,
-        encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
-            ptr_uint_id, bits, SPV_STORAGE_CLASS_FUNCTION,
-        encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
-            ptr_uint_id, exp, SPV_STORAGE_CLASS_FUNCTION,
-        encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
-            ptr_uint_id, frac, SPV_STORAGE_CLASS_FUNCTION,
-        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
-            float_id, res_2, x, 
-        encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
-            uint_id, res_3, res_2, 
-        encode_word(3, SPV_INSTRUCTION_OP_STORE),
-            bits, res_3,
-        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
-            uint_id, res_4, bits, 
-        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL),
-            uint_id, res_5, res_4, uint_23,
-        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
-            uint_id, res_6, res_5, uint_255,
-        encode_word(3, SPV_INSTRUCTION_OP_STORE),
-            exp, res_6,
-        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
-            uint_id, res_7, bits, 
-        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
-            uint_id, res_8, res_7, uint_8388607,
-        encode_word(3, SPV_INSTRUCTION_OP_STORE),
-            frac, res_8,
         encode_word(4, SPV_INSTRUCTION_OP_LOAD),
-            uint_id, res_9, exp, 
-        encode_word(5, SPV_INSTRUCTION_OP_I_EQUAL),
-            bool_id, res_10, res_9, uint_255,
-        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
-            uint_id, res_11, frac, 
-        encode_word(5, 
-            match ty {
-                IsNanOrIsInf::IsNan => SPV_INSTRUCTION_OP_I_EQUAL,
-                IsNanOrIsInf::IsInf => SPV_INSTRUCTION_OP_I_NOT_EQUAL,
-            }
-            ),
-            bool_id, res_12, res_11, uint_0,
-        encode_word(5, SPV_INSTRUCTION_OP_LOGICAL_AND),
-            bool_id, res_13, res_10, res_12,
+            float_id, res_2, x,
+    ];
+
+    let mut check_spv = nan_inf_check_spv(
+        ib,
+        NanInfCheckInputs {
+            ty,
+            bits,
+            constants: shared_constants,
+            bool_id,
+            x: res_2,
+            result_id: res_3,
+        },
+    );
+    spv.append(&mut check_spv);
+
+    #[rustfmt::skip]
+    spv.append(&mut vec![
         encode_word(2, SPV_INSTRUCTION_OP_RETURN_VALUE),
-            res_13,
+            res_3,
         encode_word(1, SPV_INSTRUCTION_OP_FUNCTION_END),
-    ];
+    ]);
 
     (is_nan, spv)
 }
+
+pub(super) struct NanInfCallInputs {
+    pub fn_id: u32,
+    pub bool_id: u32,
+    pub param_id: u32,
+    pub x: u32,
+    pub result_id: u32,
+}
+
+pub(super) fn nan_inf_call_spv(inputs: NanInfCallInputs) -> Vec<u32> {
+    //
+    //                 OpStore %param %x
+    //          %1 = OpFunctionCall %bool %_isnan_f1_ %param
+
+    let NanInfCallInputs {
+        fn_id,
+        bool_id,
+        param_id,
+        x,
+        result_id,
+    } = inputs;
+
+    #[rustfmt::skip]
+    let spv = vec![
+        encode_word(3, SPV_INSTRUCTION_OP_STORE),
+            param_id, x,
+        encode_word(5, SPV_INSTRUCTION_OP_FUNCTION_CALL),
+            bool_id, result_id, fn_id, param_id,
+    ];
+    spv
+}
@@ -4,7 +4,7 @@ bool _isnan(float x) {
     uint bits = floatBitsToUint(x);
     uint exp = (bits >> 23) & 0xffu;
     uint frac = bits & 0x7fffffu;
-    return exp == 0xffu && frac == 0u;
+    return exp == 0xffu && frac != 0u;
 }
 
 
@@ -12,7 +12,7 @@ bool _isinf(float x) {
     uint bits = floatBitsToUint(x);
     uint exp = (bits >> 23) & 0xffu;
     uint frac = bits & 0x7fffffu;
-    return exp == 0xffu && frac != 0u;
+    return exp == 0xffu && frac == 0u;
 }
 
 void main() {
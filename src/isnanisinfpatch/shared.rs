@@ -6,10 +6,16 @@ pub(super) enum IsNanOrIsInf {
     IsInf,
 }
 
+/// How the bits of a float are reinterpreted as an integer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub(super) struct NanInfSharedTypeInputs {
-    pub uint_id: u32,
-    pub ptr_uint_id: u32,
+pub(super) enum NanInfBits {
+    /// `float -> uint` or `double -> uint64_t`, a plain `OpBitcast` of the same width.
+    /// WGSL has no 16-bit integers, so `float16_t` is first converted to the `float` of `widen_id`
+    /// with `OpFConvert`, which keeps NaN and infinity, and then checked as a `float`.
+    Direct { uint_id: u32, widen_id: Option<u32> },
+    /// `double -> uvec2` for when the `Int64` capability is absent.
+    /// Component 0 holds the low word, component 1 holds the sign, exponent, and high mantissa.
+    Split { uint_id: u32, v2uint_id: u32 },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -22,14 +28,50 @@ pub(super) struct NanInfSharedFunctionInputs {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) struct NanInfFunctionType(pub u32);
 
+/// All constants share the integer type of [`NanInfBits`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) struct NanInfSharedConstants {
-    pub uint_23: u32,
-    pub uint_255: u32,
-    pub uint_8388607: u32,
+    pub uint_shift: u32,
+    pub uint_exp_mask: u32,
+    pub uint_frac_mask: u32,
     pub uint_0: u32,
 }
 
+/// Bit layout of an IEEE-754 float of a given width.
+/// For [`NanInfBits::Split`], `shift` and `frac_mask` are relative to the high word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct NanInfFloatLayout {
+    pub shift: u64,
+    pub exp_mask: u64,
+    pub frac_mask: u64,
+}
+
+pub(super) fn nan_inf_float_layout(width: u32, bits: NanInfBits) -> NanInfFloatLayout {
+    match (width, bits) {
+        // 1 sign, 8 exponent, 23 mantissa
+        (32, _) => NanInfFloatLayout {
+            shift: 23,
+            exp_mask: 0xff,
+            frac_mask: 0x7fffff,
+        },
+        // 1 sign, 11 exponent, 52 mantissa (20 of which live in the high word)
+        (64, NanInfBits::Split { .. }) => NanInfFloatLayout {
+            shift: 20,
+            exp_mask: 0x7ff,
+            frac_mask: 0xfffff,
+        },
+        (64, _) => NanInfFloatLayout {
+            shift: 52,
+            exp_mask: 0x7ff,
+            frac_mask: 0xfffffffffffff,
+        },
+        (n, _) => panic!(
+            "Float width {} not supported for isnan/isinf substitution",
+            n
+        ),
+    }
+}
+
 pub(super) fn nan_inf_fn_type_spv(
     ib: &mut u32,
     inputs: NanInfSharedFunctionInputs,
@@ -40,7 +82,7 @@ pub(super) fn nan_inf_fn_type_spv(
     let function_type = inc(ib);
     #[rustfmt::skip]
     let spv = vec![
-        encode_word(4, SPV_INSTRUCTION_OP_TYPE_FUNCTION), 
+        encode_word(4, SPV_INSTRUCTION_OP_TYPE_FUNCTION),
             function_type, inputs.bool_id, inputs.ptr_float_id,
     ];
 
@@ -49,7 +91,9 @@ pub(super) fn nan_inf_fn_type_spv(
 
 pub(super) fn nan_inf_shared_constants_spv(
     ib: &mut u32,
-    inputs: NanInfSharedTypeInputs,
+    uint_id: u32,
+    uint_width: u32,
+    layout: NanInfFloatLayout,
 ) -> (NanInfSharedConstants, Vec<u32>) {
     //
     //           %uint_23 = OpConstant %uint 23
@@ -57,28 +101,42 @@ pub(super) fn nan_inf_shared_constants_spv(
     //      %uint_8388607 = OpConstant %uint 8388607
     //            %uint_0 = OpConstant %uint 0
 
-    let uint_23 = inc(ib);
-    let uint_255 = inc(ib);
-    let uint_8388607 = inc(ib);
+    let uint_shift = inc(ib);
+    let uint_exp_mask = inc(ib);
+    let uint_frac_mask = inc(ib);
     let uint_0 = inc(ib);
 
-    #[rustfmt::skip]
-    let spv = vec![
-        encode_word(4, SPV_INSTRUCTION_OP_CONSTANT), 
-            inputs.uint_id, uint_23, 23,
-        encode_word(4, SPV_INSTRUCTION_OP_CONSTANT), 
-            inputs.uint_id, uint_255, 255,
-        encode_word(4, SPV_INSTRUCTION_OP_CONSTANT), 
-            inputs.uint_id, uint_8388607, 8388607,
-        encode_word(4, SPV_INSTRUCTION_OP_CONSTANT), 
-            inputs.uint_id, uint_0, 0,
-    ];
+    let mut spv = vec![];
+    for (id, value) in [
+        (uint_shift, layout.shift),
+        (uint_exp_mask, layout.exp_mask),
+        (uint_frac_mask, layout.frac_mask),
+        (uint_0, 0),
+    ] {
+        // 64-bit literals take two words, low-order word first.
+        if uint_width == 64 {
+            spv.append(&mut vec![
+                encode_word(5, SPV_INSTRUCTION_OP_CONSTANT),
+                uint_id,
+                id,
+                value as u32,
+                (value >> 32) as u32,
+            ]);
+        } else {
+            spv.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                uint_id,
+                id,
+                value as u32,
+            ]);
+        }
+    }
 
     (
         NanInfSharedConstants {
-            uint_23,
-            uint_255,
-            uint_8388607,
+            uint_shift,
+            uint_exp_mask,
+            uint_frac_mask,
             uint_0,
         },
         spv,
@@ -0,0 +1,366 @@
+use super::*;
+
+/// An authoritative bind group layout for [layoutpatch], such as the one an engine declares per
+/// material.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetLayout {
+    pub sets: HashMap<u32, TargetLayoutSet>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetLayoutSet {
+    pub bindings: HashMap<u32, TargetLayoutBinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetLayoutBinding {
+    pub kind: ResourceKind,
+    /// The image a texture or storage texture must be, any image matches if [None].
+    /// Storage texture stubs need this for their format, texture stubs default to a 2D `float`
+    /// texture.
+    pub image: Option<ImageReflection>,
+}
+
+/// Rewrite a module so that its bindings are exactly those of `layout`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+///
+/// Each resource keeps its set and binding if `layout` agrees on its kind, otherwise it is moved to
+/// the lowest free binding of the same kind in its set.
+/// Every binding the module does not use gets an unused stub variable.
+/// Fails if the module uses a resource that `layout` lacks, or if a stub cannot be declared.
+///
+/// Run this after all other transformations, the [CorrectionMap] no longer describes the result.
+pub fn layoutpatch(in_spv: &[u32], layout: &TargetLayout) -> Result<Vec<u32>, ()> {
+    let reflection = reflect(in_spv)?;
+
+    let spv = in_spv.to_owned();
+
+    let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations of instructions we need
+    let mut op_decorate_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_sampler_idxs = vec![];
+    let mut op_type_image_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    // Annotations go right before the first global, new globals go right after the last one
+    let mut last_annotation_idx = None;
+    let mut last_global_idx = None;
+
+    let mut previous_idx = None;
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if instruction == SPV_INSTRUCTION_OP_FUNCTION {
+            break;
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_SAMPLER => op_type_sampler_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_IMAGE => op_type_image_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            _ => {}
+        }
+        if global_result_id(&spv, spv_idx).is_some() {
+            if last_global_idx.is_none() {
+                last_annotation_idx = previous_idx;
+            }
+            last_global_idx = Some(spv_idx);
+        }
+
+        previous_idx = Some(spv_idx);
+        spv_idx += word_count as usize;
+    }
+    let last_annotation_idx = last_annotation_idx.ok_or(())?;
+    let last_global_idx = last_global_idx.ok_or(())?;
+
+    // 2. Assign each resource a binding, first those already in place
+    let matches = |target: &TargetLayoutBinding, resource: &ResourceReflection| {
+        target.kind == resource.kind
+            && target
+                .image
+                .as_ref()
+                .is_none_or(|image| resource.image.as_ref() == Some(image))
+    };
+
+    let mut rebinds = HashMap::new();
+    let mut used_bindings = HashSet::new();
+    for resource in reflection.resources.iter() {
+        let target_set = layout.sets.get(&resource.set).ok_or(())?;
+        if target_set
+            .bindings
+            .get(&resource.binding)
+            .is_some_and(|target| matches(target, resource))
+        {
+            used_bindings.insert((resource.set, resource.binding));
+            rebinds.insert((resource.set, resource.binding), resource.binding);
+        }
+    }
+    for resource in reflection.resources.iter() {
+        // Aliased variables share a binding
+        if rebinds.contains_key(&(resource.set, resource.binding)) {
+            continue;
+        }
+        let target_set = layout.sets.get(&resource.set).ok_or(())?;
+        let binding = target_set
+            .bindings
+            .iter()
+            .filter(|&(&binding, target)| {
+                !used_bindings.contains(&(resource.set, binding)) && matches(target, resource)
+            })
+            .map(|(&binding, _)| binding)
+            .min()
+            .ok_or(())?;
+        used_bindings.insert((resource.set, binding));
+        rebinds.insert((resource.set, resource.binding), binding);
+    }
+
+    // 3. Correct OpDecorate Bindings
+    let decoration_of = |target_id, decoration| {
+        op_decorate_idxs.iter().find_map(|&d_idx| {
+            (spv[d_idx + 1] == target_id && spv[d_idx + 2] == decoration).then_some(spv[d_idx + 3])
+        })
+    };
+    for &d_idx in op_decorate_idxs.iter() {
+        let target_id = spv[d_idx + 1];
+        let decoration_id = spv[d_idx + 2];
+        let binding = spv[d_idx + 3];
+        if decoration_id == SPV_DECORATION_BINDING
+            && let Some(set) = decoration_of(target_id, SPV_DECORATION_DESCRIPTOR_SET)
+            && let Some(&new_binding) = rebinds.get(&(set, binding))
+        {
+            new_spv[d_idx + 3] = new_binding;
+        }
+    }
+
+    // 4. Declare stubs for unused bindings
+    let mut ctx = NewGlobals::new(&spv, instruction_bound);
+
+    let mut stub_bindings = layout
+        .sets
+        .iter()
+        .flat_map(|(&set, target_set)| {
+            target_set
+                .bindings
+                .iter()
+                .map(move |(&binding, target)| (set, binding, target))
+        })
+        .filter(|&(set, binding, _)| !used_bindings.contains(&(set, binding)))
+        .collect::<Vec<_>>();
+    stub_bindings.sort_by_key(|&(set, binding, _)| (set, binding));
+
+    for (set, binding, target) in stub_bindings {
+        let (pointer_id, storage_class) = match target.kind {
+            ResourceKind::UniformBuffer | ResourceKind::StorageBuffer => {
+                let float_id = ctx.cached(vec![SPV_INSTRUCTION_OP_TYPE_FLOAT as u32, 32], |ctx| {
+                    ensure_type_float(
+                        ctx.spv,
+                        &op_type_float_idxs,
+                        &mut ctx.instruction_bound,
+                        &mut ctx.header,
+                        32,
+                    )
+                });
+                let struct_id = ctx.inc();
+                let pointer_id = ctx.inc();
+                #[rustfmt::skip]
+                ctx.header.append(&mut vec![
+                    encode_word(3, SPV_INSTRUCTION_OP_TYPE_STRUCT),
+                        struct_id, float_id,
+                    encode_word(4, SPV_INSTRUCTION_OP_TYPE_POINTER),
+                        pointer_id, SPV_STORAGE_CLASS_UNIFORM, struct_id,
+                ]);
+                #[rustfmt::skip]
+                ctx.decorations.append(&mut vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                        struct_id, 0, SPV_DECORATION_OFFSET, 0,
+                ]);
+                if target.kind == ResourceKind::UniformBuffer {
+                    #[rustfmt::skip]
+                    ctx.decorations.append(&mut vec![
+                        encode_word(3, SPV_INSTRUCTION_OP_DECORATE),
+                            struct_id, SPV_DECORATION_BLOCK,
+                    ]);
+                } else {
+                    // Read only storage is allowed in every stage
+                    #[rustfmt::skip]
+                    ctx.decorations.append(&mut vec![
+                        encode_word(3, SPV_INSTRUCTION_OP_DECORATE),
+                            struct_id, SPV_DECORATION_BUFFER_BLOCK,
+                        encode_word(4, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                            struct_id, 0, SPV_DECORATION_NON_WRITABLE,
+                    ]);
+                }
+                (pointer_id, SPV_STORAGE_CLASS_UNIFORM)
+            }
+            ResourceKind::Sampler => {
+                let sampler_id = ctx.cached(vec![SPV_INSTRUCTION_OP_TYPE_SAMPLER as u32], |ctx| {
+                    ensure_type_sampler(
+                        ctx.spv,
+                        &op_type_sampler_idxs,
+                        &mut ctx.instruction_bound,
+                        &mut ctx.header,
+                    )
+                });
+                let pointer_id =
+                    uniform_constant_pointer(&mut ctx, &op_type_pointer_idxs, sampler_id);
+                (pointer_id, SPV_STORAGE_CLASS_UNIFORM_CONSTANT)
+            }
+            ResourceKind::Texture | ResourceKind::StorageTexture => {
+                let storage = target.kind == ResourceKind::StorageTexture;
+                let image = match (&target.image, storage) {
+                    (Some(image), _) => image.clone(),
+                    (None, false) => ImageReflection {
+                        dim: SPV_DIMENSION_2D,
+                        depth: false,
+                        arrayed: false,
+                        multisampled: false,
+                        format: SPV_IMAGE_FORMAT_UNKNOWN,
+                        sampled_type: ReflectedType::Float { width: 32 },
+                    },
+                    // Storage textures cannot be declared without a format
+                    (None, true) => return Err(()),
+                };
+                if storage && image.format == SPV_IMAGE_FORMAT_UNKNOWN {
+                    return Err(());
+                }
+
+                let sampled_type_id = match image.sampled_type {
+                    ReflectedType::Float { width } => {
+                        ctx.cached(vec![SPV_INSTRUCTION_OP_TYPE_FLOAT as u32, width], |ctx| {
+                            ensure_type_float(
+                                ctx.spv,
+                                &op_type_float_idxs,
+                                &mut ctx.instruction_bound,
+                                &mut ctx.header,
+                                width,
+                            )
+                        })
+                    }
+                    ReflectedType::Int { width, signed } => ctx.cached(
+                        vec![SPV_INSTRUCTION_OP_TYPE_INT as u32, width, signed as u32],
+                        |ctx| {
+                            ensure_type_int(
+                                ctx.spv,
+                                &op_type_int_idxs,
+                                &mut ctx.instruction_bound,
+                                &mut ctx.header,
+                                width,
+                                signed as u32,
+                            )
+                        },
+                    ),
+                    _ => return Err(()),
+                };
+                let operands = [
+                    sampled_type_id,
+                    image.dim,
+                    image.depth as u32,
+                    image.arrayed as u32,
+                    image.multisampled as u32,
+                    if storage { 2 } else { 1 },
+                    if storage {
+                        image.format
+                    } else {
+                        SPV_IMAGE_FORMAT_UNKNOWN
+                    },
+                ];
+                let image_id = ctx.cached(
+                    [&[SPV_INSTRUCTION_OP_TYPE_IMAGE as u32][..], &operands].concat(),
+                    |ctx| {
+                        ensure_type_image(
+                            ctx.spv,
+                            &op_type_image_idxs,
+                            &mut ctx.instruction_bound,
+                            &mut ctx.header,
+                            operands,
+                        )
+                    },
+                );
+                let pointer_id =
+                    uniform_constant_pointer(&mut ctx, &op_type_pointer_idxs, image_id);
+                (pointer_id, SPV_STORAGE_CLASS_UNIFORM_CONSTANT)
+            }
+            _ => return Err(()),
+        };
+
+        let variable_id = ctx.inc();
+        #[rustfmt::skip]
+        ctx.header.append(&mut vec![
+            encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
+                pointer_id, variable_id, storage_class,
+        ]);
+        #[rustfmt::skip]
+        ctx.decorations.append(&mut vec![
+            encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                variable_id, SPV_DECORATION_DESCRIPTOR_SET, set,
+            encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                variable_id, SPV_DECORATION_BINDING, binding,
+        ]);
+        if target.kind == ResourceKind::StorageTexture {
+            #[rustfmt::skip]
+            ctx.decorations.append(&mut vec![
+                encode_word(3, SPV_INSTRUCTION_OP_DECORATE),
+                    variable_id, SPV_DECORATION_NON_READABLE,
+            ]);
+        }
+    }
+
+    // 5. Insert New Instructions
+    let instruction_bound = ctx.instruction_bound;
+    if !ctx.header.is_empty() {
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: last_global_idx,
+            instruction: ctx.header,
+        });
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: last_annotation_idx,
+            instruction: ctx.decorations,
+        });
+    }
+    insert_new_instructions(&spv, &mut new_spv, &[], &instruction_inserts);
+
+    // 6. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
+
+fn uniform_constant_pointer(
+    ctx: &mut NewGlobals,
+    op_type_pointer_idxs: &[usize],
+    type_id: u32,
+) -> u32 {
+    ctx.cached(
+        vec![
+            SPV_INSTRUCTION_OP_TYPE_POINTER as u32,
+            SPV_STORAGE_CLASS_UNIFORM_CONSTANT,
+            type_id,
+        ],
+        |ctx| {
+            ensure_type_pointer(
+                ctx.spv,
+                op_type_pointer_idxs,
+                &mut ctx.instruction_bound,
+                &mut ctx.header,
+                SPV_STORAGE_CLASS_UNIFORM_CONSTANT,
+                type_id,
+            )
+        },
+    )
+}
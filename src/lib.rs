@@ -9,17 +9,34 @@
 //! | Feature                           | `spirv-val` | `naga` | `tint` |
 //! | --------------------------------- | ----------- | ------ | ------ |
 //! | Combined Image Samplers           | ✅          | ✅     | ✅     |
-//! | Immediates (Push Constants)       | ✅          | ✅\*   | ✅     |
+//! | Immediates (Push Constants)       | ✅          | ✅ (1) | ⚠️ (2) |
 //! | Binding Arrays                    | ✅          | ✅     | ✅     |
-//! | Mixed Depth / Comparison          | ✅          | ⚠️\*   | ❌     |
-//! | isnan / isinf Patching            | ✅          | ✅     | ✅     |
+//! | Mixed Depth / Comparison          | ✅          | ✅     | ⚠️ (2) |
+//! | isnan / isinf Patching            | ✅          | ⚠️ (3) | ⚠️ (2) |
 //! | Storage Cube Patching             | ✅          | ✅     | ✅     |
-//! | Unused Image Sampler Pruning      | ✅          | ✅     | ✅     |
+//! | Unused Image Sampler Pruning      | ✅          | ✅ (4) | ⚠️ (2) |
+//! | Extended Instruction Lowering     | ⚠️ (5)      | ⚠️ (5) | ❌ (2) |
+//! | Bools in Uniform / Storage Blocks | ⚠️ (6)      | ⚠️ (6) | ❌ (2) |
+//! | 8-bit / 16-bit Storage Widening   | ✅          | ✅     | ❌ (2) |
+//! | Texel Buffer Emulation            | ✅          | ✅     | ❌ (2) |
+//! | Subpass Input Lowering            | ✅          | ✅     | ❌ (2) |
+//! | Specialization Constant Baking    | ✅          | ✅     | ❌ (2) |
+//! | Entry Point Splitting             | ✅          | ✅     | ❌ (2) |
+//! | Dead Code Elimination             | ✅          | ✅     | ❌ (2) |
+//! | Uniform Layout Repair             | ✅          | ✅     | ❌ (2) |
+//! | Scalar Block Layout               | ✅          | ✅     | ❌ (2) |
 //!
-//! > (1)\* 99% OK, just one very specific padding related `naga` bug.
+//! > (1) 99% OK, just one very specific padding related `naga` bug.
 //!
-//! > (2)\* Simple cases are OK.
-//! > With some [special patches](https://github.com/davnotdev/wgpu/tree/trunk-naga-patches), `naga` can process these.
+//! > (2) Not checked with `tint` yet, or only some of the tests were, see the tests of each feature.
+//!
+//! > (3) WGSL has no `f64`, so `double` only passes `spirv-val`.
+//!
+//! > (4) Combined image samplers must be split after pruning before `naga` can read the module.
+//!
+//! > (5) `InterpolateAtSample`, `InterpolateAtOffset`, `InterpolateAtCentroid` next to direct reads, and `Frexp` on non-32-bit floats fail, `PackDouble2x32` / `UnpackDouble2x32` need `f64`.
+//!
+//! > (6) Fails if a struct containing bools is used by both a block and anything else.
 //!
 //! ## Using the result
 //!
@@ -29,19 +46,33 @@
 //! 2. Ensure that your vertex and fragment shaders shader the same binding layout, use [`mirrorpatch`] for this purpose
 //!
 
+#![allow(clippy::result_unit_err)]
+
 use std::collections::{HashMap, HashSet};
 
+mod boolblockpatch;
 mod correction;
+mod extinstpatch;
 mod immediatespatch;
 mod isnanisinfpatch;
+mod layoutpatch;
 mod mirrorpatch;
+mod pruneunused;
 mod pruneunuseddref;
+mod reflect;
+mod scalarlayoutpatch;
+mod specconstantpatch;
 mod splitbindingarray;
 mod splitcombined;
 mod splitdref;
+mod splitentrypoints;
 mod spv;
 mod storagecubepatch;
+mod subpassinputpatch;
+mod texelbufferpatch;
+mod uniformlayoutpatch;
 mod util;
+mod widenstoragepatch;
 
 #[cfg(test)]
 mod test;
@@ -49,15 +80,27 @@ mod test;
 use spv::*;
 use util::*;
 
+pub use boolblockpatch::*;
 pub use correction::*;
+pub use extinstpatch::*;
 pub use immediatespatch::*;
 pub use isnanisinfpatch::*;
+pub use layoutpatch::*;
 pub use mirrorpatch::*;
+pub use pruneunused::*;
 pub use pruneunuseddref::*;
+pub use reflect::*;
+pub use scalarlayoutpatch::*;
+pub use specconstantpatch::*;
 pub use splitbindingarray::*;
 pub use splitcombined::*;
 pub use splitdref::*;
+pub use splitentrypoints::*;
 pub use storagecubepatch::*;
+pub use subpassinputpatch::*;
+pub use texelbufferpatch::*;
+pub use uniformlayoutpatch::*;
+pub use widenstoragepatch::*;
 
 #[derive(Debug, Clone)]
 struct InstructionInsert {
@@ -7,11 +7,39 @@ type LeftRightOutput = (Option<Vec<u32>>, Option<Vec<u32>>);
 /// This is important for ensuring patched vertex and fragment shaders have the same layout.
 /// This is because some transformations occur based off what instructions appear, so as a result,
 /// the vertex and fragment shader may have a different layout after a set of transformations
+///
+/// Immediates placed by [immediatespatch] are moved to the same set and binding in both shaders.
+/// Fails if a binding was converted in one shader but not the other, see [mirrorpatch_conflicts].
 pub fn mirrorpatch(
     left_spv: &[u32],
     left_corrections: &mut CorrectionMap,
     right_spv: &[u32],
     right_corrections: &mut CorrectionMap,
+) -> Result<LeftRightOutput, ()> {
+    if !mirrorpatch_conflicts(&[
+        (left_spv, &*left_corrections),
+        (right_spv, &*right_corrections),
+    ])
+    .is_empty()
+    {
+        return Err(());
+    }
+
+    let (l, r) = mirror_binding_pair(left_spv, left_corrections, right_spv, right_corrections)?;
+    let mut outputs = [l, r];
+    reconcile_immediates(
+        &mut [(left_spv, left_corrections), (right_spv, right_corrections)],
+        &mut outputs,
+    );
+    let [l, r] = outputs;
+    Ok((l, r))
+}
+
+fn mirror_binding_pair(
+    left_spv: &[u32],
+    left_corrections: &mut CorrectionMap,
+    right_spv: &[u32],
+    right_corrections: &mut CorrectionMap,
 ) -> Result<LeftRightOutput, ()> {
     if left_corrections.sets.is_none() && right_corrections.sets.is_none() {
         return Ok((None, None));
@@ -32,6 +60,7 @@ pub fn mirrorpatch(
         .copied()
         .collect::<Vec<_>>();
 
+    scan_set_idxs.sort();
     scan_set_idxs.dedup();
 
     for set_idx in scan_set_idxs {
@@ -102,6 +131,330 @@ pub fn mirrorpatch(
     Ok((l, r))
 }
 
+/// [mirrorpatch] for any number of shaders, such as a vertex, fragment, and compute shader sharing
+/// bind groups.
+/// Every set and binding is unified across all modules at once.
+/// Returns one output per module, in order, which is [None] if that module did not change.
+pub fn mirrorpatch_many(
+    modules: &mut [(&[u32], &mut CorrectionMap)],
+) -> Result<Vec<Option<Vec<u32>>>, ()> {
+    let conflict_modules = modules
+        .iter()
+        .map(|(spv, corrections)| (*spv, &**corrections))
+        .collect::<Vec<_>>();
+    if !mirrorpatch_conflicts(&conflict_modules).is_empty() {
+        return Err(());
+    }
+
+    let mut outputs = mirror_bindings_many(modules)?;
+    reconcile_immediates(modules, &mut outputs);
+    Ok(outputs)
+}
+
+fn mirror_bindings_many(
+    modules: &mut [(&[u32], &mut CorrectionMap)],
+) -> Result<Vec<Option<Vec<u32>>>, ()> {
+    if modules
+        .iter()
+        .all(|(_, corrections)| corrections.sets.is_none())
+    {
+        return Ok(vec![None; modules.len()]);
+    }
+
+    let corrections_maps = modules
+        .iter()
+        .map(|(_, corrections)| corrections.sets.as_ref().cloned().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    // Every correction that any module has for each set and binding
+    let mut union_bindings: HashMap<(u32, u32), CorrectionBinding> = HashMap::new();
+    for corrections_map in corrections_maps.iter() {
+        for (&set_idx, set) in corrections_map.iter() {
+            for (&binding_idx, binding) in set.bindings.iter() {
+                let union = union_bindings.entry((set_idx, binding_idx)).or_default();
+                union.corrections = union_corrections(&union.corrections, &binding.corrections);
+            }
+        }
+    }
+    // New variable ids are handed out in this order
+    let mut union_bindings = union_bindings.into_iter().collect::<Vec<_>>();
+    union_bindings.sort_by_key(|(k, _)| *k);
+
+    let mut outputs = vec![];
+    for ((spv, corrections), corrections_map) in modules.iter_mut().zip(corrections_maps.iter()) {
+        let mut affected_decorations = vec![];
+        let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+
+        for &((set_idx, binding_idx), ref union) in union_bindings.iter() {
+            let own = corrections_map
+                .get(&set_idx)
+                .and_then(|set| set.bindings.get(&binding_idx))
+                .cloned()
+                .unwrap_or_default();
+
+            push_affected_decorations(
+                &mut affected_decorations,
+                &mut instruction_bound,
+                set_idx,
+                binding_idx,
+                union,
+                &own,
+            );
+        }
+
+        let output = (!affected_decorations.is_empty())
+            .then(|| {
+                patch_spv_decorations(spv, corrections, instruction_bound, &affected_decorations)
+            })
+            .transpose()?;
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/// A binding whose type was converted in some modules but is declared unconverted in others.
+/// Mirroring cannot add a conversion, so the layouts would otherwise silently disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MirrorConflict {
+    pub set: u32,
+    pub binding: u32,
+    /// One of [`CorrectionType::ConvertStorageCube`], [`CorrectionType::ConvertTexelBuffer`], or
+    /// [`CorrectionType::ConvertSubpassInput`].
+    pub correction_type: CorrectionType,
+}
+
+/// Find every [MirrorConflict] between modules that will later be passed to [mirrorpatch] or
+/// [mirrorpatch_many], sorted by set and binding.
+/// Run the missing conversion (e.g. [storagecubepatch]) on the offending modules to resolve them.
+pub fn mirrorpatch_conflicts(modules: &[(&[u32], &CorrectionMap)]) -> Vec<MirrorConflict> {
+    let declared = modules
+        .iter()
+        .map(|(spv, _)| {
+            decorated_bindings(spv)
+                .into_iter()
+                .map(|(_, set, binding)| (set, binding))
+                .collect::<HashSet<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut conflicts = vec![];
+    for (_, corrections) in modules.iter() {
+        let Some(sets) = corrections.sets.as_ref() else {
+            continue;
+        };
+        for (&set, correction_set) in sets.iter() {
+            for (&binding, correction_binding) in correction_set.bindings.iter() {
+                for &correction_type in correction_binding.corrections.iter() {
+                    if !is_conversion(correction_type) {
+                        continue;
+                    }
+                    let conflict = MirrorConflict {
+                        set,
+                        binding,
+                        correction_type,
+                    };
+                    let missing =
+                        modules
+                            .iter()
+                            .zip(declared.iter())
+                            .any(|((_, other), other_declared)| {
+                                other_declared.contains(&(set, binding))
+                                    && !other
+                                        .sets
+                                        .as_ref()
+                                        .and_then(|sets| sets.get(&set))
+                                        .and_then(|other_set| other_set.bindings.get(&binding))
+                                        .is_some_and(|b| b.corrections.contains(&correction_type))
+                            });
+                    if missing && !conflicts.contains(&conflict) {
+                        conflicts.push(conflict);
+                    }
+                }
+            }
+        }
+    }
+    conflicts.sort_by_key(|c| (c.set, c.binding, c.correction_type as u16));
+    conflicts
+}
+
+// Conversions change the type of a binding in place rather than appending a new binding, so
+// there is nothing to mirror.
+fn is_conversion(correction_type: CorrectionType) -> bool {
+    matches!(
+        correction_type,
+        CorrectionType::ConvertStorageCube
+            | CorrectionType::ConvertTexelBuffer
+            | CorrectionType::ConvertSubpassInput
+    )
+}
+
+// Every `(id, set, binding)` triple decorated in `spv`.
+fn decorated_bindings(spv: &[u32]) -> Vec<(u32, u32, u32)> {
+    let mut sets = HashMap::new();
+    let mut bindings = HashMap::new();
+
+    let mut spv_idx = SPV_HEADER_LENGTH;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if instruction == SPV_INSTRUCTION_OP_DECORATE {
+            let target_id = spv[spv_idx + 1];
+            let decoration_id = spv[spv_idx + 2];
+            if decoration_id == SPV_DECORATION_DESCRIPTOR_SET {
+                sets.insert(target_id, spv[spv_idx + 3]);
+            } else if decoration_id == SPV_DECORATION_BINDING {
+                bindings.insert(target_id, spv[spv_idx + 3]);
+            }
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    let mut decorated = sets
+        .into_iter()
+        .filter_map(|(id, set)| bindings.get(&id).map(|&binding| (id, set, binding)))
+        .collect::<Vec<_>>();
+    decorated.sort_by_key(|&(id, set, binding)| (set, binding, id));
+    decorated
+}
+
+// Immediates may land in a different set or binding per module, for example when only the fragment
+// shader uses the last set.
+// Move every module's immediates to the largest chosen set. A binding already chosen in that set is
+// kept unless it collides with a binding some module declares, otherwise the immediates go after
+// every other binding of that set in any module.
+fn reconcile_immediates(
+    modules: &mut [(&[u32], &mut CorrectionMap)],
+    outputs: &mut [Option<Vec<u32>>],
+) {
+    // 1. Find the immediates of each module by id, ids are kept by mirroring
+    let immediates_ids = modules
+        .iter()
+        .map(|(spv, corrections)| {
+            let decorated = decorated_bindings(spv);
+            corrections
+                .immediates_buffers
+                .iter()
+                .filter_map(|buffer| {
+                    decorated
+                        .iter()
+                        .find(|&&(_, set, binding)| set == buffer.set && binding == buffer.binding)
+                        .map(|&(id, _, _)| id)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let Some(target_set) = modules
+        .iter()
+        .zip(immediates_ids.iter())
+        .filter(|(_, ids)| !ids.is_empty())
+        .filter_map(|((_, corrections), _)| corrections.immediates_set)
+        .max()
+    else {
+        return;
+    };
+
+    // 2. Keep the first chosen binding that is free in every module, or use the first binding after
+    // every other binding in `target_set`
+    let other_bindings = modules
+        .iter()
+        .zip(outputs.iter())
+        .zip(immediates_ids.iter())
+        .flat_map(|(((spv, _), output), ids)| {
+            decorated_bindings(output.as_deref().unwrap_or(spv))
+                .into_iter()
+                .filter(|&(id, set, _)| set == target_set && !ids.contains(&id))
+                .map(|(_, _, binding)| binding)
+                .collect::<Vec<_>>()
+        })
+        .collect::<HashSet<_>>();
+    let buffer_count = immediates_ids
+        .iter()
+        .map(|ids| ids.len())
+        .max()
+        .unwrap_or(0) as u32;
+    let target_binding = modules
+        .iter()
+        .filter(|(_, corrections)| corrections.immediates_set == Some(target_set))
+        .filter_map(|(_, corrections)| corrections.immediates_buffers.first())
+        .map(|buffer| buffer.binding)
+        .find(|&binding| {
+            (binding..binding + buffer_count).all(|binding| !other_bindings.contains(&binding))
+        })
+        .unwrap_or_else(|| {
+            other_bindings
+                .iter()
+                .map(|&binding| binding + 1)
+                .max()
+                .unwrap_or(0)
+        });
+
+    // 3. Redecorate immediates that are elsewhere
+    for (((spv, corrections), output), ids) in modules
+        .iter_mut()
+        .zip(outputs.iter_mut())
+        .zip(immediates_ids.iter())
+    {
+        if ids.is_empty() {
+            continue;
+        }
+
+        let mut new_spv = output.clone().unwrap_or_else(|| spv.to_vec());
+        let mut changed = false;
+        let mut spv_idx = SPV_HEADER_LENGTH;
+        while spv_idx < new_spv.len() {
+            let op = new_spv[spv_idx];
+            let word_count = hiword(op);
+            let instruction = loword(op);
+
+            if instruction == SPV_INSTRUCTION_OP_DECORATE
+                && let Some(position) = ids.iter().position(|&id| id == new_spv[spv_idx + 1])
+            {
+                let value = match new_spv[spv_idx + 2] {
+                    SPV_DECORATION_DESCRIPTOR_SET => Some(target_set),
+                    SPV_DECORATION_BINDING => Some(target_binding + position as u32),
+                    _ => None,
+                };
+                if let Some(value) = value
+                    && new_spv[spv_idx + 3] != value
+                {
+                    new_spv[spv_idx + 3] = value;
+                    changed = true;
+                }
+            }
+
+            spv_idx += word_count as usize;
+        }
+
+        if changed {
+            *output = Some(new_spv);
+        }
+        corrections.immediates_set = Some(target_set);
+        corrections.immediates_binding = Some(target_binding);
+        for (position, buffer) in corrections.immediates_buffers.iter_mut().enumerate() {
+            buffer.set = target_set;
+            buffer.binding = target_binding + position as u32;
+        }
+    }
+}
+
+// `l` followed by whatever `r` has that `l` does not, matched the same way as
+// `push_affected_decorations`.
+fn union_corrections(l: &[CorrectionType], r: &[CorrectionType]) -> Vec<CorrectionType> {
+    let mut matched = vec![false; l.len()];
+    let mut union = l.to_vec();
+    for r_correction in r.iter() {
+        match (0..l.len()).find(|&idx| !matched[idx] && l[idx] == *r_correction) {
+            Some(idx) => matched[idx] = true,
+            None => union.push(*r_correction),
+        }
+    }
+    union
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct NewVariable {
     set: u32,
@@ -151,6 +504,7 @@ fn patch_spv_decorations(
 
     // 2. Convert and insert new variables
     let mut cached_original_variable_idxs = HashMap::new();
+    let mut undeclared_variables = vec![];
     let affected_decorations = affected_decorations
         .iter()
         .map(|affected| {
@@ -181,13 +535,15 @@ fn patch_spv_decorations(
                             }))
                         .then_some(target_id)
                     }) else {
-                        // If there are no OpDecorates, no patching needs to be done.
-                        return Err(in_spv.to_vec());
+                        // This module does not declare this binding, so there is nothing to
+                        // mirror, but later bindings still have to make room for it.
+                        undeclared_variables.push(*affected);
+                        return Ok(None);
                     };
                     let idx = op_variable_idxs
                         .iter()
                         .find(|&idx| spv[idx + 2] == original_variable_id)
-                        .unwrap();
+                        .ok_or(())?;
                     cached_original_variable_idxs.insert((set, binding), idx);
                     idx
                 };
@@ -206,18 +562,18 @@ fn patch_spv_decorations(
             });
 
             // Convert into affected decoration
-            Ok(AffectedDecoration {
+            Ok(Some(AffectedDecoration {
                 original_res_id: original_variable_id,
                 new_res_ids: vec![new_res_id],
                 correction_type,
-            })
+            }))
         })
-        .collect::<Result<Vec<_>, _>>();
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>, ()>>()?;
 
-    let affected_decorations = match affected_decorations {
-        Ok(d) => d,
-        Err(spv) => return Ok(spv),
-    };
+    if affected_decorations.is_empty() && undeclared_variables.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
 
     // 3. Insert new OpDecorate
     let DecorateOut {
@@ -240,10 +596,56 @@ fn patch_spv_decorations(
         descriptor_sets_to_correct,
     });
 
-    // 6. Remove Instructions that have been Whited Out.
+    // 6. Shift bindings past the new variables of bindings this module does not declare, so that
+    //    every module ends up with the same layout
+    if !undeclared_variables.is_empty() {
+        let mut set_bindings = HashMap::new();
+        for &d_idx in op_decorate_idxs.iter() {
+            let set_binding = set_bindings.entry(spv[d_idx + 1]).or_insert((None, None));
+            match spv[d_idx + 2] {
+                SPV_DECORATION_DESCRIPTOR_SET => set_binding.0 = Some(spv[d_idx + 3]),
+                SPV_DECORATION_BINDING => set_binding.1 = Some(spv[d_idx + 3]),
+                _ => {}
+            }
+        }
+        for affected in affected_decorations.iter() {
+            let original = set_bindings[&affected.original_res_id];
+            for &new_res_id in affected.new_res_ids.iter() {
+                set_bindings.insert(new_res_id, original);
+            }
+        }
+
+        let mut spv_idx = 0;
+        while spv_idx < new_spv.len() {
+            if loword(new_spv[spv_idx]) == SPV_INSTRUCTION_OP_DECORATE
+                && new_spv[spv_idx + 2] == SPV_DECORATION_BINDING
+                && let Some(&(Some(set), Some(binding))) = set_bindings.get(&new_spv[spv_idx + 1])
+            {
+                new_spv[spv_idx + 3] += undeclared_variables
+                    .iter()
+                    .filter(|undeclared| undeclared.set == set && undeclared.binding <= binding)
+                    .count() as u32;
+            }
+            spv_idx += hiword(new_spv[spv_idx]) as usize;
+        }
+
+        // Record them so that the correction maps agree and mirroring again changes nothing
+        let sets = corrections.sets.get_or_insert_default();
+        for undeclared in undeclared_variables.iter() {
+            sets.entry(undeclared.set)
+                .or_default()
+                .bindings
+                .entry(undeclared.binding)
+                .or_default()
+                .corrections
+                .push(undeclared.correction_type);
+        }
+    }
+
+    // 7. Remove Instructions that have been Whited Out.
     prune_noops(&mut new_spv);
 
-    // 7. Write New Header and New Code
+    // 8. Write New Header and New Code
     Ok(fuse_final(spv_header, new_spv, instruction_bound))
 }
 
@@ -258,11 +660,12 @@ fn push_affected_decorations(
     let mut ll = l
         .corrections
         .iter()
+        .filter(|correction| !is_conversion(**correction))
         .map(Some)
         .enumerate()
         .collect::<Vec<_>>();
 
-    for r_correction in r.corrections.iter() {
+    for r_correction in r.corrections.iter().filter(|c| !is_conversion(**c)) {
         let idx_ty = ll
             .iter()
             .find(|(_, correction)| Some(r_correction) == correction.as_ref().copied())
@@ -299,6 +702,7 @@ fn test_push_affected_decorations() {
             CorrectionType::SplitCombined,
             CorrectionType::SplitDrefComparison,
         ],
+        ..Default::default()
     };
 
     let r = CorrectionBinding {
@@ -306,6 +710,7 @@ fn test_push_affected_decorations() {
             CorrectionType::SplitDrefRegular,
             CorrectionType::SplitDrefComparison,
         ],
+        ..Default::default()
     };
 
     let mut affected = vec![];
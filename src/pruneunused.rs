@@ -0,0 +1,169 @@
+use super::*;
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Removes functions that no entry point can reach along with global variables of any storage
+/// class, types, constants, names, and decorations that are no longer used.
+/// `Input` and `Output` variables listed by an entry point are always kept.
+/// Does not produce any side effects or corrections.
+pub fn pruneunused(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_entry_point_idxs = vec![];
+    let mut op_execution_mode_id_idxs = vec![];
+    let mut op_name_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_global_idxs = vec![];
+    let mut function_ranges = vec![];
+
+    let mut function_start = None;
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_ENTRY_POINT => op_entry_point_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXECUTION_MODE_ID => op_execution_mode_id_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_NAME | SPV_INSTRUCTION_OP_MEMBER_NAME => op_name_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE
+            | SPV_INSTRUCTION_OP_MEMBER_DECORATE
+            | SPV_INSTRUCTION_OP_DECORATE_ID
+            | SPV_INSTRUCTION_OP_DECORATE_STRING
+            | SPV_INSTRUCTION_OP_MEMBER_DECORATE_STRING => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => function_start = Some(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION_END => {
+                let start = function_start.take().ok_or(())?;
+                function_ranges.push((start, spv_idx + word_count as usize));
+            }
+            _ if function_start.is_none() && global_result_id(&spv, spv_idx).is_some() => {
+                op_global_idxs.push(spv_idx)
+            }
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    if op_entry_point_idxs.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    let variable_storage_classes = op_global_idxs
+        .iter()
+        .filter(|&&idx| loword(spv[idx]) == SPV_INSTRUCTION_OP_VARIABLE)
+        .map(|&idx| (spv[idx + 2], spv[idx + 3]))
+        .collect::<HashMap<_, _>>();
+    let is_stage_io = |id: u32| {
+        variable_storage_classes
+            .get(&id)
+            .is_some_and(|&storage_class| {
+                storage_class == SPV_STORAGE_CLASS_INPUT
+                    || storage_class == SPV_STORAGE_CLASS_OUTPUT
+            })
+    };
+
+    // 2. Find everything the entry points can reach
+    let entry_point_interfaces = op_entry_point_idxs
+        .iter()
+        .map(|&ep_idx| Ok((ep_idx, entry_point_interface_idx(&spv, ep_idx)?)))
+        .collect::<Result<Vec<_>, ()>>()?;
+
+    let mut roots = vec![];
+    for &(ep_idx, interface_idx) in entry_point_interfaces.iter() {
+        let word_count = hiword(spv[ep_idx]) as usize;
+        roots.push(spv[ep_idx + 2]);
+        roots.extend(
+            spv[interface_idx..ep_idx + word_count]
+                .iter()
+                .copied()
+                .filter(|&id| is_stage_io(id)),
+        );
+    }
+    for &idx in op_execution_mode_id_idxs.iter() {
+        roots.extend_from_slice(&spv[idx + 3..idx + hiword(spv[idx]) as usize]);
+    }
+
+    let op_decorate_id_idxs = op_decorate_idxs
+        .iter()
+        .copied()
+        .filter(|&idx| loword(spv[idx]) == SPV_INSTRUCTION_OP_DECORATE_ID)
+        .collect::<Vec<_>>();
+    let used_ids = trace_used_ids(TraceUsedIdsIn {
+        spv: &spv,
+        op_global_idxs: &op_global_idxs,
+        function_ranges: &function_ranges,
+        op_decorate_id_idxs: &op_decorate_id_idxs,
+        roots,
+    });
+
+    // 3. Drop unused variables from entry point interfaces
+    for &(ep_idx, interface_idx) in entry_point_interfaces.iter() {
+        let word_count = hiword(spv[ep_idx]) as usize;
+        let interface = &spv[interface_idx..ep_idx + word_count];
+        if interface.iter().all(|id| used_ids.contains(id)) {
+            continue;
+        }
+
+        let mut instruction = spv[ep_idx..interface_idx].to_vec();
+        instruction.extend(interface.iter().filter(|id| used_ids.contains(id)));
+        instruction[0] = encode_word(instruction.len() as u16, SPV_INSTRUCTION_OP_ENTRY_POINT);
+
+        new_spv[ep_idx..ep_idx + word_count].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: ep_idx,
+            instruction,
+        });
+    }
+
+    // 4. Remove what is not used
+    let unused_name_decorate_idxs = op_name_idxs
+        .iter()
+        .chain(op_decorate_idxs.iter())
+        .copied()
+        .filter(|&idx| !used_ids.contains(&spv[idx + 1]));
+    let unused_global_idxs = op_global_idxs
+        .iter()
+        .copied()
+        .filter(|&idx| global_result_id(&spv, idx).is_some_and(|id| !used_ids.contains(&id)));
+    let unused_function_idxs = function_ranges
+        .iter()
+        .filter(|&&(start, _)| !used_ids.contains(&spv[start + 2]))
+        .flat_map(|&(start, end)| start..end);
+
+    for spv_idx in unused_name_decorate_idxs.chain(unused_global_idxs) {
+        let word_count = hiword(spv[spv_idx]) as usize;
+        new_spv[spv_idx..spv_idx + word_count].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+    }
+    for spv_idx in unused_function_idxs {
+        new_spv[spv_idx] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+    }
+
+    // 5. Insert New Instructions
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 6. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 7. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
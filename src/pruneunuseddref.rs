@@ -1,6 +1,52 @@
 use super::*;
 
+/// Whether [pruneunuseddref_with_mode] removes unused resources or only reports them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PruneUnusedDrefMode {
+    #[default]
+    Prune,
+    Report,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnusedResourceKind {
+    Sampler,
+    Texture,
+    CombinedImageSampler,
+    StorageTexture,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnusedResource {
+    pub set: u32,
+    pub binding: u32,
+    pub kind: UnusedResourceKind,
+}
+
+/// The resources that [pruneunuseddref_with_mode] found to be unused, sorted by set and binding.
+/// Resources without a descriptor set and binding are not listed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PruneUnusedDrefReport {
+    pub unused: Vec<UnusedResource>,
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Prunes unused samplers, textures, combined image samplers, and storage textures.
+/// Equivalent to [pruneunuseddref_with_mode] with [PruneUnusedDrefMode::Prune].
 pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
+    pruneunuseddref_with_mode(in_spv, PruneUnusedDrefMode::Prune, &mut Default::default())
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Every unused resource is written to `report`.
+/// With [PruneUnusedDrefMode::Report], the module is returned unchanged.
+pub fn pruneunuseddref_with_mode(
+    in_spv: &[u32],
+    mode: PruneUnusedDrefMode,
+    report: &mut PruneUnusedDrefReport,
+) -> Result<Vec<u32>, ()> {
     let spv = in_spv.to_owned();
 
     let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
@@ -10,10 +56,14 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
 
     assert_eq!(magic_number, SPV_HEADER_MAGIC);
 
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
     let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
     let mut new_spv = spv.clone();
 
     // 1. Find locations instructions we need
+    let mut op_entry_point_idxs = vec![];
     let mut op_type_pointer_idxs = vec![];
     let mut op_type_image_idxs = vec![];
     let mut op_variable_idxs = vec![];
@@ -25,6 +75,12 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
 
     let mut op_type_sampler_id_map = HashSet::new();
     let mut op_sampled_image_id_map = HashSet::new();
+    let mut op_type_sampled_image_id_map = HashSet::new();
+    let mut op_type_array_element_map = HashMap::new();
+
+    // Ids referenced from within any function
+    let mut function_referenced_ids = HashSet::new();
+    let mut in_function = false;
 
     let mut spv_idx = 0;
     while spv_idx < spv.len() {
@@ -32,7 +88,18 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         let word_count = hiword(op);
         let instruction = loword(op);
 
+        if word_count == 0 {
+            return Err(());
+        }
+
+        if in_function {
+            function_referenced_ids.extend(referenced_ids(&spv, spv_idx));
+        }
+
         match instruction {
+            SPV_INSTRUCTION_OP_ENTRY_POINT => op_entry_point_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => in_function = true,
+            SPV_INSTRUCTION_OP_FUNCTION_END => in_function = false,
             SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_TYPE_IMAGE => op_type_image_idxs.push(spv_idx),
             SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
@@ -52,6 +119,15 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
                 op_sampled_image_id_map.insert(image_id);
                 op_sampled_image_id_map.insert(sampler_id);
             }
+            SPV_INSTRUCTION_OP_TYPE_SAMPLED_IMAGE => {
+                let result_id = spv[spv_idx + 1];
+                op_type_sampled_image_id_map.insert(result_id);
+            }
+            SPV_INSTRUCTION_OP_TYPE_ARRAY | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => {
+                let result_id = spv[spv_idx + 1];
+                let element_type_id = spv[spv_idx + 2];
+                op_type_array_element_map.insert(result_id, element_type_id);
+            }
             _ => {}
         }
 
@@ -87,6 +163,35 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         })
         .collect::<HashSet<_>>();
 
+    // Combined image samplers and storage textures, including arrays of them.
+    // Unlike samplers and textures, these are used as soon as any function references them.
+    let storage_image_type_ids = op_type_image_idxs
+        .iter()
+        .filter(|&&ti_idx| {
+            let image_dim = spv[ti_idx + 3];
+            let image_sampled = spv[ti_idx + 7];
+            image_sampled == 2 && image_dim != SPV_DIMENSION_SUBPASS_DATA
+        })
+        .map(|&ti_idx| spv[ti_idx + 1])
+        .collect::<HashSet<_>>();
+    let referenced_type_pointers_map = op_type_pointer_idxs
+        .iter()
+        .filter_map(|&tp_idx| {
+            let result_id = spv[tp_idx + 1];
+            let mut underlying_type_id = spv[tp_idx + 3];
+            while let Some(&element_type_id) = op_type_array_element_map.get(&underlying_type_id) {
+                underlying_type_id = element_type_id;
+            }
+            if op_type_sampled_image_id_map.contains(&underlying_type_id) {
+                Some((result_id, UnusedResourceKind::CombinedImageSampler))
+            } else if storage_image_type_ids.contains(&underlying_type_id) {
+                Some((result_id, UnusedResourceKind::StorageTexture))
+            } else {
+                None
+            }
+        })
+        .collect::<HashMap<_, _>>();
+
     // 3. Final all OpVariable to OpTypePointers
     let variable_result_map = op_variable_idxs
         .iter()
@@ -94,8 +199,14 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
             let tp_id = spv[idx + 1];
             let result_id = spv[idx + 2];
 
-            (image_type_pointers_map.contains(&tp_id) || sampler_type_pointers_map.contains(&tp_id))
-                .then_some((result_id, idx))
+            let kind = if image_type_pointers_map.contains(&tp_id) {
+                UnusedResourceKind::Texture
+            } else if sampler_type_pointers_map.contains(&tp_id) {
+                UnusedResourceKind::Sampler
+            } else {
+                *referenced_type_pointers_map.get(&tp_id)?
+            };
+            Some((result_id, (idx, kind)))
         })
         .collect::<HashMap<_, _>>();
 
@@ -118,6 +229,14 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         })
         .collect::<HashSet<_>>();
 
+    used_variable_idxs.extend(variable_result_map.iter().filter_map(|(&id, &(_, kind))| {
+        let is_referenced_kind = matches!(
+            kind,
+            UnusedResourceKind::CombinedImageSampler | UnusedResourceKind::StorageTexture
+        );
+        (is_referenced_kind && function_referenced_ids.contains(&id)).then_some(id)
+    }));
+
     // 5. Final all OpFunctionParameter to OpLoad
     let function_parameter_idxs = op_function_parameter_idxs.iter().filter(|&fp_idx| {
         let result_id = spv[fp_idx + 2];
@@ -144,12 +263,65 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         }
     }
 
-    // 8. Remove unused variables
+    // 7. Report unused variables
     let unused_variable_idxs = variable_result_map
         .iter()
-        .filter_map(|(id, &idx)| (!used_variable_idxs.contains(id)).then_some(idx))
+        .filter_map(|(id, &(idx, _))| (!used_variable_idxs.contains(id)).then_some(idx))
         .collect::<Vec<_>>();
 
+    let decoration_of = |target: u32, decoration: u32| {
+        op_decorate_idxs.iter().find_map(|&idx| {
+            (hiword(spv[idx]) == 4 && spv[idx + 1] == target && spv[idx + 2] == decoration)
+                .then_some(spv[idx + 3])
+        })
+    };
+    report.unused = variable_result_map
+        .iter()
+        .filter(|(id, _)| !used_variable_idxs.contains(id))
+        .filter_map(|(&id, &(_, kind))| {
+            Some(UnusedResource {
+                set: decoration_of(id, SPV_DECORATION_DESCRIPTOR_SET)?,
+                binding: decoration_of(id, SPV_DECORATION_BINDING)?,
+                kind,
+            })
+        })
+        .collect();
+    report
+        .unused
+        .sort_by_key(|resource| (resource.set, resource.binding));
+
+    if mode == PruneUnusedDrefMode::Report {
+        return Ok(in_spv.to_vec());
+    }
+
+    // 8. Remove unused variables from entry point interfaces
+    let unused_variable_ids = unused_variable_idxs
+        .iter()
+        .map(|&idx| spv[idx + 2])
+        .collect::<HashSet<_>>();
+    for &ep_idx in op_entry_point_idxs.iter() {
+        let word_count = hiword(spv[ep_idx]) as usize;
+        let interface_idx = entry_point_interface_idx(&spv, ep_idx)?;
+        let interface = &spv[interface_idx..ep_idx + word_count];
+        if !interface.iter().any(|id| unused_variable_ids.contains(id)) {
+            continue;
+        }
+
+        let mut instruction = spv[ep_idx..interface_idx].to_vec();
+        instruction.extend(
+            interface
+                .iter()
+                .filter(|id| !unused_variable_ids.contains(id)),
+        );
+        instruction[0] = encode_word(instruction.len() as u16, SPV_INSTRUCTION_OP_ENTRY_POINT);
+
+        new_spv[ep_idx..ep_idx + word_count].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: ep_idx,
+            instruction,
+        });
+    }
+
     // 9. Find OpDecorate / OpName to OpVariable
     let unused_decorate_idxs = op_decorate_idxs
         .iter()
@@ -175,7 +347,7 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
         .copied()
         .collect::<Vec<_>>();
 
-    // 9. Remove instructions
+    // 10. Remove instructions
     for spv_idx in unused_variable_idxs
         .into_iter()
         .chain(unused_decorate_idxs)
@@ -186,8 +358,9 @@ pub fn pruneunuseddref(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
 
         new_spv[spv_idx..spv_idx + word_count].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
     }
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
     prune_noops(&mut new_spv);
 
-    // 10. Write New Header and New Code
+    // 11. Write New Header and New Code
     Ok(fuse_final(spv_header, new_spv, instruction_bound))
 }
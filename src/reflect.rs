@@ -0,0 +1,572 @@
+use super::*;
+
+/// Everything [reflect] found in a module.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ModuleReflection {
+    pub entry_points: Vec<EntryPointReflection>,
+    /// Sorted by set and binding.
+    pub resources: Vec<ResourceReflection>,
+    /// In declaration order.
+    pub push_constants: Vec<PushConstantReflection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPointReflection {
+    pub name: String,
+    /// The `OpEntryPoint`'s execution model, such as `0` for vertex, `4` for fragment, and `5` for
+    /// compute.
+    pub execution_model: u32,
+    /// From `LocalSize`, `LocalSizeId`, or the `WorkgroupSize` builtin, which takes precedence for
+    /// compute, kernel, task, and mesh entry points.
+    /// Specialization constants are reported with their default value.
+    pub workgroup_size: Option<[u32; 3]>,
+    /// The entry point's `Input` variables with a location, builtins are skipped. Sorted by location.
+    pub inputs: Vec<InterfaceVariableReflection>,
+    /// The entry point's `Output` variables with a location, builtins are skipped. Sorted by location.
+    pub outputs: Vec<InterfaceVariableReflection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceVariableReflection {
+    pub name: Option<String>,
+    pub location: u32,
+    pub component: u32,
+    pub ty: ReflectedType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    UniformBuffer,
+    StorageBuffer,
+    Sampler,
+    Texture,
+    CombinedImageSampler,
+    StorageTexture,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    InputAttachment,
+    AccelerationStructure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceReflection {
+    pub name: Option<String>,
+    pub set: u32,
+    pub binding: u32,
+    pub kind: ResourceKind,
+    /// `None` if the resource is not an array, and `Some(0)` for runtime arrays.
+    pub array_size: Option<u32>,
+    /// Only set for textures, storage textures, texel buffers, and input attachments.
+    pub image: Option<ImageReflection>,
+    /// The buffer block's type, only set for uniform and storage buffers.
+    pub block: Option<ReflectedType>,
+    /// Set when the variable or every member of its block is decorated `NonReadable`.
+    pub non_readable: bool,
+    /// Set when the variable or every member of its block is decorated `NonWritable`.
+    pub non_writable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReflection {
+    /// The SPIR-V `Dim`, such as `1` for 2D and `3` for cube.
+    pub dim: u32,
+    pub depth: bool,
+    pub arrayed: bool,
+    pub multisampled: bool,
+    /// The SPIR-V `ImageFormat`, `0` if unknown.
+    pub format: u32,
+    pub sampled_type: ReflectedType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushConstantReflection {
+    pub name: Option<String>,
+    /// The end of the last member, as laid out by its `Offset` decorations.
+    pub size: u32,
+    pub members: Vec<StructMemberReflection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReflectedType {
+    Bool,
+    Int {
+        width: u32,
+        signed: bool,
+    },
+    Float {
+        width: u32,
+    },
+    Vector {
+        component: Box<ReflectedType>,
+        count: u32,
+    },
+    Matrix {
+        column: Box<ReflectedType>,
+        columns: u32,
+    },
+    /// `len` is `None` for runtime arrays.
+    Array {
+        element: Box<ReflectedType>,
+        len: Option<u32>,
+        stride: Option<u32>,
+    },
+    Struct {
+        name: Option<String>,
+        members: Vec<StructMemberReflection>,
+    },
+    /// Images, samplers, pointers, and anything else without a layout.
+    Opaque,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructMemberReflection {
+    pub name: Option<String>,
+    pub offset: Option<u32>,
+    /// `None` for runtime arrays or when the layout decorations are missing.
+    pub size: Option<u32>,
+    pub ty: ReflectedType,
+}
+
+/// List the entry points, resources, push constants, and stage interfaces of a module.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Run this after the other transformations to see the bindings that WebGPU will expect.
+pub fn reflect(in_spv: &[u32]) -> Result<ModuleReflection, ()> {
+    let magic_number = in_spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let spv = &in_spv[SPV_HEADER_LENGTH..];
+
+    // 1. Find locations instructions we need
+    let mut op_entry_point_idxs = vec![];
+    let mut op_execution_mode_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut info = ModuleInfo {
+        spv,
+        ..Default::default()
+    };
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op) as usize;
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_ENTRY_POINT => op_entry_point_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXECUTION_MODE | SPV_INSTRUCTION_OP_EXECUTION_MODE_ID => {
+                op_execution_mode_idxs.push(spv_idx)
+            }
+            SPV_INSTRUCTION_OP_NAME => {
+                let name = literal_string(&spv[spv_idx + 2..spv_idx + word_count])?;
+                info.names.insert(spv[spv_idx + 1], name);
+            }
+            SPV_INSTRUCTION_OP_MEMBER_NAME => {
+                let name = literal_string(&spv[spv_idx + 3..spv_idx + word_count])?;
+                info.member_names
+                    .insert((spv[spv_idx + 1], spv[spv_idx + 2]), name);
+            }
+            SPV_INSTRUCTION_OP_DECORATE => {
+                let operand = (word_count > 3).then(|| spv[spv_idx + 3]);
+                info.decorations
+                    .entry(spv[spv_idx + 1])
+                    .or_default()
+                    .push((spv[spv_idx + 2], operand));
+            }
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => {
+                let operand = (word_count > 4).then(|| spv[spv_idx + 4]);
+                info.member_decorations
+                    .entry((spv[spv_idx + 1], spv[spv_idx + 2]))
+                    .or_default()
+                    .push((spv[spv_idx + 3], operand));
+            }
+            SPV_INSTRUCTION_OP_TYPE_VOID..=SPV_INSTRUCTION_OP_TYPE_FORWARD_POINTER
+            | SPV_INSTRUCTION_OP_TYPE_ACCELERATION_STRUCTURE_KHR => {
+                info.type_idxs.insert(spv[spv_idx + 1], spv_idx);
+            }
+            SPV_INSTRUCTION_OP_CONSTANT | SPV_INSTRUCTION_OP_SPEC_CONSTANT => {
+                info.constants.insert(spv[spv_idx + 2], spv[spv_idx + 3]);
+            }
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE | SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE => {
+                info.composite_idxs.insert(spv[spv_idx + 2], spv_idx);
+            }
+            SPV_INSTRUCTION_OP_VARIABLE => {
+                info.variable_types
+                    .insert(spv[spv_idx + 2], spv[spv_idx + 1]);
+                op_variable_idxs.push(spv_idx);
+            }
+            // Everything we need is declared before the first function
+            SPV_INSTRUCTION_OP_FUNCTION => break,
+            _ => {}
+        }
+
+        spv_idx += word_count;
+    }
+
+    let variable_storage_classes = op_variable_idxs
+        .iter()
+        .map(|&idx| (spv[idx + 2], spv[idx + 3]))
+        .collect::<HashMap<_, _>>();
+
+    // 2. Reflect entry points and their interfaces
+    let mut entry_points = vec![];
+    for &ep_idx in op_entry_point_idxs.iter() {
+        let word_count = hiword(spv[ep_idx]) as usize;
+        let execution_model = spv[ep_idx + 1];
+        let function_id = spv[ep_idx + 2];
+        let interface_idx = entry_point_interface_idx(spv, ep_idx)?;
+        let name = literal_string(&spv[ep_idx + 3..interface_idx])?;
+
+        let mut workgroup_size = None;
+        for &idx in op_execution_mode_idxs.iter() {
+            if spv[idx + 1] != function_id || hiword(spv[idx]) < 6 {
+                continue;
+            }
+            let size = [spv[idx + 3], spv[idx + 4], spv[idx + 5]];
+            match spv[idx + 2] {
+                SPV_EXECUTION_MODE_LOCAL_SIZE => workgroup_size = Some(size),
+                SPV_EXECUTION_MODE_LOCAL_SIZE_ID => {
+                    workgroup_size = Some(size.map(|id| info.constants.get(&id).copied()))
+                        .and_then(|[x, y, z]| Some([x?, y?, z?]))
+                }
+                _ => {}
+            }
+        }
+        // `WorkgroupSize` takes precedence over any execution mode
+        if matches!(
+            execution_model,
+            SPV_EXECUTION_MODEL_GL_COMPUTE
+                | SPV_EXECUTION_MODEL_KERNEL
+                | SPV_EXECUTION_MODEL_TASK_NV
+                | SPV_EXECUTION_MODEL_MESH_NV
+                | SPV_EXECUTION_MODEL_TASK_EXT
+                | SPV_EXECUTION_MODEL_MESH_EXT
+        ) {
+            workgroup_size = info.workgroup_size_builtin().or(workgroup_size);
+        }
+
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for &id in spv[interface_idx..ep_idx + word_count].iter() {
+            let (Some(&storage_class), Some(location)) = (
+                variable_storage_classes.get(&id),
+                info.decoration(id, SPV_DECORATION_LOCATION),
+            ) else {
+                continue;
+            };
+            let variable = InterfaceVariableReflection {
+                name: info.names.get(&id).cloned(),
+                location,
+                component: info.decoration(id, SPV_DECORATION_COMPONENT).unwrap_or(0),
+                ty: info.reflect_type(info.pointee_type(id)?),
+            };
+            match storage_class {
+                SPV_STORAGE_CLASS_INPUT => inputs.push(variable),
+                SPV_STORAGE_CLASS_OUTPUT => outputs.push(variable),
+                _ => {}
+            }
+        }
+        inputs.sort_by_key(|variable| (variable.location, variable.component));
+        outputs.sort_by_key(|variable| (variable.location, variable.component));
+
+        entry_points.push(EntryPointReflection {
+            name,
+            execution_model,
+            workgroup_size,
+            inputs,
+            outputs,
+        });
+    }
+
+    // 3. Reflect resources and push constants
+    let mut resources = vec![];
+    let mut push_constants = vec![];
+    for &idx in op_variable_idxs.iter() {
+        let id = spv[idx + 2];
+        let storage_class = spv[idx + 3];
+        let pointee_type_id = info.pointee_type(id)?;
+
+        if storage_class == SPV_STORAGE_CLASS_PUSH_CONSTANT {
+            let ReflectedType::Struct { name, members } = info.reflect_type(pointee_type_id) else {
+                return Err(());
+            };
+            push_constants.push(PushConstantReflection {
+                name: info.names.get(&id).cloned().or(name),
+                size: members
+                    .iter()
+                    .filter_map(|member| Some(member.offset? + member.size?))
+                    .max()
+                    .unwrap_or(0),
+                members,
+            });
+            continue;
+        }
+
+        let (Some(set), Some(binding)) = (
+            info.decoration(id, SPV_DECORATION_DESCRIPTOR_SET),
+            info.decoration(id, SPV_DECORATION_BINDING),
+        ) else {
+            continue;
+        };
+
+        // Arrays of resources, `Some(0)` for runtime arrays
+        let mut type_id = pointee_type_id;
+        let mut array_size = None;
+        loop {
+            let type_idx = info.type_idx(type_id)?;
+            let len = match loword(spv[type_idx]) {
+                SPV_INSTRUCTION_OP_TYPE_ARRAY => {
+                    *info.constants.get(&spv[type_idx + 3]).ok_or(())?
+                }
+                SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => 0,
+                _ => break,
+            };
+            array_size = Some(array_size.unwrap_or(1) * len);
+            type_id = spv[type_idx + 2];
+        }
+
+        let type_idx = info.type_idx(type_id)?;
+        let (kind, image) = match loword(spv[type_idx]) {
+            SPV_INSTRUCTION_OP_TYPE_SAMPLER => (ResourceKind::Sampler, None),
+            SPV_INSTRUCTION_OP_TYPE_IMAGE | SPV_INSTRUCTION_OP_TYPE_SAMPLED_IMAGE => {
+                let image_idx = if loword(spv[type_idx]) == SPV_INSTRUCTION_OP_TYPE_IMAGE {
+                    type_idx
+                } else {
+                    info.type_idx(spv[type_idx + 2])?
+                };
+                let dim = spv[image_idx + 3];
+                let storage = spv[image_idx + 7] == 2;
+                let kind = match (dim, storage) {
+                    (SPV_DIMENSION_BUFFER, true) => ResourceKind::StorageTexelBuffer,
+                    (SPV_DIMENSION_BUFFER, false) => ResourceKind::UniformTexelBuffer,
+                    (SPV_DIMENSION_SUBPASS_DATA, _) => ResourceKind::InputAttachment,
+                    (_, true) => ResourceKind::StorageTexture,
+                    _ if image_idx != type_idx => ResourceKind::CombinedImageSampler,
+                    _ => ResourceKind::Texture,
+                };
+                let image = ImageReflection {
+                    dim,
+                    depth: spv[image_idx + 4] == 1,
+                    arrayed: spv[image_idx + 5] == 1,
+                    multisampled: spv[image_idx + 6] == 1,
+                    format: spv[image_idx + 8],
+                    sampled_type: info.reflect_type(spv[image_idx + 2]),
+                };
+                (kind, Some(image))
+            }
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+                let kind = if storage_class == SPV_STORAGE_CLASS_STORAGE_BUFFER
+                    || info.has_decoration(type_id, SPV_DECORATION_BUFFER_BLOCK)
+                {
+                    ResourceKind::StorageBuffer
+                } else {
+                    ResourceKind::UniformBuffer
+                };
+                (kind, None)
+            }
+            SPV_INSTRUCTION_OP_TYPE_ACCELERATION_STRUCTURE_KHR => {
+                (ResourceKind::AccelerationStructure, None)
+            }
+            _ => continue,
+        };
+
+        let block = matches!(
+            kind,
+            ResourceKind::UniformBuffer | ResourceKind::StorageBuffer
+        )
+        .then(|| info.reflect_type(type_id));
+        let access = |decoration: u32| {
+            let member_count = hiword(spv[type_idx]) as usize - 2;
+            info.has_decoration(id, decoration)
+                || (block.is_some()
+                    && member_count > 0
+                    && (0..member_count as u32)
+                        .all(|member| info.has_member_decoration(type_id, member, decoration)))
+        };
+
+        resources.push(ResourceReflection {
+            name: info
+                .names
+                .get(&id)
+                .filter(|name| !name.is_empty())
+                .or_else(|| info.names.get(&type_id))
+                .cloned(),
+            set,
+            binding,
+            kind,
+            array_size,
+            image,
+            non_readable: access(SPV_DECORATION_NON_READABLE),
+            non_writable: access(SPV_DECORATION_NON_WRITABLE),
+            block,
+        });
+    }
+    resources.sort_by_key(|resource| (resource.set, resource.binding));
+
+    Ok(ModuleReflection {
+        entry_points,
+        resources,
+        push_constants,
+    })
+}
+
+fn literal_string(words: &[u32]) -> Result<String, ()> {
+    Ok(literal_to_string_le(words)
+        .map_err(|_| ())?
+        .trim_end_matches('\0')
+        .to_owned())
+}
+
+type Decorations = Vec<(u32, Option<u32>)>;
+
+#[derive(Default)]
+struct ModuleInfo<'a> {
+    spv: &'a [u32],
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    // Only the first operand of each decoration is kept
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), Decorations>,
+    type_idxs: HashMap<u32, usize>,
+    // Only the low word of each constant is kept
+    constants: HashMap<u32, u32>,
+    composite_idxs: HashMap<u32, usize>,
+    // Global variables to their pointer type
+    variable_types: HashMap<u32, u32>,
+}
+
+impl ModuleInfo<'_> {
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations
+            .get(&id)?
+            .iter()
+            .find_map(|&(d, operand)| (d == decoration).then_some(operand).flatten())
+    }
+
+    fn has_decoration(&self, id: u32, decoration: u32) -> bool {
+        self.decorations
+            .get(&id)
+            .is_some_and(|decorations| decorations.iter().any(|&(d, _)| d == decoration))
+    }
+
+    fn member_decoration(&self, id: u32, member: u32, decoration: u32) -> Option<u32> {
+        self.member_decorations
+            .get(&(id, member))?
+            .iter()
+            .find_map(|&(d, operand)| (d == decoration).then_some(operand).flatten())
+    }
+
+    fn has_member_decoration(&self, id: u32, member: u32, decoration: u32) -> bool {
+        self.member_decorations
+            .get(&(id, member))
+            .is_some_and(|decorations| decorations.iter().any(|&(d, _)| d == decoration))
+    }
+
+    fn type_idx(&self, type_id: u32) -> Result<usize, ()> {
+        self.type_idxs.get(&type_id).copied().ok_or(())
+    }
+
+    fn pointee_type(&self, variable_id: u32) -> Result<u32, ()> {
+        let pointer_type_id = *self.variable_types.get(&variable_id).ok_or(())?;
+        Ok(self.spv[self.type_idx(pointer_type_id)? + 3])
+    }
+
+    fn workgroup_size_builtin(&self) -> Option<[u32; 3]> {
+        let &idx = self.composite_idxs.iter().find_map(|(&id, idx)| {
+            (self.decoration(id, SPV_DECORATION_BUILTIN) == Some(SPV_BUILTIN_WORKGROUP_SIZE))
+                .then_some(idx)
+        })?;
+        let spv = self.spv;
+        let component = |i: usize| self.constants.get(&spv[idx + 3 + i]).copied();
+        Some([component(0)?, component(1)?, component(2)?])
+    }
+
+    fn reflect_type(&self, type_id: u32) -> ReflectedType {
+        let spv = self.spv;
+        let Some(&idx) = self.type_idxs.get(&type_id) else {
+            return ReflectedType::Opaque;
+        };
+        match loword(spv[idx]) {
+            SPV_INSTRUCTION_OP_TYPE_BOOL => ReflectedType::Bool,
+            SPV_INSTRUCTION_OP_TYPE_INT => ReflectedType::Int {
+                width: spv[idx + 2],
+                signed: spv[idx + 3] == SPV_SIGNEDNESS_SIGNED,
+            },
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => ReflectedType::Float {
+                width: spv[idx + 2],
+            },
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => ReflectedType::Vector {
+                component: Box::new(self.reflect_type(spv[idx + 2])),
+                count: spv[idx + 3],
+            },
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => ReflectedType::Matrix {
+                column: Box::new(self.reflect_type(spv[idx + 2])),
+                columns: spv[idx + 3],
+            },
+            SPV_INSTRUCTION_OP_TYPE_ARRAY | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => {
+                ReflectedType::Array {
+                    element: Box::new(self.reflect_type(spv[idx + 2])),
+                    len: (loword(spv[idx]) == SPV_INSTRUCTION_OP_TYPE_ARRAY)
+                        .then(|| self.constants.get(&spv[idx + 3]).copied())
+                        .flatten(),
+                    stride: self.decoration(type_id, SPV_DECORATION_ARRAY_STRIDE),
+                }
+            }
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+                let members = spv[idx + 2..idx + hiword(spv[idx]) as usize]
+                    .iter()
+                    .enumerate()
+                    .map(|(member, &member_type_id)| {
+                        let member = member as u32;
+                        let ty = self.reflect_type(member_type_id);
+                        let matrix_stride =
+                            self.member_decoration(type_id, member, SPV_DECORATION_MATRIX_STRIDE);
+                        let row_major =
+                            self.has_member_decoration(type_id, member, SPV_DECORATION_ROW_MAJOR);
+                        StructMemberReflection {
+                            name: self.member_names.get(&(type_id, member)).cloned(),
+                            offset: self.member_decoration(type_id, member, SPV_DECORATION_OFFSET),
+                            size: declared_size(&ty, matrix_stride, row_major),
+                            ty,
+                        }
+                    })
+                    .collect();
+                ReflectedType::Struct {
+                    name: self.names.get(&type_id).cloned(),
+                    members,
+                }
+            }
+            _ => ReflectedType::Opaque,
+        }
+    }
+}
+
+// The size of a type as laid out by its `Offset`, `ArrayStride`, and `MatrixStride` decorations.
+fn declared_size(ty: &ReflectedType, matrix_stride: Option<u32>, row_major: bool) -> Option<u32> {
+    match ty {
+        ReflectedType::Bool => Some(4),
+        ReflectedType::Int { width, .. } | ReflectedType::Float { width } => Some(width / 8),
+        ReflectedType::Vector { component, count } => {
+            Some(count * declared_size(component, None, false)?)
+        }
+        ReflectedType::Matrix { column, columns } => {
+            let ReflectedType::Vector { count: rows, .. } = column.as_ref() else {
+                return None;
+            };
+            let vectors = if row_major { *rows } else { *columns };
+            Some(vectors * matrix_stride?)
+        }
+        ReflectedType::Array { len, stride, .. } => Some((*len)? * (*stride)?),
+        ReflectedType::Struct { members, .. } => members
+            .iter()
+            .map(|member| Some(member.offset? + member.size?))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max(),
+        ReflectedType::Opaque => None,
+    }
+}
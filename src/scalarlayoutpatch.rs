@@ -0,0 +1,1120 @@
+use super::*;
+use crate::immediatespatch::{layout::*, type_registry::*};
+
+/// What [`scalarlayoutpatch`] has done to the buffers WGSL cannot express.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScalarLayoutReport {
+    /// Uniform blocks relaid out as std140, fill them as described by [`uniformlayoutpatch`].
+    pub uniforms: UniformLayoutReport,
+    /// Storage buffers now accessed as an array of words.
+    pub word_buffers: Vec<WordStorageBuffer>,
+}
+
+/// A storage buffer that is declared as `array<u32>` and unpacked wherever it is used.
+/// Its data keeps the layout the module was compiled with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WordStorageBuffer {
+    pub set: u32,
+    pub binding: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayKind {
+    Vector,
+    Matrix,
+    Array,
+}
+
+/// A type as laid out by its decorations, offsets and strides are in bytes.
+#[derive(Debug, Clone)]
+enum Laid {
+    Scalar {
+        type_id: u32,
+        is_uint: bool,
+    },
+    /// Vectors and matrices are arrays of their components and columns, runtime arrays have no `len`.
+    Array {
+        type_id: u32,
+        kind: ArrayKind,
+        element: Box<Laid>,
+        stride: u32,
+        len: Option<u32>,
+    },
+    Struct {
+        type_id: u32,
+        members: Vec<(u32, Laid)>,
+    },
+}
+
+impl Laid {
+    fn type_id(&self) -> u32 {
+        match self {
+            Laid::Scalar { type_id, .. }
+            | Laid::Array { type_id, .. }
+            | Laid::Struct { type_id, .. } => *type_id,
+        }
+    }
+
+    /// The `(byte offset, part)` of each component, column, element, or member.
+    fn parts(&self) -> Result<Vec<(u32, &Laid)>, ()> {
+        match self {
+            Laid::Scalar { .. } | Laid::Array { len: None, .. } => Err(()),
+            Laid::Array {
+                element,
+                stride,
+                len: Some(len),
+                ..
+            } => Ok((0..*len).map(|i| (i * stride, element.as_ref())).collect()),
+            Laid::Struct { members, .. } => Ok(members
+                .iter()
+                .map(|(offset, member)| (*offset, member))
+                .collect()),
+        }
+    }
+}
+
+/// A word index into a buffer, `dynamic` is the id of a `uint` added to `constant`.
+#[derive(Debug, Clone, Copy)]
+struct WordOffset {
+    constant: u32,
+    dynamic: Option<u32>,
+}
+
+/// A pointer into a buffer that is now an array of words, it no longer exists in the output.
+#[derive(Debug, Clone)]
+struct WordPointer {
+    variable: u32,
+    storage_class: u32,
+    offset: WordOffset,
+    laid: Laid,
+}
+
+/// Types and constants the emitted code needs, declared on first use.
+struct WordContext<'a> {
+    spv: &'a [u32],
+    op_type_pointer_idxs: &'a [usize],
+    op_constant_idxs: &'a [usize],
+    instruction_bound: u32,
+    header: Vec<u32>,
+    uint_id: u32,
+    pointers: HashMap<u32, u32>,
+    constants: HashMap<u32, u32>,
+}
+
+impl WordContext<'_> {
+    fn inc(&mut self) -> u32 {
+        self.instruction_bound += 1;
+        self.instruction_bound - 1
+    }
+
+    fn uint(&mut self, value: u32) -> u32 {
+        if let Some(&id) = self.constants.get(&value) {
+            return id;
+        }
+        let id = self
+            .op_constant_idxs
+            .iter()
+            .find_map(|&c_idx| {
+                (hiword(self.spv[c_idx]) == 4
+                    && self.spv[c_idx + 1] == self.uint_id
+                    && self.spv[c_idx + 3] == value)
+                    .then_some(self.spv[c_idx + 2])
+            })
+            .unwrap_or_else(|| {
+                let new_id = self.inc();
+                self.header.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                    self.uint_id,
+                    new_id,
+                    value,
+                ]);
+                new_id
+            });
+        self.constants.insert(value, id);
+        id
+    }
+
+    fn uint_pointer(&mut self, storage_class: u32) -> u32 {
+        if let Some(&id) = self.pointers.get(&storage_class) {
+            return id;
+        }
+        let id = ensure_type_pointer(
+            self.spv,
+            self.op_type_pointer_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            storage_class,
+            self.uint_id,
+        );
+        self.pointers.insert(storage_class, id);
+        id
+    }
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Modules compiled with `-fvk-use-scalar-layout` or `-fvk-use-dx-layout` may place members where
+/// WGSL cannot, such as a `vec3` at offset 4 or an array of `vec3` with a stride of 12.
+/// Storage buffers with such a layout are redeclared as an array of `u32` words, every load and
+/// store is unpacked into word accesses so that the data does not change.
+/// Uniform blocks are then relaid out by [`uniformlayoutpatch`].
+/// Does not produce any corrections, see `report` for which buffers changed.
+///
+/// Storage buffers holding anything other than 32-bit scalars, vectors, and matrices are left as is.
+/// Atomics keep working on `uint` members, which are words themselves.
+/// Fails if a pointer into a word buffer is used in any other way, for example by a function call
+/// or an atomic on a `float` or `int`.
+pub fn scalarlayoutpatch(in_spv: &[u32], report: &mut ScalarLayoutReport) -> Result<Vec<u32>, ()> {
+    let spv = word_storage_patch(in_spv, &mut report.word_buffers)?;
+    uniformlayoutpatch(&spv, &mut report.uniforms)
+}
+
+fn word_storage_patch(
+    in_spv: &[u32],
+    word_buffers: &mut Vec<WordStorageBuffer>,
+) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations of instructions we need
+    let mut op_member_name_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_runtime_array_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_constant_composite_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_function_idxs = vec![];
+    let mut op_access_chain_idxs = vec![];
+    let mut op_load_idxs = vec![];
+    let mut op_store_idxs = vec![];
+    let mut op_array_length_idxs = vec![];
+    let mut op_atomic_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_MEMBER_NAME => op_member_name_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => op_type_runtime_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE => op_constant_composite_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => op_function_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ACCESS_CHAIN | SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN => {
+                op_access_chain_idxs.push(spv_idx)
+            }
+            SPV_INSTRUCTION_OP_LOAD => op_load_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_STORE => op_store_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ARRAY_LENGTH => op_array_length_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ATOMIC_LOAD..=SPV_INSTRUCTION_OP_ATOMIC_XOR => {
+                op_atomic_idxs.push(spv_idx)
+            }
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    // 2. Find the struct behind every storage buffer, and lay it out as decorated
+    let constant_values = op_constant_idxs
+        .iter()
+        .filter(|&&idx| hiword(spv[idx]) == 4)
+        .map(|&idx| (spv[idx + 2], spv[idx + 3]))
+        .collect::<HashMap<_, _>>();
+    let type_idxs = op_type_int_idxs
+        .iter()
+        .chain(op_type_float_idxs.iter())
+        .chain(op_type_vector_idxs.iter())
+        .chain(op_type_matrix_idxs.iter())
+        .chain(op_type_array_idxs.iter())
+        .chain(op_type_runtime_array_idxs.iter())
+        .chain(op_type_struct_idxs.iter())
+        .map(|&idx| (spv[idx + 1], idx))
+        .collect::<HashMap<_, _>>();
+    let pointer_types = op_type_pointer_idxs
+        .iter()
+        .map(|&idx| (spv[idx + 1], (spv[idx + 2], spv[idx + 3])))
+        .collect::<HashMap<_, _>>();
+    let is_ssbo = |storage_class, pointee| {
+        storage_class == SPV_STORAGE_CLASS_STORAGE_BUFFER
+            || (storage_class == SPV_STORAGE_CLASS_UNIFORM
+                && op_decorate_idxs.iter().any(|&idx| {
+                    spv[idx + 1] == pointee && spv[idx + 2] == SPV_DECORATION_BUFFER_BLOCK
+                }))
+    };
+
+    let type_registry = build_type_registry(BuildTypeRegistryIn {
+        spv: &spv,
+        op_type_bool_idxs: &op_type_bool_idxs,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_int_idxs: &op_type_int_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_type_matrix_idxs: &op_type_matrix_idxs,
+        op_type_array_idxs: &op_type_array_idxs,
+        op_type_struct_idxs: &op_type_struct_idxs,
+        op_constant_idxs: &op_constant_idxs,
+    });
+    let lay_out_in = LayOutIn {
+        spv: &spv,
+        type_idxs: &type_idxs,
+        constant_values: &constant_values,
+        op_decorate_idxs: &op_decorate_idxs,
+        op_member_decorate_idxs: &op_member_decorate_idxs,
+    };
+
+    // 3. Find storage buffers that WGSL cannot express
+    let mut word_structs: HashMap<u32, Laid> = HashMap::new();
+    for &v_idx in &op_variable_idxs {
+        let Some(&(storage_class, pointee)) = pointer_types.get(&spv[v_idx + 1]) else {
+            continue;
+        };
+        if !is_ssbo(storage_class, pointee) || word_structs.contains_key(&pointee) {
+            continue;
+        }
+        let Ok(laid) = lay_out(&lay_out_in, pointee, None) else {
+            continue;
+        };
+        if !is_wgsl_storage_layout(&laid, &type_registry) {
+            word_structs.insert(pointee, laid);
+        }
+    }
+
+    if word_structs.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    // Word structs are redeclared in place, so they must not be shared with anything else
+    if op_type_pointer_idxs.iter().any(|&idx| {
+        word_structs.contains_key(&spv[idx + 3]) && !is_ssbo(spv[idx + 2], spv[idx + 3])
+    }) || op_type_struct_idxs.iter().any(|&idx| {
+        spv[idx + 2..idx + hiword(spv[idx]) as usize]
+            .iter()
+            .any(|id| word_structs.contains_key(id))
+    }) || op_type_array_idxs
+        .iter()
+        .chain(op_type_runtime_array_idxs.iter())
+        .any(|&idx| word_structs.contains_key(&spv[idx + 2]))
+    {
+        return Err(());
+    }
+
+    // 4. Trace every pointer into a word struct, they may only be accessed, loaded, or stored
+    let mut word_pointers = HashMap::new();
+    for &v_idx in &op_variable_idxs {
+        if let Some(&(storage_class, pointee)) = pointer_types.get(&spv[v_idx + 1])
+            && let Some(laid) = word_structs.get(&pointee)
+        {
+            word_pointers.insert(
+                spv[v_idx + 2],
+                WordPointer {
+                    variable: spv[v_idx + 2],
+                    storage_class,
+                    offset: WordOffset {
+                        constant: 0,
+                        dynamic: None,
+                    },
+                    laid: laid.clone(),
+                },
+            );
+        }
+    }
+    let mut traced_pointers = word_pointers.keys().copied().collect::<HashSet<_>>();
+    for &c_idx in &op_access_chain_idxs {
+        if traced_pointers.contains(&spv[c_idx + 3]) {
+            traced_pointers.insert(spv[c_idx + 2]);
+        }
+    }
+    if let Some(&function_idx) = op_function_idxs.first() {
+        let mut idx = function_idx;
+        while idx < spv.len() {
+            let word_count = hiword(spv[idx]) as usize;
+            let instruction = loword(spv[idx]);
+            for (i, word) in spv[idx + 1..idx + word_count].iter().enumerate() {
+                let position = i + 1;
+                let allowed = match instruction {
+                    SPV_INSTRUCTION_OP_LINE => true,
+                    SPV_INSTRUCTION_OP_ACCESS_CHAIN | SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN => {
+                        position >= 2
+                    }
+                    SPV_INSTRUCTION_OP_ARRAY_LENGTH => position == 3,
+                    // Memory operands follow the pointer
+                    SPV_INSTRUCTION_OP_LOAD => position >= 3,
+                    SPV_INSTRUCTION_OP_STORE => position != 2,
+                    SPV_INSTRUCTION_OP_ATOMIC_STORE => position == 1,
+                    SPV_INSTRUCTION_OP_ATOMIC_LOAD..=SPV_INSTRUCTION_OP_ATOMIC_XOR => position == 3,
+                    _ => false,
+                };
+                if !allowed && traced_pointers.contains(word) {
+                    return Err(());
+                }
+            }
+            idx += word_count;
+        }
+    }
+
+    // 5. Ensure `uint` is declared before the first word struct
+    let mut word_struct_idxs = word_structs
+        .keys()
+        .map(|id| type_idxs[id])
+        .collect::<Vec<_>>();
+    word_struct_idxs.sort();
+    let first_word_struct_idx = word_struct_idxs[0];
+
+    let mut struct_insert = InstructionInsert {
+        previous_spv_idx: first_word_struct_idx,
+        instruction: vec![],
+    };
+    let uint_id = match op_type_int_idxs
+        .iter()
+        .copied()
+        .find(|&idx| spv[idx + 2] == 32 && spv[idx + 3] == SPV_SIGNEDNESS_UNSIGNED)
+    {
+        Some(idx) if idx < first_word_struct_idx => spv[idx + 1],
+        existing_idx => {
+            let id = match existing_idx {
+                Some(idx) => {
+                    for i in 0..hiword(spv[idx]) as usize {
+                        new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                    }
+                    spv[idx + 1]
+                }
+                None => {
+                    instruction_bound += 1;
+                    instruction_bound - 1
+                }
+            };
+            struct_insert.instruction.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_TYPE_INT),
+                id,
+                32,
+                SPV_SIGNEDNESS_UNSIGNED,
+            ]);
+            id
+        }
+    };
+
+    let mut ctx = WordContext {
+        spv: &spv,
+        op_type_pointer_idxs: &op_type_pointer_idxs,
+        op_constant_idxs: &op_constant_idxs,
+        instruction_bound,
+        header: vec![],
+        uint_id,
+        pointers: HashMap::new(),
+        constants: HashMap::new(),
+    };
+
+    // 6. Redeclare word structs as a single runtime array of words
+    let words_id = ctx.inc();
+    struct_insert.instruction.append(&mut vec![
+        encode_word(3, SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY),
+        words_id,
+        uint_id,
+    ]);
+    let mut decorations = vec![
+        encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+        words_id,
+        SPV_DECORATION_ARRAY_STRIDE,
+        4,
+    ];
+    for &s_idx in &word_struct_idxs {
+        let id = spv[s_idx + 1];
+        let member_count = hiword(spv[s_idx]) as u32 - 2;
+
+        if s_idx != first_word_struct_idx {
+            instruction_inserts.push(struct_insert);
+            struct_insert = InstructionInsert {
+                previous_spv_idx: s_idx,
+                instruction: vec![],
+            };
+        }
+        for i in 0..hiword(spv[s_idx]) as usize {
+            new_spv[s_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        struct_insert.instruction.append(&mut vec![
+            encode_word(3, SPV_INSTRUCTION_OP_TYPE_STRUCT),
+            id,
+            words_id,
+        ]);
+
+        // Read-only buffers mark every member `NonWritable`
+        let non_writable_members = op_member_decorate_idxs
+            .iter()
+            .filter(|&&idx| spv[idx + 1] == id && spv[idx + 3] == SPV_DECORATION_NON_WRITABLE)
+            .map(|&idx| spv[idx + 2])
+            .collect::<HashSet<_>>();
+        for &idx in op_member_name_idxs
+            .iter()
+            .chain(op_member_decorate_idxs.iter())
+        {
+            if spv[idx + 1] == id {
+                for i in 0..hiword(spv[idx]) as usize {
+                    new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                }
+            }
+        }
+        decorations.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+            id,
+            0,
+            SPV_DECORATION_OFFSET,
+            0,
+        ]);
+        if non_writable_members.len() as u32 == member_count {
+            decorations.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                id,
+                0,
+                SPV_DECORATION_NON_WRITABLE,
+            ]);
+        }
+    }
+    instruction_inserts.push(struct_insert);
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: last_of_indices!(op_decorate_idxs, op_member_decorate_idxs)
+            .expect("Block has no OpDecorate (missing BufferBlock decoration?)"),
+        instruction: decorations,
+    });
+
+    // 7. Access chains become word offsets
+    for &c_idx in &op_access_chain_idxs {
+        let Some(base) = word_pointers.get(&spv[c_idx + 3]) else {
+            continue;
+        };
+        let mut pointer = base.clone();
+        let mut offset_spv = vec![];
+        for &index in &spv[c_idx + 4..c_idx + hiword(spv[c_idx]) as usize] {
+            pointer.laid = match pointer.laid {
+                Laid::Scalar { .. } => return Err(()),
+                Laid::Struct { members, .. } => {
+                    let member = *constant_values.get(&index).ok_or(())?;
+                    let (byte_offset, laid) = members.into_iter().nth(member as usize).ok_or(())?;
+                    pointer.offset.constant += byte_offset / 4;
+                    laid
+                }
+                Laid::Array {
+                    element, stride, ..
+                } => {
+                    if let Some(&value) = constant_values.get(&index) {
+                        pointer.offset.constant += value * stride / 4;
+                        *element
+                    } else {
+                        //
+                        //  %unsigned = OpBitcast %uint %index
+                        //    %scaled = OpIMul %uint %unsigned %uint_stride
+                        //      %word = OpIAdd %uint %word %scaled
+                        let index_type_id =
+                            trace_previous_intermediate_id(&spv, index, c_idx).ok_or(())?;
+                        let index_type_idx = *type_idxs.get(&index_type_id).ok_or(())?;
+                        if loword(spv[index_type_idx]) != SPV_INSTRUCTION_OP_TYPE_INT
+                            || spv[index_type_idx + 2] != 32
+                        {
+                            return Err(());
+                        }
+                        let unsigned = if index_type_id == uint_id {
+                            index
+                        } else {
+                            let unsigned = ctx.inc();
+                            offset_spv.append(&mut vec![
+                                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                                uint_id,
+                                unsigned,
+                                index,
+                            ]);
+                            unsigned
+                        };
+                        let scaled = if stride == 4 {
+                            unsigned
+                        } else {
+                            let uint_stride = ctx.uint(stride / 4);
+                            let scaled = ctx.inc();
+                            offset_spv.append(&mut vec![
+                                encode_word(5, SPV_INSTRUCTION_OP_I_MUL),
+                                uint_id,
+                                scaled,
+                                unsigned,
+                                uint_stride,
+                            ]);
+                            scaled
+                        };
+                        pointer.offset.dynamic = Some(match pointer.offset.dynamic {
+                            None => scaled,
+                            Some(word) => {
+                                let sum = ctx.inc();
+                                offset_spv.append(&mut vec![
+                                    encode_word(5, SPV_INSTRUCTION_OP_I_ADD),
+                                    uint_id,
+                                    sum,
+                                    word,
+                                    scaled,
+                                ]);
+                                sum
+                            }
+                        });
+                        *element
+                    }
+                }
+            };
+        }
+        word_pointers.insert(spv[c_idx + 2], pointer);
+
+        for i in 0..hiword(spv[c_idx]) as usize {
+            new_spv[c_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        if !offset_spv.is_empty() {
+            instruction_inserts.push(InstructionInsert {
+                previous_spv_idx: c_idx,
+                instruction: offset_spv,
+            });
+        }
+    }
+
+    // 8. Load and store a word at a time
+    for &idx in &op_load_idxs {
+        let Some(pointer) = word_pointers.get(&spv[idx + 3]) else {
+            continue;
+        };
+        let mut load_spv = vec![];
+        word_load_spv(
+            &mut ctx,
+            pointer,
+            &pointer.laid,
+            pointer.offset,
+            spv[idx + 2],
+            &mut load_spv,
+        )?;
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: load_spv,
+        });
+    }
+    for &idx in &op_store_idxs {
+        let Some(pointer) = word_pointers.get(&spv[idx + 1]) else {
+            continue;
+        };
+        let mut store_spv = vec![];
+        word_store_spv(
+            &mut ctx,
+            pointer,
+            &pointer.laid,
+            pointer.offset,
+            spv[idx + 2],
+            &mut store_spv,
+        )?;
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: store_spv,
+        });
+    }
+
+    // Atomics operate on the word itself, which only has the same type for `uint`
+    for &idx in &op_atomic_idxs {
+        let pointer_position = if loword(spv[idx]) == SPV_INSTRUCTION_OP_ATOMIC_STORE {
+            1
+        } else {
+            3
+        };
+        let Some(pointer) = word_pointers.get(&spv[idx + pointer_position]) else {
+            continue;
+        };
+        let Laid::Scalar { is_uint: true, .. } = pointer.laid else {
+            return Err(());
+        };
+        let mut atomic_spv = vec![];
+        let word_ptr = word_pointer_spv(&mut ctx, pointer, pointer.offset, &mut atomic_spv);
+        let mut atomic = spv[idx..idx + hiword(spv[idx]) as usize].to_vec();
+        atomic[pointer_position] = word_ptr;
+        atomic_spv.append(&mut atomic);
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: atomic_spv,
+        });
+    }
+
+    // 9. Array lengths count words, convert them back into elements
+    for &idx in &op_array_length_idxs {
+        let Some(pointer) = word_pointers.get(&spv[idx + 3]) else {
+            continue;
+        };
+        let Laid::Struct { members, .. } = &pointer.laid else {
+            return Err(());
+        };
+        let Some(&(
+            byte_offset,
+            Laid::Array {
+                stride, len: None, ..
+            },
+        )) = members.get(spv[idx + 4] as usize)
+        else {
+            return Err(());
+        };
+
+        //
+        //  %words = OpArrayLength %uint %block 0
+        //  %words = OpISub %uint %words %uint_word_offset
+        //      %1 = OpUDiv %uint %words %uint_word_stride
+        let mut steps = vec![];
+        if byte_offset != 0 {
+            steps.push((SPV_INSTRUCTION_OP_I_SUB, byte_offset / 4));
+        }
+        if stride != 4 {
+            steps.push((SPV_INSTRUCTION_OP_U_DIV, stride / 4));
+        }
+        let words = if steps.is_empty() {
+            spv[idx + 2]
+        } else {
+            ctx.inc()
+        };
+        let mut length_spv = vec![
+            encode_word(5, SPV_INSTRUCTION_OP_ARRAY_LENGTH),
+            uint_id,
+            words,
+            pointer.variable,
+            0,
+        ];
+        let mut value = words;
+        for (i, &(instruction, operand)) in steps.iter().enumerate() {
+            let uint_operand = ctx.uint(operand);
+            let result_id = if i == steps.len() - 1 {
+                spv[idx + 2]
+            } else {
+                ctx.inc()
+            };
+            length_spv.append(&mut vec![
+                encode_word(5, instruction),
+                uint_id,
+                result_id,
+                value,
+                uint_operand,
+            ]);
+            value = result_id;
+        }
+
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: length_spv,
+        });
+    }
+
+    // 10. Report every buffer that is now an array of words
+    let decoration_value = |id: u32, decoration: u32| {
+        op_decorate_idxs.iter().find_map(|&d_idx| {
+            (spv[d_idx + 1] == id && spv[d_idx + 2] == decoration).then_some(spv[d_idx + 3])
+        })
+    };
+    for &v_idx in &op_variable_idxs {
+        let var_id = spv[v_idx + 2];
+        if word_pointers
+            .get(&var_id)
+            .is_some_and(|pointer| pointer.variable == var_id)
+        {
+            word_buffers.push(WordStorageBuffer {
+                set: decoration_value(var_id, SPV_DECORATION_DESCRIPTOR_SET).ok_or(())?,
+                binding: decoration_value(var_id, SPV_DECORATION_BINDING).ok_or(())?,
+            });
+        }
+    }
+    word_buffers.sort_by_key(|buffer| (buffer.set, buffer.binding));
+
+    // 11. Insert New Instructions
+    let header_position = last_of_indices!(
+        op_type_bool_idxs,
+        op_type_int_idxs,
+        op_type_float_idxs,
+        op_type_vector_idxs,
+        op_type_matrix_idxs,
+        op_type_array_idxs,
+        op_type_runtime_array_idxs,
+        op_type_struct_idxs,
+        op_type_pointer_idxs,
+        op_constant_idxs,
+        op_constant_composite_idxs
+    );
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: header_position.unwrap(),
+        instruction: ctx.header,
+    });
+    let instruction_bound = ctx.instruction_bound;
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 12. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 13. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
+
+struct LayOutIn<'a> {
+    spv: &'a [u32],
+    type_idxs: &'a HashMap<u32, usize>,
+    constant_values: &'a HashMap<u32, u32>,
+    op_decorate_idxs: &'a [usize],
+    op_member_decorate_idxs: &'a [usize],
+}
+
+// Lay out a type of 32-bit scalars as decorated, every offset and stride must be a whole number of
+// words.
+// `MatrixStride` is a member decoration, so it is carried down from the struct.
+fn lay_out(
+    lay_out_in: &LayOutIn,
+    id: u32,
+    matrix_stride_decoration: Option<u32>,
+) -> Result<Laid, ()> {
+    let LayOutIn {
+        spv,
+        type_idxs,
+        constant_values,
+        op_decorate_idxs,
+        op_member_decorate_idxs,
+    } = lay_out_in;
+    let idx = *type_idxs.get(&id).ok_or(())?;
+
+    match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_TYPE_INT | SPV_INSTRUCTION_OP_TYPE_FLOAT => {
+            if spv[idx + 2] != 32 {
+                return Err(());
+            }
+            Ok(Laid::Scalar {
+                type_id: id,
+                is_uint: loword(spv[idx]) == SPV_INSTRUCTION_OP_TYPE_INT
+                    && spv[idx + 3] == SPV_SIGNEDNESS_UNSIGNED,
+            })
+        }
+        SPV_INSTRUCTION_OP_TYPE_VECTOR => Ok(Laid::Array {
+            type_id: id,
+            kind: ArrayKind::Vector,
+            element: Box::new(lay_out(lay_out_in, spv[idx + 2], None)?),
+            stride: 4,
+            len: Some(spv[idx + 3]),
+        }),
+        SPV_INSTRUCTION_OP_TYPE_MATRIX => {
+            let stride = matrix_stride_decoration.ok_or(())?;
+            if stride % 4 != 0 {
+                return Err(());
+            }
+            Ok(Laid::Array {
+                type_id: id,
+                kind: ArrayKind::Matrix,
+                element: Box::new(lay_out(lay_out_in, spv[idx + 2], None)?),
+                stride,
+                len: Some(spv[idx + 3]),
+            })
+        }
+        SPV_INSTRUCTION_OP_TYPE_ARRAY | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => {
+            let stride = op_decorate_idxs
+                .iter()
+                .find_map(|&d_idx| {
+                    (spv[d_idx + 1] == id && spv[d_idx + 2] == SPV_DECORATION_ARRAY_STRIDE)
+                        .then_some(spv[d_idx + 3])
+                })
+                .ok_or(())?;
+            if stride % 4 != 0 {
+                return Err(());
+            }
+            let len = if loword(spv[idx]) == SPV_INSTRUCTION_OP_TYPE_ARRAY {
+                Some(*constant_values.get(&spv[idx + 3]).ok_or(())?)
+            } else {
+                None
+            };
+            Ok(Laid::Array {
+                type_id: id,
+                kind: ArrayKind::Array,
+                element: Box::new(lay_out(lay_out_in, spv[idx + 2], matrix_stride_decoration)?),
+                stride,
+                len,
+            })
+        }
+        SPV_INSTRUCTION_OP_TYPE_STRUCT => {
+            let mut members = vec![];
+            for (member_idx, &member_id) in spv[idx + 2..idx + hiword(spv[idx]) as usize]
+                .iter()
+                .enumerate()
+            {
+                let member_decoration = |decoration: u32| {
+                    op_member_decorate_idxs.iter().copied().find(|&md_idx| {
+                        spv[md_idx + 1] == id
+                            && spv[md_idx + 2] == member_idx as u32
+                            && spv[md_idx + 3] == decoration
+                    })
+                };
+                if member_decoration(SPV_DECORATION_ROW_MAJOR).is_some() {
+                    return Err(());
+                }
+                let offset = spv[member_decoration(SPV_DECORATION_OFFSET).ok_or(())? + 4];
+                if offset % 4 != 0 {
+                    return Err(());
+                }
+                let member_matrix_stride =
+                    member_decoration(SPV_DECORATION_MATRIX_STRIDE).map(|md_idx| spv[md_idx + 4]);
+                members.push((
+                    offset,
+                    lay_out(lay_out_in, member_id, member_matrix_stride)?,
+                ));
+            }
+            Ok(Laid::Struct {
+                type_id: id,
+                members,
+            })
+        }
+        _ => Err(()),
+    }
+}
+
+// Whether WGSL's storage address space can express a type as it is laid out, which is std430.
+fn is_wgsl_storage_layout(laid: &Laid, type_registry: &TypeRegistry) -> bool {
+    let kind_of = |laid: &Laid| type_registry.get(&laid.type_id()).map(|ty| &ty.kind);
+
+    match laid {
+        Laid::Scalar { .. }
+        | Laid::Array {
+            kind: ArrayKind::Vector,
+            ..
+        } => true,
+        Laid::Array {
+            kind: ArrayKind::Matrix,
+            element,
+            stride,
+            ..
+        } => match **element {
+            Laid::Array {
+                len: Some(column_vec_count),
+                ..
+            } => *stride == matrix_stride(column_vec_count, 4, LayoutRule::Std430),
+            _ => false,
+        },
+        Laid::Array {
+            element, stride, ..
+        } => {
+            kind_of(element).is_some_and(|kind| *stride == array_stride(kind, LayoutRule::Std430))
+                && is_wgsl_storage_layout(element, type_registry)
+        }
+        Laid::Struct { members, .. } => {
+            let mut end = 0;
+            for (offset, member) in members {
+                // Only the last member may be a runtime array, its size does not matter
+                let align_size = match member {
+                    Laid::Array {
+                        element, len: None, ..
+                    } => kind_of(element).map(|kind| (base_align(kind, LayoutRule::Std430), 0)),
+                    _ => kind_of(member).map(|kind| {
+                        (
+                            base_align(kind, LayoutRule::Std430),
+                            size_of(kind, LayoutRule::Std430),
+                        )
+                    }),
+                };
+                let Some((align, size)) = align_size else {
+                    return false;
+                };
+                if offset % align != 0
+                    || *offset < end
+                    || !is_wgsl_storage_layout(member, type_registry)
+                {
+                    return false;
+                }
+                end = offset + size;
+            }
+            true
+        }
+    }
+}
+
+fn word_index_spv(ctx: &mut WordContext, offset: WordOffset, out: &mut Vec<u32>) -> u32 {
+    match offset.dynamic {
+        None => ctx.uint(offset.constant),
+        Some(dynamic) if offset.constant == 0 => dynamic,
+        Some(dynamic) => {
+            let uint_constant = ctx.uint(offset.constant);
+            let sum = ctx.inc();
+            out.append(&mut vec![
+                encode_word(5, SPV_INSTRUCTION_OP_I_ADD),
+                ctx.uint_id,
+                sum,
+                dynamic,
+                uint_constant,
+            ]);
+            sum
+        }
+    }
+}
+
+fn word_pointer_spv(
+    ctx: &mut WordContext,
+    pointer: &WordPointer,
+    offset: WordOffset,
+    out: &mut Vec<u32>,
+) -> u32 {
+    let word_index = word_index_spv(ctx, offset, out);
+    let word_pointer_type_id = ctx.uint_pointer(pointer.storage_class);
+    let uint_0 = ctx.uint(0);
+    let word_ptr = ctx.inc();
+    out.append(&mut vec![
+        encode_word(6, SPV_INSTRUCTION_OP_ACCESS_CHAIN),
+        word_pointer_type_id,
+        word_ptr,
+        pointer.variable,
+        uint_0,
+        word_index,
+    ]);
+    word_ptr
+}
+
+//
+//  %word_ptr = OpAccessChain %_ptr_uint %buffer %uint_0 %word_index
+//      %word = OpLoad %uint %word_ptr
+//         %c = OpBitcast %float %word
+//        ...
+//         %1 = OpCompositeConstruct %v3float %c %c1 %c2
+fn word_load_spv(
+    ctx: &mut WordContext,
+    pointer: &WordPointer,
+    laid: &Laid,
+    offset: WordOffset,
+    result_id: u32,
+    out: &mut Vec<u32>,
+) -> Result<(), ()> {
+    if let Laid::Scalar { type_id, is_uint } = *laid {
+        let word_ptr = word_pointer_spv(ctx, pointer, offset, out);
+        let word = if is_uint { result_id } else { ctx.inc() };
+        out.append(&mut vec![
+            encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+            ctx.uint_id,
+            word,
+            word_ptr,
+        ]);
+        if !is_uint {
+            out.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                type_id,
+                result_id,
+                word,
+            ]);
+        }
+        return Ok(());
+    }
+
+    let parts = laid.parts()?;
+    let mut part_ids = vec![];
+    for (byte_offset, part) in parts.iter() {
+        let part_id = ctx.inc();
+        let part_offset = WordOffset {
+            constant: offset.constant + byte_offset / 4,
+            ..offset
+        };
+        word_load_spv(ctx, pointer, part, part_offset, part_id, out)?;
+        part_ids.push(part_id);
+    }
+    out.append(&mut vec![
+        encode_word(
+            3 + part_ids.len() as u16,
+            SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT,
+        ),
+        laid.type_id(),
+        result_id,
+    ]);
+    out.append(&mut part_ids);
+    Ok(())
+}
+
+//
+//         %c = OpCompositeExtract %float %value 0
+//      %word = OpBitcast %uint %c
+//  %word_ptr = OpAccessChain %_ptr_uint %buffer %uint_0 %word_index
+//              OpStore %word_ptr %word
+fn word_store_spv(
+    ctx: &mut WordContext,
+    pointer: &WordPointer,
+    laid: &Laid,
+    offset: WordOffset,
+    value: u32,
+    out: &mut Vec<u32>,
+) -> Result<(), ()> {
+    if let Laid::Scalar { is_uint, .. } = *laid {
+        let word = if is_uint {
+            value
+        } else {
+            let word = ctx.inc();
+            out.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                ctx.uint_id,
+                word,
+                value,
+            ]);
+            word
+        };
+        let word_ptr = word_pointer_spv(ctx, pointer, offset, out);
+        out.append(&mut vec![
+            encode_word(3, SPV_INSTRUCTION_OP_STORE),
+            word_ptr,
+            word,
+        ]);
+        return Ok(());
+    }
+
+    for (i, (byte_offset, part)) in laid.parts()?.into_iter().enumerate() {
+        let part_id = ctx.inc();
+        out.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+            part.type_id(),
+            part_id,
+            value,
+            i as u32,
+        ]);
+        let part_offset = WordOffset {
+            constant: offset.constant + byte_offset / 4,
+            ..offset
+        };
+        word_store_spv(ctx, pointer, part, part_offset, part_id, out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use super::*;
+
+fn inc(ib: &mut u32) -> u32 {
+    *ib += 1;
+    *ib - 1
+}
+
+mod eval;
+
+use eval::*;
+
+/// Choose which specialization constants [`specconstantpatch`] folds into regular constants.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SpecConstantMode {
+    /// Fold every specialization constant, including composites and `OpSpecConstantOp`.
+    #[default]
+    BakeAll,
+    /// Keep the scalar specialization constants that WGSL's `override` supports.
+    /// A `bool`, 32-bit int, or 32-bit float specialization constant is only folded when it has a
+    /// value or when a composite, an `OpSpecConstantOp`, or an array length depends on it.
+    Overridable,
+}
+
+/// Which `SpecId`s [`specconstantpatch`] has folded and which it has left alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpecConstantReport {
+    /// Sorted `SpecId`s that are now regular constants.
+    pub baked: Vec<u32>,
+    /// Sorted `SpecId`s that are still specialization constants, set these as pipeline overrides.
+    pub overridable: Vec<u32>,
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// `values` maps a `SpecId` to the raw bits of its value, use [`f32::to_bits`] for floats and any
+/// non-zero value for `true`.
+/// Specialization constants without a value are folded using their default.
+/// Does not produce any corrections, see `report` for which `SpecId`s were baked.
+///
+/// Fails on `OpSpecConstantOp`s that cannot be evaluated, such as `QuantizeToF16` or ones
+/// operating on structs and arrays.
+pub fn specconstantpatch(
+    in_spv: &[u32],
+    values: &HashMap<u32, u64>,
+    mode: SpecConstantMode,
+    report: &mut SpecConstantReport,
+) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    // Both regular and specialization constants, in order
+    let mut op_constant_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        match instruction {
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_TRUE
+            | SPV_INSTRUCTION_OP_CONSTANT_FALSE
+            | SPV_INSTRUCTION_OP_CONSTANT
+            | SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE
+            | SPV_INSTRUCTION_OP_CONSTANT_NULL
+            | SPV_INSTRUCTION_OP_SPEC_CONSTANT_TRUE
+            | SPV_INSTRUCTION_OP_SPEC_CONSTANT_FALSE
+            | SPV_INSTRUCTION_OP_SPEC_CONSTANT
+            | SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE
+            | SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP => op_constant_idxs.push(spv_idx),
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    let is_spec = |idx: usize| {
+        matches!(
+            loword(spv[idx]),
+            SPV_INSTRUCTION_OP_SPEC_CONSTANT_TRUE
+                | SPV_INSTRUCTION_OP_SPEC_CONSTANT_FALSE
+                | SPV_INSTRUCTION_OP_SPEC_CONSTANT
+                | SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE
+                | SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP
+        )
+    };
+    let spec_constant_idxs = op_constant_idxs
+        .iter()
+        .copied()
+        .filter(|&idx| is_spec(idx))
+        .collect::<Vec<_>>();
+    if spec_constant_idxs.is_empty() {
+        *report = SpecConstantReport::default();
+        return Ok(in_spv.to_vec());
+    }
+
+    // 2. Find the scalar and vector types that constants can be evaluated in
+    let mut types = HashMap::new();
+    for &idx in op_type_bool_idxs.iter() {
+        types.insert(
+            spv[idx + 1],
+            ConstantType {
+                kind: ScalarKind::Bool,
+                scalar_type_id: spv[idx + 1],
+                count: 1,
+            },
+        );
+    }
+    for &idx in op_type_int_idxs.iter() {
+        let kind = ScalarKind::Int {
+            width: spv[idx + 2],
+            signed: spv[idx + 3] == SPV_SIGNEDNESS_SIGNED,
+        };
+        types.insert(
+            spv[idx + 1],
+            ConstantType {
+                kind,
+                scalar_type_id: spv[idx + 1],
+                count: 1,
+            },
+        );
+    }
+    for &idx in op_type_float_idxs.iter() {
+        let kind = ScalarKind::Float {
+            width: spv[idx + 2],
+        };
+        types.insert(
+            spv[idx + 1],
+            ConstantType {
+                kind,
+                scalar_type_id: spv[idx + 1],
+                count: 1,
+            },
+        );
+    }
+    for &idx in op_type_vector_idxs.iter() {
+        if let Some(&component) = types.get(&spv[idx + 2]) {
+            types.insert(
+                spv[idx + 1],
+                ConstantType {
+                    count: spv[idx + 3],
+                    ..component
+                },
+            );
+        }
+    }
+
+    let spec_ids = op_decorate_idxs
+        .iter()
+        .filter(|&&idx| spv[idx + 2] == SPV_DECORATION_SPEC_ID)
+        .map(|&idx| (spv[idx + 1], spv[idx + 3]))
+        .collect::<HashMap<_, _>>();
+
+    // 3. Decide what to bake
+    let mut baked_ids = HashSet::new();
+    for &idx in spec_constant_idxs.iter() {
+        let id = spv[idx + 2];
+        let bake = match (mode, loword(spv[idx])) {
+            (SpecConstantMode::BakeAll, _) => true,
+            (
+                SpecConstantMode::Overridable,
+                SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE | SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP,
+            ) => true,
+            (SpecConstantMode::Overridable, _) => {
+                let overridable = types
+                    .get(&spv[idx + 1])
+                    .is_some_and(|ty| matches!(ty.kind.width(), 1 | 32));
+                match spec_ids.get(&id) {
+                    Some(spec_id) => !overridable || values.contains_key(spec_id),
+                    None => true,
+                }
+            }
+        };
+        if bake {
+            baked_ids.insert(id);
+        }
+    }
+    if mode == SpecConstantMode::Overridable {
+        // Outside of workgroup memory, WGSL array lengths cannot be `override`s
+        for &idx in op_type_array_idxs.iter() {
+            baked_ids.insert(spv[idx + 3]);
+        }
+        // Whatever a baked constant is made of must be baked too
+        for &idx in spec_constant_idxs.iter().rev() {
+            if !baked_ids.contains(&spv[idx + 2]) {
+                continue;
+            }
+            let operand_ids = match loword(spv[idx]) {
+                SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE => {
+                    &spv[idx + 3..idx + hiword(spv[idx]) as usize]
+                }
+                SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP => spec_constant_op_operand_ids(&spv, idx),
+                _ => &[],
+            };
+            baked_ids.extend(operand_ids.iter().copied());
+        }
+    }
+
+    // 4. Evaluate constants in order, folding the ones we bake
+    let mut constant_types = HashMap::new();
+    let mut constant_values: HashMap<u32, Vec<u64>> = HashMap::new();
+    for &idx in op_constant_idxs.iter() {
+        let word_count = hiword(spv[idx]) as usize;
+        let result_type_id = spv[idx + 1];
+        let result_id = spv[idx + 2];
+        let ty = types.get(&result_type_id).copied();
+        let baked = baked_ids.contains(&result_id);
+        constant_types.insert(result_id, result_type_id);
+
+        let value = match loword(spv[idx]) {
+            SPV_INSTRUCTION_OP_CONSTANT_TRUE => Some(vec![1]),
+            SPV_INSTRUCTION_OP_CONSTANT_FALSE => Some(vec![0]),
+            SPV_INSTRUCTION_OP_CONSTANT => {
+                ty.map(|ty| vec![decode_literal(ty.kind, &spv[idx + 3..idx + word_count])])
+            }
+            SPV_INSTRUCTION_OP_CONSTANT_NULL => ty.map(|ty| vec![0; ty.count as usize]),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE | SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE => {
+                if baked {
+                    new_spv[idx] =
+                        encode_word(word_count as u16, SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE);
+                }
+                // Only vectors are needed by `OpSpecConstantOp`
+                ty.filter(|ty| ty.count > 1).and_then(|_| {
+                    spv[idx + 3..idx + word_count]
+                        .iter()
+                        .map(|id| constant_values.get(id).and_then(|v| v.first().copied()))
+                        .collect::<Option<Vec<_>>>()
+                })
+            }
+            SPV_INSTRUCTION_OP_SPEC_CONSTANT_TRUE | SPV_INSTRUCTION_OP_SPEC_CONSTANT_FALSE => {
+                let value = match spec_ids.get(&result_id).and_then(|id| values.get(id)) {
+                    Some(&value) if baked => value != 0,
+                    _ => loword(spv[idx]) == SPV_INSTRUCTION_OP_SPEC_CONSTANT_TRUE,
+                };
+                if baked {
+                    new_spv[idx] = encode_word(
+                        3,
+                        if value {
+                            SPV_INSTRUCTION_OP_CONSTANT_TRUE
+                        } else {
+                            SPV_INSTRUCTION_OP_CONSTANT_FALSE
+                        },
+                    );
+                }
+                Some(vec![value as u64])
+            }
+            SPV_INSTRUCTION_OP_SPEC_CONSTANT => {
+                let ty = ty.ok_or(())?;
+                let value = match spec_ids.get(&result_id).and_then(|id| values.get(id)) {
+                    Some(&value) if baked => mask(value, ty.kind.width()),
+                    _ => decode_literal(ty.kind, &spv[idx + 3..idx + word_count]),
+                };
+                if baked {
+                    new_spv[idx] = encode_word(word_count as u16, SPV_INSTRUCTION_OP_CONSTANT);
+                    let literal = encode_literal(ty.kind, value);
+                    new_spv[idx + 3..idx + word_count].copy_from_slice(&literal);
+                }
+                Some(vec![value])
+            }
+            SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP => {
+                let ty = ty.ok_or(())?;
+                let value =
+                    eval_spec_constant_op(&spv, idx, &types, &constant_types, &constant_values)?;
+                if value.len() != ty.count as usize {
+                    return Err(());
+                }
+
+                //
+                //  Scalars:
+                //      %result = OpConstant %type <value>
+                //
+                //  Vectors:
+                //      %component_0 = OpConstant %scalar_type <value_0>
+                //      ...
+                //           %result = OpConstantComposite %type %component_0 ...
+                let instruction = if ty.count == 1 {
+                    scalar_constant_spv(ty, result_id, value[0])
+                } else {
+                    let mut instruction = vec![];
+                    let component_ids = value
+                        .iter()
+                        .map(|&component| {
+                            let component_id = inc(&mut instruction_bound);
+                            instruction.extend(scalar_constant_spv(ty, component_id, component));
+                            component_id
+                        })
+                        .collect::<Vec<_>>();
+                    instruction.extend([
+                        encode_word(
+                            3 + component_ids.len() as u16,
+                            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE,
+                        ),
+                        result_type_id,
+                        result_id,
+                    ]);
+                    instruction.extend(component_ids);
+                    instruction
+                };
+
+                for i in 0..word_count {
+                    new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                }
+                instruction_inserts.push(InstructionInsert {
+                    previous_spv_idx: idx,
+                    instruction,
+                });
+                Some(value)
+            }
+            _ => unreachable!(),
+        };
+        if let Some(value) = value {
+            constant_values.insert(result_id, value);
+        }
+    }
+
+    // 5. Remove the `SpecId`s of what was baked
+    let mut baked = vec![];
+    let mut overridable = vec![];
+    for &idx in op_decorate_idxs.iter() {
+        if spv[idx + 2] != SPV_DECORATION_SPEC_ID {
+            continue;
+        }
+        if baked_ids.contains(&spv[idx + 1]) {
+            baked.push(spv[idx + 3]);
+            for i in 0..hiword(spv[idx]) as usize {
+                new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        } else {
+            overridable.push(spv[idx + 3]);
+        }
+    }
+    baked.sort();
+    overridable.sort();
+    *report = SpecConstantReport { baked, overridable };
+
+    // 6. Insert New Instructions
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 7. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 8. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
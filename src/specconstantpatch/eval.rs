@@ -0,0 +1,265 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ScalarKind {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+}
+
+impl ScalarKind {
+    pub(super) fn width(self) -> u32 {
+        match self {
+            ScalarKind::Bool => 1,
+            ScalarKind::Int { width, .. } | ScalarKind::Float { width } => width,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ConstantType {
+    pub kind: ScalarKind,
+    pub scalar_type_id: u32,
+    /// `1` for scalars, otherwise the vector's component count.
+    pub count: u32,
+}
+
+// Values are kept as the components' raw bits, masked to the scalar width.
+pub(super) fn mask(value: u64, width: u32) -> u64 {
+    if width >= 64 {
+        value
+    } else {
+        value & ((1 << width) - 1)
+    }
+}
+
+pub(super) fn sext(value: u64, width: u32) -> i64 {
+    let shift = 64 - width.clamp(1, 64);
+    ((value << shift) as i64) >> shift
+}
+
+pub(super) fn decode_literal(kind: ScalarKind, words: &[u32]) -> u64 {
+    let value = match words {
+        [low] => *low as u64,
+        [low, high, ..] => (*low as u64) | ((*high as u64) << 32),
+        [] => 0,
+    };
+    mask(value, kind.width())
+}
+
+pub(super) fn encode_literal(kind: ScalarKind, value: u64) -> Vec<u32> {
+    match kind {
+        _ if kind.width() > 32 => vec![value as u32, (value >> 32) as u32],
+        // From spec, narrower signed types are sign extended into the word
+        ScalarKind::Int {
+            width,
+            signed: true,
+        } => vec![sext(value, width) as u32],
+        _ => vec![value as u32],
+    }
+}
+
+pub(super) fn scalar_constant_spv(ty: ConstantType, result_id: u32, value: u64) -> Vec<u32> {
+    match ty.kind {
+        ScalarKind::Bool => vec![
+            encode_word(
+                3,
+                if value != 0 {
+                    SPV_INSTRUCTION_OP_CONSTANT_TRUE
+                } else {
+                    SPV_INSTRUCTION_OP_CONSTANT_FALSE
+                },
+            ),
+            ty.scalar_type_id,
+            result_id,
+        ],
+        kind => {
+            let literal = encode_literal(kind, value);
+            let mut spv = vec![
+                encode_word(3 + literal.len() as u16, SPV_INSTRUCTION_OP_CONSTANT),
+                ty.scalar_type_id,
+                result_id,
+            ];
+            spv.extend(literal);
+            spv
+        }
+    }
+}
+
+// Only the result ids of an `OpSpecConstantOp`'s operands, literals are skipped.
+pub(super) fn spec_constant_op_operand_ids(spv: &[u32], idx: usize) -> &[u32] {
+    let operands = &spv[idx + 4..idx + hiword(spv[idx]) as usize];
+    let id_count = match spv[idx + 3] as u16 {
+        SPV_INSTRUCTION_OP_VECTOR_SHUFFLE | SPV_INSTRUCTION_OP_COMPOSITE_INSERT => 2,
+        SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT => 1,
+        _ => operands.len(),
+    };
+    &operands[..id_count.min(operands.len())]
+}
+
+fn map1(a: &[u64], f: impl Fn(u64) -> u64) -> Vec<u64> {
+    a.iter().map(|&a| f(a)).collect()
+}
+
+fn map2(a: &[u64], b: &[u64], f: impl Fn(u64, u64) -> u64) -> Result<Vec<u64>, ()> {
+    if a.len() != b.len() {
+        return Err(());
+    }
+    Ok(a.iter().zip(b.iter()).map(|(&a, &b)| f(a, b)).collect())
+}
+
+/// Evaluate the `OpSpecConstantOp` at `idx` into the components of its result.
+pub(super) fn eval_spec_constant_op(
+    spv: &[u32],
+    idx: usize,
+    types: &HashMap<u32, ConstantType>,
+    constant_types: &HashMap<u32, u32>,
+    values: &HashMap<u32, Vec<u64>>,
+) -> Result<Vec<u64>, ()> {
+    let result_type_id = spv[idx + 1];
+    let opcode = spv[idx + 3] as u16;
+    let operands = &spv[idx + 4..idx + hiword(spv[idx]) as usize];
+
+    let rw = types.get(&result_type_id).ok_or(())?.kind.width();
+    let value =
+        |n: usize| -> Result<&Vec<u64>, ()> { values.get(operands.get(n).ok_or(())?).ok_or(()) };
+    let width = |n: usize| -> Result<u32, ()> {
+        let ty = constant_types.get(operands.get(n).ok_or(())?).ok_or(())?;
+        Ok(types.get(ty).ok_or(())?.kind.width())
+    };
+    let bool_of = |v: bool| v as u64;
+
+    Ok(match opcode {
+        SPV_INSTRUCTION_OP_S_CONVERT => {
+            let w = width(0)?;
+            map1(value(0)?, |a| mask(sext(a, w) as u64, rw))
+        }
+        SPV_INSTRUCTION_OP_U_CONVERT => map1(value(0)?, |a| mask(a, rw)),
+        SPV_INSTRUCTION_OP_F_CONVERT => match (width(0)?, rw) {
+            (w, rw) if w == rw => value(0)?.clone(),
+            (32, 64) => map1(value(0)?, |a| (f32::from_bits(a as u32) as f64).to_bits()),
+            (64, 32) => map1(value(0)?, |a| (f64::from_bits(a) as f32).to_bits() as u64),
+            _ => return Err(()),
+        },
+        SPV_INSTRUCTION_OP_S_NEGATE => map1(value(0)?, |a| mask(a.wrapping_neg(), rw)),
+        SPV_INSTRUCTION_OP_NOT => map1(value(0)?, |a| mask(!a, rw)),
+        SPV_INSTRUCTION_OP_LOGICAL_NOT => map1(value(0)?, |a| bool_of(a == 0)),
+
+        SPV_INSTRUCTION_OP_I_ADD => map2(value(0)?, value(1)?, |a, b| mask(a.wrapping_add(b), rw))?,
+        SPV_INSTRUCTION_OP_I_SUB => map2(value(0)?, value(1)?, |a, b| mask(a.wrapping_sub(b), rw))?,
+        SPV_INSTRUCTION_OP_I_MUL => map2(value(0)?, value(1)?, |a, b| mask(a.wrapping_mul(b), rw))?,
+        // Division by zero is undefined, just produce zero
+        SPV_INSTRUCTION_OP_U_DIV => {
+            map2(value(0)?, value(1)?, |a, b| a.checked_div(b).unwrap_or(0))?
+        }
+        SPV_INSTRUCTION_OP_U_MOD => {
+            map2(value(0)?, value(1)?, |a, b| a.checked_rem(b).unwrap_or(0))?
+        }
+        SPV_INSTRUCTION_OP_S_DIV => map2(value(0)?, value(1)?, |a, b| {
+            let (a, b) = (sext(a, rw), sext(b, rw));
+            mask(a.checked_div(b).unwrap_or(0) as u64, rw)
+        })?,
+        SPV_INSTRUCTION_OP_S_REM => map2(value(0)?, value(1)?, |a, b| {
+            let (a, b) = (sext(a, rw), sext(b, rw));
+            mask(a.checked_rem(b).unwrap_or(0) as u64, rw)
+        })?,
+        // Unlike `SRem`, the sign of the result follows the divisor
+        SPV_INSTRUCTION_OP_S_MOD => map2(value(0)?, value(1)?, |a, b| {
+            let (a, b) = (sext(a, rw), sext(b, rw));
+            let r = a.checked_rem(b).unwrap_or(0);
+            let r = if r != 0 && (r < 0) != (b < 0) {
+                r + b
+            } else {
+                r
+            };
+            mask(r as u64, rw)
+        })?,
+
+        SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL => map2(value(0)?, value(1)?, |a, b| {
+            if b >= rw as u64 { 0 } else { a >> b }
+        })?,
+        SPV_INSTRUCTION_OP_SHIFT_RIGHT_ARITHMETIC => map2(value(0)?, value(1)?, |a, b| {
+            mask((sext(a, rw) >> b.min(rw as u64 - 1)) as u64, rw)
+        })?,
+        SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL => map2(value(0)?, value(1)?, |a, b| {
+            if b >= rw as u64 { 0 } else { mask(a << b, rw) }
+        })?,
+        SPV_INSTRUCTION_OP_BITWISE_OR => map2(value(0)?, value(1)?, |a, b| a | b)?,
+        SPV_INSTRUCTION_OP_BITWISE_XOR => map2(value(0)?, value(1)?, |a, b| a ^ b)?,
+        SPV_INSTRUCTION_OP_BITWISE_AND => map2(value(0)?, value(1)?, |a, b| a & b)?,
+
+        SPV_INSTRUCTION_OP_LOGICAL_OR => {
+            map2(value(0)?, value(1)?, |a, b| bool_of(a != 0 || b != 0))?
+        }
+        SPV_INSTRUCTION_OP_LOGICAL_AND => {
+            map2(value(0)?, value(1)?, |a, b| bool_of(a != 0 && b != 0))?
+        }
+        SPV_INSTRUCTION_OP_LOGICAL_EQUAL => {
+            map2(value(0)?, value(1)?, |a, b| bool_of((a != 0) == (b != 0)))?
+        }
+        SPV_INSTRUCTION_OP_LOGICAL_NOT_EQUAL => {
+            map2(value(0)?, value(1)?, |a, b| bool_of((a != 0) != (b != 0)))?
+        }
+
+        SPV_INSTRUCTION_OP_I_EQUAL => map2(value(0)?, value(1)?, |a, b| bool_of(a == b))?,
+        SPV_INSTRUCTION_OP_I_NOT_EQUAL => map2(value(0)?, value(1)?, |a, b| bool_of(a != b))?,
+        SPV_INSTRUCTION_OP_U_GREATER_THAN => map2(value(0)?, value(1)?, |a, b| bool_of(a > b))?,
+        SPV_INSTRUCTION_OP_U_GREATER_THAN_EQUAL => {
+            map2(value(0)?, value(1)?, |a, b| bool_of(a >= b))?
+        }
+        SPV_INSTRUCTION_OP_U_LESS_THAN => map2(value(0)?, value(1)?, |a, b| bool_of(a < b))?,
+        SPV_INSTRUCTION_OP_U_LESS_THAN_EQUAL => map2(value(0)?, value(1)?, |a, b| bool_of(a <= b))?,
+        SPV_INSTRUCTION_OP_S_GREATER_THAN
+        | SPV_INSTRUCTION_OP_S_GREATER_THAN_EQUAL
+        | SPV_INSTRUCTION_OP_S_LESS_THAN
+        | SPV_INSTRUCTION_OP_S_LESS_THAN_EQUAL => {
+            let w = width(0)?;
+            map2(value(0)?, value(1)?, |a, b| {
+                let (a, b) = (sext(a, w), sext(b, w));
+                bool_of(match opcode {
+                    SPV_INSTRUCTION_OP_S_GREATER_THAN => a > b,
+                    SPV_INSTRUCTION_OP_S_GREATER_THAN_EQUAL => a >= b,
+                    SPV_INSTRUCTION_OP_S_LESS_THAN => a < b,
+                    _ => a <= b,
+                })
+            })?
+        }
+
+        SPV_INSTRUCTION_OP_SELECT => {
+            let (condition, a, b) = (value(0)?, value(1)?, value(2)?);
+            if a.len() != b.len() || (condition.len() != 1 && condition.len() != a.len()) {
+                return Err(());
+            }
+            (0..a.len())
+                .map(|i| {
+                    if condition[i.min(condition.len() - 1)] != 0 {
+                        a[i]
+                    } else {
+                        b[i]
+                    }
+                })
+                .collect()
+        }
+        SPV_INSTRUCTION_OP_VECTOR_SHUFFLE => {
+            let components = value(0)?.iter().chain(value(1)?.iter()).collect::<Vec<_>>();
+            // An undefined `0xFFFFFFFF` component is free to be anything
+            operands[2..]
+                .iter()
+                .map(|&i| components.get(i as usize).map(|&&v| v).unwrap_or(0))
+                .collect()
+        }
+        SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT => match operands[1..] {
+            [i] => vec![*value(0)?.get(i as usize).ok_or(())?],
+            _ => return Err(()),
+        },
+        SPV_INSTRUCTION_OP_COMPOSITE_INSERT => match operands[2..] {
+            [i] => {
+                let mut composite = value(1)?.clone();
+                *composite.get_mut(i as usize).ok_or(())? = *value(0)?.first().ok_or(())?;
+                composite
+            }
+            _ => return Err(()),
+        },
+        _ => return Err(()),
+    })
+}
@@ -66,6 +66,14 @@ pub fn drefsplitter(
     let mut word_inserts: Vec<WordInsert> = vec![];
 
     let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+
+    // 0. Function parameters cannot tell naga or tint whether a sampler compares or an image is a
+    //    depth texture, so have functions read the globals they are passed instead
+    let spv = if has_dref_operation(&spv) {
+        specialize_opaque_parameters(&spv, &mut instruction_bound)?
+    } else {
+        spv
+    };
     let mut new_spv = spv.clone();
 
     // 1. Find locations instructions we need
@@ -802,3 +810,26 @@ pub fn drefsplitter(
     // 17. Write New Header and New Code
     Ok(fuse_final(spv_header, new_spv, instruction_bound))
 }
+
+fn has_dref_operation(spv: &[u32]) -> bool {
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        match loword(op) {
+            SPV_INSTRUCTION_OP_IMAGE_SAMPLE_DREF_IMPLICIT_LOD
+            | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_DREF_EXPLICIT_LOD
+            | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_DREF_IMPLICIT_LOD
+            | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_DREF_EXPLICIT_LOD
+            | SPV_INSTRUCTION_OP_IMAGE_DREF_GATHER
+            | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_DREF_IMPLICIT_LOD
+            | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_DREF_EXPLICIT_LOD
+            | SPV_INSTRUCTION_OP_IMAGE_SPARSE_DREF_GATHER => return true,
+            _ => {}
+        }
+        if hiword(op) == 0 {
+            return false;
+        }
+        spv_idx += hiword(op) as usize;
+    }
+    false
+}
@@ -0,0 +1,153 @@
+use super::*;
+
+/// A single entry point module produced by [`splitentrypoints`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPointModule {
+    pub name: String,
+    /// The `OpEntryPoint`'s execution model, such as `0` for vertex, `4` for fragment, and `5` for
+    /// compute.
+    pub execution_model: u32,
+    pub spv: Vec<u32>,
+}
+
+/// Split a module with several `OpEntryPoint`s into one module per entry point.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Functions, variables, types, constants, and decorations that an entry point does not use are
+/// stripped from its module so that each module can be transformed and mirrored on its own.
+/// Does not produce any side effects or corrections.
+pub fn splitentrypoints(in_spv: &[u32]) -> Result<Vec<EntryPointModule>, ()> {
+    let spv = in_spv.to_owned();
+
+    let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+
+    // 1. Find locations instructions we need
+    let mut op_entry_point_idxs = vec![];
+    let mut op_execution_mode_idxs = vec![];
+    let mut op_name_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    // Types, constants, and global variables
+    let mut op_global_idxs = vec![];
+    // From `OpFunction` up to and including `OpFunctionEnd`
+    let mut function_ranges = vec![];
+
+    let mut function_start = None;
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_ENTRY_POINT => op_entry_point_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXECUTION_MODE | SPV_INSTRUCTION_OP_EXECUTION_MODE_ID => {
+                op_execution_mode_idxs.push(spv_idx)
+            }
+            SPV_INSTRUCTION_OP_NAME | SPV_INSTRUCTION_OP_MEMBER_NAME => op_name_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE
+            | SPV_INSTRUCTION_OP_MEMBER_DECORATE
+            | SPV_INSTRUCTION_OP_DECORATE_ID
+            | SPV_INSTRUCTION_OP_DECORATE_STRING
+            | SPV_INSTRUCTION_OP_MEMBER_DECORATE_STRING => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => function_start = Some(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION_END => {
+                let start = function_start.take().ok_or(())?;
+                function_ranges.push((start, spv_idx + word_count as usize));
+            }
+            _ if function_start.is_none() && global_result_id(&spv, spv_idx).is_some() => {
+                op_global_idxs.push(spv_idx)
+            }
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    let op_decorate_id_idxs = op_decorate_idxs
+        .iter()
+        .copied()
+        .filter(|&idx| loword(spv[idx]) == SPV_INSTRUCTION_OP_DECORATE_ID)
+        .collect::<Vec<_>>();
+
+    let mut modules = vec![];
+    for &ep_idx in op_entry_point_idxs.iter() {
+        let execution_model = spv[ep_idx + 1];
+        let function_id = spv[ep_idx + 2];
+
+        let execution_mode_idxs = op_execution_mode_idxs
+            .iter()
+            .copied()
+            .filter(|&idx| spv[idx + 1] == function_id)
+            .collect::<Vec<_>>();
+
+        // 2. Find everything the entry point can reach
+        let (name, used_ids) = entry_point_used_ids(EntryPointUsedIdsIn {
+            spv: &spv,
+            op_global_idxs: &op_global_idxs,
+            function_ranges: &function_ranges,
+            op_decorate_id_idxs: &op_decorate_id_idxs,
+            op_execution_mode_idxs: &execution_mode_idxs,
+            ep_idx,
+        })?;
+
+        // 3. Whiteout everything else
+        let mut new_spv = spv.clone();
+        let mut whiteout = |idx: usize| {
+            for i in 0..hiword(spv[idx]) as usize {
+                new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        };
+
+        for &idx in op_entry_point_idxs.iter() {
+            if idx != ep_idx {
+                whiteout(idx);
+            }
+        }
+        for &idx in op_execution_mode_idxs.iter() {
+            if !execution_mode_idxs.contains(&idx) {
+                whiteout(idx);
+            }
+        }
+        for &idx in op_name_idxs.iter().chain(op_decorate_idxs.iter()) {
+            if !used_ids.contains(&spv[idx + 1]) {
+                whiteout(idx);
+            }
+        }
+        for &idx in op_global_idxs.iter() {
+            if global_result_id(&spv, idx).is_some_and(|id| !used_ids.contains(&id)) {
+                whiteout(idx);
+            }
+        }
+        for &(start, end) in function_ranges.iter() {
+            if !used_ids.contains(&spv[start + 2]) {
+                let mut idx = start;
+                while idx < end {
+                    whiteout(idx);
+                    idx += hiword(spv[idx]) as usize;
+                }
+            }
+        }
+
+        // 4. Remove Instructions that have been Whited Out.
+        prune_noops(&mut new_spv);
+
+        // 5. Write New Header and New Code
+        modules.push(EntryPointModule {
+            name,
+            execution_model,
+            spv: fuse_final(spv_header.clone(), new_spv, instruction_bound),
+        });
+    }
+
+    Ok(modules)
+}
@@ -5,6 +5,12 @@ pub const SPV_HEADER_INSTRUCTION_BOUND_OFFSET: usize = 3;
 
 pub const SPV_INSTRUCTION_OP_NOP: u16 = 1;
 pub const SPV_INSTRUCTION_OP_NAME: u16 = 5;
+pub const SPV_INSTRUCTION_OP_MEMBER_NAME: u16 = 6;
+pub const SPV_INSTRUCTION_OP_STRING: u16 = 7;
+pub const SPV_INSTRUCTION_OP_LINE: u16 = 8;
+pub const SPV_INSTRUCTION_OP_CAPABILITY: u16 = 17;
+pub const SPV_INSTRUCTION_OP_ENTRY_POINT: u16 = 15;
+pub const SPV_INSTRUCTION_OP_EXECUTION_MODE: u16 = 16;
 pub const SPV_INSTRUCTION_OP_TYPE_VOID: u16 = 19;
 pub const SPV_INSTRUCTION_OP_TYPE_BOOL: u16 = 20;
 pub const SPV_INSTRUCTION_OP_TYPE_INT: u16 = 21;
@@ -15,10 +21,23 @@ pub const SPV_INSTRUCTION_OP_TYPE_IMAGE: u16 = 25;
 pub const SPV_INSTRUCTION_OP_TYPE_SAMPLER: u16 = 26;
 pub const SPV_INSTRUCTION_OP_TYPE_SAMPLED_IMAGE: u16 = 27;
 pub const SPV_INSTRUCTION_OP_TYPE_ARRAY: u16 = 28;
+pub const SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY: u16 = 29;
 pub const SPV_INSTRUCTION_OP_TYPE_STRUCT: u16 = 30;
 pub const SPV_INSTRUCTION_OP_TYPE_POINTER: u16 = 32;
 pub const SPV_INSTRUCTION_OP_TYPE_FUNCTION: u16 = 33;
+pub const SPV_INSTRUCTION_OP_TYPE_FORWARD_POINTER: u16 = 39;
+pub const SPV_INSTRUCTION_OP_TYPE_ACCELERATION_STRUCTURE_KHR: u16 = 5341;
+pub const SPV_INSTRUCTION_OP_CONSTANT_TRUE: u16 = 41;
+pub const SPV_INSTRUCTION_OP_CONSTANT_FALSE: u16 = 42;
 pub const SPV_INSTRUCTION_OP_CONSTANT: u16 = 43;
+pub const SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE: u16 = 44;
+pub const SPV_INSTRUCTION_OP_CONSTANT_SAMPLER: u16 = 45;
+pub const SPV_INSTRUCTION_OP_CONSTANT_NULL: u16 = 46;
+pub const SPV_INSTRUCTION_OP_SPEC_CONSTANT_TRUE: u16 = 48;
+pub const SPV_INSTRUCTION_OP_SPEC_CONSTANT_FALSE: u16 = 49;
+pub const SPV_INSTRUCTION_OP_SPEC_CONSTANT: u16 = 50;
+pub const SPV_INSTRUCTION_OP_SPEC_CONSTANT_COMPOSITE: u16 = 51;
+pub const SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP: u16 = 52;
 pub const SPV_INSTRUCTION_OP_FUNCTION_PARAMETER: u16 = 55;
 pub const SPV_INSTRUCTION_OP_FUNCTION_CALL: u16 = 57;
 pub const SPV_INSTRUCTION_OP_FUNCTION_END: u16 = 56;
@@ -29,10 +48,23 @@ pub const SPV_INSTRUCTION_OP_STORE: u16 = 62;
 pub const SPV_INSTRUCTION_OP_COPY_MEMORY: u16 = 63;
 pub const SPV_INSTRUCTION_OP_ACCESS_CHAIN: u16 = 65;
 pub const SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN: u16 = 66;
+pub const SPV_INSTRUCTION_OP_ARRAY_LENGTH: u16 = 68;
 pub const SPV_INSTRUCTION_OP_DECORATE: u16 = 71;
 pub const SPV_INSTRUCTION_OP_MEMBER_DECORATE: u16 = 72;
+pub const SPV_INSTRUCTION_OP_EXECUTION_MODE_ID: u16 = 331;
+pub const SPV_INSTRUCTION_OP_DECORATE_ID: u16 = 332;
+pub const SPV_INSTRUCTION_OP_DECORATE_STRING: u16 = 5632;
+pub const SPV_INSTRUCTION_OP_MEMBER_DECORATE_STRING: u16 = 5633;
+pub const SPV_INSTRUCTION_OP_VECTOR_SHUFFLE: u16 = 79;
 pub const SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT: u16 = 80;
+pub const SPV_INSTRUCTION_OP_COPY_OBJECT: u16 = 83;
 pub const SPV_INSTRUCTION_OP_SAMPLED_IMAGE: u16 = 86;
+pub const SPV_INSTRUCTION_OP_F_CONVERT: u16 = 115;
+pub const SPV_INSTRUCTION_OP_CONVERT_F_TO_S: u16 = 110;
+pub const SPV_INSTRUCTION_OP_CONVERT_S_TO_F: u16 = 111;
+pub const SPV_INSTRUCTION_OP_CONVERT_U_TO_F: u16 = 112;
+pub const SPV_INSTRUCTION_OP_U_CONVERT: u16 = 113;
+pub const SPV_INSTRUCTION_OP_S_CONVERT: u16 = 114;
 pub const SPV_INSTRUCTION_OP_BITCAST: u16 = 124;
 pub const SPV_INSTRUCTION_OP_LABEL: u16 = 248;
 pub const SPV_INSTRUCTION_OP_RETURN_VALUE: u16 = 254;
@@ -44,6 +76,9 @@ pub const SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_EXPLICIT_LOD: u16 = 92;
 pub const SPV_INSTRUCTION_OP_IMAGE_GATHER: u16 = 96;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_IMPLICIT_LOD: u16 = 305;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_EXPLICIT_LOD: u16 = 306;
+pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_IMPLICIT_LOD: u16 = 309;
+pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_EXPLICIT_LOD: u16 = 310;
+pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_FETCH: u16 = 313;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_GATHER: u16 = 314;
 
 pub const SPV_INSTRUCTION_OP_IMAGE_SAMPLE_DREF_IMPLICIT_LOD: u16 = 89;
@@ -53,29 +88,73 @@ pub const SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_DREF_EXPLICIT_LOD: u16 = 94;
 pub const SPV_INSTRUCTION_OP_IMAGE_DREF_GATHER: u16 = 97;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_DREF_IMPLICIT_LOD: u16 = 307;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_DREF_EXPLICIT_LOD: u16 = 308;
+pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_DREF_IMPLICIT_LOD: u16 = 311;
+pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_DREF_EXPLICIT_LOD: u16 = 312;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_DREF_GATHER: u16 = 315;
 
 pub const SPV_INSTRUCTION_OP_IS_NAN: u16 = 156;
 pub const SPV_INSTRUCTION_OP_IS_INF: u16 = 157;
+pub const SPV_INSTRUCTION_OP_I_ADD: u16 = 128;
+pub const SPV_INSTRUCTION_OP_I_SUB: u16 = 130;
+pub const SPV_INSTRUCTION_OP_I_MUL: u16 = 132;
+pub const SPV_INSTRUCTION_OP_U_DIV: u16 = 134;
+pub const SPV_INSTRUCTION_OP_S_DIV: u16 = 135;
+pub const SPV_INSTRUCTION_OP_U_MOD: u16 = 137;
+pub const SPV_INSTRUCTION_OP_S_REM: u16 = 138;
+pub const SPV_INSTRUCTION_OP_S_MOD: u16 = 139;
+pub const SPV_INSTRUCTION_OP_F_SUB: u16 = 131;
+pub const SPV_INSTRUCTION_OP_LOGICAL_EQUAL: u16 = 164;
+pub const SPV_INSTRUCTION_OP_LOGICAL_NOT_EQUAL: u16 = 165;
+pub const SPV_INSTRUCTION_OP_LOGICAL_OR: u16 = 166;
 pub const SPV_INSTRUCTION_OP_LOGICAL_AND: u16 = 167;
+pub const SPV_INSTRUCTION_OP_LOGICAL_NOT: u16 = 168;
 pub const SPV_INSTRUCTION_OP_I_EQUAL: u16 = 170;
 pub const SPV_INSTRUCTION_OP_I_NOT_EQUAL: u16 = 171;
+pub const SPV_INSTRUCTION_OP_U_GREATER_THAN: u16 = 172;
+pub const SPV_INSTRUCTION_OP_U_GREATER_THAN_EQUAL: u16 = 174;
+pub const SPV_INSTRUCTION_OP_U_LESS_THAN: u16 = 176;
+pub const SPV_INSTRUCTION_OP_S_LESS_THAN: u16 = 177;
+pub const SPV_INSTRUCTION_OP_U_LESS_THAN_EQUAL: u16 = 178;
+pub const SPV_INSTRUCTION_OP_S_LESS_THAN_EQUAL: u16 = 179;
+pub const SPV_INSTRUCTION_OP_F_ORD_EQUAL: u16 = 180;
+pub const SPV_INSTRUCTION_OP_F_UNORD_NOT_EQUAL: u16 = 183;
+pub const SPV_INSTRUCTION_OP_F_ORD_GREATER_THAN: u16 = 186;
 pub const SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL: u16 = 194;
+pub const SPV_INSTRUCTION_OP_SHIFT_RIGHT_ARITHMETIC: u16 = 195;
+pub const SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL: u16 = 196;
+pub const SPV_INSTRUCTION_OP_BITWISE_OR: u16 = 197;
+pub const SPV_INSTRUCTION_OP_BITWISE_XOR: u16 = 198;
 pub const SPV_INSTRUCTION_OP_BITWISE_AND: u16 = 199;
+pub const SPV_INSTRUCTION_OP_NOT: u16 = 200;
+pub const SPV_INSTRUCTION_OP_ATOMIC_LOAD: u16 = 227;
+pub const SPV_INSTRUCTION_OP_ATOMIC_STORE: u16 = 228;
+pub const SPV_INSTRUCTION_OP_ATOMIC_AND: u16 = 240;
+pub const SPV_INSTRUCTION_OP_ATOMIC_OR: u16 = 241;
+pub const SPV_INSTRUCTION_OP_ATOMIC_XOR: u16 = 242;
+pub const SPV_INSTRUCTION_OP_GROUP_I_ADD: u16 = 264;
+pub const SPV_INSTRUCTION_OP_GROUP_S_MAX: u16 = 271;
+pub const SPV_INSTRUCTION_OP_GROUP_NON_UNIFORM_BALLOT_BIT_COUNT: u16 = 342;
+pub const SPV_INSTRUCTION_OP_GROUP_NON_UNIFORM_I_ADD: u16 = 349;
+pub const SPV_INSTRUCTION_OP_GROUP_NON_UNIFORM_LOGICAL_XOR: u16 = 364;
 
+pub const SPV_INSTRUCTION_OP_EXTENSION: u16 = 10;
 pub const SPV_INSTRUCTION_OP_EXT_INST_IMPORT: u16 = 11;
 pub const SPV_INSTRUCTION_OP_EXT_INST: u16 = 12;
 pub const SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT: u16 = 81;
+pub const SPV_INSTRUCTION_OP_COMPOSITE_INSERT: u16 = 82;
 pub const SPV_INSTRUCTION_OP_IMAGE_FETCH: u16 = 95;
 pub const SPV_INSTRUCTION_OP_IMAGE_TEXEL_POINTER: u16 = 67;
 pub const SPV_INSTRUCTION_OP_IMAGE_READ: u16 = 98;
 pub const SPV_INSTRUCTION_OP_IMAGE_WRITE: u16 = 99;
 pub const SPV_INSTRUCTION_OP_IMAGE_SPARSE_READ: u16 = 320;
+pub const SPV_INSTRUCTION_OP_IMAGE: u16 = 100;
+pub const SPV_INSTRUCTION_OP_IMAGE_QUERY_SIZE: u16 = 104;
 pub const SPV_INSTRUCTION_OP_S_NEGATE: u16 = 126;
 pub const SPV_INSTRUCTION_OP_SELECT: u16 = 169;
 pub const SPV_INSTRUCTION_OP_S_GREATER_THAN: u16 = 173;
 pub const SPV_INSTRUCTION_OP_S_GREATER_THAN_EQUAL: u16 = 175;
 pub const SPV_INSTRUCTION_OP_PHI: u16 = 245;
+pub const SPV_INSTRUCTION_OP_LOOP_MERGE: u16 = 246;
 pub const SPV_INSTRUCTION_OP_SELECTION_MERGE: u16 = 247;
 pub const SPV_INSTRUCTION_OP_BRANCH: u16 = 249;
 pub const SPV_INSTRUCTION_OP_BRANCH_CONDITIONAL: u16 = 250;
@@ -84,26 +163,94 @@ pub const SPV_INSTRUCTION_OP_SWITCH: u16 = 251;
 pub const SPV_SELECTION_CONTROL_NONE: u32 = 0;
 
 pub const SPV_STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+pub const SPV_STORAGE_CLASS_INPUT: u32 = 1;
 pub const SPV_STORAGE_CLASS_UNIFORM: u32 = 2;
+pub const SPV_STORAGE_CLASS_OUTPUT: u32 = 3;
 pub const SPV_STORAGE_CLASS_FUNCTION: u32 = 7;
 pub const SPV_STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+pub const SPV_STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
 
 pub const SPV_DECORATION_RELAXED_PRECISION: u32 = 0;
+pub const SPV_DECORATION_SPEC_ID: u32 = 1;
+pub const SPV_DECORATION_BLOCK: u32 = 2;
+pub const SPV_DECORATION_BUFFER_BLOCK: u32 = 3;
+pub const SPV_DECORATION_ROW_MAJOR: u32 = 4;
+pub const SPV_DECORATION_COL_MAJOR: u32 = 5;
 pub const SPV_DECORATION_ARRAY_STRIDE: u32 = 6;
 pub const SPV_DECORATION_MATRIX_STRIDE: u32 = 7;
+pub const SPV_DECORATION_BUILTIN: u32 = 11;
+pub const SPV_DECORATION_CENTROID: u32 = 16;
+pub const SPV_DECORATION_SAMPLE: u32 = 17;
 pub const SPV_DECORATION_RESTRICT: u32 = 19;
 pub const SPV_DECORATION_ALIASED: u32 = 20;
 pub const SPV_DECORATION_VOLATILE: u32 = 21;
 pub const SPV_DECORATION_COHERENT: u32 = 23;
 pub const SPV_DECORATION_NON_WRITABLE: u32 = 24;
 pub const SPV_DECORATION_NON_READABLE: u32 = 25;
+pub const SPV_DECORATION_LOCATION: u32 = 30;
+pub const SPV_DECORATION_COMPONENT: u32 = 31;
 pub const SPV_DECORATION_BINDING: u32 = 33;
 pub const SPV_DECORATION_DESCRIPTOR_SET: u32 = 34;
 pub const SPV_DECORATION_OFFSET: u32 = 35;
+pub const SPV_DECORATION_INPUT_ATTACHMENT_INDEX: u32 = 43;
+pub const SPV_BUILTIN_FRAG_COORD: u32 = 15;
+pub const SPV_BUILTIN_WORKGROUP_SIZE: u32 = 25;
+pub const SPV_EXECUTION_MODEL_FRAGMENT: u32 = 4;
+pub const SPV_EXECUTION_MODEL_GL_COMPUTE: u32 = 5;
+pub const SPV_EXECUTION_MODEL_KERNEL: u32 = 6;
+pub const SPV_EXECUTION_MODEL_TASK_NV: u32 = 5267;
+pub const SPV_EXECUTION_MODEL_MESH_NV: u32 = 5268;
+pub const SPV_EXECUTION_MODEL_TASK_EXT: u32 = 5364;
+pub const SPV_EXECUTION_MODEL_MESH_EXT: u32 = 5365;
+pub const SPV_EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+pub const SPV_EXECUTION_MODE_LOCAL_SIZE_ID: u32 = 38;
 pub const SPV_FUNCTION_CONTROL_INLINE: u32 = 1;
 pub const SPV_SIGNEDNESS_UNSIGNED: u32 = 0;
 pub const SPV_SIGNEDNESS_SIGNED: u32 = 1;
+pub const SPV_CAPABILITY_INT64: u32 = 11;
+pub const SPV_CAPABILITY_INTERPOLATION_FUNCTION: u32 = 52;
+pub const SPV_CAPABILITY_FLOAT16: u32 = 9;
+pub const SPV_CAPABILITY_INT16: u32 = 22;
+pub const SPV_CAPABILITY_INT8: u32 = 39;
+pub const SPV_CAPABILITY_STORAGE_BUFFER_16_BIT_ACCESS: u32 = 4433;
+pub const SPV_CAPABILITY_UNIFORM_AND_STORAGE_BUFFER_16_BIT_ACCESS: u32 = 4434;
+pub const SPV_CAPABILITY_STORAGE_PUSH_CONSTANT_16: u32 = 4435;
+pub const SPV_CAPABILITY_STORAGE_INPUT_OUTPUT_16: u32 = 4436;
+pub const SPV_CAPABILITY_STORAGE_BUFFER_8_BIT_ACCESS: u32 = 4448;
+pub const SPV_CAPABILITY_UNIFORM_AND_STORAGE_BUFFER_8_BIT_ACCESS: u32 = 4449;
+pub const SPV_CAPABILITY_STORAGE_PUSH_CONSTANT_8: u32 = 4450;
+pub const SPV_CAPABILITY_SAMPLED_BUFFER: u32 = 46;
+pub const SPV_CAPABILITY_IMAGE_BUFFER: u32 = 47;
+pub const SPV_CAPABILITY_INPUT_ATTACHMENT: u32 = 40;
+pub const SPV_SCOPE_DEVICE: u32 = 1;
+pub const SPV_MEMORY_SEMANTICS_NONE: u32 = 0;
 pub const SPV_DIMENSION_2D: u32 = 1;
 pub const SPV_DIMENSION_CUBE: u32 = 3;
+pub const SPV_DIMENSION_BUFFER: u32 = 5;
+pub const SPV_DIMENSION_SUBPASS_DATA: u32 = 6;
+pub const SPV_IMAGE_FORMAT_UNKNOWN: u32 = 0;
+pub const SPV_IMAGE_FORMAT_RGBA32F: u32 = 1;
+pub const SPV_IMAGE_FORMAT_R32F: u32 = 3;
+pub const SPV_IMAGE_FORMAT_RGBA8: u32 = 4;
+pub const SPV_IMAGE_FORMAT_R32UI: u32 = 33;
+pub const SPV_IMAGE_OPERANDS_LOD: u32 = 0x2;
 
+pub const SPV_GLSL_STD_INSTRUCTION_TRUNC: u32 = 3;
+pub const SPV_GLSL_STD_INSTRUCTION_FABS: u32 = 4;
 pub const SPV_GLSL_STD_INSTRUCTION_SABS: u32 = 5;
+pub const SPV_GLSL_STD_INSTRUCTION_MODF: u32 = 35;
+pub const SPV_GLSL_STD_INSTRUCTION_MODF_STRUCT: u32 = 36;
+pub const SPV_GLSL_STD_INSTRUCTION_FREXP: u32 = 51;
+pub const SPV_GLSL_STD_INSTRUCTION_FREXP_STRUCT: u32 = 52;
+pub const SPV_GLSL_STD_INSTRUCTION_PACK_UNORM_4X8: u32 = 55;
+pub const SPV_GLSL_STD_INSTRUCTION_PACK_HALF_2X16: u32 = 58;
+pub const SPV_GLSL_STD_INSTRUCTION_UNPACK_HALF_2X16: u32 = 62;
+pub const SPV_GLSL_STD_INSTRUCTION_UNPACK_UNORM_4X8: u32 = 64;
+pub const SPV_GLSL_STD_INSTRUCTION_PACK_DOUBLE_2X32: u32 = 59;
+pub const SPV_GLSL_STD_INSTRUCTION_UNPACK_DOUBLE_2X32: u32 = 65;
+pub const SPV_GLSL_STD_INSTRUCTION_FIND_I_LSB: u32 = 73;
+pub const SPV_GLSL_STD_INSTRUCTION_FIND_S_MSB: u32 = 74;
+pub const SPV_GLSL_STD_INSTRUCTION_FIND_U_MSB: u32 = 75;
+pub const SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_CENTROID: u32 = 76;
+pub const SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_SAMPLE: u32 = 77;
+pub const SPV_GLSL_STD_INSTRUCTION_INTERPOLATE_AT_OFFSET: u32 = 78;
@@ -0,0 +1,401 @@
+use super::*;
+
+fn inc(ib: &mut u32) -> u32 {
+    *ib += 1;
+    *ib - 1
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+///
+/// Each subpass input becomes a sampled texture 2D (multisampled if it was `subpassInputMS`)
+/// that is read at the current fragment's pixel.
+/// A `FragCoord` input is added to every fragment entry point if the shader does not have one.
+pub fn subpassinputpatch(in_spv: &[u32], corrections: &mut CorrectionMap) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let mut word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_capability_idxs = vec![];
+    let mut op_entry_point_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_image_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_runtime_array_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_constant_composite_idxs = vec![];
+    let mut op_constant_null_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_image_typed_idxs = vec![];
+    let mut op_image_read_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        match instruction {
+            SPV_INSTRUCTION_OP_CAPABILITY => op_capability_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ENTRY_POINT => op_entry_point_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_IMAGE => op_type_image_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => op_type_runtime_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE => op_constant_composite_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_NULL => op_constant_null_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            // Yes, offsets 1 and 2 are identical
+            SPV_INSTRUCTION_OP_LOAD
+            | SPV_INSTRUCTION_OP_FUNCTION_PARAMETER
+            | SPV_INSTRUCTION_OP_COPY_OBJECT => op_image_typed_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_IMAGE_READ => op_image_read_idxs.push(spv_idx),
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    // 2. Find OpTypeImage, change SubpassData -> 2D
+    let subpass_image_types = op_type_image_idxs
+        .iter()
+        .filter_map(|&idx| {
+            let result_id = spv[idx + 1];
+            let dim = spv[idx + 3];
+            let multisampled = spv[idx + 6];
+
+            (dim == SPV_DIMENSION_SUBPASS_DATA).then(|| {
+                new_spv[idx + 3] = SPV_DIMENSION_2D;
+                // 1: sampling
+                new_spv[idx + 7] = 1;
+                (result_id, multisampled == 1)
+            })
+        })
+        .collect::<HashMap<_, _>>();
+
+    if subpass_image_types.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    // 3. Find OpTypePointer -> OpVariable and every value holding a subpass input
+    let type_pointer_ids = op_type_pointer_idxs
+        .iter()
+        .filter_map(|&idx| {
+            subpass_image_types
+                .contains_key(&spv[idx + 3])
+                .then_some(spv[idx + 1])
+        })
+        .collect::<Vec<_>>();
+    let subpass_variable_ids = op_variable_idxs
+        .iter()
+        .filter_map(|&idx| {
+            type_pointer_ids
+                .contains(&spv[idx + 1])
+                .then_some(spv[idx + 2])
+        })
+        .collect::<Vec<_>>();
+    let subpass_image_ids = op_image_typed_idxs
+        .iter()
+        .filter_map(|&idx| {
+            subpass_image_types
+                .get(&spv[idx + 1])
+                .map(|&multisampled| (spv[idx + 2], multisampled))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // 4. Find or declare gl_FragCoord and the types to turn it into a pixel coordinate
+    let mut header_insert = InstructionInsert {
+        previous_spv_idx: last_of_indices!(
+            op_type_bool_idxs,
+            op_type_int_idxs,
+            op_type_float_idxs,
+            op_type_vector_idxs,
+            op_type_matrix_idxs,
+            op_type_image_idxs,
+            op_type_array_idxs,
+            op_type_runtime_array_idxs,
+            op_type_struct_idxs,
+            op_type_pointer_idxs,
+            op_constant_idxs,
+            op_constant_composite_idxs,
+            op_constant_null_idxs
+        )
+        .unwrap(),
+        instruction: vec![],
+    };
+
+    let float_id = ensure_type_float(
+        &spv,
+        &op_type_float_idxs,
+        &mut instruction_bound,
+        &mut header_insert.instruction,
+        32,
+    );
+    let v2float_id = ensure_type_vector(
+        &spv,
+        &op_type_vector_idxs,
+        &mut instruction_bound,
+        &mut header_insert.instruction,
+        float_id,
+        2,
+    );
+    let v4float_id = ensure_type_vector(
+        &spv,
+        &op_type_vector_idxs,
+        &mut instruction_bound,
+        &mut header_insert.instruction,
+        float_id,
+        4,
+    );
+    let int_id = ensure_type_int(
+        &spv,
+        &op_type_int_idxs,
+        &mut instruction_bound,
+        &mut header_insert.instruction,
+        32,
+        SPV_SIGNEDNESS_SIGNED,
+    );
+    let v2int_id = ensure_type_vector(
+        &spv,
+        &op_type_vector_idxs,
+        &mut instruction_bound,
+        &mut header_insert.instruction,
+        int_id,
+        2,
+    );
+    let int_0 = op_constant_idxs
+        .iter()
+        .find_map(|&c_idx| {
+            (hiword(spv[c_idx]) == 4 && spv[c_idx + 1] == int_id && spv[c_idx + 3] == 0)
+                .then_some(spv[c_idx + 2])
+        })
+        .unwrap_or_else(|| {
+            let new_id = inc(&mut instruction_bound);
+            header_insert.instruction.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                int_id,
+                new_id,
+                0,
+            ]);
+            new_id
+        });
+
+    let mut new_decorations = vec![];
+    let frag_coord_id = op_decorate_idxs
+        .iter()
+        .find_map(|&d_idx| {
+            (spv[d_idx + 2] == SPV_DECORATION_BUILTIN && spv[d_idx + 3] == SPV_BUILTIN_FRAG_COORD)
+                .then_some(spv[d_idx + 1])
+        })
+        .unwrap_or_else(|| {
+            let v4float_ptr_id = ensure_type_pointer(
+                &spv,
+                &op_type_pointer_idxs,
+                &mut instruction_bound,
+                &mut header_insert.instruction,
+                SPV_STORAGE_CLASS_INPUT,
+                v4float_id,
+            );
+            let new_id = inc(&mut instruction_bound);
+            header_insert.instruction.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
+                v4float_ptr_id,
+                new_id,
+                SPV_STORAGE_CLASS_INPUT,
+            ]);
+            new_decorations.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                new_id,
+                SPV_DECORATION_BUILTIN,
+                SPV_BUILTIN_FRAG_COORD,
+            ]);
+            for &ep_idx in &op_entry_point_idxs {
+                if spv[ep_idx + 1] == SPV_EXECUTION_MODEL_FRAGMENT {
+                    word_inserts.push(WordInsert {
+                        idx: ep_idx + hiword(spv[ep_idx]) as usize - 1,
+                        word: new_id,
+                        head_idx: ep_idx,
+                    });
+                }
+            }
+            new_id
+        });
+
+    // 5. Patch OpImageRead -> OpImageFetch at the fragment's pixel
+    let is_zero_constant = |id| {
+        op_constant_null_idxs
+            .iter()
+            .any(|&c_idx| spv[c_idx + 2] == id)
+            || op_constant_composite_idxs.iter().any(|&c_idx| {
+                spv[c_idx + 2] == id
+                    && (3..hiword(spv[c_idx]) as usize).all(|c_word| {
+                        op_constant_idxs
+                            .iter()
+                            .any(|&idx| spv[idx + 2] == spv[c_idx + c_word] && spv[idx + 3] == 0)
+                    })
+            })
+    };
+
+    for &op_idx in &op_image_read_idxs {
+        let op_word_count = hiword(spv[op_idx]) as usize;
+        let result_type_id = spv[op_idx + 1];
+        let result_id = spv[op_idx + 2];
+        let image_id = spv[op_idx + 3];
+        let offset_id = spv[op_idx + 4];
+
+        let Some(&multisampled) = subpass_image_ids.get(&image_id) else {
+            continue;
+        };
+
+        //
+        //    %frag_coord = OpLoad %v4float %gl_FragCoord
+        //            %xy = OpVectorShuffle %v2float %frag_coord %frag_coord 0 1
+        //         %pixel = OpConvertFToS %v2int %xy
+        //         %coord = OpIAdd %v2int %pixel %offset
+        //        %result = OpImageFetch %v4float %image %coord Lod %int_0
+        //        %result = OpImageFetch %v4float %image %coord Sample %sample
+        let frag_coord = inc(&mut instruction_bound);
+        let xy = inc(&mut instruction_bound);
+        let pixel = inc(&mut instruction_bound);
+        #[rustfmt::skip]
+        let mut new_instructions = vec![
+            encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                v4float_id, frag_coord, frag_coord_id,
+            encode_word(7, SPV_INSTRUCTION_OP_VECTOR_SHUFFLE),
+                v2float_id, xy, frag_coord, frag_coord, 0, 1,
+            encode_word(4, SPV_INSTRUCTION_OP_CONVERT_F_TO_S),
+                v2int_id, pixel, xy,
+        ];
+        let coord = if is_zero_constant(offset_id) {
+            pixel
+        } else {
+            let coord = inc(&mut instruction_bound);
+            #[rustfmt::skip]
+            new_instructions.append(&mut vec![
+                encode_word(5, SPV_INSTRUCTION_OP_I_ADD),
+                    v2int_id, coord, pixel, offset_id,
+            ]);
+            coord
+        };
+
+        // The Lod operand comes before every operand but Bias, which subpass reads cannot have
+        let mut operands = spv[op_idx + 5..op_idx + op_word_count].to_vec();
+        if !multisampled {
+            match operands.first_mut() {
+                Some(mask) => {
+                    *mask |= SPV_IMAGE_OPERANDS_LOD;
+                    operands.insert(1, int_0);
+                }
+                None => operands.append(&mut vec![SPV_IMAGE_OPERANDS_LOD, int_0]),
+            }
+        }
+        new_instructions.append(&mut vec![
+            encode_word(5 + operands.len() as u16, SPV_INSTRUCTION_OP_IMAGE_FETCH),
+            result_type_id,
+            result_id,
+            image_id,
+            coord,
+        ]);
+        new_instructions.append(&mut operands);
+
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: op_idx,
+            instruction: new_instructions,
+        });
+        new_spv[op_idx..op_idx + op_word_count].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+    }
+
+    // 6. Remove InputAttachmentIndex and the InputAttachment capability
+    let mut input_attachment_indices = vec![];
+    for &d_idx in &op_decorate_idxs {
+        if spv[d_idx + 2] == SPV_DECORATION_INPUT_ATTACHMENT_INDEX {
+            input_attachment_indices.push((spv[d_idx + 1], spv[d_idx + 3]));
+            new_spv[d_idx..d_idx + 4].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+        }
+    }
+    for &c_idx in &op_capability_idxs {
+        if spv[c_idx + 1] == SPV_CAPABILITY_INPUT_ATTACHMENT {
+            new_spv[c_idx..c_idx + 2].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+        }
+    }
+
+    // 7. Fill Correction Map
+    decorate(DecorateIn {
+        spv: &spv,
+        instruction_inserts: &mut vec![],
+        first_op_deocrate_idx: op_decorate_idxs.first().copied(),
+        op_decorate_idxs: &op_decorate_idxs,
+        affected_decorations: &subpass_variable_ids
+            .iter()
+            .map(|id| AffectedDecoration {
+                original_res_id: *id,
+                new_res_ids: vec![*id],
+                correction_type: CorrectionType::ConvertSubpassInput,
+            })
+            .collect::<Vec<_>>(),
+        corrections,
+    });
+
+    let decoration_of = |target_id, decoration| {
+        op_decorate_idxs.iter().find_map(|&d_idx| {
+            (spv[d_idx + 1] == target_id && spv[d_idx + 2] == decoration).then_some(spv[d_idx + 3])
+        })
+    };
+    for (variable_id, input_attachment_index) in input_attachment_indices {
+        if let Some(set) = decoration_of(variable_id, SPV_DECORATION_DESCRIPTOR_SET)
+            && let Some(binding) = decoration_of(variable_id, SPV_DECORATION_BINDING)
+            && let Some(binding) = corrections
+                .sets
+                .as_mut()
+                .and_then(|sets| sets.get_mut(&set))
+                .and_then(|set| set.bindings.get_mut(&binding))
+        {
+            binding.input_attachment_index = Some(input_attachment_index);
+        }
+    }
+
+    // 8. Insert New Instructions
+    if !new_decorations.is_empty() {
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: *op_decorate_idxs
+                .last()
+                .expect("Subpass input has no OpDecorate (missing Binding decoration?)"),
+            instruction: new_decorations,
+        });
+    }
+    instruction_inserts.insert(0, header_insert);
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 9. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 10. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
@@ -1,18 +1,35 @@
 use super::{
-    combimgsampsplitter, drefsplitter, immediatespatch, isnanisinfpatch, mirrorpatch,
-    pruneunuseddref, splitbindingarray, storagecubepatch, u8_slice_to_u32_vec, u32_slice_to_u8_vec,
+    CorrectionMap, ExtInstReport, IsNanIsInfMode, PruneUnusedDrefMode, PruneUnusedDrefReport,
+    SPV_DECORATION_ARRAY_STRIDE, SPV_GLSL_STD_INSTRUCTION_UNPACK_UNORM_4X8, SPV_HEADER_LENGTH,
+    SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT, SPV_INSTRUCTION_OP_DECORATE,
+    SPV_INSTRUCTION_OP_EXT_INST, SPV_INSTRUCTION_OP_I_EQUAL, SPV_INSTRUCTION_OP_I_NOT_EQUAL,
+    SPV_INSTRUCTION_OP_IS_INF, SPV_INSTRUCTION_OP_IS_NAN, SPV_INSTRUCTION_OP_LOGICAL_AND,
+    SPV_INSTRUCTION_OP_STORE, SpecConstantMode, SpecConstantReport, TexelBufferFormat,
+    UnusedResource, UnusedResourceKind, boolblockpatch, combimgsampsplitter, drefsplitter,
+    extinstpatch, extinstpatch_with_report, hiword, immediatespatch, isnanisinfpatch, loword,
+    mirrorpatch, mirrorpatch_many, pruneunused, pruneunuseddref, pruneunuseddref_with_mode,
+    reflect, specconstantpatch, splitbindingarray, splitentrypoints, storagecubepatch,
+    subpassinputpatch, texelbufferpatch, u8_slice_to_u32_vec, u32_slice_to_u8_vec,
+    widenstoragepatch,
 };
 
 use naga::{back, front, valid};
 use spirv_tools::val::{self, Validator};
+use std::collections::HashMap;
 
+mod test_immediatespatch;
+mod test_layoutpatch;
 mod test_mirrorpatch;
+mod test_reflect;
+mod test_scalarlayoutpatch;
+mod test_uniformlayoutpatch;
 
 const SPV_VALIDATE: u8 = 0b0000001;
 const NAGA_VALIDATE: u8 = 0b0000010;
 const NAGA_CONVERT: u8 = 0b0000100;
 const NAGA_FRONT_ONLY: u8 = 0b0001000;
-const DO_ALL: u8 = 0xff;
+const NAGA_FLOAT16: u8 = 0b0010000;
+const DO_ALL: u8 = SPV_VALIDATE | NAGA_VALIDATE | NAGA_CONVERT | NAGA_FRONT_ONLY;
 
 #[macro_export]
 macro_rules! test_with_spv_and_fn {
@@ -53,7 +70,11 @@ fn try_spv_to_wgsl(spv: &[u32], flags: u8) {
 
     if flags & NAGA_VALIDATE != 0 {
         let module = front::spv::parse_u8_slice(&spv_u8, &front::spv::Options::default()).unwrap();
-        let caps = valid::Capabilities::default();
+        let mut caps = valid::Capabilities::default();
+        if flags & NAGA_FLOAT16 != 0 {
+            caps |= valid::Capabilities::SHADER_FLOAT16
+                | valid::Capabilities::SHADER_FLOAT16_IN_FLOAT32;
+        }
         let mut info = valid::Validator::new(valid::ValidationFlags::all(), caps);
         let info = info.validate(&module).unwrap();
 
@@ -104,13 +125,13 @@ test_with_spv_and_fn!(
 );
 test_with_spv_and_fn!(
     splitdref_test_nested_image,
-    SPV_VALIDATE,
+    DO_ALL,
     "./test/splitdref/test_nested_image.spv",
     drefsplitter
 );
 test_with_spv_and_fn!(
     splitdref_test_nested2_image,
-    SPV_VALIDATE,
+    DO_ALL,
     "./test/splitdref/test_nested2_image.spv",
     drefsplitter
 );
@@ -120,62 +141,327 @@ test_with_spv_and_fn!(
     "./test/splitdref/test_sampler.spv",
     drefsplitter
 );
+test_with_spv_and_fn!(
+    splitdref_test_mixed_dref,
+    DO_ALL,
+    "./test/splitdref/test_mixed_dref.spv",
+    drefsplitter
+);
 test_with_spv_and_fn!(
     splitdref_test_nested_sampler,
-    SPV_VALIDATE,
+    DO_ALL,
     "./test/splitdref/test_nested_sampler.spv",
     drefsplitter
 );
 test_with_spv_and_fn!(
     splitdref_test_nested2_sampler,
-    SPV_VALIDATE,
+    DO_ALL,
     "./test/splitdref/test_nested2_sampler.spv",
     drefsplitter
 );
 test_with_spv_and_fn!(
     splitdref_test_hidden_dref,
-    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    DO_ALL,
     "./test/splitdref/test_hidden_dref.spv",
     drefsplitter
 );
 test_with_spv_and_fn!(
     splitdref_test_hidden2_dref,
-    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    DO_ALL,
     "./test/splitdref/test_hidden2_dref.spv",
     drefsplitter
 );
 test_with_spv_and_fn!(
     splitdref_test_hidden3_dref,
-    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    DO_ALL,
     "./test/splitdref/test_hidden3_dref.spv",
     drefsplitter
 );
 test_with_spv_and_fn!(
     splitdref_test_cross_dref,
-    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    DO_ALL,
     "./test/splitdref/test_cross_dref.spv",
     drefsplitter
 );
 
 // ---
 
+fn isnanisinfpatch_function(spv: &[u32]) -> Result<Vec<u32>, ()> {
+    isnanisinfpatch(spv, IsNanIsInfMode::Function)
+}
+
+fn isnanisinfpatch_inline(spv: &[u32]) -> Result<Vec<u32>, ()> {
+    isnanisinfpatch(spv, IsNanIsInfMode::Inline)
+}
+
+fn isnanisinfpatch_comparison(spv: &[u32]) -> Result<Vec<u32>, ()> {
+    isnanisinfpatch(spv, IsNanIsInfMode::Comparison)
+}
+
 test_with_spv_and_fn_no_correction![
     isnanisinfpatch_isnanisinf,
     DO_ALL,
     "./test/isnanisinfpatch/isnanisinf.spv",
-    isnanisinfpatch
+    isnanisinfpatch_function
 ];
 test_with_spv_and_fn_no_correction![
     isnanisinfpatch_isnanisinf_vectored,
     DO_ALL,
     "./test/isnanisinfpatch/isnanisinf_vectored.spv",
-    isnanisinfpatch
+    isnanisinfpatch_function
 ];
 test_with_spv_and_fn_no_correction![
     isnanisinfpatch_isnanisinf_immediate,
     DO_ALL,
     "./test/isnanisinfpatch/isnanisinf_immediate.spv",
-    isnanisinfpatch
+    isnanisinfpatch_function
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f16,
+    SPV_VALIDATE | NAGA_VALIDATE | NAGA_CONVERT | NAGA_FLOAT16,
+    "./test/isnanisinfpatch/isnanisinf_f16.spv",
+    isnanisinfpatch_function
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f64,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/isnanisinfpatch/isnanisinf_f64.spv",
+    isnanisinfpatch_function
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f64_int64,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/isnanisinfpatch/isnanisinf_f64_int64.spv",
+    isnanisinfpatch_function
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_inline,
+    DO_ALL,
+    "./test/isnanisinfpatch/isnanisinf.spv",
+    isnanisinfpatch_inline
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_vectored_inline,
+    DO_ALL,
+    "./test/isnanisinfpatch/isnanisinf_vectored.spv",
+    isnanisinfpatch_inline
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_immediate_inline,
+    DO_ALL,
+    "./test/isnanisinfpatch/isnanisinf_immediate.spv",
+    isnanisinfpatch_inline
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f16_inline,
+    SPV_VALIDATE | NAGA_VALIDATE | NAGA_CONVERT | NAGA_FLOAT16,
+    "./test/isnanisinfpatch/isnanisinf_f16.spv",
+    isnanisinfpatch_inline
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f64_inline,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/isnanisinfpatch/isnanisinf_f64.spv",
+    isnanisinfpatch_inline
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f64_int64_inline,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/isnanisinfpatch/isnanisinf_f64_int64.spv",
+    isnanisinfpatch_inline
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_comparison,
+    DO_ALL,
+    "./test/isnanisinfpatch/isnanisinf.spv",
+    isnanisinfpatch_comparison
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_vectored_comparison,
+    DO_ALL,
+    "./test/isnanisinfpatch/isnanisinf_vectored.spv",
+    isnanisinfpatch_comparison
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_immediate_comparison,
+    DO_ALL,
+    "./test/isnanisinfpatch/isnanisinf_immediate.spv",
+    isnanisinfpatch_comparison
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f16_comparison,
+    SPV_VALIDATE | NAGA_VALIDATE | NAGA_CONVERT | NAGA_FLOAT16,
+    "./test/isnanisinfpatch/isnanisinf_f16.spv",
+    isnanisinfpatch_comparison
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f64_comparison,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/isnanisinfpatch/isnanisinf_f64.spv",
+    isnanisinfpatch_comparison
+];
+test_with_spv_and_fn_no_correction![
+    isnanisinfpatch_isnanisinf_f64_int64_comparison,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/isnanisinfpatch/isnanisinf_f64_int64.spv",
+    isnanisinfpatch_comparison
+];
+
+// A NaN has a non zero fraction and an infinity has a zero fraction, validation cannot tell the two
+// apart, so check which comparison replaced each `OpIsNan` and `OpIsInf`.
+#[test]
+fn isnanisinfpatch_predicates() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./test/isnanisinfpatch/isnanisinf.spv"));
+    let out_spv = isnanisinfpatch_inline(&spv).unwrap();
+
+    let instructions = |spv: &[u32]| {
+        let mut instructions = vec![];
+        let mut spv_idx = SPV_HEADER_LENGTH;
+        while spv_idx < spv.len() {
+            let word_count = hiword(spv[spv_idx]) as usize;
+            instructions.push(spv[spv_idx..spv_idx + word_count].to_vec());
+            spv_idx += word_count;
+        }
+        instructions
+    };
+    let result_of = |opcode: u16| {
+        instructions(&spv)
+            .into_iter()
+            .find(|words| loword(words[0]) == opcode)
+            .unwrap()[2]
+    };
+    let out_instructions = instructions(&out_spv);
+    let definition = |id: u32| {
+        out_instructions
+            .iter()
+            .find(|words| {
+                words.len() > 2 && loword(words[0]) != SPV_INSTRUCTION_OP_STORE && words[2] == id
+            })
+            .unwrap()
+    };
+    let fraction_comparison = |id: u32| {
+        let and = definition(id);
+        assert_eq!(loword(and[0]), SPV_INSTRUCTION_OP_LOGICAL_AND);
+        loword(definition(and[4])[0])
+    };
+
+    assert_eq!(
+        fraction_comparison(result_of(SPV_INSTRUCTION_OP_IS_NAN)),
+        SPV_INSTRUCTION_OP_I_NOT_EQUAL
+    );
+    assert_eq!(
+        fraction_comparison(result_of(SPV_INSTRUCTION_OP_IS_INF)),
+        SPV_INSTRUCTION_OP_I_EQUAL
+    );
+}
+
+// ---
+
+test_with_spv_and_fn_no_correction![
+    extinstpatch_modf_frexp,
+    DO_ALL,
+    "./test/extinstpatch/extinst_modf_frexp.spv",
+    extinstpatch
+];
+test_with_spv_and_fn_no_correction![
+    extinstpatch_interpolate,
+    DO_ALL,
+    "./test/extinstpatch/extinst_interpolate.spv",
+    extinstpatch
+];
+
+#[test]
+fn extinstpatch_interpolate_at_sample_or_offset() {
+    let spv = u8_slice_to_u32_vec(include_bytes!(
+        "./test/extinstpatch/extinst_interpolate_sample.spv"
+    ));
+    assert!(extinstpatch(&spv).is_err());
+}
+
+#[test]
+fn extinstpatch_interpolate_at_centroid_mixed_with_load() {
+    let spv = u8_slice_to_u32_vec(include_bytes!(
+        "./test/extinstpatch/extinst_interpolate_mixed.spv"
+    ));
+    assert!(extinstpatch(&spv).is_err());
+}
+
+#[test]
+fn extinstpatch_interpolate_report() {
+    let spv = u8_slice_to_u32_vec(include_bytes!(
+        "./test/extinstpatch/extinst_interpolate.spv"
+    ));
+
+    let mut report = ExtInstReport::default();
+    extinstpatch_with_report(&spv, &mut report).unwrap();
+    assert_eq!(report.centroid_locations, vec![0]);
+
+    // Nothing is left over from the previous module
+    let spv = u8_slice_to_u32_vec(include_bytes!("./test/extinstpatch/extinst_modf_frexp.spv"));
+    extinstpatch_with_report(&spv, &mut report).unwrap();
+    assert_eq!(report, ExtInstReport::default());
+}
+
+#[test]
+fn extinstpatch_frexp_double() {
+    let spv = u8_slice_to_u32_vec(include_bytes!(
+        "./test/extinstpatch/extinst_frexp_double.spv"
+    ));
+    assert!(extinstpatch(&spv).is_err());
+}
+test_with_spv_and_fn_no_correction![
+    extinstpatch_findlsb,
+    DO_ALL,
+    "./test/extinstpatch/extinst_findlsb.spv",
+    extinstpatch
+];
+test_with_spv_and_fn_no_correction![
+    extinstpatch_packdouble,
+    SPV_VALIDATE | NAGA_FRONT_ONLY,
+    "./test/extinstpatch/extinst_packdouble.spv",
+    extinstpatch
+];
+
+// ---
+
+test_with_spv_and_fn_no_correction![
+    boolblockpatch_boolblock,
+    DO_ALL,
+    "./test/boolblockpatch/boolblock.spv",
+    boolblockpatch
+];
+test_with_spv_and_fn_no_correction![
+    boolblockpatch_boolblock_nested,
+    DO_ALL,
+    "./test/boolblockpatch/boolblock_nested.spv",
+    boolblockpatch
+];
+test_with_spv_and_fn_no_correction![
+    boolblockpatch_boolblock_aggregate,
+    DO_ALL,
+    "./test/boolblockpatch/boolblock_aggregate.spv",
+    boolblockpatch
+];
+
+#[test]
+fn boolblockpatch_boolblock_shared() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./test/boolblockpatch/boolblock_shared.spv"));
+    assert!(boolblockpatch(&spv).is_err());
+}
+
+// ---
+
+test_with_spv_and_fn_no_correction![
+    widenstoragepatch_widen,
+    DO_ALL | NAGA_FLOAT16,
+    "./test/widenstoragepatch/widen.spv",
+    widenstoragepatch
+];
+test_with_spv_and_fn_no_correction![
+    widenstoragepatch_widen_vertices,
+    DO_ALL | NAGA_FLOAT16,
+    "./test/widenstoragepatch/widen_vertices.spv",
+    widenstoragepatch
 ];
 
 // ---
@@ -201,6 +487,148 @@ test_with_spv_and_fn!(
 
 // ---
 
+fn texelbufferpatch_rgba32f(spv: &[u32], corrections: &mut CorrectionMap) -> Result<Vec<u32>, ()> {
+    texelbufferpatch(
+        spv,
+        &Default::default(),
+        TexelBufferFormat::Rgba32Float,
+        corrections,
+    )
+}
+
+test_with_spv_and_fn!(
+    texelbufferpatch_texelbuffer,
+    DO_ALL,
+    "./test/texelbufferpatch/texelbuffer.spv",
+    texelbufferpatch_rgba32f
+);
+test_with_spv_and_fn!(
+    texelbufferpatch_texelbuffer_skinning,
+    DO_ALL,
+    "./test/texelbufferpatch/texelbuffer_skinning.spv",
+    texelbufferpatch_rgba32f
+);
+
+// The three `samplerBuffer`s share one `OpTypeImage`, only their bindings tell their formats apart.
+#[test]
+fn texelbufferpatch_texelbuffer_formats() {
+    let spv = u8_slice_to_u32_vec(include_bytes!(
+        "./test/texelbufferpatch/texelbuffer_formats.spv"
+    ));
+    let formats = HashMap::from([
+        ((0, 0), TexelBufferFormat::R32Float),
+        ((0, 1), TexelBufferFormat::Rgba8Unorm),
+    ]);
+    let out_spv = texelbufferpatch(
+        &spv,
+        &formats,
+        TexelBufferFormat::Rgba32Float,
+        &mut Default::default(),
+    )
+    .unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    let mut instructions = vec![];
+    let mut spv_idx = SPV_HEADER_LENGTH;
+    while spv_idx < out_spv.len() {
+        let word_count = hiword(out_spv[spv_idx]) as usize;
+        instructions.push(&out_spv[spv_idx..spv_idx + word_count]);
+        spv_idx += word_count;
+    }
+    let count = |f: &dyn Fn(&[u32]) -> bool| instructions.iter().filter(|words| f(words)).count();
+
+    // `R32Float` widens its texel into a `vec4`
+    assert_eq!(
+        count(&|words| loword(words[0]) == SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+        1
+    );
+    // `Rgba8Unorm` unpacks its texel
+    assert_eq!(
+        count(&|words| loword(words[0]) == SPV_INSTRUCTION_OP_EXT_INST
+            && words[4] == SPV_GLSL_STD_INSTRUCTION_UNPACK_UNORM_4X8),
+        1
+    );
+    // `u_offsets` falls back to `Rgba32Float`, an array of `vec4`
+    assert_eq!(
+        count(&|words| loword(words[0]) == SPV_INSTRUCTION_OP_DECORATE
+            && words[2] == SPV_DECORATION_ARRAY_STRIDE
+            && words[3] == 16),
+        1
+    );
+}
+
+// ---
+
+test_with_spv_and_fn!(
+    subpassinputpatch_subpass,
+    DO_ALL,
+    "./test/subpassinputpatch/subpass.spv",
+    subpassinputpatch
+);
+test_with_spv_and_fn!(
+    subpassinputpatch_subpass_nested,
+    DO_ALL,
+    "./test/subpassinputpatch/subpass_nested.spv",
+    subpassinputpatch
+);
+
+// ---
+
+fn specconstantpatch_bake_all(
+    spv: &[u32],
+    report: &mut SpecConstantReport,
+) -> Result<Vec<u32>, ()> {
+    let values = [(0, 32), (1, 8), (3, 0.25f32.to_bits() as u64)].into();
+    specconstantpatch(spv, &values, SpecConstantMode::BakeAll, report)
+}
+
+fn specconstantpatch_overridable(
+    spv: &[u32],
+    report: &mut SpecConstantReport,
+) -> Result<Vec<u32>, ()> {
+    specconstantpatch(
+        spv,
+        &Default::default(),
+        SpecConstantMode::Overridable,
+        report,
+    )
+}
+
+test_with_spv_and_fn!(
+    specconstantpatch_spec_bake_all,
+    DO_ALL,
+    "./test/specconstantpatch/spec.spv",
+    specconstantpatch_bake_all
+);
+test_with_spv_and_fn!(
+    specconstantpatch_spec_overridable,
+    DO_ALL,
+    "./test/specconstantpatch/spec.spv",
+    specconstantpatch_overridable
+);
+
+#[test]
+fn specconstantpatch_spec_report() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./test/specconstantpatch/spec.spv"));
+
+    let mut report = SpecConstantReport::default();
+    specconstantpatch_bake_all(&spv, &mut report).unwrap();
+    assert_eq!(report.baked, vec![0, 1, 2, 3, 4]);
+    assert!(report.overridable.is_empty());
+
+    // Only `SCALE` is not used to build other constants
+    specconstantpatch_overridable(&spv, &mut report).unwrap();
+    assert_eq!(report.baked, vec![0, 1, 2, 3]);
+    assert_eq!(report.overridable, vec![4]);
+
+    // Nothing is left over from the previous module
+    let spv = u8_slice_to_u32_vec(include_bytes!("./test/isnanisinfpatch/isnanisinf.spv"));
+    specconstantpatch_overridable(&spv, &mut report).unwrap();
+    assert_eq!(report, SpecConstantReport::default());
+}
+
+// ---
+
 // TODO: This only tests shader validity, not functionality
 test_with_spv_and_fn_no_correction![
     pruneunuseddref_pruneunuseddref,
@@ -221,6 +649,44 @@ test_with_spv_and_fn_no_correction![
     pruneunuseddref
 ];
 
+// Combined image samplers must be split before `naga` can read them
+fn pruneunuseddref_then_combimgsampsplitter(
+    spv: &[u32],
+    corrections: &mut CorrectionMap,
+) -> Result<Vec<u32>, ()> {
+    combimgsampsplitter(&pruneunuseddref(spv)?, corrections)
+}
+
+test_with_spv_and_fn![
+    pruneunuseddref_pruneunuseddref_combined,
+    DO_ALL,
+    "./test/pruneunuseddref/pruneunuseddref_combined.spv",
+    pruneunuseddref_then_combimgsampsplitter
+];
+
+#[test]
+fn pruneunuseddref_pruneunuseddref_combined_report() {
+    let spv = u8_slice_to_u32_vec(include_bytes!(
+        "./test/pruneunuseddref/pruneunuseddref_combined.spv"
+    ));
+
+    let mut report = PruneUnusedDrefReport::default();
+    let out_spv =
+        pruneunuseddref_with_mode(&spv, PruneUnusedDrefMode::Report, &mut report).unwrap();
+    assert_eq!(out_spv, spv);
+
+    let unused = |set, binding, kind| UnusedResource { set, binding, kind };
+    assert_eq!(
+        report.unused,
+        vec![
+            unused(0, 0, UnusedResourceKind::CombinedImageSampler),
+            unused(0, 2, UnusedResourceKind::CombinedImageSampler),
+            unused(1, 0, UnusedResourceKind::StorageTexture),
+            unused(2, 0, UnusedResourceKind::Texture),
+        ]
+    );
+}
+
 // ---
 
 test_with_spv_and_fn![
@@ -237,6 +703,12 @@ test_with_spv_and_fn![
     "./test/immediatespatch/mat2_direct.spv",
     immediatespatch
 ];
+test_with_spv_and_fn![
+    immediatespatch_mat2_load,
+    SPV_VALIDATE,
+    "./test/immediatespatch/mat2_load.spv",
+    immediatespatch
+];
 test_with_spv_and_fn![
     immediatespatch_array_of_mat2,
     DO_ALL,
@@ -306,3 +778,44 @@ test_with_spv_and_fn![
     "./test/splitbindingarray/image_binding_array.spv",
     splitbindingarray
 ];
+
+// ---
+
+test_with_spv_and_fn_no_correction![
+    pruneunused_unused,
+    DO_ALL,
+    "./test/pruneunused/unused.spv",
+    pruneunused
+];
+test_with_spv_and_fn_no_correction![
+    pruneunused_unused_interface,
+    DO_ALL,
+    "./test/pruneunused/unused_interface.spv",
+    pruneunused
+];
+
+// ---
+
+#[test]
+fn splitentrypoints_multi() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./test/splitentrypoints/multi.spv"));
+    let modules = splitentrypoints(&spv).unwrap();
+
+    let names = modules.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["vs_main", "ps_main"]);
+    for module in modules.iter() {
+        try_spv_to_wgsl(&module.spv, DO_ALL);
+    }
+
+    // Each entry point only keeps the resources it reaches
+    let set_bindings = |spv| {
+        reflect(spv)
+            .unwrap()
+            .resources
+            .iter()
+            .map(|resource| (resource.set, resource.binding))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(set_bindings(&modules[0].spv), vec![(0, 0), (1, 0)]);
+    assert_eq!(set_bindings(&modules[1].spv), vec![(0, 1), (0, 2)]);
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Params {
+    bool enabled;
+    bvec2 flags;
+    bool mask[2];
+} u_params;
+
+layout(set = 0, binding = 1) buffer State {
+    bool written;
+    bvec3 written_flags;
+} s_state;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    if (u_params.enabled && u_params.flags.y && u_params.mask[1]) {
+        o_color = vec4(1.0);
+    } else {
+        o_color = vec4(0.0);
+    }
+    s_state.written = u_params.flags.x;
+    s_state.written_flags = bvec3(u_params.enabled, u_params.mask[0], true);
+}
@@ -0,0 +1,27 @@
+#version 450
+
+struct Material {
+    vec4 color;
+    bool lit;
+    bvec2 flags;
+};
+
+layout(set = 0, binding = 0) uniform Params {
+    Material material;
+    bool toggles[3];
+} u_params;
+
+layout(set = 0, binding = 1) buffer State {
+    Material last;
+    bool toggles[3];
+} s_state;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    Material material = u_params.material;
+    bool toggles[3] = u_params.toggles;
+    s_state.last = material;
+    s_state.toggles = toggles;
+    o_color = material.color * float(material.lit && toggles[1]);
+}
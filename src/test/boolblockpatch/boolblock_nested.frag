@@ -0,0 +1,30 @@
+#version 450
+
+struct Light {
+    vec3 color;
+    bool enabled;
+    bvec4 channels;
+};
+
+layout(set = 0, binding = 0) uniform Lights {
+    Light lights[2];
+    bool any_enabled;
+} u_lights;
+
+layout(set = 0, binding = 1) buffer Visibility {
+    uint count;
+    bool visible[];
+} s_visibility;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < 2; i++) {
+        if (u_lights.lights[i].enabled && u_lights.lights[i].channels.w) {
+            color += u_lights.lights[i].color;
+        }
+        s_visibility.visible[i] = u_lights.lights[i].channels.x;
+    }
+    o_color = vec4(color, float(u_lights.any_enabled));
+}
@@ -0,0 +1,6 @@
+set -e
+
+glslc -O0 boolblock.frag -o boolblock.spv
+glslc -O0 boolblock_nested.frag -o boolblock_nested.spv
+glslc -O0 boolblock_aggregate.frag -o boolblock_aggregate.spv
+spirv-as boolblock_shared.spvasm -o boolblock_shared.spv
@@ -8,3 +8,13 @@ set -e
 (cd pruneunuseddref; ./compile.sh)
 (cd immediatespatch; ./compile.sh)
 (cd splitbindingarray; ./compile.sh)
+(cd extinstpatch; ./compile.sh)
+(cd boolblockpatch; ./compile.sh)
+(cd widenstoragepatch; ./compile.sh)
+(cd texelbufferpatch; ./compile.sh)
+(cd subpassinputpatch; ./compile.sh)
+(cd specconstantpatch; ./compile.sh)
+(cd splitentrypoints; ./compile.sh)
+(cd pruneunused; ./compile.sh)
+(cd scalarlayoutpatch; ./compile.sh)
+(cd uniformlayoutpatch; ./compile.sh)
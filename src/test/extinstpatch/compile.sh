@@ -0,0 +1,9 @@
+set -e
+
+glslc -O0 extinst_modf_frexp.frag -o extinst_modf_frexp.spv
+glslc -O0 extinst_interpolate.frag -o extinst_interpolate.spv
+glslc -O0 extinst_interpolate_sample.frag -o extinst_interpolate_sample.spv
+glslc -O0 extinst_interpolate_mixed.frag -o extinst_interpolate_mixed.spv
+glslc -O0 extinst_findlsb.frag -o extinst_findlsb.spv
+glslc -O0 extinst_packdouble.frag -o extinst_packdouble.spv
+spirv-as extinst_frexp_double.spvasm -o extinst_frexp_double.spv
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) flat in uvec4 v_in;
+layout(location = 1) flat in int v_sin;
+layout(location = 0) out ivec4 o_lsb;
+layout(location = 1) out ivec4 o_msb;
+
+void main() {
+    o_lsb = findLSB(v_in) + findLSB(v_sin);
+    o_msb = findMSB(v_in);
+}
@@ -0,0 +1,8 @@
+#version 450
+
+layout(location = 0) in vec4 v_in;
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    o_color = interpolateAtCentroid(v_in) + vec4(interpolateAtCentroid(v_in.y));
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec4 v_in;
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    vec4 whole;
+    vec4 fractional = modf(v_in, whole);
+
+    ivec4 exponent;
+    vec4 significand = frexp(v_in, exponent);
+
+    float s_whole;
+    float s_fractional = modf(v_in.x, s_whole);
+
+    o_color = fractional + whole + significand + vec4(exponent) + s_fractional + s_whole;
+}
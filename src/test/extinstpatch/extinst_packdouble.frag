@@ -0,0 +1,9 @@
+#version 450
+
+layout(location = 0) flat in uvec2 v_in;
+layout(location = 0) out uvec2 o_out;
+
+void main() {
+    double d = packDouble2x32(v_in);
+    o_out = unpackDouble2x32(d * 2.0lf);
+}
@@ -5,3 +5,9 @@ glslc -O0 mat2_direct.frag -o mat2_direct.spv
 glslc -O0 array_of_mat2.frag -o array_of_mat2.spv
 glslc -O0 nested_struct.frag -o nested_struct.spv
 glslc -O0 row_major.frag -o row_major.spv
+glslc -O0 shared_set.vert -o shared_set.spv
+glslc -O0 mat2_load.frag -o mat2_load.spv
+dxc -spirv -T lib_6_3 multi_entry.hlsl -Fo multi_entry.spv
+glslc -O0 shared_range.vert -o shared_range.vert.spv
+glslc -O0 shared_range.frag -o shared_range.frag.spv
+spirv-as vec2_mat2.spvasm -o vec2_mat2.spv
@@ -0,0 +1,16 @@
+#version 450
+
+layout(push_constant) uniform PC {
+    mat2 m;
+    mat3x2 n;
+    mat2 mats[2];
+    float x;
+} pc;
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    vec2 a = pc.m * vec2(pc.x);
+    vec2 b = pc.n * vec3(1.0);
+    vec2 c = pc.mats[1] * pc.m[1];
+    o_color = vec4(a + b, c.x + pc.mats[0][1][0], 1.0);
+}
@@ -0,0 +1,26 @@
+struct VsImmediates {
+    row_major float4x4 transform;
+    float2 offset;
+};
+
+struct PsImmediates {
+    float weights[4];
+    uint index;
+};
+
+[[vk::push_constant]] VsImmediates vs_immediates;
+[[vk::push_constant]] PsImmediates ps_immediates;
+
+float weight() {
+    return ps_immediates.weights[ps_immediates.index];
+}
+
+[shader("vertex")]
+float4 vs_main(float4 position : POSITION) : SV_Position {
+    return mul(position, vs_immediates.transform) + float4(vs_immediates.offset, 0.0, 0.0);
+}
+
+[shader("pixel")]
+float4 ps_main() : SV_Target0 {
+    return weight().xxxx;
+}
@@ -0,0 +1,13 @@
+#version 450
+
+// The fragment stage's push constant range is 64..96.
+layout(push_constant) uniform PushConstants {
+    layout(offset = 64) float weights[4];
+    vec4 color;
+} pc;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = pc.color * pc.weights[2];
+}
@@ -0,0 +1,12 @@
+#version 450
+
+// The vertex stage's push constant range is 0..64.
+layout(push_constant) uniform PushConstants {
+    mat4 transform;
+} pc;
+
+layout(location = 0) in vec4 position;
+
+void main() {
+    gl_Position = pc.transform * position;
+}
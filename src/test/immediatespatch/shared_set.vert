@@ -0,0 +1,14 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 offset;
+} pc;
+
+// Binding 0 is left free for immediates sharing this set.
+layout(set = 0, binding = 1) uniform Uniforms {
+    float scale;
+} u;
+
+void main() {
+    gl_Position = pc.offset * u.scale;
+}
@@ -3,4 +3,6 @@ set -e
 glslc isnanisinf.frag -o isnanisinf.spv
 glslc isnanisinf_vectored.frag -o isnanisinf_vectored.spv
 glslc isnanisinf_immediate.frag -o isnanisinf_immediate.spv
-
+glslc isnanisinf_f16.frag -o isnanisinf_f16.spv
+glslc isnanisinf_f64.frag -o isnanisinf_f64.spv
+glslc isnanisinf_f64_int64.frag -o isnanisinf_f64_int64.spv
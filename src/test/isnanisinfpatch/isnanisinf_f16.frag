@@ -0,0 +1,17 @@
+#version 450
+#extension GL_EXT_shader_explicit_arithmetic_types_float16 : require
+
+bool ra, rb;
+bvec2 rc, rd;
+
+void main() {
+    float16_t a = 0.0hf;
+    float16_t b = 1.0hf;
+    ra = isnan(a - b);
+    rb = isinf(a - b);
+
+    f16vec2 va = f16vec2(0.0hf, 0.0hf);
+    f16vec2 vb = f16vec2(1.0hf, 0.0hf);
+    rc = isnan(va - vb);
+    rd = isinf(va - vb);
+}
@@ -0,0 +1,16 @@
+#version 450
+
+bool ra, rb;
+bvec2 rc, rd;
+
+void main() {
+    double a = 0.0lf;
+    double b = 1.0lf;
+    ra = isnan(a - b);
+    rb = isinf(a - b);
+
+    dvec2 va = dvec2(0.0lf, 0.0lf);
+    dvec2 vb = dvec2(1.0lf, 0.0lf);
+    rc = isnan(va - vb);
+    rd = isinf(va - vb);
+}
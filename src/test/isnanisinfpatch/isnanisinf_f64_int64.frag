@@ -0,0 +1,13 @@
+#version 450
+#extension GL_ARB_gpu_shader_int64 : require
+
+bool ra, rb;
+uint64_t rc;
+
+void main() {
+    double a = 0.0lf;
+    double b = 1.0lf;
+    ra = isnan(a - b);
+    rb = isinf(a - b);
+    rc = 0ul;
+}
@@ -0,0 +1,3 @@
+set -e
+
+glslc layout.frag -o layout.spv
@@ -0,0 +1,14 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Uniforms {
+    vec4 tint;
+} u;
+layout(set = 0, binding = 1) uniform texture2D u_texture;
+layout(set = 0, binding = 2) uniform sampler u_sampler;
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    o_color = texture(sampler2D(u_texture, u_sampler), v_uv) * u.tint;
+}
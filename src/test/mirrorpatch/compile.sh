@@ -4,4 +4,8 @@ glslc test1.vert -o test1.vert.spv
 glslc test1.frag -o test1.frag.spv
 glslc test2.vert -o test2.vert.spv
 glslc test2.frag -o test2.frag.spv
+glslc test3.frag -o test3.frag.spv
 
+glslc immediates.vert -o immediates.vert.spv
+glslc immediates.frag -o immediates.frag.spv
+glslc immediates_shared.frag -o immediates_shared.frag.spv
@@ -0,0 +1,21 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 offset;
+} pc;
+
+layout(set = 0, binding = 0) uniform Uniforms {
+    float scale;
+} u;
+
+// Only the fragment shader uses set 1, so immediates would otherwise land on this binding in the
+// vertex shader.
+layout(set = 1, binding = 0) uniform Other {
+    float scale;
+} v;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    o_color = pc.offset * u.scale * v.scale;
+}
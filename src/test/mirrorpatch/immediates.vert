@@ -0,0 +1,13 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 offset;
+} pc;
+
+layout(set = 0, binding = 0) uniform Uniforms {
+    float scale;
+} u;
+
+void main() {
+    gl_Position = pc.offset * u.scale;
+}
@@ -0,0 +1,20 @@
+#version 450
+
+layout(push_constant) uniform PushConstants {
+    vec4 offset;
+} pc;
+
+layout(set = 0, binding = 0) uniform Uniforms {
+    float scale;
+} u;
+
+// Immediates share set 0, this binding is past where either shader would place them on its own.
+layout(set = 0, binding = 2) uniform Other {
+    float scale;
+} v;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    o_color = pc.offset * u.scale * v.scale;
+}
@@ -0,0 +1,4 @@
+set -e
+
+glslc -O0 unused.frag -o unused.spv
+glslc -O0 --target-env=vulkan1.2 unused.frag -o unused_interface.spv
@@ -0,0 +1,34 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Material {
+    vec4 tint;
+} u_material;
+
+layout(set = 0, binding = 1) uniform Unused {
+    vec4 never_read;
+} u_unused;
+
+layout(set = 1, binding = 0) buffer Lights {
+    vec4 lights[];
+} u_lights;
+
+layout(set = 1, binding = 1, rgba8) uniform readonly image2D u_unused_image;
+
+layout(push_constant) uniform Immediates {
+    float unused_scale;
+} u_immediates;
+
+layout(set = 0, binding = 2) uniform texture2D u_texture;
+layout(set = 0, binding = 3) uniform sampler u_sampler;
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec3 v_unused_normal;
+layout(location = 0) out vec4 o_color;
+
+vec4 unused_lighting() {
+    return u_lights.lights[0] * u_immediates.unused_scale;
+}
+
+void main() {
+    o_color = texture(sampler2D(u_texture, u_sampler), v_uv) * u_material.tint;
+}
@@ -4,3 +4,4 @@ glslc pruneunuseddref.frag -o pruneunuseddref.spv
 glslc pruneunuseddref_nested.frag -o pruneunuseddref_nested.spv
 glslc pruneunuseddref_storage.frag -o pruneunuseddref_storage.spv
 
+glslc pruneunuseddref_combined.frag -o pruneunuseddref_combined.spv
@@ -0,0 +1,15 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2DShadow u_unused_shadow;
+layout(set = 0, binding = 1) uniform sampler2D u_used;
+layout(set = 0, binding = 2) uniform sampler2D u_unused_array[4];
+
+layout(rgba32f, set = 1, binding = 0) uniform writeonly image2D u_unused_storage;
+layout(rgba32f, set = 1, binding = 1) uniform writeonly image2D u_storage;
+
+layout(set = 2, binding = 0) uniform texture2D u_unused_texture;
+
+void main() {
+    vec4 color = texture(u_used, vec2(0.0, 0.0));
+    imageStore(u_storage, ivec2(0, 0), color);
+}
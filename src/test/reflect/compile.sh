@@ -0,0 +1,3 @@
+set -e
+
+spirv-as workgroup_size_builtin.spvasm -o workgroup_size_builtin.spv
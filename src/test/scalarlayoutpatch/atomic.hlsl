@@ -0,0 +1,11 @@
+struct Bin {
+    uint count;
+    float3 center;
+};
+
+RWStructuredBuffer<Bin> bins : register(u0);
+
+[numthreads(64, 1, 1)]
+void main(uint3 id : SV_DispatchThreadID) {
+    InterlockedAdd(bins[id.x % 16].count, 1);
+}
@@ -0,0 +1,4 @@
+set -e
+
+dxc -spirv -T cs_6_0 -E main -fvk-use-scalar-layout particles.hlsl -Fo particles.spv
+dxc -spirv -T cs_6_0 -E main -fvk-use-scalar-layout atomic.hlsl -Fo atomic.spv
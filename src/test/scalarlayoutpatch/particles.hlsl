@@ -0,0 +1,27 @@
+struct Particle {
+    float mass;
+    float3 position;
+    float3 velocity;
+};
+
+cbuffer Params : register(b0) {
+    float dt;
+    float3 gravity;
+};
+
+RWStructuredBuffer<Particle> particles : register(u1);
+StructuredBuffer<float3> forces : register(t2);
+
+[numthreads(64, 1, 1)]
+void main(uint3 id : SV_DispatchThreadID) {
+    uint count, stride;
+    particles.GetDimensions(count, stride);
+    if (id.x >= count) {
+        return;
+    }
+
+    Particle p = particles[id.x];
+    p.velocity += (forces[id.x] / p.mass + gravity) * dt;
+    p.position += p.velocity * dt;
+    particles[id.x] = p;
+}
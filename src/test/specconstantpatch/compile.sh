@@ -0,0 +1,3 @@
+set -e
+
+glslc -O0 spec.comp -o spec.spv
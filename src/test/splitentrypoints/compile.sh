@@ -0,0 +1,3 @@
+set -e
+
+dxc -spirv -T lib_6_3 multi.hlsl -Fo multi.spv
@@ -0,0 +1,33 @@
+cbuffer Frame : register(b0, space0) {
+    float4x4 view_proj;
+    float4 tint_color;
+};
+
+cbuffer Object : register(b0, space1) {
+    float4x4 model;
+};
+
+Texture2D albedo : register(t1, space0);
+SamplerState albedo_sampler : register(s2, space0);
+
+struct VsOutput {
+    float4 position : SV_Position;
+    float4 color : COLOR0;
+};
+
+float4 tint(float4 color) {
+    return color * tint_color;
+}
+
+[shader("vertex")]
+VsOutput vs_main(float3 position : POSITION, float4 color : COLOR0) {
+    VsOutput output;
+    output.position = mul(view_proj, mul(model, float4(position, 1.0)));
+    output.color = tint(color);
+    return output;
+}
+
+[shader("pixel")]
+float4 ps_main(float4 color : COLOR0) : SV_Target0 {
+    return albedo.Sample(albedo_sampler, float2(0.5, 0.5)) * color;
+}
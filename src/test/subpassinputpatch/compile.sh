@@ -0,0 +1,4 @@
+set -e
+
+glslc -O0 subpass.frag -o subpass.spv
+glslc -O0 subpass_nested.frag -o subpass_nested.spv
@@ -0,0 +1,14 @@
+#version 450
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput u_albedo;
+layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput u_normal;
+layout(input_attachment_index = 2, set = 0, binding = 2) uniform subpassInputMS u_depth;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    vec3 albedo = subpassLoad(u_albedo).rgb;
+    vec3 normal = subpassLoad(u_normal).xyz * 2.0 - 1.0;
+    float depth = subpassLoad(u_depth, 0).r;
+    o_color = vec4(albedo * max(dot(normal, vec3(0.0, 0.0, 1.0)), 0.0) * depth, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 450
+
+layout(input_attachment_index = 0, set = 1, binding = 0) uniform usubpassInput u_ids;
+layout(input_attachment_index = 1, set = 1, binding = 1) uniform subpassInput u_lighting;
+
+layout(location = 0) out vec4 o_color;
+
+uint load_id(usubpassInput ids) {
+    return subpassLoad(ids).r;
+}
+
+void main() {
+    uint id = load_id(u_ids);
+    vec4 lighting = subpassLoad(u_lighting);
+    o_color = id == 0u ? vec4(gl_FragCoord.z) : lighting;
+}
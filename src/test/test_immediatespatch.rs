@@ -0,0 +1,588 @@
+use super::*;
+use crate::{
+    ImmediatesBuffer, ImmediatesBufferKind, ImmediatesCopy, ImmediatesEntryPoint, ImmediatesMember,
+    ImmediatesSetMode, ResourceKind, immediatespatch_shared, mirrorpatch_many, reflect,
+    repack_immediates, splitentrypoints,
+};
+
+fn uniform_bindings(spv: &[u32]) -> Vec<(u32, u32, Option<String>)> {
+    reflect(spv)
+        .unwrap()
+        .resources
+        .into_iter()
+        .map(|resource| (resource.set, resource.binding, resource.name))
+        .collect()
+}
+
+#[test]
+fn immediatespatch_immediates_buffers() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/immediates.spv"));
+    let mut corrections = CorrectionMap::default();
+    immediatespatch(&spv, &mut corrections).unwrap();
+
+    assert_eq!(corrections.immediates_set, Some(0));
+    assert_eq!(corrections.immediates_binding, Some(0));
+    assert_eq!(
+        corrections.immediates_buffer_kind,
+        Some(ImmediatesBufferKind::Uniform)
+    );
+    assert_eq!(
+        corrections.immediates_buffers,
+        vec![ImmediatesBuffer {
+            set: 0,
+            binding: 0,
+            size: 160,
+            members: vec![
+                ImmediatesMember {
+                    push_constant_offset: 0,
+                    offset: 0,
+                    size: 16,
+                },
+                ImmediatesMember {
+                    push_constant_offset: 16,
+                    offset: 16,
+                    size: 64,
+                },
+                ImmediatesMember {
+                    push_constant_offset: 80,
+                    offset: 80,
+                    size: 4,
+                },
+                // `float arr[3]` has a stride of 16 rather than 4
+                ImmediatesMember {
+                    push_constant_offset: 96,
+                    offset: 96,
+                    size: 64,
+                },
+            ],
+            copies: vec![
+                ImmediatesCopy {
+                    push_constant_offset: 0,
+                    offset: 0,
+                    size: 84,
+                },
+                ImmediatesCopy {
+                    push_constant_offset: 96,
+                    offset: 96,
+                    size: 12,
+                },
+                ImmediatesCopy {
+                    push_constant_offset: 108,
+                    offset: 112,
+                    size: 4,
+                },
+                ImmediatesCopy {
+                    push_constant_offset: 112,
+                    offset: 128,
+                    size: 4,
+                },
+                ImmediatesCopy {
+                    push_constant_offset: 116,
+                    offset: 144,
+                    size: 4,
+                },
+            ],
+            entry_points: vec![ImmediatesEntryPoint {
+                name: String::from("main"),
+                execution_model: 4,
+            }],
+        }]
+    );
+}
+
+#[test]
+fn immediatespatch_shared_set() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/shared_set.spv"));
+
+    // The first free binding of the shared set
+    let mut corrections = CorrectionMap {
+        immediates_set: Some(0),
+        immediates_set_mode: Some(ImmediatesSetMode::Absolute),
+        ..Default::default()
+    };
+    let out_spv = immediatespatch(&spv, &mut corrections).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+    assert_eq!(corrections.immediates_binding, Some(0));
+    assert_eq!(
+        uniform_bindings(&out_spv),
+        vec![(0, 0, Some("pc".to_owned())), (0, 1, Some("u".to_owned()))]
+    );
+    assert_eq!(corrections.immediates_buffers[0].size, 16);
+
+    // A chosen binding
+    let mut corrections = CorrectionMap {
+        immediates_set: Some(0),
+        immediates_set_mode: Some(ImmediatesSetMode::Absolute),
+        immediates_binding: Some(4),
+        ..Default::default()
+    };
+    let out_spv = immediatespatch(&spv, &mut corrections).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+    assert_eq!(
+        uniform_bindings(&out_spv),
+        vec![(0, 1, Some("u".to_owned())), (0, 4, Some("pc".to_owned()))]
+    );
+    assert_eq!(
+        (
+            corrections.immediates_buffers[0].set,
+            corrections.immediates_buffers[0].binding
+        ),
+        (0, 4)
+    );
+
+    // A chosen binding that is already in use
+    let mut corrections = CorrectionMap {
+        immediates_set: Some(0),
+        immediates_set_mode: Some(ImmediatesSetMode::Absolute),
+        immediates_binding: Some(1),
+        ..Default::default()
+    };
+    assert!(immediatespatch(&spv, &mut corrections).is_err());
+}
+
+#[test]
+fn immediatespatch_multi_entry() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/multi_entry.spv"));
+    let mut corrections = CorrectionMap::default();
+    let out_spv = immediatespatch(&spv, &mut corrections).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    // Each stage has its own block, so each buffer is only used by one entry point.
+    let buffers = corrections
+        .immediates_buffers
+        .iter()
+        .map(|buffer| {
+            (
+                buffer.set,
+                buffer.binding,
+                buffer.size,
+                buffer.entry_points.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        buffers,
+        vec![
+            (
+                0,
+                0,
+                80,
+                vec![ImmediatesEntryPoint {
+                    name: String::from("vs_main"),
+                    execution_model: 0,
+                }]
+            ),
+            // `ps_immediates` is only read through `weight()`
+            (
+                0,
+                1,
+                80,
+                vec![ImmediatesEntryPoint {
+                    name: String::from("ps_main"),
+                    execution_model: 4,
+                }]
+            ),
+        ]
+    );
+
+    // Splitting afterwards leaves each entry point with only the buffer it uses.
+    let modules = splitentrypoints(&out_spv).unwrap();
+    assert_eq!(
+        uniform_bindings(&modules[0].spv),
+        vec![(0, 0, Some(String::from("vs_immediates")))]
+    );
+    assert_eq!(
+        uniform_bindings(&modules[1].spv),
+        vec![(0, 1, Some(String::from("ps_immediates")))]
+    );
+}
+
+#[test]
+fn immediatespatch_shared_range() {
+    let vert_spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/shared_range.vert.spv"));
+    let frag_spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/shared_range.frag.spv"));
+    let mut vert_map = CorrectionMap::default();
+    let mut frag_map = CorrectionMap::default();
+    let outputs =
+        immediatespatch_shared(&mut [(&vert_spv, &mut vert_map), (&frag_spv, &mut frag_map)])
+            .unwrap();
+
+    // On its own, the fragment shader's `weights` would move to offset 0.
+    let expected = ImmediatesBuffer {
+        set: 0,
+        binding: 0,
+        size: 144,
+        members: vec![
+            ImmediatesMember {
+                push_constant_offset: 0,
+                offset: 0,
+                size: 64,
+            },
+            ImmediatesMember {
+                push_constant_offset: 64,
+                offset: 64,
+                size: 64,
+            },
+            ImmediatesMember {
+                push_constant_offset: 80,
+                offset: 128,
+                size: 16,
+            },
+        ],
+        copies: vec![
+            ImmediatesCopy {
+                push_constant_offset: 0,
+                offset: 0,
+                size: 68,
+            },
+            ImmediatesCopy {
+                push_constant_offset: 68,
+                offset: 80,
+                size: 4,
+            },
+            ImmediatesCopy {
+                push_constant_offset: 72,
+                offset: 96,
+                size: 4,
+            },
+            ImmediatesCopy {
+                push_constant_offset: 76,
+                offset: 112,
+                size: 4,
+            },
+            ImmediatesCopy {
+                push_constant_offset: 80,
+                offset: 128,
+                size: 16,
+            },
+        ],
+        entry_points: vec![],
+    };
+    for (corrections, execution_model) in [(&vert_map, 0), (&frag_map, 4)] {
+        assert_eq!(
+            corrections.immediates_buffers,
+            vec![ImmediatesBuffer {
+                entry_points: vec![ImmediatesEntryPoint {
+                    name: String::from("main"),
+                    execution_model,
+                }],
+                ..expected.clone()
+            }]
+        );
+    }
+
+    let mirrored =
+        mirrorpatch_many(&mut [(&outputs[0], &mut vert_map), (&outputs[1], &mut frag_map)])
+            .unwrap();
+    for (output, mirrored) in outputs.iter().zip(mirrored) {
+        let output = mirrored.unwrap_or(output.clone());
+        try_spv_to_wgsl(&output, DO_ALL);
+        assert_eq!(
+            uniform_bindings(&output),
+            vec![(0, 0, Some(String::from("pc")))]
+        );
+    }
+}
+
+#[test]
+fn immediatespatch_shared_range_storage_buffer() {
+    let vert_spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/shared_range.vert.spv"));
+    let frag_spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/shared_range.frag.spv"));
+    let mut vert_map = CorrectionMap {
+        immediates_buffer_kind: Some(ImmediatesBufferKind::ReadOnlyStorage),
+        ..Default::default()
+    };
+    let mut frag_map = CorrectionMap {
+        immediates_buffer_kind: Some(ImmediatesBufferKind::ReadOnlyStorage),
+        ..Default::default()
+    };
+    let outputs =
+        immediatespatch_shared(&mut [(&vert_spv, &mut vert_map), (&frag_spv, &mut frag_map)])
+            .unwrap();
+    for output in outputs.iter() {
+        try_spv_to_wgsl(output, DO_ALL);
+    }
+
+    // Push constant offsets are kept, so the whole range is copied as is.
+    for corrections in [&vert_map, &frag_map] {
+        let buffer = &corrections.immediates_buffers[0];
+        assert_eq!(buffer.size, 96);
+        assert_eq!(
+            buffer.copies,
+            vec![ImmediatesCopy {
+                push_constant_offset: 0,
+                offset: 0,
+                size: 96,
+            }]
+        );
+    }
+
+    // Every module must use the same kind of buffer.
+    assert!(
+        immediatespatch_shared(&mut [
+            (&vert_spv, &mut CorrectionMap::default()),
+            (&frag_spv, &mut frag_map),
+        ])
+        .is_err()
+    );
+}
+
+// Repack push constant data of `1.0, 2.0, 3.0, ...` and read the uniform buffer back as floats.
+fn repack_sequence(spv: &[u32]) -> Vec<f32> {
+    let mut corrections = CorrectionMap::default();
+    immediatespatch(spv, &mut corrections).unwrap();
+    let buffer = &corrections.immediates_buffers[0];
+
+    let push_constant_size = buffer
+        .copies
+        .iter()
+        .map(|copy| copy.push_constant_offset + copy.size)
+        .max()
+        .unwrap();
+    let push_constant_data = (1..=push_constant_size / 4)
+        .flat_map(|i| (i as f32).to_ne_bytes())
+        .collect::<Vec<_>>();
+    assert!(
+        repack_immediates(buffer, &push_constant_data[..push_constant_data.len() - 1]).is_err()
+    );
+
+    let data = repack_immediates(buffer, &push_constant_data).unwrap();
+    assert_eq!(data.len(), buffer.size as usize);
+    data.chunks_exact(4)
+        .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+        .collect()
+}
+
+#[test]
+fn immediatespatch_repack_arrays() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/immediates.spv"));
+    #[rustfmt::skip]
+    assert_eq!(
+        repack_sequence(&spv),
+        vec![
+            // color, transform, scale
+            1.0, 2.0, 3.0, 4.0,
+            5.0, 6.0, 7.0, 8.0,
+            9.0, 10.0, 11.0, 12.0,
+            13.0, 14.0, 15.0, 16.0,
+            17.0, 18.0, 19.0, 20.0,
+            21.0, 0.0, 0.0, 0.0,
+            // inner.v, inner.arr
+            25.0, 26.0, 27.0, 0.0,
+            28.0, 0.0, 0.0, 0.0,
+            29.0, 0.0, 0.0, 0.0,
+            30.0, 0.0, 0.0, 0.0,
+        ]
+    );
+}
+
+#[test]
+fn immediatespatch_repack_matrices() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/mat2_direct.spv"));
+    #[rustfmt::skip]
+    assert_eq!(
+        repack_sequence(&spv),
+        vec![
+            1.0, 2.0, 0.0, 0.0,
+            3.0, 4.0, 0.0, 0.0,
+            5.0, 0.0, 0.0, 0.0,
+        ]
+    );
+
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/array_of_mat2.spv"));
+    #[rustfmt::skip]
+    assert_eq!(
+        repack_sequence(&spv),
+        vec![
+            1.0, 2.0, 0.0, 0.0,
+            3.0, 4.0, 0.0, 0.0,
+            5.0, 6.0, 0.0, 0.0,
+            7.0, 8.0, 0.0, 0.0,
+        ]
+    );
+}
+
+#[test]
+fn immediatespatch_repack_row_major() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/row_major.spv"));
+    // Rows of a `mat4` are already 16 byte aligned.
+    assert_eq!(
+        repack_sequence(&spv),
+        (1..=20).map(|i| i as f32).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn immediatespatch_repack_nested_struct() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/nested_struct.spv"));
+    // Padding in the push constant data is not carried over.
+    #[rustfmt::skip]
+    assert_eq!(
+        repack_sequence(&spv),
+        vec![
+            1.0, 0.0, 0.0, 0.0,
+            5.0, 6.0, 7.0, 0.0,
+            9.0, 10.0, 0.0, 0.0,
+            13.0, 0.0, 0.0, 0.0,
+        ]
+    );
+}
+
+#[test]
+fn immediatespatch_storage_buffer() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/immediates.spv"));
+    let mut corrections = CorrectionMap {
+        immediates_buffer_kind: Some(ImmediatesBufferKind::ReadOnlyStorage),
+        ..Default::default()
+    };
+    let out_spv = immediatespatch(&spv, &mut corrections).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    let resource = &reflect(&out_spv).unwrap().resources[0];
+    assert_eq!(resource.kind, ResourceKind::StorageBuffer);
+    assert!(resource.non_writable);
+
+    // The std430 layout is kept, so push constant data only needs its padding zeroed.
+    assert_eq!(
+        corrections.immediates_buffer_kind,
+        Some(ImmediatesBufferKind::ReadOnlyStorage)
+    );
+    assert_eq!(
+        corrections.immediates_buffers,
+        vec![ImmediatesBuffer {
+            set: 0,
+            binding: 0,
+            size: 128,
+            members: vec![
+                ImmediatesMember {
+                    push_constant_offset: 0,
+                    offset: 0,
+                    size: 16,
+                },
+                ImmediatesMember {
+                    push_constant_offset: 16,
+                    offset: 16,
+                    size: 64,
+                },
+                ImmediatesMember {
+                    push_constant_offset: 80,
+                    offset: 80,
+                    size: 4,
+                },
+                ImmediatesMember {
+                    push_constant_offset: 96,
+                    offset: 96,
+                    size: 32,
+                },
+            ],
+            copies: vec![
+                ImmediatesCopy {
+                    push_constant_offset: 0,
+                    offset: 0,
+                    size: 84,
+                },
+                ImmediatesCopy {
+                    push_constant_offset: 96,
+                    offset: 96,
+                    size: 24,
+                },
+            ],
+            entry_points: vec![ImmediatesEntryPoint {
+                name: String::from("main"),
+                execution_model: 4,
+            }],
+        }]
+    );
+}
+
+#[test]
+fn immediatespatch_storage_buffer_mat2() {
+    // Unlike uniforms, `mat2` keeps its 8 byte column stride which naga accepts.
+    for spv in [
+        &include_bytes!("./immediatespatch/mat2_direct.spv")[..],
+        &include_bytes!("./immediatespatch/array_of_mat2.spv")[..],
+    ] {
+        let spv = u8_slice_to_u32_vec(spv);
+        let mut corrections = CorrectionMap {
+            immediates_buffer_kind: Some(ImmediatesBufferKind::ReadOnlyStorage),
+            ..Default::default()
+        };
+        let out_spv = immediatespatch(&spv, &mut corrections).unwrap();
+        try_spv_to_wgsl(&out_spv, DO_ALL);
+    }
+}
+
+#[test]
+fn immediatespatch_pad_matcx2() {
+    for spv in [
+        &include_bytes!("./immediatespatch/mat2_direct.spv")[..],
+        &include_bytes!("./immediatespatch/array_of_mat2.spv")[..],
+        &include_bytes!("./immediatespatch/mat2_load.spv")[..],
+    ] {
+        let spv = u8_slice_to_u32_vec(spv);
+        let mut corrections = CorrectionMap::default();
+        immediatespatch(&spv, &mut corrections).unwrap();
+
+        // The layout is the same, just no longer a matrix.
+        let mut padded_corrections = CorrectionMap {
+            pad_matcx2: true,
+            ..Default::default()
+        };
+        let out_spv = immediatespatch(&spv, &mut padded_corrections).unwrap();
+        try_spv_to_wgsl(&out_spv, DO_ALL);
+        assert_eq!(
+            padded_corrections.immediates_buffers,
+            corrections.immediates_buffers
+        );
+    }
+}
+
+#[test]
+fn immediatespatch_mat2_after_vec2() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/vec2_mat2.spv"));
+    let mut corrections = CorrectionMap {
+        pad_matcx2: true,
+        ..Default::default()
+    };
+    let out_spv = immediatespatch(&spv, &mut corrections).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    // std140 aligns matrices like arrays, so `m` moves from offset 8 to 16
+    let buffer = &corrections.immediates_buffers[0];
+    assert_eq!(buffer.size, 48);
+    assert_eq!(
+        buffer.members,
+        vec![
+            ImmediatesMember {
+                push_constant_offset: 0,
+                offset: 0,
+                size: 8,
+            },
+            ImmediatesMember {
+                push_constant_offset: 8,
+                offset: 16,
+                size: 32,
+            },
+        ]
+    );
+    assert_eq!(
+        buffer.copies,
+        vec![
+            ImmediatesCopy {
+                push_constant_offset: 0,
+                offset: 0,
+                size: 8,
+            },
+            ImmediatesCopy {
+                push_constant_offset: 8,
+                offset: 16,
+                size: 8,
+            },
+            ImmediatesCopy {
+                push_constant_offset: 16,
+                offset: 32,
+                size: 8,
+            },
+        ]
+    );
+}
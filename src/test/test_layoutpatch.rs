@@ -0,0 +1,115 @@
+use super::*;
+use crate::{
+    ImageReflection, ReflectedType, ResourceKind, TargetLayout, TargetLayoutBinding,
+    TargetLayoutSet, layoutpatch, reflect,
+};
+
+fn target_set(bindings: &[(u32, ResourceKind, Option<ImageReflection>)]) -> TargetLayoutSet {
+    TargetLayoutSet {
+        bindings: bindings
+            .iter()
+            .map(|(binding, kind, image)| {
+                (
+                    *binding,
+                    TargetLayoutBinding {
+                        kind: *kind,
+                        image: image.clone(),
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+fn float_image(dim: u32, format: u32) -> ImageReflection {
+    ImageReflection {
+        dim,
+        depth: false,
+        arrayed: false,
+        multisampled: false,
+        format,
+        sampled_type: ReflectedType::Float { width: 32 },
+    }
+}
+
+#[test]
+fn layoutpatch_layout() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./layoutpatch/layout.spv"));
+    let layout = TargetLayout {
+        sets: [
+            (
+                0,
+                target_set(&[
+                    (0, ResourceKind::Sampler, None),
+                    (1, ResourceKind::Texture, Some(float_image(1, 0))),
+                    (2, ResourceKind::UniformBuffer, None),
+                    (3, ResourceKind::StorageBuffer, None),
+                ]),
+            ),
+            (
+                1,
+                target_set(&[
+                    (0, ResourceKind::Texture, Some(float_image(3, 0))),
+                    (1, ResourceKind::StorageTexture, Some(float_image(1, 4))),
+                ]),
+            ),
+        ]
+        .into_iter()
+        .collect(),
+    };
+
+    let out_spv = layoutpatch(&spv, &layout).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    let resources = reflect(&out_spv)
+        .unwrap()
+        .resources
+        .into_iter()
+        .map(|resource| (resource.set, resource.binding, resource.kind, resource.name))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        resources,
+        vec![
+            (0, 0, ResourceKind::Sampler, Some("u_sampler".to_owned())),
+            (0, 1, ResourceKind::Texture, Some("u_texture".to_owned())),
+            (0, 2, ResourceKind::UniformBuffer, Some("u".to_owned())),
+            (0, 3, ResourceKind::StorageBuffer, None),
+            (1, 0, ResourceKind::Texture, None),
+            (1, 1, ResourceKind::StorageTexture, None),
+        ]
+    );
+}
+
+#[test]
+fn layoutpatch_layout_missing() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./layoutpatch/layout.spv"));
+
+    // No uniform buffer
+    let layout = TargetLayout {
+        sets: [(
+            0,
+            target_set(&[
+                (0, ResourceKind::Sampler, None),
+                (1, ResourceKind::Texture, None),
+            ]),
+        )]
+        .into_iter()
+        .collect(),
+    };
+    assert!(layoutpatch(&spv, &layout).is_err());
+
+    // A cube texture instead of a 2D texture
+    let layout = TargetLayout {
+        sets: [(
+            0,
+            target_set(&[
+                (0, ResourceKind::UniformBuffer, None),
+                (1, ResourceKind::Texture, Some(float_image(3, 0))),
+                (2, ResourceKind::Sampler, None),
+            ]),
+        )]
+        .into_iter()
+        .collect(),
+    };
+    assert!(layoutpatch(&spv, &layout).is_err());
+}
@@ -1,5 +1,8 @@
 use super::*;
-use crate::{CorrectionBinding, CorrectionMap};
+use crate::{
+    CorrectionBinding, CorrectionMap, CorrectionType, MirrorConflict, mirrorpatch_conflicts,
+    reflect,
+};
 
 macro_rules! test_mirrorpatch_with_spv_and_fn {
     ($NAME:ident, $VERT:expr, $FRAG:expr, $ASSERT:expr) => {
@@ -93,3 +96,324 @@ test_mirrorpatch_with_spv_and_fn!(
     "./mirrorpatch/test2.frag.spv",
     test_mirrorpatch2_assert
 );
+
+fn split_for_mirrorpatch(spv: &[u8]) -> (Vec<u32>, CorrectionMap) {
+    let mut corrections = CorrectionMap::default();
+    let spv = combimgsampsplitter(&u8_slice_to_u32_vec(spv), &mut corrections).unwrap();
+    let spv = drefsplitter(&spv, &mut corrections).unwrap();
+    (spv, corrections)
+}
+
+#[test]
+fn test_mirrorpatch_many_matches_mirrorpatch() {
+    let (vert_spv, vert_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test2.vert.spv"));
+    let (frag_spv, frag_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test2.frag.spv"));
+
+    let (mut left_map, mut right_map) = (vert_map.clone(), frag_map.clone());
+    let (left_spv, right_spv) =
+        mirrorpatch(&vert_spv, &mut left_map, &frag_spv, &mut right_map).unwrap();
+
+    let (mut many_left_map, mut many_right_map) = (vert_map, frag_map);
+    let outputs = mirrorpatch_many(&mut [
+        (&vert_spv, &mut many_left_map),
+        (&frag_spv, &mut many_right_map),
+    ])
+    .unwrap();
+
+    assert_eq!(outputs, vec![left_spv, right_spv]);
+    assert_eq!(many_left_map, left_map);
+    assert_eq!(many_right_map, right_map);
+}
+
+#[test]
+fn test_mirrorpatch_many() {
+    let (vert_spv, mut vert_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test1.vert.spv"));
+    let (frag_spv, mut frag_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test1.frag.spv"));
+    let (other_spv, mut other_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test2.vert.spv"));
+
+    let outputs = mirrorpatch_many(&mut [
+        (&vert_spv, &mut vert_map),
+        (&frag_spv, &mut frag_map),
+        (&other_spv, &mut other_map),
+    ])
+    .unwrap();
+
+    assert_eq!(vert_map, frag_map);
+    // `test2.vert` omits bindings 1 and 3, so they have nothing to mirror.
+    let mut other_map = other_map.clone();
+    for binding in [1, 3] {
+        other_map
+            .sets
+            .as_mut()
+            .unwrap()
+            .get_mut(&0)
+            .unwrap()
+            .bindings
+            .insert(binding, CorrectionBinding::default());
+    }
+    assert_eq!(other_map, frag_map);
+
+    let [Some(vert_spv), None, Some(other_spv)] = outputs.as_slice() else {
+        panic!("Expected only the vertex shaders to change");
+    };
+    try_spv_to_wgsl(vert_spv, DO_ALL);
+    try_spv_to_wgsl(other_spv, DO_ALL);
+}
+
+fn immediates_binding(spv: &[u32], name: &str) -> (u32, u32) {
+    let resource = reflect(spv)
+        .unwrap()
+        .resources
+        .into_iter()
+        .find(|resource| resource.name.as_deref() == Some(name))
+        .unwrap();
+    (resource.set, resource.binding)
+}
+
+// Immediates are used by a different entry point in each shader.
+fn without_entry_points(corrections: &CorrectionMap) -> CorrectionMap {
+    let mut corrections = corrections.clone();
+    for buffer in corrections.immediates_buffers.iter_mut() {
+        buffer.entry_points.clear();
+    }
+    corrections
+}
+
+#[test]
+fn test_mirrorpatch_immediates() {
+    let mut vert_map = CorrectionMap::default();
+    let mut frag_map = CorrectionMap::default();
+    let vert_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates.vert.spv")),
+        &mut vert_map,
+    )
+    .unwrap();
+    let frag_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates.frag.spv")),
+        &mut frag_map,
+    )
+    .unwrap();
+
+    // The vertex shader's immediates collide with the fragment shader's `v`.
+    assert_eq!(vert_map.immediates_set, Some(1));
+    assert_eq!(frag_map.immediates_set, Some(2));
+
+    let (new_vert_spv, new_frag_spv) =
+        mirrorpatch(&vert_spv, &mut vert_map, &frag_spv, &mut frag_map).unwrap();
+
+    assert!(new_frag_spv.is_none());
+    let new_vert_spv = new_vert_spv.unwrap();
+    try_spv_to_wgsl(&new_vert_spv, DO_ALL);
+
+    assert_eq!(
+        without_entry_points(&vert_map),
+        without_entry_points(&frag_map)
+    );
+    assert_eq!(vert_map.immediates_set, Some(2));
+    assert_eq!(vert_map.immediates_binding, Some(0));
+    assert_eq!(immediates_binding(&new_vert_spv, "pc"), (2, 0));
+    assert_eq!(immediates_binding(&frag_spv, "pc"), (2, 0));
+
+    // `mirrorpatch_many` reconciles the same way.
+    let mut vert_map = CorrectionMap::default();
+    let mut frag_map = CorrectionMap::default();
+    let vert_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates.vert.spv")),
+        &mut vert_map,
+    )
+    .unwrap();
+    let frag_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates.frag.spv")),
+        &mut frag_map,
+    )
+    .unwrap();
+    let outputs =
+        mirrorpatch_many(&mut [(&vert_spv, &mut vert_map), (&frag_spv, &mut frag_map)]).unwrap();
+    assert_eq!(outputs, vec![Some(new_vert_spv), None]);
+    assert_eq!(
+        without_entry_points(&vert_map),
+        without_entry_points(&frag_map)
+    );
+}
+
+#[test]
+fn test_mirrorpatch_immediates_shared_set() {
+    let immediates_in_set_0 = || CorrectionMap {
+        immediates_set: Some(0),
+        ..Default::default()
+    };
+    let mut vert_map = immediates_in_set_0();
+    let mut frag_map = immediates_in_set_0();
+    let vert_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates.vert.spv")),
+        &mut vert_map,
+    )
+    .unwrap();
+    let frag_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates_shared.frag.spv")),
+        &mut frag_map,
+    )
+    .unwrap();
+
+    // Both fill the gap at binding 1, below the fragment shader's `v` at binding 2.
+    assert_eq!(immediates_binding(&vert_spv, "pc"), (0, 1));
+    assert_eq!(immediates_binding(&frag_spv, "pc"), (0, 1));
+
+    // The agreed binding does not collide with `v`, so it is kept.
+    let (new_vert_spv, new_frag_spv) =
+        mirrorpatch(&vert_spv, &mut vert_map, &frag_spv, &mut frag_map).unwrap();
+    assert_eq!((new_vert_spv, new_frag_spv), (None, None));
+    assert_eq!(
+        without_entry_points(&vert_map),
+        without_entry_points(&frag_map)
+    );
+    assert_eq!(vert_map.immediates_binding, Some(1));
+
+    // An explicit binding that collides with `v` in the other shader is moved.
+    let mut vert_map = CorrectionMap {
+        immediates_binding: Some(2),
+        ..immediates_in_set_0()
+    };
+    let mut frag_map = immediates_in_set_0();
+    let vert_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates.vert.spv")),
+        &mut vert_map,
+    )
+    .unwrap();
+    let frag_spv = immediatespatch(
+        &u8_slice_to_u32_vec(include_bytes!("./mirrorpatch/immediates_shared.frag.spv")),
+        &mut frag_map,
+    )
+    .unwrap();
+    assert_eq!(immediates_binding(&vert_spv, "pc"), (0, 2));
+
+    let (new_vert_spv, new_frag_spv) =
+        mirrorpatch(&vert_spv, &mut vert_map, &frag_spv, &mut frag_map).unwrap();
+    assert!(new_frag_spv.is_none());
+    let new_vert_spv = new_vert_spv.unwrap();
+    try_spv_to_wgsl(&new_vert_spv, DO_ALL);
+
+    // The fragment shader's binding is free in both, so both use it.
+    assert_eq!(immediates_binding(&new_vert_spv, "pc"), (0, 1));
+    assert_eq!(immediates_binding(&frag_spv, "pc"), (0, 1));
+    assert_eq!(immediates_binding(&frag_spv, "v"), (0, 2));
+    assert_eq!(
+        without_entry_points(&vert_map),
+        without_entry_points(&frag_map)
+    );
+    assert_eq!(vert_map.immediates_binding, Some(1));
+    assert_eq!(
+        (
+            vert_map.immediates_buffers[0].set,
+            vert_map.immediates_buffers[0].binding
+        ),
+        (0, 1)
+    );
+}
+
+#[test]
+fn test_mirrorpatch_storagecube() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./storagecubepatch/storagecube.spv"));
+
+    let mut converted_map = CorrectionMap::default();
+    let converted_spv = storagecubepatch(&spv, &mut converted_map).unwrap();
+
+    // Both converted, the conversion adds no binding to mirror.
+    let mut other_map = converted_map.clone();
+    let (l, r) = mirrorpatch(
+        &converted_spv,
+        &mut converted_map.clone(),
+        &converted_spv,
+        &mut other_map,
+    )
+    .unwrap();
+    assert_eq!((l, r), (None, None));
+    assert_eq!(other_map, converted_map);
+
+    // Only one converted, the layouts cannot be mirrored.
+    let mut unconverted_map = CorrectionMap::default();
+    let conflicts =
+        mirrorpatch_conflicts(&[(&converted_spv, &converted_map), (&spv, &unconverted_map)]);
+    assert_eq!(
+        conflicts,
+        [0, 1]
+            .map(|binding| MirrorConflict {
+                set: 0,
+                binding,
+                correction_type: CorrectionType::ConvertStorageCube,
+            })
+            .to_vec()
+    );
+    assert!(
+        mirrorpatch(
+            &converted_spv,
+            &mut converted_map.clone(),
+            &spv,
+            &mut unconverted_map
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_mirrorpatch_missing_corrected_binding() {
+    let (vert_spv, mut vert_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test2.vert.spv"));
+    let (frag_spv, mut frag_map) =
+        split_for_mirrorpatch(include_bytes!("./mirrorpatch/test3.frag.spv"));
+
+    // `test3.frag` splits bindings 2 and 3, `test2.vert` only declares binding 2.
+    let (vert_spv, frag_out) =
+        mirrorpatch(&vert_spv, &mut vert_map, &frag_spv, &mut frag_map).unwrap();
+    assert!(frag_out.is_none());
+    let vert_spv = vert_spv.unwrap();
+    try_spv_to_wgsl(&vert_spv, DO_ALL);
+
+    // Binding 2 is still mirrored, and `u_other_b` still lands where it does in `test3.frag`.
+    let named_bindings = |spv| {
+        reflect(spv)
+            .unwrap()
+            .resources
+            .into_iter()
+            .map(|resource| (resource.binding, resource.name))
+            .collect::<Vec<_>>()
+    };
+    let frag_bindings = named_bindings(&frag_spv);
+    let vert_bindings = named_bindings(&vert_spv);
+    assert_eq!(
+        vert_bindings,
+        vec![
+            (0, Some("u_regular_sampler".to_owned())),
+            (2, Some("u_mixed_texture".to_owned())),
+            (3, None),
+            (6, Some("u_other_b".to_owned())),
+        ]
+    );
+    assert!(
+        vert_bindings
+            .iter()
+            .all(|binding| frag_bindings.contains(binding))
+    );
+
+    // Only binding 1 is missing, it was never corrected.
+    let mut vert_map = vert_map.clone();
+    vert_map
+        .sets
+        .as_mut()
+        .unwrap()
+        .get_mut(&0)
+        .unwrap()
+        .bindings
+        .insert(1, CorrectionBinding::default());
+    assert_eq!(vert_map, frag_map);
+
+    assert_eq!(
+        mirrorpatch(&vert_spv, &mut vert_map, &frag_spv, &mut frag_map).unwrap(),
+        (None, None)
+    );
+}
@@ -0,0 +1,136 @@
+use super::*;
+use crate::{ReflectedType, ResourceKind, reflect};
+
+fn reflect_bytes(spv: &[u8]) -> crate::ModuleReflection {
+    reflect(&u8_slice_to_u32_vec(spv)).unwrap()
+}
+
+fn kinds(reflection: &crate::ModuleReflection) -> Vec<(u32, u32, ResourceKind)> {
+    reflection
+        .resources
+        .iter()
+        .map(|resource| (resource.set, resource.binding, resource.kind))
+        .collect()
+}
+
+#[test]
+fn reflect_unused() {
+    let reflection = reflect_bytes(include_bytes!("./pruneunused/unused.spv"));
+
+    assert_eq!(
+        kinds(&reflection),
+        vec![
+            (0, 0, ResourceKind::UniformBuffer),
+            (0, 1, ResourceKind::UniformBuffer),
+            (0, 2, ResourceKind::Texture),
+            (0, 3, ResourceKind::Sampler),
+            (1, 0, ResourceKind::StorageBuffer),
+            (1, 1, ResourceKind::StorageTexture),
+        ]
+    );
+    let unused_image = &reflection.resources[5];
+    assert_eq!(unused_image.name.as_deref(), Some("u_unused_image"));
+    assert!(unused_image.non_writable && !unused_image.non_readable);
+
+    let [push_constants] = reflection.push_constants.as_slice() else {
+        panic!("Expected one push constant block");
+    };
+    assert_eq!(push_constants.name.as_deref(), Some("u_immediates"));
+    assert_eq!(push_constants.size, 4);
+
+    let [entry_point] = reflection.entry_points.as_slice() else {
+        panic!("Expected one entry point");
+    };
+    assert_eq!(entry_point.execution_model, 4);
+    let locations = |variables: &[crate::InterfaceVariableReflection]| {
+        variables
+            .iter()
+            .map(|variable| (variable.name.clone().unwrap(), variable.location))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        locations(&entry_point.inputs),
+        vec![("v_uv".to_owned(), 0), ("v_unused_normal".to_owned(), 1)]
+    );
+    assert_eq!(
+        locations(&entry_point.outputs),
+        vec![("o_color".to_owned(), 0)]
+    );
+}
+
+#[test]
+fn reflect_push_constant_layout() {
+    let reflection = reflect_bytes(include_bytes!("./immediatespatch/nested_struct.spv"));
+
+    let [push_constants] = reflection.push_constants.as_slice() else {
+        panic!("Expected one push constant block");
+    };
+    assert_eq!(push_constants.size, 52);
+    let offsets = push_constants
+        .members
+        .iter()
+        .map(|member| member.offset)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, vec![Some(0), Some(48)]);
+
+    let ReflectedType::Struct { name, members } = &push_constants.members[0].ty else {
+        panic!("Expected `outer` to be a struct");
+    };
+    assert_eq!(name.as_deref(), Some("Level1"));
+    assert_eq!(members[1].offset, Some(32));
+    assert_eq!(members[1].size, Some(8));
+}
+
+#[test]
+fn reflect_workgroup_size() {
+    let reflection = reflect_bytes(include_bytes!("./texelbufferpatch/texelbuffer.spv"));
+    assert_eq!(reflection.entry_points[0].workgroup_size, Some([64, 1, 1]));
+    assert_eq!(
+        kinds(&reflection),
+        vec![
+            (0, 0, ResourceKind::UniformTexelBuffer),
+            (0, 1, ResourceKind::StorageTexelBuffer),
+            (0, 2, ResourceKind::StorageTexelBuffer),
+            (0, 3, ResourceKind::StorageTexelBuffer),
+        ]
+    );
+
+    // `local_size_x_id` defaults to 1
+    let reflection = reflect_bytes(include_bytes!("./specconstantpatch/spec.spv"));
+    assert_eq!(reflection.entry_points[0].workgroup_size, Some([1, 1, 1]));
+
+    // Without any execution mode, the builtin alone sets it
+    let reflection = reflect_bytes(include_bytes!("./reflect/workgroup_size_builtin.spv"));
+    assert_eq!(reflection.entry_points[0].workgroup_size, Some([8, 4, 1]));
+}
+
+#[test]
+fn reflect_multiple_entry_points() {
+    let reflection = reflect_bytes(include_bytes!("./splitentrypoints/multi.spv"));
+
+    let entry_points = reflection
+        .entry_points
+        .iter()
+        .map(|entry_point| {
+            let locations = |variables: &[crate::InterfaceVariableReflection]| {
+                variables
+                    .iter()
+                    .map(|variable| variable.location)
+                    .collect::<Vec<_>>()
+            };
+            (
+                entry_point.name.as_str(),
+                entry_point.execution_model,
+                locations(&entry_point.inputs),
+                locations(&entry_point.outputs),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entry_points,
+        vec![
+            ("vs_main", 0, vec![0, 1], vec![0]),
+            ("ps_main", 4, vec![0], vec![0]),
+        ]
+    );
+}
@@ -0,0 +1,138 @@
+use super::*;
+use crate::{
+    ScalarLayoutReport, UniformCopy, UniformRelayout, WordStorageBuffer, scalarlayoutpatch,
+};
+
+// Every word index into the runtime array of a word buffer as `(stride, offset)`, for indices of
+// the form `i * stride + offset`.
+fn word_indices(spv: &[u32], buffer_name: &str) -> Vec<(u32, u32)> {
+    use naga::{BinaryOperator, Expression, Literal};
+
+    let module =
+        front::spv::parse_u8_slice(&u32_slice_to_u8_vec(spv), &Default::default()).unwrap();
+    let constant = |expression: &Expression| match *expression {
+        Expression::Literal(Literal::U32(value)) => Some(value),
+        Expression::Literal(Literal::I32(value)) => Some(value as u32),
+        _ => None,
+    };
+    let mut indices = vec![];
+    for function in module
+        .functions
+        .iter()
+        .map(|(_, function)| function)
+        .chain(module.entry_points.iter().map(|ep| &ep.function))
+    {
+        let expressions = &function.expressions;
+        let value = |handle| match expressions[handle] {
+            Expression::Constant(c) => {
+                constant(&module.global_expressions[module.constants[c].init])
+            }
+            ref expression => constant(expression),
+        };
+        // `i * stride + offset`, or `i * stride` for the first word
+        let linear = |handle| match expressions[handle] {
+            Expression::Binary {
+                op: BinaryOperator::Add,
+                left,
+                right,
+            } => match expressions[left] {
+                Expression::Binary {
+                    op: BinaryOperator::Multiply,
+                    right: stride,
+                    ..
+                } => Some((value(stride)?, value(right)?)),
+                _ => None,
+            },
+            Expression::Binary {
+                op: BinaryOperator::Multiply,
+                right: stride,
+                ..
+            } => Some((value(stride)?, 0)),
+            _ => None,
+        };
+        for (_, expression) in expressions.iter() {
+            let Expression::Access { base, index } = *expression else {
+                continue;
+            };
+            let Expression::AccessIndex { base, index: 0 } = expressions[base] else {
+                continue;
+            };
+            let Expression::GlobalVariable(global) = expressions[base] else {
+                continue;
+            };
+            if module.global_variables[global].name.as_deref() == Some(buffer_name) {
+                indices.push(linear(index).unwrap());
+            }
+        }
+    }
+    indices.sort();
+    indices.dedup();
+    indices
+}
+
+#[test]
+fn scalarlayoutpatch_particles() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./scalarlayoutpatch/particles.spv"));
+    let mut report = ScalarLayoutReport::default();
+    let out_spv = scalarlayoutpatch(&spv, &mut report).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    // `position` at offset 4 and a stride of 28 are not valid WGSL
+    assert_eq!(
+        report.word_buffers,
+        vec![
+            WordStorageBuffer { set: 0, binding: 1 },
+            WordStorageBuffer { set: 0, binding: 2 },
+        ]
+    );
+    // `mass` is word 0, `position` words 1 to 3, and `velocity` words 4 to 6 of each particle
+    assert_eq!(
+        word_indices(&out_spv, "particles"),
+        (0..7).map(|word| (7, word)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        word_indices(&out_spv, "forces"),
+        (0..3).map(|word| (3, word)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        report.uniforms.relaid_out,
+        vec![UniformRelayout {
+            set: 0,
+            binding: 0,
+            size: 32,
+            copies: vec![
+                UniformCopy {
+                    original_offset: 0,
+                    offset: 0,
+                    size: 4,
+                },
+                UniformCopy {
+                    original_offset: 4,
+                    offset: 16,
+                    size: 12,
+                },
+            ],
+        }]
+    );
+}
+
+#[test]
+fn scalarlayoutpatch_std430_unchanged() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./specconstantpatch/spec.spv"));
+    let mut report = ScalarLayoutReport::default();
+    let out_spv = scalarlayoutpatch(&spv, &mut report).unwrap();
+    assert_eq!(out_spv, spv);
+    assert_eq!(report, ScalarLayoutReport::default());
+}
+
+#[test]
+fn scalarlayoutpatch_atomic() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./scalarlayoutpatch/atomic.spv"));
+    let mut report = ScalarLayoutReport::default();
+    let out_spv = scalarlayoutpatch(&spv, &mut report).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+    assert_eq!(
+        report.word_buffers,
+        vec![WordStorageBuffer { set: 0, binding: 0 }]
+    );
+}
@@ -0,0 +1,139 @@
+use super::*;
+use crate::{
+    UniformCopy, UniformLayoutReport, UniformRelayout, repack_uniform, uniformlayoutpatch,
+};
+
+#[test]
+fn uniformlayoutpatch_dx_layout() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./uniformlayoutpatch/dx_layout.spv"));
+    let mut report = UniformLayoutReport::default();
+    let out_spv = uniformlayoutpatch(&spv, &mut report).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    // `Other` is already valid WGSL and keeps its layout.
+    assert_eq!(
+        report.relaid_out,
+        vec![UniformRelayout {
+            set: 0,
+            binding: 0,
+            size: 80,
+            copies: vec![
+                UniformCopy {
+                    original_offset: 0,
+                    offset: 0,
+                    size: 8,
+                },
+                UniformCopy {
+                    original_offset: 16,
+                    offset: 16,
+                    size: 4,
+                },
+                UniformCopy {
+                    original_offset: 32,
+                    offset: 32,
+                    size: 4,
+                },
+                // `bias` no longer shares the last element of `weights`
+                UniformCopy {
+                    original_offset: 36,
+                    offset: 48,
+                    size: 4,
+                },
+                UniformCopy {
+                    original_offset: 48,
+                    offset: 64,
+                    size: 16,
+                },
+            ],
+        }]
+    );
+
+    let original_data = (1..=16)
+        .flat_map(|i| (i as f32).to_ne_bytes())
+        .collect::<Vec<_>>();
+    let data = repack_uniform(&report.relaid_out[0], &original_data)
+        .unwrap()
+        .chunks_exact(4)
+        .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    #[rustfmt::skip]
+    assert_eq!(
+        data,
+        vec![
+            1.0, 2.0, 0.0, 0.0,
+            5.0, 0.0, 0.0, 0.0,
+            9.0, 0.0, 0.0, 0.0,
+            10.0, 0.0, 0.0, 0.0,
+            13.0, 14.0, 15.0, 16.0,
+        ]
+    );
+    assert!(repack_uniform(&report.relaid_out[0], &original_data[..60]).is_err());
+}
+
+#[test]
+fn uniformlayoutpatch_std140_unchanged() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./immediatespatch/shared_set.spv"));
+    let mut report = UniformLayoutReport::default();
+    let out_spv = uniformlayoutpatch(&spv, &mut report).unwrap();
+    assert_eq!(out_spv, spv);
+    assert!(report.relaid_out.is_empty());
+}
+
+#[test]
+fn uniformlayoutpatch_matcx2() {
+    let spv = u8_slice_to_u32_vec(include_bytes!("./uniformlayoutpatch/matcx2.spv"));
+    let mut report = UniformLayoutReport::default();
+    let out_spv = uniformlayoutpatch(&spv, &mut report).unwrap();
+    try_spv_to_wgsl(&out_spv, DO_ALL);
+
+    // The columns of `rotate` keep their offsets, `scale` no longer follows the last one
+    assert_eq!(
+        report.relaid_out,
+        vec![UniformRelayout {
+            set: 0,
+            binding: 0,
+            size: 64,
+            copies: vec![
+                UniformCopy {
+                    original_offset: 0,
+                    offset: 0,
+                    size: 8,
+                },
+                UniformCopy {
+                    original_offset: 16,
+                    offset: 16,
+                    size: 8,
+                },
+                UniformCopy {
+                    original_offset: 32,
+                    offset: 32,
+                    size: 8,
+                },
+                UniformCopy {
+                    original_offset: 40,
+                    offset: 48,
+                    size: 4,
+                },
+            ],
+        }]
+    );
+
+    let original_data = (1..=11)
+        .flat_map(|i| (i as f32).to_ne_bytes())
+        .collect::<Vec<_>>();
+    let data = repack_uniform(&report.relaid_out[0], &original_data)
+        .unwrap()
+        .chunks_exact(4)
+        .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    #[rustfmt::skip]
+    assert_eq!(
+        data,
+        vec![
+            1.0, 2.0, 0.0, 0.0,
+            5.0, 6.0, 0.0, 0.0,
+            9.0, 10.0, 0.0, 0.0,
+            11.0, 0.0, 0.0, 0.0,
+        ]
+    );
+}
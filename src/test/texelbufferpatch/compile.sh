@@ -0,0 +1,5 @@
+set -e
+
+glslc -O0 texelbuffer.comp -o texelbuffer.spv
+glslc -O0 texelbuffer_skinning.vert -o texelbuffer_skinning.spv
+spirv-as texelbuffer_formats.spvasm -o texelbuffer_formats.spv
@@ -0,0 +1,21 @@
+#version 450
+
+layout(location = 0) in vec3 a_position;
+layout(location = 1) in uvec4 a_joints;
+layout(location = 2) in vec4 a_weights;
+
+layout(set = 0, binding = 0) uniform samplerBuffer u_bones;
+
+mat4 bone(uint joint) {
+    int base = int(joint) * 4;
+    return mat4(
+        texelFetch(u_bones, base),
+        texelFetch(u_bones, base + 1),
+        texelFetch(u_bones, base + 2),
+        texelFetch(u_bones, base + 3));
+}
+
+void main() {
+    mat4 skin = a_weights.x * bone(a_joints.x) + a_weights.y * bone(a_joints.y);
+    gl_Position = skin * vec4(a_position, 1.0);
+}
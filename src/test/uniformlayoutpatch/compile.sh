@@ -0,0 +1,4 @@
+set -e
+
+dxc -spirv -T ps_6_0 -E main -fvk-use-dx-layout dx_layout.hlsl -Fo dx_layout.spv
+spirv-as matcx2.spvasm -o matcx2.spv
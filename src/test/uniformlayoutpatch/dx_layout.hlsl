@@ -0,0 +1,19 @@
+// Compiled with `-fvk-use-dx-layout`, `bias` is packed into the last element of `weights` and
+// `tint` does not cross a 16 byte boundary.
+cbuffer Params : register(b0, space0) {
+    float2 uv_offset; // Offset 0
+    float weights[2]; // Offset 16, ArrayStride 16
+    float bias;       // Offset 36
+    float3 tint;      // Offset 48
+    float alpha;      // Offset 60
+};
+
+// Already laid out the same as std140.
+cbuffer Other : register(b1, space0) {
+    float4 scale;
+};
+
+float4 main() : SV_Target0 {
+    float3 color = tint * (weights[1] + bias) + float3(uv_offset, 0.0);
+    return float4(color, alpha) * scale;
+}
@@ -0,0 +1,4 @@
+set -e
+
+glslc -O0 widen.comp -o widen.spv
+glslc -O0 widen_vertices.frag -o widen_vertices.spv
@@ -0,0 +1,26 @@
+#version 450
+#extension GL_EXT_shader_16bit_storage : require
+#extension GL_EXT_shader_8bit_storage : require
+
+struct Vertex {
+    f16vec4 position;
+    u8vec4 color;
+    uint16_t bone;
+    uint16_t weight;
+};
+
+layout(set = 0, binding = 0) readonly buffer Vertices {
+    Vertex vertices[];
+} s_vertices;
+
+layout(location = 0) flat in int i_index;
+
+layout(location = 0) out vec4 o_color;
+
+void main() {
+    int index = min(i_index, s_vertices.vertices.length() - 1);
+    vec4 position = vec4(s_vertices.vertices[index].position);
+    vec4 color = vec4(s_vertices.vertices[index].color) / 255.0;
+    float weight = float(s_vertices.vertices[index].weight) / 65535.0;
+    o_color = color * weight + position * float(s_vertices.vertices[index].bone);
+}
@@ -0,0 +1,713 @@
+use super::*;
+
+fn inc(ib: &mut u32) -> u32 {
+    *ib += 1;
+    *ib - 1
+}
+
+mod format;
+
+use format::*;
+
+/// How the texels of a texel buffer are laid out inside of the storage buffer that replaces it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TexelBufferFormat {
+    /// One `f32` per texel, stored as the bits of a `u32`.
+    R32Float,
+    /// One `vec4<f32>` per texel.
+    #[default]
+    Rgba32Float,
+    /// Four normalized `u8` per texel, packed into a `u32`.
+    Rgba8Unorm,
+    /// One `u32` per texel.
+    R32Uint,
+}
+
+impl TexelBufferFormat {
+    fn from_image_format(image_format: u32) -> Option<Self> {
+        match image_format {
+            SPV_IMAGE_FORMAT_R32F => Some(TexelBufferFormat::R32Float),
+            SPV_IMAGE_FORMAT_RGBA32F => Some(TexelBufferFormat::Rgba32Float),
+            SPV_IMAGE_FORMAT_RGBA8 => Some(TexelBufferFormat::Rgba8Unorm),
+            SPV_IMAGE_FORMAT_R32UI => Some(TexelBufferFormat::R32Uint),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TexelOperation {
+    Fetch(usize),
+    Read(usize),
+    Write(usize),
+    QuerySize(usize),
+}
+
+impl TexelOperation {
+    fn get(&self) -> usize {
+        match self {
+            TexelOperation::Fetch(idx)
+            | TexelOperation::Read(idx)
+            | TexelOperation::Write(idx)
+            | TexelOperation::QuerySize(idx) => *idx,
+        }
+    }
+
+    fn image_offset(&self) -> usize {
+        match self {
+            TexelOperation::Fetch(_) | TexelOperation::Read(_) | TexelOperation::QuerySize(_) => 3,
+            TexelOperation::Write(_) => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TexelBuffer {
+    variable_id: u32,
+    format: TexelBufferFormat,
+    read_only: bool,
+}
+
+struct TexelBufferContext<'a> {
+    spv: &'a [u32],
+    op_type_float_idxs: &'a [usize],
+    op_type_vector_idxs: &'a [usize],
+    op_constant_idxs: &'a [usize],
+    op_ext_inst_import_idxs: &'a [usize],
+    instruction_bound: u32,
+    header: Vec<u32>,
+    ext_inst_import_header: Vec<u32>,
+    uint_id: u32,
+    float_id: Option<u32>,
+    v4float_id: Option<u32>,
+    glsl_std_id: Option<u32>,
+    constants: HashMap<(u32, u32), u32>,
+}
+
+impl TexelBufferContext<'_> {
+    fn inc(&mut self) -> u32 {
+        inc(&mut self.instruction_bound)
+    }
+
+    fn constant(&mut self, type_id: u32, value: u32) -> u32 {
+        if let Some(&id) = self.constants.get(&(type_id, value)) {
+            return id;
+        }
+        let id = self
+            .op_constant_idxs
+            .iter()
+            .find_map(|&c_idx| {
+                (hiword(self.spv[c_idx]) == 4
+                    && self.spv[c_idx + 1] == type_id
+                    && self.spv[c_idx + 3] == value)
+                    .then_some(self.spv[c_idx + 2])
+            })
+            .unwrap_or_else(|| {
+                let new_id = inc(&mut self.instruction_bound);
+                self.header.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                    type_id,
+                    new_id,
+                    value,
+                ]);
+                new_id
+            });
+        self.constants.insert((type_id, value), id);
+        id
+    }
+
+    fn uint(&mut self, value: u32) -> u32 {
+        self.constant(self.uint_id, value)
+    }
+
+    fn float(&mut self, value: f32) -> u32 {
+        let float_id = self.float_type();
+        self.constant(float_id, value.to_bits())
+    }
+
+    fn float_type(&mut self) -> u32 {
+        if let Some(id) = self.float_id {
+            return id;
+        }
+        let id = ensure_type_float(
+            self.spv,
+            self.op_type_float_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            32,
+        );
+        self.float_id = Some(id);
+        id
+    }
+
+    fn v4float_type(&mut self) -> u32 {
+        if let Some(id) = self.v4float_id {
+            return id;
+        }
+        let float_id = self.float_type();
+        let id = ensure_type_vector(
+            self.spv,
+            self.op_type_vector_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            float_id,
+            4,
+        );
+        self.v4float_id = Some(id);
+        id
+    }
+
+    fn glsl_std(&mut self) -> u32 {
+        if let Some(id) = self.glsl_std_id {
+            return id;
+        }
+        let id = ensure_ext_inst_import(
+            self.spv,
+            self.op_ext_inst_import_idxs,
+            &mut self.instruction_bound,
+            &mut self.ext_inst_import_header,
+            |s| s.starts_with("GLSL.std."),
+            "GLSL.std.450",
+        );
+        self.glsl_std_id = Some(id);
+        id
+    }
+}
+
+const TEXEL_BUFFER_CAPABILITIES: [u32; 2] =
+    [SPV_CAPABILITY_SAMPLED_BUFFER, SPV_CAPABILITY_IMAGE_BUFFER];
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+///
+/// Texel buffers that do not declare a format, such as `samplerBuffer`, are read as the format
+/// `formats` maps their `(set, binding)` to, or as `default_format` if they are missing from it.
+/// `usamplerBuffer` and `uimageBuffer` are always read as [`TexelBufferFormat::R32Uint`].
+///
+/// Fails on signed integer texel buffers, formats other than [`TexelBufferFormat`], image atomics,
+/// and texel buffers passed into functions.
+pub fn texelbufferpatch(
+    in_spv: &[u32],
+    formats: &HashMap<(u32, u32), TexelBufferFormat>,
+    default_format: TexelBufferFormat,
+    corrections: &mut CorrectionMap,
+) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_capability_idxs = vec![];
+    let mut op_extension_idxs = vec![];
+    let mut op_ext_inst_import_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_image_idxs = vec![];
+    let mut op_type_sampled_image_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_runtime_array_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_constant_composite_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_function_parameter_idxs = vec![];
+    let mut op_load_idxs = vec![];
+    let mut op_image_idxs = vec![];
+    let mut op_image_texel_pointer_idxs = vec![];
+
+    let mut texel_operation_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        match instruction {
+            SPV_INSTRUCTION_OP_CAPABILITY => op_capability_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXTENSION => op_extension_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXT_INST_IMPORT => op_ext_inst_import_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_IMAGE => op_type_image_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_SAMPLED_IMAGE => op_type_sampled_image_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => op_type_runtime_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE => op_constant_composite_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION_PARAMETER => op_function_parameter_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_LOAD => op_load_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_IMAGE => op_image_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_IMAGE_TEXEL_POINTER => op_image_texel_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_IMAGE_FETCH => {
+                texel_operation_idxs.push(TexelOperation::Fetch(spv_idx))
+            }
+            SPV_INSTRUCTION_OP_IMAGE_READ => {
+                texel_operation_idxs.push(TexelOperation::Read(spv_idx))
+            }
+            SPV_INSTRUCTION_OP_IMAGE_WRITE => {
+                texel_operation_idxs.push(TexelOperation::Write(spv_idx))
+            }
+            SPV_INSTRUCTION_OP_IMAGE_QUERY_SIZE => {
+                texel_operation_idxs.push(TexelOperation::QuerySize(spv_idx))
+            }
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    // 2. Find OpTypeImage with Dim Buffer and decide on their formats, [None] if the variable's
+    //    binding decides
+    let mut buffer_image_formats = HashMap::new();
+    for &idx in &op_type_image_idxs {
+        let result_id = spv[idx + 1];
+        let sampled_type_id = spv[idx + 2];
+        let dim = spv[idx + 3];
+        let image_format = spv[idx + 8];
+
+        if dim != SPV_DIMENSION_BUFFER {
+            continue;
+        }
+
+        let signedness = op_type_int_idxs
+            .iter()
+            .find_map(|&t_idx| (spv[t_idx + 1] == sampled_type_id).then_some(spv[t_idx + 3]));
+        let format = match (
+            TexelBufferFormat::from_image_format(image_format),
+            signedness,
+        ) {
+            (_, Some(SPV_SIGNEDNESS_SIGNED)) => return Err(()),
+            (Some(format), _) => Some(format),
+            (None, Some(_)) if image_format == SPV_IMAGE_FORMAT_UNKNOWN => {
+                Some(TexelBufferFormat::R32Uint)
+            }
+            (None, None) if image_format == SPV_IMAGE_FORMAT_UNKNOWN => None,
+            (None, _) => return Err(()),
+        };
+        if format
+            .is_some_and(|format| (format == TexelBufferFormat::R32Uint) != signedness.is_some())
+        {
+            return Err(());
+        }
+
+        // 1: sampling, 2: read/write
+        let sampled = spv[idx + 7];
+        buffer_image_formats.insert(result_id, (format, sampled == 1));
+    }
+
+    if buffer_image_formats.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    // 3. Find OpTypeSampledImage -> OpTypePointer -> OpVariable
+    let sampled_image_type_ids = op_type_sampled_image_idxs
+        .iter()
+        .filter_map(|&idx| {
+            buffer_image_formats
+                .get(&spv[idx + 2])
+                .map(|&format| (spv[idx + 1], format))
+        })
+        .collect::<HashMap<_, _>>();
+    let buffer_type_formats = buffer_image_formats
+        .iter()
+        .chain(sampled_image_type_ids.iter())
+        .map(|(&id, &format)| (id, format))
+        .collect::<HashMap<_, _>>();
+    let type_pointer_formats = op_type_pointer_idxs
+        .iter()
+        .filter_map(|&idx| {
+            buffer_type_formats
+                .get(&spv[idx + 3])
+                .map(|&format| (spv[idx + 1], format))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Texel buffers cannot be passed around as function parameters
+    if op_function_parameter_idxs.iter().any(|&idx| {
+        let result_type_id = spv[idx + 1];
+        buffer_type_formats.contains_key(&result_type_id)
+            || type_pointer_formats.contains_key(&result_type_id)
+    }) {
+        return Err(());
+    }
+
+    let has_decoration = |target_id, decoration| {
+        op_decorate_idxs
+            .iter()
+            .any(|&d_idx| spv[d_idx + 1] == target_id && spv[d_idx + 2] == decoration)
+    };
+
+    let decoration_of = |target_id, decoration| {
+        op_decorate_idxs.iter().find_map(|&d_idx| {
+            (spv[d_idx + 1] == target_id && spv[d_idx + 2] == decoration).then_some(spv[d_idx + 3])
+        })
+    };
+
+    let mut texel_buffers = vec![];
+    for &idx in &op_variable_idxs {
+        let Some(&(format, sampled)) = type_pointer_formats.get(&spv[idx + 1]) else {
+            continue;
+        };
+        let variable_id = spv[idx + 2];
+        let format = match format {
+            Some(format) => format,
+            None => {
+                let format = decoration_of(variable_id, SPV_DECORATION_DESCRIPTOR_SET)
+                    .zip(decoration_of(variable_id, SPV_DECORATION_BINDING))
+                    .and_then(|set_binding| formats.get(&set_binding).copied())
+                    .unwrap_or(default_format);
+                // Float texel buffers cannot be read as integers
+                if format == TexelBufferFormat::R32Uint {
+                    return Err(());
+                }
+                format
+            }
+        };
+        texel_buffers.push(TexelBuffer {
+            variable_id,
+            format,
+            read_only: sampled || has_decoration(variable_id, SPV_DECORATION_NON_WRITABLE),
+        });
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+    }
+
+    if op_image_texel_pointer_idxs.iter().any(|&idx| {
+        texel_buffers
+            .iter()
+            .any(|buffer| buffer.variable_id == spv[idx + 3])
+    }) {
+        return Err(());
+    }
+
+    // 4. Find OpLoad -> OpImage of every texel buffer
+    let mut loaded_buffers = HashMap::new();
+    for &idx in &op_load_idxs {
+        let result_id = spv[idx + 2];
+        let pointer_id = spv[idx + 3];
+        if let Some(&buffer) = texel_buffers
+            .iter()
+            .find(|buffer| buffer.variable_id == pointer_id)
+        {
+            loaded_buffers.insert(result_id, buffer);
+            for i in 0..hiword(spv[idx]) as usize {
+                new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        }
+    }
+    for &idx in &op_image_idxs {
+        let result_id = spv[idx + 2];
+        let sampled_image_id = spv[idx + 3];
+        if let Some(&buffer) = loaded_buffers.get(&sampled_image_id) {
+            loaded_buffers.insert(result_id, buffer);
+            for i in 0..hiword(spv[idx]) as usize {
+                new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        }
+    }
+
+    // 5. Declare the storage buffers, reusing the ids of the texel buffer variables
+    let mut header = vec![];
+    let uint_id = ensure_type_int(
+        &spv,
+        &op_type_int_idxs,
+        &mut instruction_bound,
+        &mut header,
+        32,
+        SPV_SIGNEDNESS_UNSIGNED,
+    );
+    let mut ctx = TexelBufferContext {
+        spv: &spv,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_constant_idxs: &op_constant_idxs,
+        op_ext_inst_import_idxs: &op_ext_inst_import_idxs,
+        instruction_bound,
+        header,
+        ext_inst_import_header: vec![],
+        uint_id,
+        float_id: None,
+        v4float_id: None,
+        glsl_std_id: None,
+        constants: HashMap::new(),
+    };
+    let uint_0 = ctx.uint(0);
+
+    let mut decorations = vec![];
+    let mut variables = vec![];
+    let mut element_types = HashMap::new();
+    for buffer in &texel_buffers {
+        let element_type_id = match buffer.format {
+            TexelBufferFormat::Rgba32Float => ctx.v4float_type(),
+            _ => ctx.uint_id,
+        };
+        let (runtime_array_id, _) = *element_types.entry(element_type_id).or_insert_with(|| {
+            let runtime_array_id = ctx.inc();
+            let element_pointer_id = ensure_type_pointer(
+                &spv,
+                &op_type_pointer_idxs,
+                &mut ctx.instruction_bound,
+                &mut ctx.header,
+                SPV_STORAGE_CLASS_UNIFORM,
+                element_type_id,
+            );
+            let stride = if element_type_id == ctx.uint_id {
+                4
+            } else {
+                16
+            };
+            #[rustfmt::skip]
+            ctx.header.append(&mut vec![
+                encode_word(3, SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY),
+                    runtime_array_id, element_type_id,
+            ]);
+            #[rustfmt::skip]
+            decorations.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                    runtime_array_id, SPV_DECORATION_ARRAY_STRIDE, stride,
+            ]);
+            (runtime_array_id, element_pointer_id)
+        });
+
+        let struct_id = ctx.inc();
+        let struct_pointer_id = ctx.inc();
+        #[rustfmt::skip]
+        variables.append(&mut vec![
+            encode_word(3, SPV_INSTRUCTION_OP_TYPE_STRUCT),
+                struct_id, runtime_array_id,
+            encode_word(4, SPV_INSTRUCTION_OP_TYPE_POINTER),
+                struct_pointer_id, SPV_STORAGE_CLASS_UNIFORM, struct_id,
+            encode_word(4, SPV_INSTRUCTION_OP_VARIABLE),
+                struct_pointer_id, buffer.variable_id, SPV_STORAGE_CLASS_UNIFORM,
+        ]);
+        #[rustfmt::skip]
+        decorations.append(&mut vec![
+            encode_word(3, SPV_INSTRUCTION_OP_DECORATE),
+                struct_id, SPV_DECORATION_BUFFER_BLOCK,
+            encode_word(5, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                struct_id, 0, SPV_DECORATION_OFFSET, 0,
+        ]);
+        if buffer.read_only {
+            #[rustfmt::skip]
+            decorations.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                    struct_id, 0, SPV_DECORATION_NON_WRITABLE,
+            ]);
+        }
+    }
+
+    // Access now lives on the block member
+    for &d_idx in &op_decorate_idxs {
+        let target_id = spv[d_idx + 1];
+        let decoration = spv[d_idx + 2];
+        if (decoration == SPV_DECORATION_NON_WRITABLE || decoration == SPV_DECORATION_NON_READABLE)
+            && texel_buffers
+                .iter()
+                .any(|buffer| buffer.variable_id == target_id)
+        {
+            for i in 0..hiword(spv[d_idx]) as usize {
+                new_spv[d_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        }
+    }
+
+    // 6. Patch OpImage{Fetch, Read, Write, QuerySize} into buffer accesses
+    for operation in &texel_operation_idxs {
+        let op_idx = operation.get();
+        let op_word_count = hiword(spv[op_idx]) as usize;
+        let Some(buffer) = loaded_buffers.get(&spv[op_idx + operation.image_offset()]) else {
+            continue;
+        };
+
+        let element_type_id = match buffer.format {
+            TexelBufferFormat::Rgba32Float => ctx.v4float_type(),
+            _ => ctx.uint_id,
+        };
+        let element_pointer_type_id = element_types[&element_type_id].1;
+
+        let element_pointer = |ctx: &mut TexelBufferContext, coord_id| {
+            let element_pointer_id = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(6, SPV_INSTRUCTION_OP_ACCESS_CHAIN),
+                    element_pointer_type_id, element_pointer_id, buffer.variable_id, uint_0, coord_id,
+            ];
+            (element_pointer_id, spv)
+        };
+
+        let new_instructions = match operation {
+            // Image operands such as `Sample` have no meaning for buffers
+            TexelOperation::Fetch(_) | TexelOperation::Read(_) if op_word_count == 5 => {
+                let result_type_id = spv[op_idx + 1];
+                let result_id = spv[op_idx + 2];
+                let (element_pointer_id, mut instructions) =
+                    element_pointer(&mut ctx, spv[op_idx + 4]);
+                instructions.append(&mut texel_load_spv(
+                    &mut ctx,
+                    buffer.format,
+                    result_type_id,
+                    result_id,
+                    element_pointer_id,
+                ));
+                instructions
+            }
+            TexelOperation::Write(_) if op_word_count == 4 => {
+                let (element_pointer_id, mut instructions) =
+                    element_pointer(&mut ctx, spv[op_idx + 2]);
+                instructions.append(&mut texel_store_spv(
+                    &mut ctx,
+                    buffer.format,
+                    element_pointer_id,
+                    spv[op_idx + 3],
+                ));
+                instructions
+            }
+            TexelOperation::QuerySize(_) => {
+                //
+                //     %length = OpArrayLength %uint %variable 0
+                //     %result = OpBitcast %int %length
+                let result_type_id = spv[op_idx + 1];
+                let result_id = spv[op_idx + 2];
+                if result_type_id == ctx.uint_id {
+                    vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_ARRAY_LENGTH),
+                        ctx.uint_id,
+                        result_id,
+                        buffer.variable_id,
+                        0,
+                    ]
+                } else {
+                    let length_id = ctx.inc();
+                    #[rustfmt::skip]
+                    let instructions = vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_ARRAY_LENGTH),
+                            ctx.uint_id, length_id, buffer.variable_id, 0,
+                        encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                            result_type_id, result_id, length_id,
+                    ];
+                    instructions
+                }
+            }
+            _ => return Err(()),
+        };
+
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: op_idx,
+            instruction: new_instructions,
+        });
+        new_spv[op_idx..op_idx + op_word_count].fill(encode_word(1, SPV_INSTRUCTION_OP_NOP));
+    }
+
+    // 7. Remove the texel buffer types and capabilities
+    let removed_type_ids = buffer_type_formats
+        .keys()
+        .chain(type_pointer_formats.keys())
+        .copied()
+        .collect::<HashSet<_>>();
+    let removed_idxs = op_type_image_idxs
+        .iter()
+        .chain(op_type_sampled_image_idxs.iter())
+        .chain(op_type_pointer_idxs.iter())
+        .filter(|&&idx| removed_type_ids.contains(&spv[idx + 1]))
+        .chain(
+            op_capability_idxs
+                .iter()
+                .filter(|&&idx| TEXEL_BUFFER_CAPABILITIES.contains(&spv[idx + 1])),
+        );
+    for &idx in removed_idxs {
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+    }
+
+    // 8. Fill Correction Map
+    decorate(DecorateIn {
+        spv: &spv,
+        instruction_inserts: &mut vec![],
+        first_op_deocrate_idx: op_decorate_idxs.first().copied(),
+        op_decorate_idxs: &op_decorate_idxs,
+        affected_decorations: &texel_buffers
+            .iter()
+            .map(|buffer| AffectedDecoration {
+                original_res_id: buffer.variable_id,
+                new_res_ids: vec![buffer.variable_id],
+                correction_type: CorrectionType::ConvertTexelBuffer,
+            })
+            .collect::<Vec<_>>(),
+        corrections,
+    });
+
+    // 9. Insert New Instructions
+    let header_position = last_of_indices!(
+        op_type_bool_idxs,
+        op_type_int_idxs,
+        op_type_float_idxs,
+        op_type_vector_idxs,
+        op_type_matrix_idxs,
+        op_type_image_idxs,
+        op_type_sampled_image_idxs,
+        op_type_array_idxs,
+        op_type_runtime_array_idxs,
+        op_type_struct_idxs,
+        op_type_pointer_idxs,
+        op_constant_idxs,
+        op_constant_composite_idxs
+    );
+    ctx.header.append(&mut variables);
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: header_position.unwrap(),
+        instruction: ctx.header,
+    });
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: last_of_indices!(op_decorate_idxs, op_member_decorate_idxs)
+            .expect("Texel buffer has no OpDecorate (missing Binding decoration?)"),
+        instruction: decorations,
+    });
+    instruction_inserts.insert(
+        0,
+        InstructionInsert {
+            previous_spv_idx: last_of_indices!(
+                op_capability_idxs,
+                op_extension_idxs,
+                op_ext_inst_import_idxs
+            )
+            .expect("Module has no OpCapability?"),
+            instruction: ctx.ext_inst_import_header,
+        },
+    );
+    let instruction_bound = ctx.instruction_bound;
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 10. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 11. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
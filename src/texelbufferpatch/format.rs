@@ -0,0 +1,150 @@
+use super::*;
+
+// Load the element at `pointer` and expand it into the `vec4` texel that the image instruction returned.
+pub(super) fn texel_load_spv(
+    ctx: &mut TexelBufferContext,
+    format: TexelBufferFormat,
+    result_type_id: u32,
+    result_id: u32,
+    pointer: u32,
+) -> Vec<u32> {
+    //
+    //  R32Float:
+    //       %bits = OpLoad %uint %pointer
+    //      %value = OpBitcast %float %bits
+    //     %result = OpCompositeConstruct %v4float %value %float_0 %float_0 %float_1
+    //
+    //  Rgba32Float:
+    //     %result = OpLoad %v4float %pointer
+    //
+    //  Rgba8Unorm:
+    //       %bits = OpLoad %uint %pointer
+    //     %result = OpExtInst %v4float %glsl_std UnpackUnorm4x8 %bits
+    //
+    //  R32Uint:
+    //      %value = OpLoad %uint %pointer
+    //     %result = OpCompositeConstruct %v4uint %value %uint_0 %uint_0 %uint_1
+
+    let uint_id = ctx.uint_id;
+    match format {
+        TexelBufferFormat::R32Float => {
+            let float_id = ctx.float_type();
+            let float_0 = ctx.float(0.0);
+            let float_1 = ctx.float(1.0);
+            let bits = ctx.inc();
+            let value = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                    uint_id, bits, pointer,
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                    float_id, value, bits,
+                encode_word(7, SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+                    result_type_id, result_id, value, float_0, float_0, float_1,
+            ];
+            spv
+        }
+        TexelBufferFormat::Rgba32Float => vec![
+            encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+            result_type_id,
+            result_id,
+            pointer,
+        ],
+        TexelBufferFormat::Rgba8Unorm => {
+            let glsl_std_id = ctx.glsl_std();
+            let bits = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                    uint_id, bits, pointer,
+                encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                    result_type_id, result_id, glsl_std_id, SPV_GLSL_STD_INSTRUCTION_UNPACK_UNORM_4X8, bits,
+            ];
+            spv
+        }
+        TexelBufferFormat::R32Uint => {
+            let uint_0 = ctx.uint(0);
+            let uint_1 = ctx.uint(1);
+            let value = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                    uint_id, value, pointer,
+                encode_word(7, SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+                    result_type_id, result_id, value, uint_0, uint_0, uint_1,
+            ];
+            spv
+        }
+    }
+}
+
+// Pack the `vec4` `texel` of an image write into an element and store it at `pointer`.
+// Components that the format does not have are dropped.
+pub(super) fn texel_store_spv(
+    ctx: &mut TexelBufferContext,
+    format: TexelBufferFormat,
+    pointer: u32,
+    texel: u32,
+) -> Vec<u32> {
+    //
+    //  R32Float:
+    //      %value = OpCompositeExtract %float %texel 0
+    //       %bits = OpBitcast %uint %value
+    //               OpStore %pointer %bits
+    //
+    //  Rgba32Float:
+    //               OpStore %pointer %texel
+    //
+    //  Rgba8Unorm:
+    //       %bits = OpExtInst %uint %glsl_std PackUnorm4x8 %texel
+    //               OpStore %pointer %bits
+    //
+    //  R32Uint:
+    //      %value = OpCompositeExtract %uint %texel 0
+    //               OpStore %pointer %value
+
+    let uint_id = ctx.uint_id;
+    match format {
+        TexelBufferFormat::R32Float => {
+            let float_id = ctx.float_type();
+            let value = ctx.inc();
+            let bits = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                    float_id, value, texel, 0,
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                    uint_id, bits, value,
+                encode_word(3, SPV_INSTRUCTION_OP_STORE),
+                    pointer, bits,
+            ];
+            spv
+        }
+        TexelBufferFormat::Rgba32Float => {
+            vec![encode_word(3, SPV_INSTRUCTION_OP_STORE), pointer, texel]
+        }
+        TexelBufferFormat::Rgba8Unorm => {
+            let glsl_std_id = ctx.glsl_std();
+            let bits = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                    uint_id, bits, glsl_std_id, SPV_GLSL_STD_INSTRUCTION_PACK_UNORM_4X8, texel,
+                encode_word(3, SPV_INSTRUCTION_OP_STORE),
+                    pointer, bits,
+            ];
+            spv
+        }
+        TexelBufferFormat::R32Uint => {
+            let value = ctx.inc();
+            #[rustfmt::skip]
+            let spv = vec![
+                encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                    uint_id, value, texel, 0,
+                encode_word(3, SPV_INSTRUCTION_OP_STORE),
+                    pointer, value,
+            ];
+            spv
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use super::*;
+use crate::immediatespatch::{
+    layout::*, padmatcx2::*, relayout_type_recursive, repack::*, type_registry::*,
+};
+
+/// The uniform blocks that [`uniformlayoutpatch`] has relaid out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UniformLayoutReport {
+    pub relaid_out: Vec<UniformRelayout>,
+}
+
+/// A uniform buffer that no longer has the layout it was compiled with.
+/// Use this to allocate the buffer and convert data laid out for the original block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UniformRelayout {
+    pub set: u32,
+    pub binding: u32,
+    /// The std140 size of the buffer in bytes.
+    pub size: u32,
+    /// Every contiguous region of the original block and where it lands in the buffer.
+    /// Copying each region is enough to convert data, see [`repack_uniform`].
+    pub copies: Vec<UniformCopy>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UniformCopy {
+    /// The offset of this region in the original block.
+    pub original_offset: u32,
+    /// The offset of this region in the buffer.
+    pub offset: u32,
+    /// The size of this region in bytes, identical on both sides.
+    pub size: u32,
+}
+
+/// Convert data laid out for the original uniform block into the contents of the relaid out buffer.
+/// The result is `relayout.size` bytes long, padding is zeroed.
+pub fn repack_uniform(relayout: &UniformRelayout, original_data: &[u8]) -> Result<Vec<u8>, ()> {
+    repack_regions(
+        relayout.size,
+        relayout
+            .copies
+            .iter()
+            .map(|copy| (copy.original_offset, copy.offset, copy.size)),
+        original_data,
+    )
+}
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// WGSL requires arrays and structs in the uniform address space to be aligned to 16 bytes, which
+/// blocks compiled with `-fvk-use-dx-layout` or scalar block layout may not follow.
+/// Such uniform blocks are relaid out as std140, blocks that WGSL can already express are left as
+/// is.
+/// Blocks with `matCx2` members are always relaid out, and those members become arrays of `vec4`
+/// columns, see [`CorrectionMap::pad_matcx2`].
+/// Does not produce any corrections, see `report` for how to fill each relaid out buffer.
+///
+/// Fails if a relaid out block shares a struct or array type with another block, or uses a
+/// `matCx2` member other than by loading it or its columns.
+pub fn uniformlayoutpatch(
+    in_spv: &[u32],
+    report: &mut UniformLayoutReport,
+) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations of instructions we need
+    let mut op_variable_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        match instruction {
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    // 2. Find the struct behind every block, uniform blocks are `Block` in the `Uniform` storage class
+    let is_decorated = |id: u32, decoration: u32| {
+        op_decorate_idxs
+            .iter()
+            .any(|&d_idx| spv[d_idx + 1] == id && spv[d_idx + 2] == decoration)
+    };
+    let block_variables = op_variable_idxs
+        .iter()
+        .filter_map(|&v_idx| {
+            let result_type_id = spv[v_idx + 1];
+            let result_id = spv[v_idx + 2];
+            let storage_class = spv[v_idx + 3];
+            op_type_pointer_idxs.iter().find_map(|&tp_idx| {
+                let underlying_type_id = spv[tp_idx + 3];
+                (spv[tp_idx + 1] == result_type_id
+                    && (is_decorated(underlying_type_id, SPV_DECORATION_BLOCK)
+                        || is_decorated(underlying_type_id, SPV_DECORATION_BUFFER_BLOCK)))
+                .then_some((result_id, storage_class, underlying_type_id))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // 3. Build a registry of every relevant OpType*
+    let type_registry = build_type_registry(BuildTypeRegistryIn {
+        spv: &spv,
+        op_type_bool_idxs: &op_type_bool_idxs,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_int_idxs: &op_type_int_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_type_matrix_idxs: &op_type_matrix_idxs,
+        op_type_array_idxs: &op_type_array_idxs,
+        op_type_struct_idxs: &op_type_struct_idxs,
+        op_constant_idxs: &op_constant_idxs,
+    });
+
+    // 4. Find uniform blocks that WGSL cannot express
+    let check_in = CheckLayoutIn {
+        spv: &spv,
+        op_decorate_idxs: &op_decorate_idxs,
+        op_member_decorate_idxs: &op_member_decorate_idxs,
+    };
+    let mut relayout_struct_ids = vec![];
+    for &(_, storage_class, block_struct_id) in block_variables.iter() {
+        if storage_class != SPV_STORAGE_CLASS_UNIFORM
+            || !is_decorated(block_struct_id, SPV_DECORATION_BLOCK)
+            || relayout_struct_ids.contains(&block_struct_id)
+        {
+            continue;
+        }
+        let block = type_registry.get(&block_struct_id).ok_or(())?;
+        if wgsl_uniform_size(&check_in, block, None).is_none() {
+            relayout_struct_ids.push(block_struct_id);
+        }
+    }
+
+    if relayout_struct_ids.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    // Decorations belong to types, so a relaid out type would change the layout of other blocks too
+    let relayout_type_ids = relayout_struct_ids
+        .iter()
+        .flat_map(|block_struct_id| aggregate_type_ids(&type_registry[block_struct_id]))
+        .collect::<HashSet<_>>();
+    for &(_, _, block_struct_id) in block_variables.iter() {
+        if let Some(block) = type_registry.get(&block_struct_id)
+            && !relayout_struct_ids.contains(&block_struct_id)
+            && aggregate_type_ids(block)
+                .iter()
+                .any(|id| relayout_type_ids.contains(id))
+        {
+            return Err(());
+        }
+    }
+
+    // 5. Rewrite Offset / ArrayStride / MatrixStride decorations as std140
+    for &block_struct_id in relayout_struct_ids.iter() {
+        relayout_type_recursive(
+            &spv,
+            &mut new_spv,
+            block_struct_id,
+            &type_registry,
+            &op_decorate_idxs,
+            &op_member_decorate_idxs,
+        );
+    }
+
+    // 6. Report the layout of each new buffer and how to fill it from the original data
+    let decoration_value = |id: u32, decoration: u32| {
+        op_decorate_idxs.iter().find_map(|&d_idx| {
+            (spv[d_idx + 1] == id && spv[d_idx + 2] == decoration).then_some(spv[d_idx + 3])
+        })
+    };
+    for &(var_id, _, block_struct_id) in block_variables.iter() {
+        if !relayout_struct_ids.contains(&block_struct_id) {
+            continue;
+        }
+        let block = &type_registry[&block_struct_id];
+        let set = decoration_value(var_id, SPV_DECORATION_DESCRIPTOR_SET).ok_or(())?;
+        let binding = decoration_value(var_id, SPV_DECORATION_BINDING).ok_or(())?;
+        let copies = collect_copies(
+            &CollectCopiesIn {
+                spv: &spv,
+                new_spv: &new_spv,
+                op_decorate_idxs: &op_decorate_idxs,
+                op_member_decorate_idxs: &op_member_decorate_idxs,
+            },
+            block,
+        )?;
+        report.relaid_out.push(UniformRelayout {
+            set,
+            binding,
+            size: size_of(&block.kind, LayoutRule::Std140),
+            copies: copies
+                .into_iter()
+                .map(|copy| UniformCopy {
+                    original_offset: copy.push_constant_offset,
+                    offset: copy.offset,
+                    size: copy.size,
+                })
+                .collect(),
+        });
+    }
+    report
+        .relaid_out
+        .sort_by_key(|relayout| (relayout.set, relayout.binding));
+
+    // 7. Write New Header and New Code
+    let out_spv = fuse_final(spv_header, new_spv, instruction_bound);
+
+    // 8. Hide the std140 `matCx2` members from naga
+    let variable_ids = block_variables
+        .iter()
+        .filter(|(_, _, block_struct_id)| relayout_struct_ids.contains(block_struct_id))
+        .map(|&(var_id, _, _)| var_id)
+        .collect::<Vec<_>>();
+    padmatcx2(&out_spv, &variable_ids)
+}
+
+struct CheckLayoutIn<'a> {
+    spv: &'a [u32],
+    op_decorate_idxs: &'a [usize],
+    op_member_decorate_idxs: &'a [usize],
+}
+
+// The size of a type as laid out by its decorations, or `None` if WGSL's uniform address space
+// cannot express that layout.
+// `MatrixStride` is a member decoration, so it is carried down from the struct.
+fn wgsl_uniform_size(
+    check_in: &CheckLayoutIn,
+    ty: &Type,
+    matrix_stride_decoration: Option<u32>,
+) -> Option<u32> {
+    let CheckLayoutIn {
+        spv,
+        op_decorate_idxs,
+        op_member_decorate_idxs,
+    } = check_in;
+
+    match &ty.kind {
+        TypeKind::Scalar { .. } | TypeKind::Vector { .. } => {
+            Some(size_of(&ty.kind, LayoutRule::Std140))
+        }
+        // std140 gives `matCx2` a `MatrixStride` of 16, but WGSL's is 8
+        TypeKind::Matrix { column, .. } if column_vec_count(column) == 2 => None,
+        TypeKind::Matrix { column, .. } => {
+            let expected_stride = matrix_stride(
+                column_vec_count(column),
+                column_scalar_width(column),
+                LayoutRule::Std140,
+            );
+            (matrix_stride_decoration? == expected_stride)
+                .then(|| size_of(&ty.kind, LayoutRule::Std140))
+        }
+        TypeKind::Array { element, len } => {
+            let stride = op_decorate_idxs.iter().find_map(|&d_idx| {
+                (spv[d_idx + 1] == ty.id && spv[d_idx + 2] == SPV_DECORATION_ARRAY_STRIDE)
+                    .then_some(spv[d_idx + 3])
+            })?;
+            let element_size = wgsl_uniform_size(check_in, element, matrix_stride_decoration)?;
+            (stride == array_stride(&element.kind, LayoutRule::Std140) && element_size <= stride)
+                .then_some(len * stride)
+        }
+        TypeKind::Struct { members } => {
+            let mut end = 0;
+            for (member_idx, member) in members.iter().enumerate() {
+                let member_literal = |decoration: u32| {
+                    op_member_decorate_idxs.iter().find_map(|&md_idx| {
+                        (spv[md_idx + 1] == ty.id
+                            && spv[md_idx + 2] == member_idx as u32
+                            && spv[md_idx + 3] == decoration)
+                            .then_some(spv[md_idx + 4])
+                    })
+                };
+                let offset = member_literal(SPV_DECORATION_OFFSET)?;
+                if offset % base_align(&member.kind, LayoutRule::Std140) != 0 || offset < end {
+                    return None;
+                }
+                let member_matrix_stride = member_literal(SPV_DECORATION_MATRIX_STRIDE);
+                end = offset + wgsl_uniform_size(check_in, member, member_matrix_stride)?;
+            }
+            // Struct sizes round up to 16, so nothing may follow within a struct's last 16 bytes.
+            Some(round_up(end, base_align(&ty.kind, LayoutRule::Std140)))
+        }
+    }
+}
+
+// Every struct and array type within a type, including itself.
+fn aggregate_type_ids(ty: &Type) -> Vec<u32> {
+    match &ty.kind {
+        TypeKind::Array { element, .. } => {
+            let mut ids = vec![ty.id];
+            ids.extend(aggregate_type_ids(element));
+            ids
+        }
+        TypeKind::Struct { members } => {
+            let mut ids = vec![ty.id];
+            ids.extend(members.iter().flat_map(aggregate_type_ids));
+            ids
+        }
+        _ => vec![],
+    }
+}
@@ -6,8 +6,11 @@ mod decorate;
 mod ensure;
 mod function;
 mod instruction;
+mod new_globals;
 mod opaque_trace;
 mod pointer;
+mod reachability;
+mod specialize;
 
 pub use copy_decorate::*;
 pub use correct_decorate::*;
@@ -15,8 +18,11 @@ pub use decorate::*;
 pub use ensure::*;
 pub use function::*;
 pub use instruction::*;
+pub use new_globals::*;
 pub use opaque_trace::*;
 pub use pointer::*;
+pub use reachability::*;
+pub use specialize::*;
 
 pub fn hiword(value: u32) -> u16 {
     ((value >> 16) & 0xFFFF) as u16
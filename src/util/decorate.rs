@@ -120,12 +120,7 @@ pub fn decorate(d_in: DecorateIn) -> DecorateOut {
                 .entry(set)
                 .or_insert(CorrectionSet::default())
                 .bindings
-                .insert(
-                    binding,
-                    CorrectionBinding {
-                        corrections: vec![],
-                    },
-                );
+                .insert(binding, CorrectionBinding::default());
         }
 
         corrections.sets = Some(new_correction_sets);
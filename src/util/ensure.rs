@@ -93,6 +93,30 @@ pub fn ensure_type_int(
     }
 }
 
+pub fn ensure_type_float(
+    spv: &[u32],
+    op_type_float_idxs: &[usize],
+    instruction_bound: &mut u32,
+    header: &mut Vec<u32>,
+    template_width: u32,
+) -> u32 {
+    if let Some(idx) = op_type_float_idxs
+        .iter()
+        .find(|&&ty_idx| spv[ty_idx + 2] == template_width)
+    {
+        spv[idx + 1]
+    } else {
+        let new_id = *instruction_bound;
+        *instruction_bound += 1;
+        header.append(&mut vec![
+            encode_word(3, SPV_INSTRUCTION_OP_TYPE_FLOAT),
+            new_id,
+            template_width,
+        ]);
+        new_id
+    }
+}
+
 pub fn ensure_type_vector(
     spv: &[u32],
     op_type_vector_idxs: &[usize],
@@ -148,3 +172,47 @@ pub fn ensure_type_pointer(
         new_id
     }
 }
+
+pub fn ensure_type_sampler(
+    spv: &[u32],
+    op_type_sampler_idxs: &[usize],
+    instruction_bound: &mut u32,
+    header: &mut Vec<u32>,
+) -> u32 {
+    if let Some(idx) = op_type_sampler_idxs.first() {
+        spv[idx + 1]
+    } else {
+        let new_id = *instruction_bound;
+        *instruction_bound += 1;
+        header.append(&mut vec![
+            encode_word(2, SPV_INSTRUCTION_OP_TYPE_SAMPLER),
+            new_id,
+        ]);
+        new_id
+    }
+}
+
+// `template_operands` are the `OpTypeImage` operands following the result id, without an access
+// qualifier.
+pub fn ensure_type_image(
+    spv: &[u32],
+    op_type_image_idxs: &[usize],
+    instruction_bound: &mut u32,
+    header: &mut Vec<u32>,
+    template_operands: [u32; 7],
+) -> u32 {
+    if let Some(idx) = op_type_image_idxs.iter().find(|&&ty_idx| {
+        hiword(spv[ty_idx]) == 9 && spv[ty_idx + 2..ty_idx + 9] == template_operands
+    }) {
+        spv[idx + 1]
+    } else {
+        let new_id = *instruction_bound;
+        *instruction_bound += 1;
+        header.append(&mut vec![
+            encode_word(9, SPV_INSTRUCTION_OP_TYPE_IMAGE),
+            new_id,
+        ]);
+        header.extend_from_slice(&template_operands);
+        new_id
+    }
+}
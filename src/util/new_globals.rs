@@ -0,0 +1,39 @@
+use super::*;
+
+/// Types, constants, and decorations that a transformation declares as it goes.
+pub struct NewGlobals<'a> {
+    pub spv: &'a [u32],
+    pub instruction_bound: u32,
+    pub header: Vec<u32>,
+    pub decorations: Vec<u32>,
+    // The `ensure_type_*` helpers only search `spv`, so remember what was already added to `header`
+    new_types: HashMap<Vec<u32>, u32>,
+}
+
+impl<'a> NewGlobals<'a> {
+    pub fn new(spv: &'a [u32], instruction_bound: u32) -> Self {
+        NewGlobals {
+            spv,
+            instruction_bound,
+            header: vec![],
+            decorations: vec![],
+            new_types: HashMap::new(),
+        }
+    }
+
+    pub fn inc(&mut self) -> u32 {
+        self.instruction_bound += 1;
+        self.instruction_bound - 1
+    }
+
+    /// Only call `ensure` the first time `key` is seen, `key` is usually the instruction without
+    /// its result id.
+    pub fn cached<F: FnOnce(&mut Self) -> u32>(&mut self, key: Vec<u32>, ensure: F) -> u32 {
+        if let Some(&id) = self.new_types.get(&key) {
+            return id;
+        }
+        let id = ensure(self);
+        self.new_types.insert(key, id);
+        id
+    }
+}
@@ -0,0 +1,177 @@
+use super::*;
+
+// Find which ids are used by walking from a set of roots, such as an entry point's function and
+// interface, through every type, constant, global variable, and function that they reference.
+// Function bodies are walked as a whole once their `OpFunction` id is used.
+
+pub struct TraceUsedIdsIn<'a> {
+    pub spv: &'a [u32],
+
+    // Types, constants, and global variables, see `global_result_id`
+    pub op_global_idxs: &'a [usize],
+    // From `OpFunction` up to and including `OpFunctionEnd`
+    pub function_ranges: &'a [(usize, usize)],
+    pub op_decorate_id_idxs: &'a [usize],
+
+    pub roots: Vec<u32>,
+}
+
+pub fn trace_used_ids(tu_in: TraceUsedIdsIn) -> HashSet<u32> {
+    let TraceUsedIdsIn {
+        spv,
+        op_global_idxs,
+        function_ranges,
+        op_decorate_id_idxs,
+        roots,
+    } = tu_in;
+
+    enum Definition {
+        Global(usize),
+        Function(usize, usize),
+    }
+
+    let mut definitions = HashMap::new();
+    for &idx in op_global_idxs.iter() {
+        if let Some(id) = global_result_id(spv, idx) {
+            definitions.insert(id, Definition::Global(idx));
+        }
+    }
+    for &(start, end) in function_ranges.iter() {
+        definitions.insert(spv[start + 2], Definition::Function(start, end));
+    }
+
+    let mut used_ids = HashSet::new();
+    let mut pending = roots;
+    while let Some(id) = pending.pop() {
+        if !used_ids.insert(id) {
+            continue;
+        }
+        match definitions.get(&id) {
+            Some(&Definition::Global(idx)) => pending.extend(referenced_ids(spv, idx)),
+            Some(&Definition::Function(start, end)) => {
+                let mut idx = start;
+                while idx < end {
+                    pending.extend(referenced_ids(spv, idx));
+                    idx += hiword(spv[idx]) as usize;
+                }
+            }
+            None => {}
+        }
+        for &idx in op_decorate_id_idxs.iter() {
+            if spv[idx + 1] == id {
+                pending.extend_from_slice(&spv[idx + 3..idx + hiword(spv[idx]) as usize]);
+            }
+        }
+    }
+    used_ids
+}
+
+// The result id of a type, constant, or global variable.
+pub fn global_result_id(spv: &[u32], idx: usize) -> Option<u32> {
+    match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_TYPE_VOID..=SPV_INSTRUCTION_OP_TYPE_FORWARD_POINTER => {
+            Some(spv[idx + 1])
+        }
+        SPV_INSTRUCTION_OP_CONSTANT_TRUE..=SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP
+        | SPV_INSTRUCTION_OP_VARIABLE => Some(spv[idx + 2]),
+        _ => None,
+    }
+}
+
+// Every operand that may be an id.
+// Literals that are known to never be ids are skipped, the rest are over approximated.
+pub fn referenced_ids(spv: &[u32], idx: usize) -> Vec<u32> {
+    let word_count = hiword(spv[idx]) as usize;
+    let words = |range: std::ops::Range<usize>| {
+        spv[idx + range.start.min(word_count)..idx + range.end.min(word_count)].to_vec()
+    };
+    match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_TYPE_VECTOR
+        | SPV_INSTRUCTION_OP_TYPE_MATRIX
+        | SPV_INSTRUCTION_OP_TYPE_IMAGE => words(2..3),
+        SPV_INSTRUCTION_OP_TYPE_POINTER => words(3..4),
+        SPV_INSTRUCTION_OP_TYPE_FORWARD_POINTER => words(1..2),
+        SPV_INSTRUCTION_OP_TYPE_VOID..=SPV_INSTRUCTION_OP_TYPE_FLOAT => vec![],
+        SPV_INSTRUCTION_OP_CONSTANT
+        | SPV_INSTRUCTION_OP_SPEC_CONSTANT
+        | SPV_INSTRUCTION_OP_CONSTANT_TRUE
+        | SPV_INSTRUCTION_OP_CONSTANT_FALSE
+        | SPV_INSTRUCTION_OP_CONSTANT_NULL
+        | SPV_INSTRUCTION_OP_CONSTANT_SAMPLER
+        | SPV_INSTRUCTION_OP_SPEC_CONSTANT_TRUE
+        | SPV_INSTRUCTION_OP_SPEC_CONSTANT_FALSE => words(1..2),
+        SPV_INSTRUCTION_OP_SPEC_CONSTANT_OP => [words(1..2), words(4..word_count)].concat(),
+        SPV_INSTRUCTION_OP_VARIABLE => [words(1..2), words(4..5)].concat(),
+        SPV_INSTRUCTION_OP_FUNCTION => [words(1..2), words(4..5)].concat(),
+        SPV_INSTRUCTION_OP_LOAD => words(1..4),
+        SPV_INSTRUCTION_OP_STORE | SPV_INSTRUCTION_OP_COPY_MEMORY => words(1..3),
+        SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT => words(1..4),
+        SPV_INSTRUCTION_OP_COMPOSITE_INSERT | SPV_INSTRUCTION_OP_VECTOR_SHUFFLE => words(1..5),
+        SPV_INSTRUCTION_OP_EXT_INST => [words(1..4), words(5..word_count)].concat(),
+        SPV_INSTRUCTION_OP_SWITCH => words(1..2),
+        SPV_INSTRUCTION_OP_LINE
+        | SPV_INSTRUCTION_OP_SELECTION_MERGE
+        | SPV_INSTRUCTION_OP_LOOP_MERGE
+        | SPV_INSTRUCTION_OP_BRANCH => vec![],
+        _ => words(1..word_count),
+    }
+}
+
+// The index of an `OpEntryPoint`'s first interface id, which follows its nul terminated name.
+pub fn entry_point_interface_idx(spv: &[u32], ep_idx: usize) -> Result<usize, ()> {
+    let word_count = hiword(spv[ep_idx]) as usize;
+    let name_word_count = spv[ep_idx + 3..ep_idx + word_count]
+        .iter()
+        .position(|word| word.to_le_bytes().contains(&0))
+        .ok_or(())?
+        + 1;
+    Ok(ep_idx + 3 + name_word_count)
+}
+
+pub struct EntryPointUsedIdsIn<'a> {
+    pub spv: &'a [u32],
+
+    pub op_global_idxs: &'a [usize],
+    pub function_ranges: &'a [(usize, usize)],
+    pub op_decorate_id_idxs: &'a [usize],
+    // Only `OpExecutionModeId` operands are roots, `OpExecutionMode` ones are literals
+    pub op_execution_mode_idxs: &'a [usize],
+
+    pub ep_idx: usize,
+}
+
+// The name of an `OpEntryPoint`, and every id it can reach from its function, interface, and
+// execution modes.
+pub fn entry_point_used_ids(epu_in: EntryPointUsedIdsIn) -> Result<(String, HashSet<u32>), ()> {
+    let EntryPointUsedIdsIn {
+        spv,
+        op_global_idxs,
+        function_ranges,
+        op_decorate_id_idxs,
+        op_execution_mode_idxs,
+        ep_idx,
+    } = epu_in;
+
+    let function_id = spv[ep_idx + 2];
+    let interface_idx = entry_point_interface_idx(spv, ep_idx)?;
+    let name = literal_to_string_le(&spv[ep_idx + 3..interface_idx])
+        .map_err(|_| ())?
+        .trim_end_matches('\0')
+        .to_owned();
+
+    let mut roots = vec![function_id];
+    roots.extend_from_slice(&spv[interface_idx..ep_idx + hiword(spv[ep_idx]) as usize]);
+    for &idx in op_execution_mode_idxs.iter() {
+        if loword(spv[idx]) == SPV_INSTRUCTION_OP_EXECUTION_MODE_ID && spv[idx + 1] == function_id {
+            roots.extend_from_slice(&spv[idx + 3..idx + hiword(spv[idx]) as usize]);
+        }
+    }
+    let used_ids = trace_used_ids(TraceUsedIdsIn {
+        spv,
+        op_global_idxs,
+        function_ranges,
+        op_decorate_id_idxs,
+        roots,
+    });
+    Ok((name, used_ids))
+}
@@ -0,0 +1,463 @@
+use super::*;
+
+// Give every function that takes images or samplers through pointer parameters one copy per
+// distinct set of global variables it is called with, and have each copy read those globals
+// directly instead of its parameters.
+// A parameter is only replaced when the function does nothing but load it or pass it on, and a
+// call only specializes it when the argument is a global variable, everything else is left as is.
+// The first copy of a function keeps its original ids, the others are given fresh ones along with
+// copies of their names and decorations.
+
+struct Function {
+    start: usize,
+    end: usize,
+    parameters: Vec<(u32, u32)>,
+    replaceable: Vec<bool>,
+    local_ids: Vec<u32>,
+}
+
+type InstanceKey = (usize, Vec<Option<u32>>);
+
+pub fn specialize_opaque_parameters(
+    spv: &[u32],
+    instruction_bound: &mut u32,
+) -> Result<Vec<u32>, ()> {
+    // 1. Find functions, their parameters, and what can be passed to them
+    let mut functions: Vec<Function> = vec![];
+    let mut function_positions = HashMap::new();
+    let mut global_ids = HashSet::new();
+    let mut opaque_type_ids = HashSet::new();
+    let mut opaque_pointer_type_ids = HashSet::new();
+    let mut uniform_constant_variable_ids = HashSet::new();
+    let mut op_type_function_idxs = HashMap::new();
+    let mut op_target_idxs: HashMap<u32, Vec<usize>> = HashMap::new();
+    let mut entry_point_ids = HashSet::new();
+    let mut called_ids = HashSet::new();
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op) as usize;
+        let instruction = loword(op);
+
+        if word_count == 0 {
+            return Err(());
+        }
+
+        if let Some(function) = functions.last_mut().filter(|function| function.end == 0) {
+            match instruction {
+                SPV_INSTRUCTION_OP_FUNCTION_PARAMETER => {
+                    function
+                        .parameters
+                        .push((spv[spv_idx + 2], spv[spv_idx + 1]));
+                }
+                SPV_INSTRUCTION_OP_FUNCTION_CALL => {
+                    called_ids.insert(spv[spv_idx + 3]);
+                }
+                SPV_INSTRUCTION_OP_FUNCTION_END => function.end = spv_idx + word_count,
+                _ => {}
+            }
+            if let Some(id) = local_result_id(spv, spv_idx) {
+                function.local_ids.push(id);
+            }
+        } else {
+            match instruction {
+                SPV_INSTRUCTION_OP_FUNCTION => {
+                    function_positions.insert(spv[spv_idx + 2], functions.len());
+                    global_ids.insert(spv[spv_idx + 2]);
+                    functions.push(Function {
+                        start: spv_idx,
+                        end: 0,
+                        parameters: vec![],
+                        replaceable: vec![],
+                        local_ids: vec![],
+                    });
+                }
+                SPV_INSTRUCTION_OP_ENTRY_POINT => {
+                    entry_point_ids.insert(spv[spv_idx + 2]);
+                }
+                SPV_INSTRUCTION_OP_NAME
+                | SPV_INSTRUCTION_OP_DECORATE
+                | SPV_INSTRUCTION_OP_DECORATE_ID
+                | SPV_INSTRUCTION_OP_DECORATE_STRING => {
+                    op_target_idxs
+                        .entry(spv[spv_idx + 1])
+                        .or_default()
+                        .push(spv_idx);
+                }
+                SPV_INSTRUCTION_OP_EXT_INST_IMPORT | SPV_INSTRUCTION_OP_STRING => {
+                    global_ids.insert(spv[spv_idx + 1]);
+                }
+                SPV_INSTRUCTION_OP_TYPE_IMAGE | SPV_INSTRUCTION_OP_TYPE_SAMPLER => {
+                    opaque_type_ids.insert(spv[spv_idx + 1]);
+                }
+                SPV_INSTRUCTION_OP_TYPE_POINTER
+                    if spv[spv_idx + 2] == SPV_STORAGE_CLASS_UNIFORM_CONSTANT
+                        && opaque_type_ids.contains(&spv[spv_idx + 3]) =>
+                {
+                    opaque_pointer_type_ids.insert(spv[spv_idx + 1]);
+                }
+                SPV_INSTRUCTION_OP_TYPE_FUNCTION => {
+                    op_type_function_idxs.insert(spv[spv_idx + 1], spv_idx);
+                }
+                SPV_INSTRUCTION_OP_VARIABLE
+                    if spv[spv_idx + 3] == SPV_STORAGE_CLASS_UNIFORM_CONSTANT =>
+                {
+                    uniform_constant_variable_ids.insert(spv[spv_idx + 2]);
+                }
+                _ => {}
+            }
+            if let Some(id) = global_result_id(spv, spv_idx) {
+                global_ids.insert(id);
+            }
+        }
+
+        spv_idx += word_count;
+    }
+
+    if functions.iter().any(|function| function.end == 0) {
+        return Err(());
+    }
+
+    // 2. A parameter can be replaced if it points to an image or sampler that is only loaded or
+    //    passed on to another function
+    for function in functions.iter_mut() {
+        function.local_ids.retain(|id| !global_ids.contains(id));
+        function.replaceable = function
+            .parameters
+            .iter()
+            .map(|&(parameter_id, type_id)| {
+                opaque_pointer_type_ids.contains(&type_id)
+                    && function_uses_only_as_pointer(spv, function, parameter_id)
+            })
+            .collect();
+    }
+
+    if !functions
+        .iter()
+        .any(|function| function.replaceable.contains(&true))
+    {
+        return Ok(spv.to_vec());
+    }
+
+    // 3. Emit every function nothing calls, then every copy their calls ask for
+    let mut instance_ids: HashMap<InstanceKey, u32> = HashMap::new();
+    let mut pending: Vec<(InstanceKey, u32)> = vec![];
+    for (position, function) in functions.iter().enumerate() {
+        let function_id = spv[function.start + 2];
+        if entry_point_ids.contains(&function_id) || !called_ids.contains(&function_id) {
+            let key = (position, vec![None; function.parameters.len()]);
+            instance_ids.insert(key.clone(), function_id);
+            pending.push((key, function_id));
+        }
+    }
+    pending.reverse();
+
+    let mut instruction_inserts = vec![];
+    let mut new_op_type_functions: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut kept_ids = HashSet::new();
+    let mut bodies: Vec<Vec<Vec<u32>>> = vec![vec![]; functions.len()];
+
+    while let Some(((position, key), instance_id)) = pending.pop() {
+        let function = &functions[position];
+        let function_id = spv[function.start + 2];
+
+        let replacements = function
+            .parameters
+            .iter()
+            .zip(key.iter())
+            .filter_map(|(&(parameter_id, _), &global)| Some((parameter_id, global?)))
+            .collect::<HashMap<_, _>>();
+
+        let mut renames = HashMap::new();
+        if instance_id == function_id {
+            kept_ids.insert(function_id);
+            kept_ids.extend(
+                function
+                    .local_ids
+                    .iter()
+                    .filter(|id| !replacements.contains_key(id)),
+            );
+        } else {
+            let mut renamed = vec![(function_id, instance_id)];
+            for &id in function
+                .local_ids
+                .iter()
+                .filter(|id| !replacements.contains_key(id))
+            {
+                renamed.push((id, *instruction_bound));
+                *instruction_bound += 1;
+            }
+            for &(id, new_id) in renamed.iter() {
+                for &target_idx in op_target_idxs.get(&id).into_iter().flatten() {
+                    let mut instruction =
+                        spv[target_idx..target_idx + hiword(spv[target_idx]) as usize].to_vec();
+                    instruction[1] = new_id;
+                    instruction_inserts.push(InstructionInsert {
+                        previous_spv_idx: target_idx,
+                        instruction,
+                    });
+                }
+            }
+            renames.extend(renamed);
+        }
+
+        let mut body = vec![];
+        let mut spv_idx = function.start;
+        while spv_idx < function.end {
+            let op = spv[spv_idx];
+            let word_count = hiword(op) as usize;
+            let instruction = loword(op);
+
+            if instruction == SPV_INSTRUCTION_OP_FUNCTION_PARAMETER
+                && replacements.contains_key(&spv[spv_idx + 2])
+            {
+                spv_idx += word_count;
+                continue;
+            }
+
+            let mut words = spv[spv_idx..spv_idx + word_count].to_vec();
+            for offset in id_operand_offsets(spv, spv_idx) {
+                if let Some(&id) = replacements.get(&words[offset]) {
+                    words[offset] = id;
+                } else if let Some(&id) = renames.get(&words[offset]) {
+                    words[offset] = id;
+                }
+            }
+
+            match instruction {
+                SPV_INSTRUCTION_OP_FUNCTION if !replacements.is_empty() => {
+                    let type_idx = *op_type_function_idxs.get(&spv[spv_idx + 4]).ok_or(())?;
+                    let mut signature = vec![spv[type_idx + 2]];
+                    signature.extend(
+                        function
+                            .parameters
+                            .iter()
+                            .filter(|(parameter_id, _)| !replacements.contains_key(parameter_id))
+                            .map(|&(_, type_id)| type_id),
+                    );
+                    let existing_type_id = op_type_function_idxs.iter().find_map(|(&id, &idx)| {
+                        (spv[idx + 2..idx + hiword(spv[idx]) as usize] == signature[..])
+                            .then_some(id)
+                    });
+                    words[4] = match existing_type_id {
+                        Some(id) => id,
+                        None => *new_op_type_functions
+                            .entry(signature.clone())
+                            .or_insert_with(|| {
+                                let id = *instruction_bound;
+                                *instruction_bound += 1;
+                                let mut instruction = vec![
+                                    encode_word(
+                                        signature.len() as u16 + 2,
+                                        SPV_INSTRUCTION_OP_TYPE_FUNCTION,
+                                    ),
+                                    id,
+                                ];
+                                instruction.extend_from_slice(&signature);
+                                instruction_inserts.push(InstructionInsert {
+                                    previous_spv_idx: type_idx,
+                                    instruction,
+                                });
+                                id
+                            }),
+                    };
+                }
+                SPV_INSTRUCTION_OP_FUNCTION_CALL => {
+                    if let Some(&callee_position) = function_positions.get(&words[3]) {
+                        let callee = &functions[callee_position];
+                        let callee_key = callee
+                            .replaceable
+                            .iter()
+                            .zip(words[4..].iter())
+                            .map(|(&replaceable, &argument)| {
+                                (replaceable && uniform_constant_variable_ids.contains(&argument))
+                                    .then_some(argument)
+                            })
+                            .collect::<Vec<_>>();
+                        let callee_id = spv[callee.start + 2];
+                        let callee_has_instance =
+                            instance_ids.keys().any(|(p, _)| *p == callee_position);
+                        let key = (callee_position, callee_key.clone());
+                        let callee_instance_id = match instance_ids.get(&key) {
+                            Some(&id) => id,
+                            None => {
+                                let id = if callee_has_instance {
+                                    let id = *instruction_bound;
+                                    *instruction_bound += 1;
+                                    id
+                                } else {
+                                    callee_id
+                                };
+                                instance_ids.insert(key.clone(), id);
+                                pending.push((key, id));
+                                id
+                            }
+                        };
+
+                        let mut call = words[..4].to_vec();
+                        call[3] = callee_instance_id;
+                        call.extend(
+                            words[4..]
+                                .iter()
+                                .zip(callee_key.iter())
+                                .filter(|(_, global)| global.is_none())
+                                .map(|(&argument, _)| argument),
+                        );
+                        call[0] = encode_word(call.len() as u16, SPV_INSTRUCTION_OP_FUNCTION_CALL);
+                        words = call;
+                    }
+                }
+                _ => {}
+            }
+
+            body.extend(words);
+            spv_idx += word_count;
+        }
+
+        bodies[position].push(body);
+    }
+
+    // 4. Names and decorations of ids that no longer exist go away
+    let first_function_idx = functions[0].start;
+    let mut new_spv = spv[..first_function_idx].to_vec();
+    for function in functions.iter() {
+        let ids =
+            std::iter::once(spv[function.start + 2]).chain(function.local_ids.iter().copied());
+        for id in ids.filter(|id| !kept_ids.contains(id)) {
+            for &target_idx in op_target_idxs.get(&id).into_iter().flatten() {
+                for word in new_spv
+                    .iter_mut()
+                    .skip(target_idx)
+                    .take(hiword(spv[target_idx]) as usize)
+                {
+                    *word = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                }
+            }
+        }
+    }
+
+    // 5. Insert, with each function's copies following where it used to be
+    insert_new_instructions(spv, &mut new_spv, &[], &instruction_inserts);
+    prune_noops(&mut new_spv);
+    new_spv.extend(bodies.into_iter().flatten().flatten());
+
+    Ok(new_spv)
+}
+
+// Whether a parameter is only ever the pointer of an `OpLoad` or an argument of an `OpFunctionCall`.
+fn function_uses_only_as_pointer(spv: &[u32], function: &Function, parameter_id: u32) -> bool {
+    let mut spv_idx = function.start;
+    while spv_idx < function.end {
+        let instruction = loword(spv[spv_idx]);
+        for offset in id_operand_offsets(spv, spv_idx) {
+            if spv[spv_idx + offset] != parameter_id {
+                continue;
+            }
+            let allowed = match instruction {
+                SPV_INSTRUCTION_OP_FUNCTION_PARAMETER => offset == 2,
+                SPV_INSTRUCTION_OP_LOAD => offset == 3,
+                SPV_INSTRUCTION_OP_FUNCTION_CALL => offset >= 4,
+                _ => false,
+            };
+            if !allowed {
+                return false;
+            }
+        }
+        spv_idx += hiword(spv[spv_idx]) as usize;
+    }
+    true
+}
+
+// The id an instruction inside of a function defines.
+// Instructions without a result that are not listed here only ever yield ids that are already
+// defined elsewhere in the function or globally, which is harmless.
+fn local_result_id(spv: &[u32], idx: usize) -> Option<u32> {
+    let word_count = hiword(spv[idx]) as usize;
+    match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_LABEL => Some(spv[idx + 1]),
+        SPV_INSTRUCTION_OP_STORE
+        | SPV_INSTRUCTION_OP_COPY_MEMORY
+        | SPV_INSTRUCTION_OP_LINE
+        | SPV_INSTRUCTION_OP_SELECTION_MERGE
+        | SPV_INSTRUCTION_OP_LOOP_MERGE
+        | SPV_INSTRUCTION_OP_BRANCH
+        | SPV_INSTRUCTION_OP_BRANCH_CONDITIONAL
+        | SPV_INSTRUCTION_OP_SWITCH
+        | SPV_INSTRUCTION_OP_RETURN_VALUE
+        | SPV_INSTRUCTION_OP_IMAGE_WRITE
+        | SPV_INSTRUCTION_OP_ATOMIC_STORE
+        | SPV_INSTRUCTION_OP_FUNCTION_END => None,
+        _ if word_count >= 3 => Some(spv[idx + 2]),
+        _ => None,
+    }
+}
+
+// The offsets of every operand of an instruction inside of a function that is an id, including
+// its result type and result.
+// Unlike `referenced_ids` this has to be exact as the ids found are rewritten, `OpSwitch` is
+// assumed to use 32 bit literals.
+fn id_operand_offsets(spv: &[u32], idx: usize) -> Vec<usize> {
+    let word_count = hiword(spv[idx]) as usize;
+    let image_operands_at = |mask_offset: usize| {
+        (1..mask_offset.min(word_count))
+            .chain(mask_offset + 1..word_count)
+            .collect()
+    };
+    match loword(spv[idx]) {
+        SPV_INSTRUCTION_OP_FUNCTION => vec![1, 2, 4],
+        SPV_INSTRUCTION_OP_VARIABLE => (1..word_count).filter(|&offset| offset != 3).collect(),
+        SPV_INSTRUCTION_OP_LOAD | SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT => {
+            (1..4.min(word_count)).collect()
+        }
+        SPV_INSTRUCTION_OP_STORE | SPV_INSTRUCTION_OP_COPY_MEMORY => {
+            (1..3.min(word_count)).collect()
+        }
+        SPV_INSTRUCTION_OP_COMPOSITE_INSERT | SPV_INSTRUCTION_OP_VECTOR_SHUFFLE => {
+            (1..5.min(word_count)).collect()
+        }
+        SPV_INSTRUCTION_OP_EXT_INST => (1..word_count).filter(|&offset| offset != 4).collect(),
+        SPV_INSTRUCTION_OP_SWITCH => (1..word_count)
+            .filter(|&offset| offset < 3 || offset % 2 == 0)
+            .collect(),
+        SPV_INSTRUCTION_OP_LINE
+        | SPV_INSTRUCTION_OP_SELECTION_MERGE
+        | SPV_INSTRUCTION_OP_BRANCH => {
+            vec![1]
+        }
+        SPV_INSTRUCTION_OP_LOOP_MERGE => vec![1, 2],
+        SPV_INSTRUCTION_OP_BRANCH_CONDITIONAL => (1..4.min(word_count)).collect(),
+        SPV_INSTRUCTION_OP_IMAGE_WRITE => image_operands_at(4),
+        SPV_INSTRUCTION_OP_IMAGE_SAMPLE_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_FETCH
+        | SPV_INSTRUCTION_OP_IMAGE_READ
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_FETCH
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_READ => image_operands_at(5),
+        SPV_INSTRUCTION_OP_IMAGE_SAMPLE_DREF_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_DREF_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_DREF_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SAMPLE_PROJ_DREF_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_GATHER
+        | SPV_INSTRUCTION_OP_IMAGE_DREF_GATHER
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_DREF_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_DREF_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_DREF_IMPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_SAMPLE_PROJ_DREF_EXPLICIT_LOD
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_GATHER
+        | SPV_INSTRUCTION_OP_IMAGE_SPARSE_DREF_GATHER => image_operands_at(6),
+        SPV_INSTRUCTION_OP_GROUP_I_ADD..=SPV_INSTRUCTION_OP_GROUP_S_MAX
+        | SPV_INSTRUCTION_OP_GROUP_NON_UNIFORM_BALLOT_BIT_COUNT
+        | SPV_INSTRUCTION_OP_GROUP_NON_UNIFORM_I_ADD
+            ..=SPV_INSTRUCTION_OP_GROUP_NON_UNIFORM_LOGICAL_XOR => {
+            (1..word_count).filter(|&offset| offset != 4).collect()
+        }
+        _ => (1..word_count).collect(),
+    }
+}
@@ -0,0 +1,1667 @@
+use super::*;
+use crate::immediatespatch::{layout::*, type_registry::*};
+
+fn inc(ib: &mut u32) -> u32 {
+    *ib += 1;
+    *ib - 1
+}
+
+mod access;
+mod extract;
+mod insert;
+
+use access::*;
+use extract::*;
+use insert::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NarrowKind {
+    Unsigned,
+    Signed,
+    Float,
+}
+
+/// An 8-bit or 16-bit integer, or a 16-bit float.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NarrowScalar {
+    kind: NarrowKind,
+    width: u32,
+}
+
+/// The 32-bit scalar a narrow value is widened to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WideKind {
+    Uint,
+    Int,
+    Float,
+}
+
+impl From<NarrowKind> for WideKind {
+    fn from(kind: NarrowKind) -> Self {
+        match kind {
+            NarrowKind::Unsigned => WideKind::Uint,
+            NarrowKind::Signed => WideKind::Int,
+            NarrowKind::Float => WideKind::Float,
+        }
+    }
+}
+
+fn narrow_mask(width: u32) -> u32 {
+    (1 << width) - 1
+}
+
+/// A narrow scalar or vector member, or an array of them.
+#[derive(Debug, Clone, Copy)]
+struct NarrowMember {
+    scalar: NarrowScalar,
+    count: Option<u32>,
+    /// `(length, ArrayStride)`, runtime arrays have no length.
+    array: Option<(Option<u32>, u32)>,
+}
+
+impl NarrowMember {
+    fn element_size(&self) -> u32 {
+        self.count.unwrap_or(1) * self.scalar.width / 8
+    }
+
+    fn size(&self) -> Option<u32> {
+        match self.array {
+            Some((Some(len), stride)) => Some(len * stride),
+            Some((None, _)) => None,
+            None => Some(self.element_size()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MemberMap {
+    Kept(u32),
+    Packed {
+        new_index: u32,
+        byte_offset: u32,
+        member: NarrowMember,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NewMember {
+    Kept(u32),
+    Range {
+        first_member: u32,
+        word_offset: u32,
+        word_count: Option<u32>,
+    },
+}
+
+/// Consecutive narrow members, `end` is unknown for runtime arrays.
+struct MemberRange {
+    start: u32,
+    end: Option<u32>,
+    members: Vec<(usize, u32, NarrowMember)>,
+}
+
+/// A struct with narrow members, consecutive narrow members share one array of `u32` words.
+struct PackedStruct {
+    idx: usize,
+    new_members: Vec<NewMember>,
+    member_map: Vec<MemberMap>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChainPointer {
+    pointee: u32,
+    storage_class: u32,
+    ssbo: bool,
+}
+
+/// A pointer into a packed range, it no longer exists in the output.
+#[derive(Debug, Clone)]
+struct NarrowPointer {
+    /// The base and indices leading up to the word array.
+    prefix: Vec<u32>,
+    word_pointer_type_id: u32,
+    byte: ByteOffset,
+    /// Every possible byte offset is a multiple of 4.
+    aligned: bool,
+    scalar: NarrowScalar,
+    count: Option<u32>,
+    is_array: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WideValue {
+    id: u32,
+    kind: WideKind,
+}
+
+/// Types and constants the emitted code needs, declared on first use.
+struct WidenContext<'a> {
+    spv: &'a [u32],
+    op_type_int_idxs: &'a [usize],
+    op_type_float_idxs: &'a [usize],
+    op_type_vector_idxs: &'a [usize],
+    op_type_pointer_idxs: &'a [usize],
+    op_constant_idxs: &'a [usize],
+    op_ext_inst_import_idxs: &'a [usize],
+    instruction_bound: u32,
+    header: Vec<u32>,
+    ext_inst_import_header: Vec<u32>,
+    uint_id: u32,
+    int_id: Option<u32>,
+    float_id: Option<u32>,
+    glsl_std_id: Option<u32>,
+    vectors: HashMap<(u32, u32), u32>,
+    pointers: HashMap<u32, u32>,
+    constants: HashMap<(u32, u32), u32>,
+}
+
+impl WidenContext<'_> {
+    fn inc(&mut self) -> u32 {
+        inc(&mut self.instruction_bound)
+    }
+
+    fn constant(&mut self, type_id: u32, value: u32) -> u32 {
+        if let Some(&id) = self.constants.get(&(type_id, value)) {
+            return id;
+        }
+        let id = self
+            .op_constant_idxs
+            .iter()
+            .find_map(|&c_idx| {
+                (hiword(self.spv[c_idx]) == 4
+                    && self.spv[c_idx + 1] == type_id
+                    && self.spv[c_idx + 3] == value)
+                    .then_some(self.spv[c_idx + 2])
+            })
+            .unwrap_or_else(|| {
+                let new_id = inc(&mut self.instruction_bound);
+                self.header.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                    type_id,
+                    new_id,
+                    value,
+                ]);
+                new_id
+            });
+        self.constants.insert((type_id, value), id);
+        id
+    }
+
+    fn uint(&mut self, value: u32) -> u32 {
+        self.constant(self.uint_id, value)
+    }
+
+    fn float_zero(&mut self) -> u32 {
+        let float_id = self.float_type();
+        self.constant(float_id, 0)
+    }
+
+    fn int_type(&mut self) -> u32 {
+        if let Some(id) = self.int_id {
+            return id;
+        }
+        let id = ensure_type_int(
+            self.spv,
+            self.op_type_int_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            32,
+            SPV_SIGNEDNESS_SIGNED,
+        );
+        self.int_id = Some(id);
+        id
+    }
+
+    fn float_type(&mut self) -> u32 {
+        if let Some(id) = self.float_id {
+            return id;
+        }
+        let id = ensure_type_float(
+            self.spv,
+            self.op_type_float_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            32,
+        );
+        self.float_id = Some(id);
+        id
+    }
+
+    fn vector_type(&mut self, component_type_id: u32, count: u32) -> u32 {
+        if let Some(&id) = self.vectors.get(&(component_type_id, count)) {
+            return id;
+        }
+        let id = ensure_type_vector(
+            self.spv,
+            self.op_type_vector_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            component_type_id,
+            count,
+        );
+        self.vectors.insert((component_type_id, count), id);
+        id
+    }
+
+    fn wide_type(&mut self, kind: WideKind, count: Option<u32>) -> u32 {
+        let scalar_id = match kind {
+            WideKind::Uint => self.uint_id,
+            WideKind::Int => self.int_type(),
+            WideKind::Float => self.float_type(),
+        };
+        match count {
+            Some(count) => self.vector_type(scalar_id, count),
+            None => scalar_id,
+        }
+    }
+
+    fn uint_pointer(&mut self, storage_class: u32) -> u32 {
+        if let Some(&id) = self.pointers.get(&storage_class) {
+            return id;
+        }
+        let id = ensure_type_pointer(
+            self.spv,
+            self.op_type_pointer_idxs,
+            &mut self.instruction_bound,
+            &mut self.header,
+            storage_class,
+            self.uint_id,
+        );
+        self.pointers.insert(storage_class, id);
+        id
+    }
+
+    fn glsl_std(&mut self) -> u32 {
+        if let Some(id) = self.glsl_std_id {
+            return id;
+        }
+        let id = ensure_ext_inst_import(
+            self.spv,
+            self.op_ext_inst_import_idxs,
+            &mut self.instruction_bound,
+            &mut self.ext_inst_import_header,
+            |s| s.starts_with("GLSL.std."),
+            "GLSL.std.450",
+        );
+        self.glsl_std_id = Some(id);
+        id
+    }
+}
+
+const NARROW_STORAGE_CAPABILITIES: [u32; 10] = [
+    SPV_CAPABILITY_INT8,
+    SPV_CAPABILITY_INT16,
+    SPV_CAPABILITY_FLOAT16,
+    SPV_CAPABILITY_STORAGE_BUFFER_16_BIT_ACCESS,
+    SPV_CAPABILITY_UNIFORM_AND_STORAGE_BUFFER_16_BIT_ACCESS,
+    SPV_CAPABILITY_STORAGE_PUSH_CONSTANT_16,
+    SPV_CAPABILITY_STORAGE_INPUT_OUTPUT_16,
+    SPV_CAPABILITY_STORAGE_BUFFER_8_BIT_ACCESS,
+    SPV_CAPABILITY_UNIFORM_AND_STORAGE_BUFFER_8_BIT_ACCESS,
+    SPV_CAPABILITY_STORAGE_PUSH_CONSTANT_8,
+];
+
+const NARROW_STORAGE_EXTENSIONS: [&str; 2] = ["SPV_KHR_8bit_storage", "SPV_KHR_16bit_storage"];
+
+/// Perform the operation on a `Vec<u32>`.
+/// Use [u8_slice_to_u32_vec] to convert a `&[u8]` into a `Vec<u32>`.
+/// Does not produce any side effects or corrections.
+///
+/// Fails when a struct or array containing narrow members is loaded, stored, or copied as a whole,
+/// or when narrow members cannot be packed into words without moving their neighbours.
+pub fn widenstoragepatch(in_spv: &[u32]) -> Result<Vec<u32>, ()> {
+    let spv = in_spv.to_owned();
+
+    let mut instruction_bound = spv[SPV_HEADER_INSTRUCTION_BOUND_OFFSET];
+    let magic_number = spv[SPV_HEADER_MAGIC_NUM_OFFSET];
+
+    let spv_header = spv[0..SPV_HEADER_LENGTH].to_owned();
+
+    assert_eq!(magic_number, SPV_HEADER_MAGIC);
+
+    let mut instruction_inserts = vec![];
+    let word_inserts = vec![];
+
+    let spv = spv.into_iter().skip(SPV_HEADER_LENGTH).collect::<Vec<_>>();
+    let mut new_spv = spv.clone();
+
+    // 1. Find locations instructions we need
+    let mut op_capability_idxs = vec![];
+    let mut op_extension_idxs = vec![];
+    let mut op_ext_inst_import_idxs = vec![];
+    let mut op_name_idxs = vec![];
+    let mut op_member_name_idxs = vec![];
+    let mut op_decorate_idxs = vec![];
+    let mut op_member_decorate_idxs = vec![];
+    let mut op_type_bool_idxs = vec![];
+    let mut op_type_int_idxs = vec![];
+    let mut op_type_float_idxs = vec![];
+    let mut op_type_vector_idxs = vec![];
+    let mut op_type_matrix_idxs = vec![];
+    let mut op_type_array_idxs = vec![];
+    let mut op_type_runtime_array_idxs = vec![];
+    let mut op_type_struct_idxs = vec![];
+    let mut op_type_pointer_idxs = vec![];
+    let mut op_constant_idxs = vec![];
+    let mut op_constant_composite_idxs = vec![];
+    let mut op_variable_idxs = vec![];
+    let mut op_function_idxs = vec![];
+    let mut op_access_chain_idxs = vec![];
+    let mut op_load_idxs = vec![];
+    let mut op_store_idxs = vec![];
+    let mut op_copy_memory_idxs = vec![];
+    let mut op_array_length_idxs = vec![];
+    let mut op_convert_idxs = vec![];
+
+    let mut spv_idx = 0;
+    while spv_idx < spv.len() {
+        let op = spv[spv_idx];
+        let word_count = hiword(op);
+        let instruction = loword(op);
+
+        match instruction {
+            SPV_INSTRUCTION_OP_CAPABILITY => op_capability_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXTENSION => op_extension_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_EXT_INST_IMPORT => op_ext_inst_import_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_NAME => op_name_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_NAME => op_member_name_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_DECORATE => op_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_MEMBER_DECORATE => op_member_decorate_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_BOOL => op_type_bool_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_INT => op_type_int_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_FLOAT => op_type_float_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_VECTOR => op_type_vector_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_MATRIX => op_type_matrix_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_ARRAY => op_type_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY => op_type_runtime_array_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_STRUCT => op_type_struct_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_TYPE_POINTER => op_type_pointer_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT => op_constant_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_CONSTANT_COMPOSITE => op_constant_composite_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_VARIABLE => op_variable_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_FUNCTION => op_function_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ACCESS_CHAIN | SPV_INSTRUCTION_OP_IN_BOUNDS_ACCESS_CHAIN => {
+                op_access_chain_idxs.push(spv_idx)
+            }
+            SPV_INSTRUCTION_OP_LOAD => op_load_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_STORE => op_store_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_COPY_MEMORY => op_copy_memory_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_ARRAY_LENGTH => op_array_length_idxs.push(spv_idx),
+            SPV_INSTRUCTION_OP_U_CONVERT
+            | SPV_INSTRUCTION_OP_S_CONVERT
+            | SPV_INSTRUCTION_OP_F_CONVERT
+            | SPV_INSTRUCTION_OP_CONVERT_U_TO_F
+            | SPV_INSTRUCTION_OP_CONVERT_S_TO_F => op_convert_idxs.push(spv_idx),
+            _ => {}
+        }
+
+        spv_idx += word_count as usize;
+    }
+
+    // 2. Find narrow types
+    let mut narrow_scalars = HashMap::new();
+    for &idx in &op_type_int_idxs {
+        let width = spv[idx + 2];
+        if width == 8 || width == 16 {
+            let kind = if spv[idx + 3] == SPV_SIGNEDNESS_SIGNED {
+                NarrowKind::Signed
+            } else {
+                NarrowKind::Unsigned
+            };
+            narrow_scalars.insert(spv[idx + 1], NarrowScalar { kind, width });
+        }
+    }
+    for &idx in &op_type_float_idxs {
+        if spv[idx + 2] == 16 {
+            narrow_scalars.insert(
+                spv[idx + 1],
+                NarrowScalar {
+                    kind: NarrowKind::Float,
+                    width: 16,
+                },
+            );
+        }
+    }
+
+    if narrow_scalars.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    let narrow_vectors = op_type_vector_idxs
+        .iter()
+        .filter_map(|&idx| {
+            narrow_scalars
+                .get(&spv[idx + 2])
+                .map(|&scalar| (spv[idx + 1], (scalar, spv[idx + 3])))
+        })
+        .collect::<HashMap<_, _>>();
+    let narrow_element = |id| {
+        narrow_scalars
+            .get(&id)
+            .map(|&scalar| (scalar, None))
+            .or_else(|| {
+                narrow_vectors
+                    .get(&id)
+                    .map(|&(scalar, count)| (scalar, Some(count)))
+            })
+    };
+
+    let wide_kinds = op_type_int_idxs
+        .iter()
+        .filter(|&&idx| spv[idx + 2] == 32)
+        .map(|&idx| {
+            let kind = if spv[idx + 3] == SPV_SIGNEDNESS_SIGNED {
+                WideKind::Int
+            } else {
+                WideKind::Uint
+            };
+            (spv[idx + 1], kind)
+        })
+        .chain(
+            op_type_float_idxs
+                .iter()
+                .filter(|&&idx| spv[idx + 2] == 32)
+                .map(|&idx| (spv[idx + 1], WideKind::Float)),
+        )
+        .collect::<HashMap<_, _>>();
+    let wide_kind_of = |type_id| {
+        let scalar_id = op_type_vector_idxs
+            .iter()
+            .find_map(|&idx| (spv[idx + 1] == type_id).then_some(spv[idx + 2]))
+            .unwrap_or(type_id);
+        wide_kinds.get(&scalar_id).copied()
+    };
+
+    let constant_values = op_constant_idxs
+        .iter()
+        .filter(|&&idx| hiword(spv[idx]) == 4)
+        .map(|&idx| (spv[idx + 2], spv[idx + 3]))
+        .collect::<HashMap<_, _>>();
+    let array_types = op_type_array_idxs
+        .iter()
+        .map(|&idx| {
+            (
+                spv[idx + 1],
+                (spv[idx + 2], constant_values.get(&spv[idx + 3]).copied()),
+            )
+        })
+        .chain(
+            op_type_runtime_array_idxs
+                .iter()
+                .map(|&idx| (spv[idx + 1], (spv[idx + 2], None))),
+        )
+        .collect::<HashMap<_, _>>();
+    let struct_idxs = op_type_struct_idxs
+        .iter()
+        .map(|&idx| (spv[idx + 1], idx))
+        .collect::<HashMap<_, _>>();
+    let struct_members = |idx: usize| &spv[idx + 2..idx + hiword(spv[idx]) as usize];
+    let pointer_types = op_type_pointer_idxs
+        .iter()
+        .map(|&idx| (spv[idx + 1], (spv[idx + 2], spv[idx + 3])))
+        .collect::<HashMap<_, _>>();
+    let array_strides = op_decorate_idxs
+        .iter()
+        .filter(|&&idx| spv[idx + 2] == SPV_DECORATION_ARRAY_STRIDE)
+        .map(|&idx| (spv[idx + 1], spv[idx + 3]))
+        .collect::<HashMap<_, _>>();
+    let member_offsets = op_member_decorate_idxs
+        .iter()
+        .filter(|&&idx| spv[idx + 3] == SPV_DECORATION_OFFSET)
+        .map(|&idx| ((spv[idx + 1], spv[idx + 2]), spv[idx + 4]))
+        .collect::<HashMap<_, _>>();
+    let is_ssbo = |storage_class, pointee| {
+        storage_class == SPV_STORAGE_CLASS_STORAGE_BUFFER
+            || (storage_class == SPV_STORAGE_CLASS_UNIFORM
+                && op_decorate_idxs.iter().any(|&idx| {
+                    spv[idx + 1] == pointee && spv[idx + 2] == SPV_DECORATION_BUFFER_BLOCK
+                }))
+    };
+
+    // Visit through arrays, structs, and matrices
+    let contains = |id, matches: &dyn Fn(u32) -> bool| {
+        let mut to_visit = vec![id];
+        while let Some(id) = to_visit.pop() {
+            if matches(id) {
+                return true;
+            }
+            if let Some(&(element_id, _)) = array_types.get(&id) {
+                to_visit.push(element_id);
+            } else if let Some(&idx) = struct_idxs.get(&id) {
+                to_visit.extend_from_slice(struct_members(idx));
+            } else if let Some(&idx) = op_type_matrix_idxs.iter().find(|&&idx| spv[idx + 1] == id) {
+                to_visit.push(spv[idx + 2]);
+            }
+        }
+        false
+    };
+    let contains_narrow = |id| contains(id, &|id| narrow_element(id).is_some());
+
+    // 3. Find the structs behind storage buffers with narrow members, and group them into word ranges
+    let type_registry = build_type_registry(BuildTypeRegistryIn {
+        spv: &spv,
+        op_type_bool_idxs: &op_type_bool_idxs,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_int_idxs: &op_type_int_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_type_matrix_idxs: &op_type_matrix_idxs,
+        op_type_array_idxs: &op_type_array_idxs,
+        op_type_struct_idxs: &op_type_struct_idxs,
+        op_constant_idxs: &op_constant_idxs,
+    });
+
+    let roots = op_variable_idxs
+        .iter()
+        .filter_map(|&idx| {
+            let &(storage_class, pointee) = pointer_types.get(&spv[idx + 1])?;
+            is_ssbo(storage_class, pointee).then_some(pointee)
+        })
+        .collect::<HashSet<_>>();
+
+    let mut packed_structs = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut to_visit = roots.iter().copied().collect::<Vec<_>>();
+    while let Some(id) = to_visit.pop() {
+        if !visited.insert(id) {
+            continue;
+        }
+        if let Some(&(element_id, _)) = array_types.get(&id) {
+            to_visit.push(element_id);
+            continue;
+        }
+        let Some(&s_idx) = struct_idxs.get(&id) else {
+            continue;
+        };
+
+        let mut narrow_members = vec![];
+        for &member_id in struct_members(s_idx) {
+            let narrow_member = if let Some((scalar, count)) = narrow_element(member_id) {
+                Some(NarrowMember {
+                    scalar,
+                    count,
+                    array: None,
+                })
+            } else if let Some(&(element_id, len)) = array_types.get(&member_id)
+                && let Some((scalar, count)) = narrow_element(element_id)
+            {
+                Some(NarrowMember {
+                    scalar,
+                    count,
+                    array: Some((len, *array_strides.get(&member_id).ok_or(())?)),
+                })
+            } else {
+                None
+            };
+
+            if narrow_member.is_none() && contains_narrow(member_id) {
+                // Only structs can hold narrow members, directly or through arrays
+                let mut inner_id = member_id;
+                while let Some(&(element_id, _)) = array_types.get(&inner_id) {
+                    inner_id = element_id;
+                }
+                if !struct_idxs.contains_key(&inner_id) {
+                    return Err(());
+                }
+                to_visit.push(member_id);
+            }
+            narrow_members.push(narrow_member);
+        }
+        if narrow_members.iter().all(Option::is_none) {
+            continue;
+        }
+
+        let mut new_members = vec![];
+        let mut member_map = vec![MemberMap::Kept(0); narrow_members.len()];
+        let mut kept_end = 0;
+        let mut range: Option<MemberRange> = None;
+
+        let close_range =
+            |range: MemberRange, new_members: &mut Vec<NewMember>, member_map: &mut [MemberMap]| {
+                let MemberRange {
+                    start,
+                    end,
+                    members,
+                } = range;
+                let new_index = new_members.len() as u32;
+                new_members.push(NewMember::Range {
+                    first_member: members[0].0 as u32,
+                    word_offset: start / 4,
+                    word_count: end.map(|end| end.div_ceil(4) - start / 4),
+                });
+                for (member, offset, narrow_member) in members {
+                    member_map[member] = MemberMap::Packed {
+                        new_index,
+                        byte_offset: offset - start,
+                        member: narrow_member,
+                    };
+                }
+            };
+
+        for (member, narrow_member) in narrow_members.iter().enumerate() {
+            let offset = *member_offsets.get(&(id, member as u32)).ok_or(())?;
+            match narrow_member {
+                Some(narrow_member) => {
+                    let end = narrow_member.size().map(|size| offset + size);
+                    match &mut range {
+                        Some(range) => {
+                            range.end = range.end.zip(end).map(|(a, b)| a.max(b));
+                            range.members.push((member, offset, *narrow_member));
+                        }
+                        None => {
+                            let start = offset / 4 * 4;
+                            if start < kept_end {
+                                return Err(());
+                            }
+                            range = Some(MemberRange {
+                                start,
+                                end,
+                                members: vec![(member, offset, *narrow_member)],
+                            });
+                        }
+                    }
+                }
+                None => {
+                    if let Some(range) = range.take() {
+                        let word_end = range.end.ok_or(())?.div_ceil(4) * 4;
+                        if offset < word_end {
+                            return Err(());
+                        }
+                        close_range(range, &mut new_members, &mut member_map);
+                    }
+                    member_map[member] = MemberMap::Kept(new_members.len() as u32);
+                    new_members.push(NewMember::Kept(member as u32));
+                    kept_end = type_registry
+                        .get(&struct_members(s_idx)[member])
+                        .map(|ty| offset + size_of(&ty.kind, LayoutRule::Std430))
+                        .unwrap_or(u32::MAX);
+                }
+            }
+        }
+        if let Some(range) = range.take() {
+            // Growing the tail of a nested struct would move whatever follows it
+            if let Some(end) = range.end
+                && end % 4 != 0
+                && !roots.contains(&id)
+                && type_registry
+                    .get(&id)
+                    .is_none_or(|ty| base_align(&ty.kind, LayoutRule::Std430) < 4)
+            {
+                return Err(());
+            }
+            close_range(range, &mut new_members, &mut member_map);
+        }
+
+        packed_structs.insert(
+            id,
+            PackedStruct {
+                idx: s_idx,
+                new_members,
+                member_map,
+            },
+        );
+    }
+
+    if packed_structs.is_empty() {
+        return Ok(in_spv.to_vec());
+    }
+
+    // Packed structs are rewritten in place, so they must not be shared with anything else
+    if op_type_pointer_idxs.iter().any(|&idx| {
+        packed_structs.contains_key(&spv[idx + 3]) && !is_ssbo(spv[idx + 2], spv[idx + 3])
+    }) {
+        return Err(());
+    }
+    let contains_packed = |id| contains(id, &|id| packed_structs.contains_key(&id));
+
+    // 4. Ensure `uint` is declared before the first packed struct
+    let mut packed_struct_ids = packed_structs.keys().copied().collect::<Vec<_>>();
+    packed_struct_ids.sort_by_key(|id| packed_structs[id].idx);
+    let first_packed_idx = packed_structs[&packed_struct_ids[0]].idx;
+
+    let mut struct_insert = InstructionInsert {
+        previous_spv_idx: first_packed_idx,
+        instruction: vec![],
+    };
+    let uint_id = match op_type_int_idxs
+        .iter()
+        .copied()
+        .find(|&idx| spv[idx + 2] == 32 && spv[idx + 3] == SPV_SIGNEDNESS_UNSIGNED)
+    {
+        Some(idx) if idx < first_packed_idx => spv[idx + 1],
+        existing_idx => {
+            let id = match existing_idx {
+                Some(idx) => {
+                    for i in 0..hiword(spv[idx]) as usize {
+                        new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                    }
+                    spv[idx + 1]
+                }
+                None => inc(&mut instruction_bound),
+            };
+            struct_insert.instruction.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_TYPE_INT),
+                id,
+                32,
+                SPV_SIGNEDNESS_UNSIGNED,
+            ]);
+            id
+        }
+    };
+
+    let mut ctx = WidenContext {
+        spv: &spv,
+        op_type_int_idxs: &op_type_int_idxs,
+        op_type_float_idxs: &op_type_float_idxs,
+        op_type_vector_idxs: &op_type_vector_idxs,
+        op_type_pointer_idxs: &op_type_pointer_idxs,
+        op_constant_idxs: &op_constant_idxs,
+        op_ext_inst_import_idxs: &op_ext_inst_import_idxs,
+        instruction_bound,
+        header: vec![],
+        ext_inst_import_header: vec![],
+        uint_id,
+        int_id: None,
+        float_id: None,
+        glsl_std_id: None,
+        vectors: HashMap::new(),
+        pointers: HashMap::new(),
+        constants: HashMap::new(),
+    };
+
+    // 5. Redeclare packed structs with word arrays in place of their narrow ranges
+    let mut uint_arrays = HashMap::new();
+    let mut names = vec![];
+    let mut decorations = vec![];
+
+    for &id in &packed_struct_ids {
+        let PackedStruct {
+            idx: s_idx,
+            new_members,
+            ..
+        } = &packed_structs[&id];
+        let s_idx = *s_idx;
+        let members = struct_members(s_idx);
+
+        if s_idx != first_packed_idx {
+            instruction_inserts.push(struct_insert);
+            struct_insert = InstructionInsert {
+                previous_spv_idx: s_idx,
+                instruction: vec![],
+            };
+        }
+
+        let mut member_types = vec![];
+        for &new_member in new_members {
+            let word_count = match new_member {
+                NewMember::Kept(member) => {
+                    member_types.push(members[member as usize]);
+                    continue;
+                }
+                NewMember::Range { word_count, .. } => word_count,
+            };
+            if let Some(&array_id) = uint_arrays.get(&word_count) {
+                member_types.push(array_id);
+                continue;
+            }
+
+            let array_id = ctx.inc();
+            match word_count {
+                Some(count) => {
+                    let length_id = match op_constant_idxs.iter().find(|&&c_idx| {
+                        c_idx < s_idx
+                            && spv[c_idx + 1] == uint_id
+                            && constant_values.get(&spv[c_idx + 2]) == Some(&count)
+                    }) {
+                        Some(&c_idx) => spv[c_idx + 2],
+                        None => {
+                            let length_id = ctx.inc();
+                            struct_insert.instruction.append(&mut vec![
+                                encode_word(4, SPV_INSTRUCTION_OP_CONSTANT),
+                                uint_id,
+                                length_id,
+                                count,
+                            ]);
+                            ctx.constants.insert((uint_id, count), length_id);
+                            length_id
+                        }
+                    };
+                    struct_insert.instruction.append(&mut vec![
+                        encode_word(4, SPV_INSTRUCTION_OP_TYPE_ARRAY),
+                        array_id,
+                        uint_id,
+                        length_id,
+                    ]);
+                }
+                None => {
+                    struct_insert.instruction.append(&mut vec![
+                        encode_word(3, SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY),
+                        array_id,
+                        uint_id,
+                    ]);
+                }
+            }
+            decorations.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_DECORATE),
+                array_id,
+                SPV_DECORATION_ARRAY_STRIDE,
+                4,
+            ]);
+            uint_arrays.insert(word_count, array_id);
+            member_types.push(array_id);
+        }
+
+        for i in 0..hiword(spv[s_idx]) as usize {
+            new_spv[s_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        struct_insert.instruction.append(&mut vec![
+            encode_word(
+                2 + member_types.len() as u16,
+                SPV_INSTRUCTION_OP_TYPE_STRUCT,
+            ),
+            id,
+        ]);
+        struct_insert.instruction.append(&mut member_types);
+
+        // Member names and decorations follow their members, word ranges take after their first member
+        for &idx in op_member_name_idxs
+            .iter()
+            .chain(op_member_decorate_idxs.iter())
+        {
+            if spv[idx + 1] == id {
+                for i in 0..hiword(spv[idx]) as usize {
+                    new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                }
+            }
+        }
+        for (new_index, &new_member) in new_members.iter().enumerate() {
+            let (member, word_offset) = match new_member {
+                NewMember::Kept(member) => (member, None),
+                NewMember::Range {
+                    first_member,
+                    word_offset,
+                    ..
+                } => (first_member, Some(word_offset)),
+            };
+            for &idx in &op_member_name_idxs {
+                if spv[idx + 1] == id && spv[idx + 2] == member {
+                    let mut name = spv[idx..idx + hiword(spv[idx]) as usize].to_vec();
+                    name[2] = new_index as u32;
+                    names.append(&mut name);
+                }
+            }
+            for &idx in &op_member_decorate_idxs {
+                if spv[idx + 1] == id
+                    && spv[idx + 2] == member
+                    && (word_offset.is_none() || spv[idx + 3] != SPV_DECORATION_OFFSET)
+                {
+                    let mut decoration = spv[idx..idx + hiword(spv[idx]) as usize].to_vec();
+                    decoration[2] = new_index as u32;
+                    decorations.append(&mut decoration);
+                }
+            }
+            if let Some(word_offset) = word_offset {
+                decorations.append(&mut vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_MEMBER_DECORATE),
+                    id,
+                    new_index as u32,
+                    SPV_DECORATION_OFFSET,
+                    word_offset * 4,
+                ]);
+            }
+        }
+    }
+    instruction_inserts.push(struct_insert);
+
+    if !names.is_empty() {
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: last_of_indices!(op_name_idxs, op_member_name_idxs).unwrap(),
+            instruction: names,
+        });
+    }
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: last_of_indices!(op_decorate_idxs, op_member_decorate_idxs)
+            .expect("Block has no OpDecorate (missing Block decoration?)"),
+        instruction: decorations,
+    });
+
+    // 6. Remap access chains through packed structs, chains into word ranges become byte offsets
+    let mut chain_pointers = HashMap::new();
+    for &idx in &op_variable_idxs {
+        if let Some(&(storage_class, pointee)) = pointer_types.get(&spv[idx + 1]) {
+            chain_pointers.insert(
+                spv[idx + 2],
+                ChainPointer {
+                    pointee,
+                    storage_class,
+                    ssbo: is_ssbo(storage_class, pointee),
+                },
+            );
+        }
+    }
+
+    let child_type = |ty, index| {
+        if let Some(&s_idx) = struct_idxs.get(&ty) {
+            let member = *constant_values.get(&index)?;
+            struct_members(s_idx).get(member as usize).copied()
+        } else if let Some(&(element_id, _)) = array_types.get(&ty) {
+            Some(element_id)
+        } else {
+            op_type_vector_idxs
+                .iter()
+                .chain(op_type_matrix_idxs.iter())
+                .find_map(|&idx| (spv[idx + 1] == ty).then_some(spv[idx + 2]))
+        }
+    };
+
+    let mut narrow_pointers: HashMap<u32, NarrowPointer> = HashMap::new();
+    for &c_idx in &op_access_chain_idxs {
+        let result_id = spv[c_idx + 2];
+        let base_id = spv[c_idx + 3];
+        let indices = &spv[c_idx + 4..c_idx + hiword(spv[c_idx]) as usize];
+
+        if narrow_pointers.contains_key(&base_id) {
+            return Err(());
+        }
+        let Some(&base) = chain_pointers.get(&base_id) else {
+            continue;
+        };
+        let Some(&(_, pointee)) = pointer_types.get(&spv[c_idx + 1]) else {
+            continue;
+        };
+        let chain_pointer = ChainPointer { pointee, ..base };
+        if !base.ssbo {
+            chain_pointers.insert(result_id, chain_pointer);
+            continue;
+        }
+
+        let mut ty = Some(base.pointee);
+        let mut new_indices = vec![];
+        let mut narrow = None;
+        for (i, &index) in indices.iter().enumerate() {
+            let Some(packed_struct) = ty.and_then(|ty| packed_structs.get(&ty)) else {
+                new_indices.push(index);
+                ty = ty.and_then(|ty| child_type(ty, index));
+                continue;
+            };
+            let member = *constant_values.get(&index).ok_or(())?;
+            match *packed_struct.member_map.get(member as usize).ok_or(())? {
+                MemberMap::Kept(new_index) => {
+                    new_indices.push(if new_index == member {
+                        index
+                    } else {
+                        ctx.uint(new_index)
+                    });
+                    ty = ty.and_then(|ty| child_type(ty, index));
+                }
+                MemberMap::Packed {
+                    new_index,
+                    byte_offset,
+                    member,
+                } => {
+                    new_indices.push(ctx.uint(new_index));
+                    narrow = Some((i, byte_offset, member));
+                    break;
+                }
+            }
+        }
+
+        let Some((i, byte_offset, member)) = narrow else {
+            for (i, &index) in new_indices.iter().enumerate() {
+                new_spv[c_idx + 4 + i] = index;
+            }
+            chain_pointers.insert(result_id, chain_pointer);
+            continue;
+        };
+
+        let mut rest = indices[i + 1..].iter();
+        let mut terms = vec![];
+        let mut is_array = false;
+        let mut count = member.count;
+        if let Some((_, stride)) = member.array {
+            match rest.next() {
+                Some(&index) => terms.push((index, stride)),
+                None => is_array = true,
+            }
+        }
+        if !is_array
+            && member.count.is_some()
+            && let Some(&index) = rest.next()
+        {
+            terms.push((index, member.scalar.width / 8));
+            count = None;
+        }
+        if rest.next().is_some() {
+            return Err(());
+        }
+
+        //
+        //  %unsigned = OpBitcast %uint %index
+        //    %scaled = OpIMul %uint %unsigned %uint_stride
+        //      %byte = OpIAdd %uint %byte %scaled
+        let mut byte_spv = vec![];
+        let mut constant_byte = byte_offset;
+        let mut dynamic_byte = None;
+        let mut aligned = true;
+        for (index, scale) in terms {
+            if let Some(&value) = constant_values.get(&index) {
+                constant_byte += value * scale;
+                continue;
+            }
+            aligned &= scale % 4 == 0;
+
+            let index_type_id = trace_previous_intermediate_id(&spv, index, c_idx).ok_or(())?;
+            let unsigned = if wide_kind_of(index_type_id) == Some(WideKind::Int) {
+                let unsigned = ctx.inc();
+                byte_spv.append(&mut vec![
+                    encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                    uint_id,
+                    unsigned,
+                    index,
+                ]);
+                unsigned
+            } else {
+                index
+            };
+            let scaled = if scale == 1 {
+                unsigned
+            } else {
+                let uint_scale = ctx.uint(scale);
+                let scaled = ctx.inc();
+                byte_spv.append(&mut vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_I_MUL),
+                    uint_id,
+                    scaled,
+                    unsigned,
+                    uint_scale,
+                ]);
+                scaled
+            };
+            dynamic_byte = Some(match dynamic_byte {
+                None => scaled,
+                Some(byte) => {
+                    let sum = ctx.inc();
+                    byte_spv.append(&mut vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_I_ADD),
+                        uint_id,
+                        sum,
+                        byte,
+                        scaled,
+                    ]);
+                    sum
+                }
+            });
+        }
+        aligned &= constant_byte % 4 == 0;
+
+        let byte = match dynamic_byte {
+            None => ByteOffset::Constant(constant_byte),
+            Some(byte) if constant_byte == 0 => ByteOffset::Dynamic(byte),
+            Some(byte) => {
+                let uint_constant_byte = ctx.uint(constant_byte);
+                let sum = ctx.inc();
+                byte_spv.append(&mut vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_I_ADD),
+                    uint_id,
+                    sum,
+                    byte,
+                    uint_constant_byte,
+                ]);
+                ByteOffset::Dynamic(sum)
+            }
+        };
+
+        let mut prefix = vec![base_id];
+        prefix.append(&mut new_indices);
+        narrow_pointers.insert(
+            result_id,
+            NarrowPointer {
+                prefix,
+                word_pointer_type_id: ctx.uint_pointer(base.storage_class),
+                byte,
+                aligned,
+                scalar: member.scalar,
+                count,
+                is_array,
+            },
+        );
+
+        for i in 0..hiword(spv[c_idx]) as usize {
+            new_spv[c_idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        if !byte_spv.is_empty() {
+            instruction_inserts.push(InstructionInsert {
+                previous_spv_idx: c_idx,
+                instruction: byte_spv,
+            });
+        }
+    }
+
+    let is_packed_aggregate = |ptr: u32| {
+        chain_pointers
+            .get(&ptr)
+            .is_some_and(|chain_pointer: &ChainPointer| {
+                chain_pointer.ssbo && contains_packed(chain_pointer.pointee)
+            })
+    };
+    let word_pointer_spv = |ctx: &mut WidenContext, pointer: &NarrowPointer, word_index| {
+        let word_ptr = ctx.inc();
+        let mut spv = vec![
+            encode_word(
+                4 + pointer.prefix.len() as u16,
+                SPV_INSTRUCTION_OP_ACCESS_CHAIN,
+            ),
+            pointer.word_pointer_type_id,
+            word_ptr,
+        ];
+        spv.extend_from_slice(&pointer.prefix);
+        spv.push(word_index);
+        (word_ptr, spv)
+    };
+
+    // Narrow values that are left over are dropped once nothing uses them
+    let mut uses = HashMap::new();
+    if let Some(&function_idx) = op_function_idxs.first() {
+        let mut idx = function_idx;
+        while idx < spv.len() {
+            let word_count = hiword(spv[idx]) as usize;
+            for &word in &spv[idx + 1..idx + word_count] {
+                *uses.entry(word).or_insert(0i32) += 1;
+            }
+            idx += word_count;
+        }
+    }
+    let mut keeps_narrow = false;
+
+    // 7. Load narrow values a word at a time, widening them to 32 bits
+    let mut wide_values = HashMap::new();
+    let mut pending_loads = vec![];
+    for &idx in &op_load_idxs {
+        let ptr = spv[idx + 3];
+        let Some(pointer) = narrow_pointers.get(&ptr) else {
+            if is_packed_aggregate(ptr) {
+                return Err(());
+            }
+            continue;
+        };
+        if pointer.is_array {
+            return Err(());
+        }
+
+        let kind = WideKind::from(pointer.scalar.kind);
+        let mut load_spv = vec![];
+        let mut words = HashMap::new();
+        let mut components = vec![];
+        for c in 0..pointer.count.unwrap_or(1) {
+            let (access, mut access_spv) =
+                word_access_spv(&mut ctx, pointer.byte, c * pointer.scalar.width / 8);
+            load_spv.append(&mut access_spv);
+
+            let word = match words.get(&access.word_index) {
+                Some(&word) => word,
+                None => {
+                    let (word_ptr, mut word_ptr_spv) =
+                        word_pointer_spv(&mut ctx, pointer, access.word_index);
+                    let word = ctx.inc();
+                    load_spv.append(&mut word_ptr_spv);
+                    load_spv.append(&mut vec![
+                        encode_word(4, SPV_INSTRUCTION_OP_LOAD),
+                        uint_id,
+                        word,
+                        word_ptr,
+                    ]);
+                    if access.constant_shift.is_some() {
+                        words.insert(access.word_index, word);
+                    }
+                    word
+                }
+            };
+
+            let (component, mut extract_spv) =
+                narrow_extract_spv(&mut ctx, pointer.scalar, word, access);
+            load_spv.append(&mut extract_spv);
+            components.push(component);
+        }
+
+        let wide_id = match pointer.count {
+            Some(count) => {
+                let wide_type_id = ctx.wide_type(kind, Some(count));
+                let wide_id = ctx.inc();
+                load_spv.append(&mut vec![
+                    encode_word(3 + count as u16, SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+                    wide_type_id,
+                    wide_id,
+                ]);
+                load_spv.append(&mut components);
+                wide_id
+            }
+            None => components[0],
+        };
+
+        wide_values.insert(spv[idx + 2], WideValue { id: wide_id, kind });
+        pending_loads.push((idx, load_spv));
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+    }
+
+    // 8. Fold conversions of loaded values into the widened values
+    let mut narrowed_values = HashMap::new();
+    for &idx in &op_convert_idxs {
+        let instruction = loword(spv[idx]);
+        let result_type_id = spv[idx + 1];
+        let operand = spv[idx + 3];
+
+        if let Some(&wide) = wide_values.get(&operand) {
+            let is_wide = wide_kind_of(result_type_id);
+            let fold = match (instruction, wide.kind) {
+                (SPV_INSTRUCTION_OP_U_CONVERT, WideKind::Uint)
+                | (SPV_INSTRUCTION_OP_S_CONVERT, WideKind::Int)
+                | (SPV_INSTRUCTION_OP_F_CONVERT, WideKind::Float)
+                    if is_wide.is_some() =>
+                {
+                    Some(if is_wide == Some(wide.kind) {
+                        SPV_INSTRUCTION_OP_COPY_OBJECT
+                    } else {
+                        SPV_INSTRUCTION_OP_BITCAST
+                    })
+                }
+                (SPV_INSTRUCTION_OP_CONVERT_U_TO_F, WideKind::Uint)
+                | (SPV_INSTRUCTION_OP_CONVERT_S_TO_F, WideKind::Int) => Some(instruction),
+                _ => None,
+            };
+            if let Some(instruction) = fold {
+                new_spv[idx] = encode_word(4, instruction);
+                new_spv[idx + 3] = wide.id;
+                *uses.get_mut(&operand).unwrap() -= 1;
+            }
+        } else if narrow_element(result_type_id).is_some()
+            && instruction != SPV_INSTRUCTION_OP_CONVERT_U_TO_F
+            && instruction != SPV_INSTRUCTION_OP_CONVERT_S_TO_F
+            && let Some(kind) =
+                trace_previous_intermediate_id(&spv, operand, idx).and_then(wide_kind_of)
+        {
+            narrowed_values.insert(spv[idx + 2], (idx, WideValue { id: operand, kind }));
+        }
+    }
+
+    // 9. Store narrow values a word at a time
+    for &idx in &op_store_idxs {
+        let ptr = spv[idx + 1];
+        let value = spv[idx + 2];
+        let Some(pointer) = narrow_pointers.get(&ptr) else {
+            if is_packed_aggregate(ptr) {
+                return Err(());
+            }
+            continue;
+        };
+        if pointer.is_array {
+            return Err(());
+        }
+
+        let scalar = pointer.scalar;
+        let count = pointer.count.unwrap_or(1);
+        let matches_scalar =
+            |wide: &WideValue| (wide.kind == WideKind::Float) == (scalar.kind == NarrowKind::Float);
+        let mut store_spv = vec![];
+
+        let known_wide = wide_values
+            .get(&value)
+            .or_else(|| narrowed_values.get(&value).map(|(_, wide)| wide))
+            .copied()
+            .filter(matches_scalar);
+        let wide = match known_wide {
+            Some(wide) => {
+                *uses.get_mut(&value).unwrap() -= 1;
+                wide
+            }
+            None => {
+                let kind = WideKind::from(scalar.kind);
+                let wide_type_id = ctx.wide_type(kind, pointer.count);
+                let wide_id = ctx.inc();
+                let instruction = match scalar.kind {
+                    NarrowKind::Unsigned => SPV_INSTRUCTION_OP_U_CONVERT,
+                    NarrowKind::Signed => SPV_INSTRUCTION_OP_S_CONVERT,
+                    NarrowKind::Float => SPV_INSTRUCTION_OP_F_CONVERT,
+                };
+                store_spv.append(&mut vec![
+                    encode_word(4, instruction),
+                    wide_type_id,
+                    wide_id,
+                    value,
+                ]);
+                keeps_narrow = true;
+                WideValue { id: wide_id, kind }
+            }
+        };
+
+        let mut bits = vec![];
+        for c in 0..count {
+            let component = if pointer.count.is_some() {
+                let wide_scalar_type_id = ctx.wide_type(wide.kind, None);
+                let component = ctx.inc();
+                store_spv.append(&mut vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                    wide_scalar_type_id,
+                    component,
+                    wide.id,
+                    c,
+                ]);
+                component
+            } else {
+                wide.id
+            };
+            let (component_bits, mut bits_spv) =
+                narrow_bits_spv(&mut ctx, scalar, wide.kind, component);
+            store_spv.append(&mut bits_spv);
+            bits.push(component_bits);
+        }
+
+        let component_size = scalar.width / 8;
+        if pointer.aligned && (count * component_size) % 4 == 0 {
+            // Whole words are overwritten, nothing else lives in them
+            let per_word = 4 / component_size;
+            for (w, word_bits) in bits.chunks(per_word as usize).enumerate() {
+                let lanes = word_bits
+                    .iter()
+                    .enumerate()
+                    .map(|(lane, &lane_bits)| (lane_bits, lane as u32 * scalar.width))
+                    .collect::<Vec<_>>();
+                let (word, mut word_spv) = narrow_word_spv(&mut ctx, &lanes);
+                let (access, mut access_spv) =
+                    word_access_spv(&mut ctx, pointer.byte, w as u32 * 4);
+                let (word_ptr, mut word_ptr_spv) =
+                    word_pointer_spv(&mut ctx, pointer, access.word_index);
+                store_spv.append(&mut word_spv);
+                store_spv.append(&mut access_spv);
+                store_spv.append(&mut word_ptr_spv);
+                store_spv.append(&mut vec![
+                    encode_word(3, SPV_INSTRUCTION_OP_STORE),
+                    word_ptr,
+                    word,
+                ]);
+            }
+        } else {
+            for (c, &component_bits) in bits.iter().enumerate() {
+                let (access, mut access_spv) =
+                    word_access_spv(&mut ctx, pointer.byte, c as u32 * component_size);
+                let (word_ptr, mut word_ptr_spv) =
+                    word_pointer_spv(&mut ctx, pointer, access.word_index);
+                store_spv.append(&mut access_spv);
+                store_spv.append(&mut word_ptr_spv);
+                store_spv.append(&mut narrow_insert_spv(
+                    &mut ctx,
+                    scalar,
+                    word_ptr,
+                    access,
+                    component_bits,
+                ));
+            }
+        }
+
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: store_spv,
+        });
+    }
+
+    if op_copy_memory_idxs.iter().any(|&idx| {
+        [spv[idx + 1], spv[idx + 2]]
+            .iter()
+            .any(|&ptr| narrow_pointers.contains_key(&ptr) || is_packed_aggregate(ptr))
+    }) {
+        return Err(());
+    }
+
+    // 10. Array lengths of word ranges count words, convert them back into elements
+    for &idx in &op_array_length_idxs {
+        let Some(chain_pointer) = chain_pointers.get(&spv[idx + 3]) else {
+            continue;
+        };
+        let Some(packed_struct) = packed_structs.get(&chain_pointer.pointee) else {
+            continue;
+        };
+        let (new_index, byte_offset, member) = match *packed_struct
+            .member_map
+            .get(spv[idx + 4] as usize)
+            .ok_or(())?
+        {
+            MemberMap::Kept(new_index) => {
+                new_spv[idx + 4] = new_index;
+                continue;
+            }
+            MemberMap::Packed {
+                new_index,
+                byte_offset,
+                member,
+            } => (new_index, byte_offset, member),
+        };
+        let (_, stride) = member.array.ok_or(())?;
+
+        //
+        //  %words = OpArrayLength %uint %block new_index
+        //  %bytes = OpIMul %uint %words %uint_4
+        //  %bytes = OpISub %uint %bytes %uint_byte_offset
+        //      %1 = OpUDiv %uint %bytes %uint_stride
+        let words = ctx.inc();
+        let mut length_spv = vec![
+            encode_word(5, SPV_INSTRUCTION_OP_ARRAY_LENGTH),
+            uint_id,
+            words,
+            spv[idx + 3],
+            new_index,
+        ];
+        let mut steps = vec![(SPV_INSTRUCTION_OP_I_MUL, 4)];
+        if byte_offset != 0 {
+            steps.push((SPV_INSTRUCTION_OP_I_SUB, byte_offset));
+        }
+        if stride != 1 {
+            steps.push((SPV_INSTRUCTION_OP_U_DIV, stride));
+        }
+        let mut value = words;
+        for (i, &(instruction, operand)) in steps.iter().enumerate() {
+            let uint_operand = ctx.uint(operand);
+            let result_id = if i == steps.len() - 1 {
+                spv[idx + 2]
+            } else {
+                ctx.inc()
+            };
+            length_spv.append(&mut vec![
+                encode_word(5, instruction),
+                uint_id,
+                result_id,
+                value,
+                uint_operand,
+            ]);
+            value = result_id;
+        }
+
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: length_spv,
+        });
+    }
+
+    // 11. Narrow values that are still used are converted back from the widened values
+    for (idx, mut load_spv) in pending_loads {
+        let result_id = spv[idx + 2];
+        if uses[&result_id] > 1 {
+            let wide = wide_values[&result_id];
+            let instruction = match wide.kind {
+                WideKind::Uint => SPV_INSTRUCTION_OP_U_CONVERT,
+                WideKind::Int => SPV_INSTRUCTION_OP_S_CONVERT,
+                WideKind::Float => SPV_INSTRUCTION_OP_F_CONVERT,
+            };
+            load_spv.append(&mut vec![
+                encode_word(4, instruction),
+                spv[idx + 1],
+                result_id,
+                wide.id,
+            ]);
+            keeps_narrow = true;
+        }
+        instruction_inserts.push(InstructionInsert {
+            previous_spv_idx: idx,
+            instruction: load_spv,
+        });
+    }
+    for (result_id, &(idx, _)) in &narrowed_values {
+        if uses[result_id] > 1 {
+            continue;
+        }
+        for i in 0..hiword(spv[idx]) as usize {
+            new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+        }
+    }
+
+    // 12. Remove narrow types and their capabilities once nothing refers to them
+    let mut narrow_types = narrow_scalars
+        .keys()
+        .chain(narrow_vectors.keys())
+        .copied()
+        .collect::<HashSet<_>>();
+    let mut derived_type_idxs = op_type_matrix_idxs
+        .iter()
+        .chain(op_type_array_idxs.iter())
+        .chain(op_type_runtime_array_idxs.iter())
+        .chain(op_type_pointer_idxs.iter())
+        .copied()
+        .collect::<Vec<_>>();
+    derived_type_idxs.sort();
+    for &idx in &derived_type_idxs {
+        let underlying_type_id = if loword(spv[idx]) == SPV_INSTRUCTION_OP_TYPE_POINTER {
+            spv[idx + 3]
+        } else {
+            spv[idx + 2]
+        };
+        if narrow_types.contains(&underlying_type_id) {
+            narrow_types.insert(spv[idx + 1]);
+        }
+    }
+
+    let first_type_idx = narrow_scalars
+        .keys()
+        .filter_map(|id| {
+            op_type_int_idxs
+                .iter()
+                .chain(op_type_float_idxs.iter())
+                .find(|&&idx| spv[idx + 1] == *id)
+        })
+        .min()
+        .copied()
+        .unwrap();
+    let mut is_referenced = false;
+    let mut idx = first_type_idx;
+    while idx < new_spv.len() {
+        let word_count = hiword(new_spv[idx]) as usize;
+        let words = &new_spv[idx + 1..idx + word_count];
+        is_referenced |= match loword(new_spv[idx]) {
+            SPV_INSTRUCTION_OP_NOP => false,
+            SPV_INSTRUCTION_OP_TYPE_INT
+            | SPV_INSTRUCTION_OP_TYPE_FLOAT
+            | SPV_INSTRUCTION_OP_TYPE_VECTOR
+            | SPV_INSTRUCTION_OP_TYPE_MATRIX
+            | SPV_INSTRUCTION_OP_TYPE_ARRAY
+            | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY
+            | SPV_INSTRUCTION_OP_TYPE_POINTER
+                if narrow_types.contains(&words[0]) =>
+            {
+                false
+            }
+            SPV_INSTRUCTION_OP_TYPE_STRUCT | SPV_INSTRUCTION_OP_TYPE_FUNCTION => {
+                words[1..].iter().any(|id| narrow_types.contains(id))
+            }
+            _ => words.first().is_some_and(|id| narrow_types.contains(id)),
+        };
+        idx += word_count;
+    }
+
+    if !keeps_narrow && !is_referenced {
+        let mut idx = first_type_idx;
+        while idx < spv.len() {
+            let word_count = hiword(spv[idx]) as usize;
+            if matches!(
+                loword(spv[idx]),
+                SPV_INSTRUCTION_OP_TYPE_INT
+                    | SPV_INSTRUCTION_OP_TYPE_FLOAT
+                    | SPV_INSTRUCTION_OP_TYPE_VECTOR
+                    | SPV_INSTRUCTION_OP_TYPE_MATRIX
+                    | SPV_INSTRUCTION_OP_TYPE_ARRAY
+                    | SPV_INSTRUCTION_OP_TYPE_RUNTIME_ARRAY
+                    | SPV_INSTRUCTION_OP_TYPE_POINTER
+            ) && narrow_types.contains(&spv[idx + 1])
+            {
+                for i in 0..word_count {
+                    new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+                }
+            }
+            idx += word_count;
+        }
+
+        let removed_idxs = op_name_idxs
+            .iter()
+            .chain(op_decorate_idxs.iter())
+            .filter(|&&idx| narrow_types.contains(&spv[idx + 1]))
+            .chain(
+                op_capability_idxs
+                    .iter()
+                    .filter(|&&idx| NARROW_STORAGE_CAPABILITIES.contains(&spv[idx + 1])),
+            )
+            .chain(op_extension_idxs.iter().filter(|&&idx| {
+                literal_to_string_le(&spv[idx + 1..idx + hiword(spv[idx]) as usize]).is_ok_and(
+                    |extension| {
+                        NARROW_STORAGE_EXTENSIONS.contains(&extension.trim_end_matches('\0'))
+                    },
+                )
+            }));
+        for &idx in removed_idxs {
+            for i in 0..hiword(spv[idx]) as usize {
+                new_spv[idx + i] = encode_word(1, SPV_INSTRUCTION_OP_NOP);
+            }
+        }
+    }
+
+    // 13. Insert New Instructions
+    let header_position = last_of_indices!(
+        op_type_bool_idxs,
+        op_type_int_idxs,
+        op_type_float_idxs,
+        op_type_vector_idxs,
+        op_type_matrix_idxs,
+        op_type_array_idxs,
+        op_type_runtime_array_idxs,
+        op_type_struct_idxs,
+        op_type_pointer_idxs,
+        op_constant_idxs,
+        op_constant_composite_idxs
+    );
+    instruction_inserts.push(InstructionInsert {
+        previous_spv_idx: header_position.unwrap(),
+        instruction: ctx.header,
+    });
+    instruction_inserts.insert(
+        0,
+        InstructionInsert {
+            previous_spv_idx: last_of_indices!(
+                op_capability_idxs,
+                op_extension_idxs,
+                op_ext_inst_import_idxs
+            )
+            .expect("Module has no OpCapability?"),
+            instruction: ctx.ext_inst_import_header,
+        },
+    );
+    let instruction_bound = ctx.instruction_bound;
+    insert_new_instructions(&spv, &mut new_spv, &word_inserts, &instruction_inserts);
+
+    // 14. Remove Instructions that have been Whited Out.
+    prune_noops(&mut new_spv);
+
+    // 15. Write New Header and New Code
+    Ok(fuse_final(spv_header, new_spv, instruction_bound))
+}
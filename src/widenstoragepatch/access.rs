@@ -0,0 +1,87 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ByteOffset {
+    Constant(u32),
+    Dynamic(u32),
+}
+
+/// `constant_shift` is known when the byte offset is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct WordAccess {
+    pub word_index: u32,
+    pub shift: u32,
+    pub constant_shift: Option<u32>,
+}
+
+// Find the `u32` word and bit shift of the narrow component at `byte + extra`.
+pub(super) fn word_access_spv(
+    ctx: &mut WidenContext,
+    byte: ByteOffset,
+    extra: u32,
+) -> (WordAccess, Vec<u32>) {
+    //
+    //       %byte = OpIAdd %uint %byte %uint_extra
+    //       %word = OpShiftRightLogical %uint %byte %uint_2
+    //  %sub_bytes = OpBitwiseAnd %uint %byte %uint_3
+    //      %shift = OpShiftLeftLogical %uint %sub_bytes %uint_3
+
+    let byte = match byte {
+        ByteOffset::Constant(byte) => {
+            let byte = byte + extra;
+            let shift_value = (byte & 3) * 8;
+            return (
+                WordAccess {
+                    word_index: ctx.uint(byte >> 2),
+                    shift: ctx.uint(shift_value),
+                    constant_shift: Some(shift_value),
+                },
+                vec![],
+            );
+        }
+        ByteOffset::Dynamic(byte) => byte,
+    };
+
+    let uint_id = ctx.uint_id;
+    let uint_2 = ctx.uint(2);
+    let uint_3 = ctx.uint(3);
+
+    let mut spv = vec![];
+    let byte = if extra == 0 {
+        byte
+    } else {
+        let uint_extra = ctx.uint(extra);
+        let added = ctx.inc();
+        spv.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_I_ADD),
+            uint_id,
+            added,
+            byte,
+            uint_extra,
+        ]);
+        added
+    };
+
+    let word_index = ctx.inc();
+    let sub_bytes = ctx.inc();
+    let shift = ctx.inc();
+
+    #[rustfmt::skip]
+    spv.append(&mut vec![
+        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL),
+            uint_id, word_index, byte, uint_2,
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+            uint_id, sub_bytes, byte, uint_3,
+        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+            uint_id, shift, sub_bytes, uint_3,
+    ]);
+
+    (
+        WordAccess {
+            word_index,
+            shift,
+            constant_shift: None,
+        },
+        spv,
+    )
+}
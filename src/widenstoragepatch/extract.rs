@@ -0,0 +1,129 @@
+use super::*;
+
+// Extract one narrow component out of the already loaded `word`, widened to 32 bits.
+// Unsigned integers are zero extended, signed integers are sign extended.
+pub(super) fn narrow_extract_spv(
+    ctx: &mut WidenContext,
+    scalar: NarrowScalar,
+    word: u32,
+    access: WordAccess,
+) -> (u32, Vec<u32>) {
+    //
+    //  uint8_t / uint16_t:
+    //    %shifted = OpShiftRightLogical %uint %word %shift
+    //      %value = OpBitwiseAnd %uint %shifted %uint_mask
+    //
+    //  int8_t / int16_t:
+    //       %left = OpISub %uint %uint_32_minus_width %shift
+    //       %high = OpShiftLeftLogical %uint %word %left
+    //     %signed = OpBitcast %int %high
+    //      %value = OpShiftRightArithmetic %int %signed %uint_32_minus_width
+    //
+    //  float16_t:
+    //    %shifted = OpShiftRightLogical %uint %word %shift
+    //       %bits = OpBitwiseAnd %uint %shifted %uint_0xffff
+    //       %pair = OpExtInst %v2float %glsl_std UnpackHalf2x16 %bits
+    //      %value = OpCompositeExtract %float %pair 0
+
+    let NarrowScalar { kind, width } = scalar;
+    let uint_id = ctx.uint_id;
+    let mut spv = vec![];
+
+    let shift_right = |ctx: &mut WidenContext, spv: &mut Vec<u32>| {
+        if access.constant_shift == Some(0) {
+            return word;
+        }
+        let shifted = ctx.inc();
+        spv.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_SHIFT_RIGHT_LOGICAL),
+            uint_id,
+            shifted,
+            word,
+            access.shift,
+        ]);
+        shifted
+    };
+
+    let mask = |ctx: &mut WidenContext, spv: &mut Vec<u32>, shifted| {
+        if access.constant_shift == Some(32 - width) {
+            return shifted;
+        }
+        let uint_mask = ctx.uint(narrow_mask(width));
+        let masked = ctx.inc();
+        spv.append(&mut vec![
+            encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+            uint_id,
+            masked,
+            shifted,
+            uint_mask,
+        ]);
+        masked
+    };
+
+    let value = match kind {
+        NarrowKind::Unsigned => {
+            let shifted = shift_right(ctx, &mut spv);
+            mask(ctx, &mut spv, shifted)
+        }
+        NarrowKind::Signed => {
+            let int_id = ctx.int_type();
+            let uint_unused_bits = ctx.uint(32 - width);
+            let high = match access.constant_shift {
+                Some(shift) if shift + width == 32 => word,
+                Some(shift) => {
+                    let uint_left = ctx.uint(32 - width - shift);
+                    let high = ctx.inc();
+                    spv.append(&mut vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+                        uint_id,
+                        high,
+                        word,
+                        uint_left,
+                    ]);
+                    high
+                }
+                None => {
+                    let left = ctx.inc();
+                    let high = ctx.inc();
+                    #[rustfmt::skip]
+                    spv.append(&mut vec![
+                        encode_word(5, SPV_INSTRUCTION_OP_I_SUB),
+                            uint_id, left, uint_unused_bits, access.shift,
+                        encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+                            uint_id, high, word, left,
+                    ]);
+                    high
+                }
+            };
+            let signed = ctx.inc();
+            let value = ctx.inc();
+            #[rustfmt::skip]
+            spv.append(&mut vec![
+                encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+                    int_id, signed, high,
+                encode_word(5, SPV_INSTRUCTION_OP_SHIFT_RIGHT_ARITHMETIC),
+                    int_id, value, signed, uint_unused_bits,
+            ]);
+            value
+        }
+        NarrowKind::Float => {
+            let shifted = shift_right(ctx, &mut spv);
+            let bits = mask(ctx, &mut spv, shifted);
+            let float_id = ctx.float_type();
+            let v2float_id = ctx.vector_type(float_id, 2);
+            let glsl_std_id = ctx.glsl_std();
+            let pair = ctx.inc();
+            let value = ctx.inc();
+            #[rustfmt::skip]
+            spv.append(&mut vec![
+                encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                    v2float_id, pair, glsl_std_id, SPV_GLSL_STD_INSTRUCTION_UNPACK_HALF_2X16, bits,
+                encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_EXTRACT),
+                    float_id, value, pair, 0,
+            ]);
+            value
+        }
+    };
+
+    (value, spv)
+}
@@ -0,0 +1,170 @@
+use super::*;
+
+// Narrow the widened component `value` into its raw bits, with everything above the narrow width cleared.
+pub(super) fn narrow_bits_spv(
+    ctx: &mut WidenContext,
+    scalar: NarrowScalar,
+    value_kind: WideKind,
+    value: u32,
+) -> (u32, Vec<u32>) {
+    //
+    //  uint8_t / uint16_t / int8_t / int16_t:
+    //   %unsigned = OpBitcast %uint %value
+    //       %bits = OpBitwiseAnd %uint %unsigned %uint_mask
+    //
+    //  float16_t:
+    //       %pair = OpCompositeConstruct %v2float %value %float_0
+    //       %bits = OpExtInst %uint %glsl_std PackHalf2x16 %pair
+
+    let uint_id = ctx.uint_id;
+    let bits = ctx.inc();
+
+    if scalar.kind == NarrowKind::Float {
+        let float_id = ctx.float_type();
+        let v2float_id = ctx.vector_type(float_id, 2);
+        let float_0 = ctx.float_zero();
+        let glsl_std_id = ctx.glsl_std();
+        let pair = ctx.inc();
+        #[rustfmt::skip]
+        let spv = vec![
+            encode_word(5, SPV_INSTRUCTION_OP_COMPOSITE_CONSTRUCT),
+                v2float_id, pair, value, float_0,
+            encode_word(6, SPV_INSTRUCTION_OP_EXT_INST),
+                uint_id, bits, glsl_std_id, SPV_GLSL_STD_INSTRUCTION_PACK_HALF_2X16, pair,
+        ];
+        return (bits, spv);
+    }
+
+    let uint_mask = ctx.uint(narrow_mask(scalar.width));
+    let mut spv = vec![];
+    let unsigned = if value_kind == WideKind::Int {
+        let unsigned = ctx.inc();
+        spv.append(&mut vec![
+            encode_word(4, SPV_INSTRUCTION_OP_BITCAST),
+            uint_id,
+            unsigned,
+            value,
+        ]);
+        unsigned
+    } else {
+        value
+    };
+    spv.append(&mut vec![
+        encode_word(5, SPV_INSTRUCTION_OP_BITWISE_AND),
+        uint_id,
+        bits,
+        unsigned,
+        uint_mask,
+    ]);
+    (bits, spv)
+}
+
+// Merge `bits` into the word at `word_ptr` without disturbing neighbouring components.
+// Neighbouring components may be written by other invocations, so both steps are atomic.
+pub(super) fn narrow_insert_spv(
+    ctx: &mut WidenContext,
+    scalar: NarrowScalar,
+    word_ptr: u32,
+    access: WordAccess,
+    bits: u32,
+) -> Vec<u32> {
+    //
+    //    %shifted = OpShiftLeftLogical %uint %bits %shift
+    //  %lane_mask = OpShiftLeftLogical %uint %uint_mask %shift
+    //      %clear = OpNot %uint %lane_mask
+    //          %_ = OpAtomicAnd %uint %word_ptr %uint_1 %uint_0 %clear
+    //          %_ = OpAtomicOr %uint %word_ptr %uint_1 %uint_0 %shifted
+
+    let uint_id = ctx.uint_id;
+    let scope = ctx.uint(SPV_SCOPE_DEVICE);
+    let semantics = ctx.uint(SPV_MEMORY_SEMANTICS_NONE);
+    let mask = narrow_mask(scalar.width);
+    let mut spv = vec![];
+
+    let (shifted, clear) = match access.constant_shift {
+        Some(0) => (bits, ctx.uint(!mask)),
+        Some(shift) => {
+            let shifted = ctx.inc();
+            spv.append(&mut vec![
+                encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+                uint_id,
+                shifted,
+                bits,
+                access.shift,
+            ]);
+            (shifted, ctx.uint(!(mask << shift)))
+        }
+        None => {
+            let uint_mask = ctx.uint(mask);
+            let shifted = ctx.inc();
+            let lane_mask = ctx.inc();
+            let clear = ctx.inc();
+            #[rustfmt::skip]
+            spv.append(&mut vec![
+                encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+                    uint_id, shifted, bits, access.shift,
+                encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+                    uint_id, lane_mask, uint_mask, access.shift,
+                encode_word(4, SPV_INSTRUCTION_OP_NOT),
+                    uint_id, clear, lane_mask,
+            ]);
+            (shifted, clear)
+        }
+    };
+
+    let cleared = ctx.inc();
+    let merged = ctx.inc();
+    #[rustfmt::skip]
+    spv.append(&mut vec![
+        encode_word(7, SPV_INSTRUCTION_OP_ATOMIC_AND),
+            uint_id, cleared, word_ptr, scope, semantics, clear,
+        encode_word(7, SPV_INSTRUCTION_OP_ATOMIC_OR),
+            uint_id, merged, word_ptr, scope, semantics, shifted,
+    ]);
+    spv
+}
+
+// Combine the raw bits of every component that lives in one word.
+// `lanes` pairs each component's bits with its constant bit shift.
+pub(super) fn narrow_word_spv(ctx: &mut WidenContext, lanes: &[(u32, u32)]) -> (u32, Vec<u32>) {
+    //
+    //  %shifted = OpShiftLeftLogical %uint %bits %uint_shift
+    //     %word = OpBitwiseOr %uint %word %shifted
+
+    let uint_id = ctx.uint_id;
+    let mut spv = vec![];
+    let mut word = None;
+
+    for &(bits, shift) in lanes {
+        let shifted = if shift == 0 {
+            bits
+        } else {
+            let uint_shift = ctx.uint(shift);
+            let shifted = ctx.inc();
+            spv.append(&mut vec![
+                encode_word(5, SPV_INSTRUCTION_OP_SHIFT_LEFT_LOGICAL),
+                uint_id,
+                shifted,
+                bits,
+                uint_shift,
+            ]);
+            shifted
+        };
+        word = Some(match word {
+            None => shifted,
+            Some(word) => {
+                let merged = ctx.inc();
+                spv.append(&mut vec![
+                    encode_word(5, SPV_INSTRUCTION_OP_BITWISE_OR),
+                    uint_id,
+                    merged,
+                    word,
+                    shifted,
+                ]);
+                merged
+            }
+        });
+    }
+
+    (word.expect("Word has no components"), spv)
+}